@@ -1,20 +1,63 @@
 #![feature(extern_types)]
 extern crate clap;
 extern crate nrt;
-extern crate parity_wasm;
 use clap::{App, Arg};
+use nrt::codegen::{BoundsCheckMode, CompileOptions};
 use nrt::runtime::Compartment;
 use nrt::wasm::Module;
 use std::fs::File;
 use std::io::Write;
 
-fn compile(file: &str) {
-    let wasm_module = Module::from(parity_wasm::deserialize_file(file).unwrap());
+fn compile(file: &str, options: &CompileOptions) {
+    let bytes = std::fs::read(file).unwrap();
+    let wasm_module = match Module::parse(&bytes) {
+        Ok(module) => module,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
-    let compiled_module = nrt::codegen::compile_module(&wasm_module);
+    if let Err(e) = nrt::wasm::validate(&wasm_module) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 
-    let compartment = Compartment::new();
-    nrt::runtime::setup_env(&compartment, &wasm_module);
+    let object_code = nrt::codegen::compile_module(&wasm_module, options);
+
+    let compartment = match Compartment::with_bounds_check(options.bounds_check) {
+        Ok(compartment) => compartment,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let stdlib = nrt::stdlib::host_module();
+    let instance = match nrt::runtime::setup_env(&compartment, &wasm_module, &object_code, &stdlib)
+    {
+        Ok(instance) => instance,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match instance.get_func("main") {
+        Some(main) if main.get_type().params().is_empty() => {
+            if let Err(trap) = main.call(&[]) {
+                eprintln!("{}", trap);
+                std::process::exit(1);
+            }
+        }
+        Some(main) => {
+            eprintln!("main has unsupported type {:?}", main.get_type());
+            std::process::exit(1);
+        }
+        None => {
+            eprintln!("the module doesn't export a main function");
+            std::process::exit(1);
+        }
+    }
 }
 
 fn main() {
@@ -25,8 +68,17 @@ fn main() {
                 .required(true)
                 .index(1),
         )
+        .arg(
+            Arg::with_name("explicit-bounds-checks")
+                .long("explicit-bounds-checks")
+                .help("check memory accesses explicitly instead of relying on guard pages"),
+        )
         .get_matches();
 
     let wasm_file = matches.value_of("WASM-FILE").unwrap();
-    compile(wasm_file);
+    let mut options = CompileOptions::default();
+    if matches.is_present("explicit-bounds-checks") {
+        options.bounds_check = BoundsCheckMode::Explicit;
+    }
+    compile(wasm_file, &options);
 }
@@ -177,6 +177,16 @@ fn main() {
        .cpp_link_stdlib(None) // we handle this below
        .compile("rustllvm");
 
+    // The runtime unwinds from traps with sigsetjmp/siglongjmp, which can't be called from Rust,
+    // and raises the wasm exceptions with the unwinder of the platform.
+    if target.contains("linux") {
+        cc::Build::new()
+            .file("src/platform/posix/trap.c")
+            .file("src/platform/posix/exception.c")
+            .flag("-funwind-tables")
+            .compile("nrtplatform");
+    }
+
     let (llvm_kind, llvm_link_arg) = detect_llvm_link();
 
     // Link in all LLVM libraries, if we're uwring the "wrong" llvm-config then
@@ -8,6 +8,7 @@ use std::ffi::CString;
 use std::ops::Deref;
 use crate::wasm::call_conv::CallConv as WASMCallConv;
 use crate::wasm::FunctionType as WASMFunctionType;
+use crate::wasm::ValueType as WASMValueType;
 
 define_type_wrapper!(pub Type, llvm::Type);
 
@@ -44,6 +45,10 @@ impl<'ll> Type<'ll> {
         Type::from(unsafe { llvm::LLVMIntTypeInContext(*ctx, 128) })
     }
 
+    pub fn int(ctx: Context<'ll>, num_bits: u32) -> Self {
+        Type::from(unsafe { llvm::LLVMIntTypeInContext(*ctx, num_bits) })
+    }
+
     pub fn f32(ctx: Context<'ll>) -> Self {
         Type::from(unsafe { llvm::LLVMFloatTypeInContext(*ctx) })
     }
@@ -81,12 +86,28 @@ impl<'ll> Type<'ll> {
         Type::from(unsafe { llvm::LLVMVectorType(self.0, len as c_uint) })
     }
 
+    // The type which a function with the results returns. Multiple results are returned as a
+    // struct.
+    pub fn func_results(ctx: &ContextCodeGen<'ll>, results: &[WASMValueType]) -> Self {
+        match results {
+            [] => ctx.get_basic_type(WASMValueType::None),
+            [res] => ctx.get_basic_type(*res),
+            results => {
+                let types = results
+                    .iter()
+                    .map(|t| ctx.get_basic_type(*t))
+                    .collect::<Vec<_>>();
+                Type::struct_(ctx.get_llvm_wrapper(), &types, false)
+            }
+        }
+    }
+
     pub fn func(
         ctx: &ContextCodeGen<'ll>,
         func_type: &WASMFunctionType,
         call_conv: WASMCallConv,
     ) -> Self {
-        let res_type = ctx.get_basic_type(func_type.res().unwrap_or_default());
+        let res_type = Type::func_results(ctx, func_type.results());
         let param_types = {
             let types = func_type
                 .params()
@@ -111,7 +132,22 @@ impl<'ll> Type<'ll> {
         })
     }
 
+    pub fn function(res_type: Type<'ll>, param_types: &[Type<'ll>]) -> Self {
+        Type::from(unsafe {
+            llvm::LLVMFunctionType(
+                *res_type,
+                param_types.as_ptr() as *mut _,
+                param_types.len() as c_uint,
+                0,
+            )
+        })
+    }
+
     pub fn get_element_type(&self) -> Self {
         Type::from(unsafe { llvm::LLVMGetElementType(self.0) })
     }
+
+    pub fn vector_size(&self) -> u32 {
+        unsafe { llvm::LLVMGetVectorSize(self.0) }
+    }
 }
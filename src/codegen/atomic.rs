@@ -0,0 +1,353 @@
+use super::_type::Type;
+use super::common::Literal;
+use super::memory::coerce_address_to_ptr;
+use super::value::Value;
+use super::{ContextCodeGen, FunctionCodeGen, ModuleCodeGen};
+use crate::llvm::{AtomicOrdering, AtomicRmwBinOp, IntPredicate};
+use crate::wasm::types::{I32, I64};
+use crate::wasm::{FunctionType, MemArg, Module as WASMModule, ValueType};
+
+pub trait AtomicInstrEmit<'ll> {
+    declare_atomic_instrs!(declear_op, _);
+
+    fn atomic_fence(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+    );
+}
+
+impl<'ll> FunctionCodeGen<'ll> {
+    // The address of an atomic access of `num_bytes` bytes, which traps unless it's naturally
+    // aligned. Unlike the other accesses, the alignment of atomic accesses is checked since the
+    // targets don't guarantee their atomicity otherwise.
+    fn emit_atomic_address(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        arg: &MemArg,
+        num_bytes: u32,
+    ) -> Value<'ll> {
+        let addr = self.pop();
+        let bounded_addr = self.get_offset_and_bounded_addr(
+            ctx,
+            module,
+            arg.memory as usize,
+            addr,
+            arg.offset,
+            num_bytes,
+        );
+        if num_bytes > 1 {
+            let misaligned_bits = self
+                .builder
+                .create_and(bounded_addr, I64::from(num_bytes as i64 - 1).emit_const(ctx));
+            let is_misaligned = self.builder.create_icmp(
+                IntPredicate::IntNE,
+                misaligned_bits,
+                I64::from(0).emit_const(ctx),
+            );
+            self.emit_conditional_trap(ctx, module, is_misaligned, "misalignedAtomicTrap");
+        }
+        bounded_addr
+    }
+
+    fn emit_atomic_ptr(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        arg: &MemArg,
+        mem_ty: Type<'ll>,
+        num_bytes: u32,
+    ) -> Value<'ll> {
+        let addr = self.emit_atomic_address(ctx, module, arg, num_bytes);
+        coerce_address_to_ptr(
+            self.builder,
+            self.memory_base_ptrs[arg.memory as usize],
+            addr,
+            mem_ty,
+        )
+    }
+
+    fn emit_atomic_store(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        arg: &MemArg,
+        value: Value<'ll>,
+        mem_ty: Type<'ll>,
+        num_bytes: u32,
+    ) {
+        let ptr = self.emit_atomic_ptr(ctx, module, arg, mem_ty, num_bytes);
+        let store = self.builder.create_store(value, ptr);
+        store.set_alignment(num_bytes);
+        store.set_ordering(AtomicOrdering::SequentiallyConsistent);
+        store.set_volatile(true);
+    }
+
+    // `memory.atomic.wait32` and `memory.atomic.wait64` park the thread in the runtime, which
+    // traps if the memory isn't shared.
+    fn emit_atomic_wait(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        arg: &MemArg,
+        name: &str,
+        value_type: ValueType,
+        num_bytes: u32,
+    ) {
+        let timeout = self.pop();
+        let expected = self.pop();
+        let addr = self.emit_atomic_address(ctx, module, arg, num_bytes);
+        let res = self.emit_runtime_intrinsic(
+            ctx,
+            module,
+            name,
+            FunctionType::new(
+                vec![ValueType::I64, value_type, ValueType::I64, ValueType::I32],
+                vec![ValueType::I32],
+            ),
+            vec![
+                addr,
+                expected,
+                timeout,
+                I32::from(arg.memory as i32).emit_const(ctx),
+            ],
+        );
+        self.push(res[0]);
+    }
+}
+
+macro_rules! emit_atomic_load {
+    ($name:ident, $mem_type:ident, $num_bytes:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let ptr = self.emit_atomic_ptr(ctx, module, &arg, ctx.$mem_type, $num_bytes);
+            let load = self.builder.create_load(ptr);
+            load.set_alignment($num_bytes);
+            load.set_ordering(AtomicOrdering::SequentiallyConsistent);
+            load.set_volatile(true);
+            self.push(load);
+        }
+    };
+    ($name:ident, $mem_type:ident, $num_bytes:expr, $res_type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let ptr = self.emit_atomic_ptr(ctx, module, &arg, ctx.$mem_type, $num_bytes);
+            let load = self.builder.create_load(ptr);
+            load.set_alignment($num_bytes);
+            load.set_ordering(AtomicOrdering::SequentiallyConsistent);
+            load.set_volatile(true);
+            self.push(self.builder.create_zext(load, ctx.$res_type));
+        }
+    };
+}
+
+macro_rules! emit_atomic_store {
+    ($name:ident, $mem_type:ident, $num_bytes:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let value = self.pop();
+            self.emit_atomic_store(ctx, module, &arg, value, ctx.$mem_type, $num_bytes);
+        }
+    };
+    ($name:ident, $mem_type:ident, $num_bytes:expr, wrap) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let value = self.pop();
+            let value = self.builder.create_trunc(value, ctx.$mem_type);
+            self.emit_atomic_store(ctx, module, &arg, value, ctx.$mem_type, $num_bytes);
+        }
+    };
+}
+
+macro_rules! emit_atomic_rmw {
+    ($name:ident, $op:ident, $mem_type:ident, $num_bytes:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let value = self.pop();
+            let ptr = self.emit_atomic_ptr(ctx, module, &arg, ctx.$mem_type, $num_bytes);
+            let old = self.builder.create_atomic_rmw(AtomicRmwBinOp::$op, ptr, value);
+            self.push(old);
+        }
+    };
+    ($name:ident, $op:ident, $mem_type:ident, $num_bytes:expr, $res_type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let value = self.pop();
+            let value = self.builder.create_trunc(value, ctx.$mem_type);
+            let ptr = self.emit_atomic_ptr(ctx, module, &arg, ctx.$mem_type, $num_bytes);
+            let old = self.builder.create_atomic_rmw(AtomicRmwBinOp::$op, ptr, value);
+            self.push(self.builder.create_zext(old, ctx.$res_type));
+        }
+    };
+}
+
+// The expected value of the narrow forms is wrapped like the replacement.
+macro_rules! emit_atomic_cmpxchg {
+    ($name:ident, $mem_type:ident, $num_bytes:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let replacement = self.pop();
+            let expected = self.pop();
+            let ptr = self.emit_atomic_ptr(ctx, module, &arg, ctx.$mem_type, $num_bytes);
+            let pair = self.builder.create_atomic_cmpxchg(ptr, expected, replacement);
+            self.push(self.builder.create_extract_value(pair, 0));
+        }
+    };
+    ($name:ident, $mem_type:ident, $num_bytes:expr, $res_type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let replacement = self.pop();
+            let replacement = self.builder.create_trunc(replacement, ctx.$mem_type);
+            let expected = self.pop();
+            let expected = self.builder.create_trunc(expected, ctx.$mem_type);
+            let ptr = self.emit_atomic_ptr(ctx, module, &arg, ctx.$mem_type, $num_bytes);
+            let pair = self.builder.create_atomic_cmpxchg(ptr, expected, replacement);
+            let old = self.builder.create_extract_value(pair, 0);
+            self.push(self.builder.create_zext(old, ctx.$res_type));
+        }
+    };
+}
+
+macro_rules! emit_atomic_rmw_ops {
+    ($op:ident, $name:ident, $name64:ident, $name8:ident, $name16:ident, $name64_8:ident, $name64_16:ident, $name64_32:ident) => {
+        emit_atomic_rmw!($name, $op, i32_type, 4);
+        emit_atomic_rmw!($name64, $op, i64_type, 8);
+        emit_atomic_rmw!($name8, $op, i8_type, 1, i32_type);
+        emit_atomic_rmw!($name16, $op, i16_type, 2, i32_type);
+        emit_atomic_rmw!($name64_8, $op, i8_type, 1, i64_type);
+        emit_atomic_rmw!($name64_16, $op, i16_type, 2, i64_type);
+        emit_atomic_rmw!($name64_32, $op, i32_type, 4, i64_type);
+    };
+}
+
+impl<'ll> AtomicInstrEmit<'ll> for FunctionCodeGen<'ll> {
+    emit_atomic_load!(i32_atomic_load, i32_type, 4);
+    emit_atomic_load!(i64_atomic_load, i64_type, 8);
+    emit_atomic_load!(i32_atomic_load8_u, i8_type, 1, i32_type);
+    emit_atomic_load!(i32_atomic_load16_u, i16_type, 2, i32_type);
+    emit_atomic_load!(i64_atomic_load8_u, i8_type, 1, i64_type);
+    emit_atomic_load!(i64_atomic_load16_u, i16_type, 2, i64_type);
+    emit_atomic_load!(i64_atomic_load32_u, i32_type, 4, i64_type);
+
+    emit_atomic_store!(i32_atomic_store, i32_type, 4);
+    emit_atomic_store!(i64_atomic_store, i64_type, 8);
+    emit_atomic_store!(i32_atomic_store8, i8_type, 1, wrap);
+    emit_atomic_store!(i32_atomic_store16, i16_type, 2, wrap);
+    emit_atomic_store!(i64_atomic_store8, i8_type, 1, wrap);
+    emit_atomic_store!(i64_atomic_store16, i16_type, 2, wrap);
+    emit_atomic_store!(i64_atomic_store32, i32_type, 4, wrap);
+
+    emit_atomic_rmw_ops!(
+        AtomicAdd,
+        i32_atomic_rmw_add,
+        i64_atomic_rmw_add,
+        i32_atomic_rmw8_add_u,
+        i32_atomic_rmw16_add_u,
+        i64_atomic_rmw8_add_u,
+        i64_atomic_rmw16_add_u,
+        i64_atomic_rmw32_add_u
+    );
+    emit_atomic_rmw_ops!(
+        AtomicSub,
+        i32_atomic_rmw_sub,
+        i64_atomic_rmw_sub,
+        i32_atomic_rmw8_sub_u,
+        i32_atomic_rmw16_sub_u,
+        i64_atomic_rmw8_sub_u,
+        i64_atomic_rmw16_sub_u,
+        i64_atomic_rmw32_sub_u
+    );
+    emit_atomic_rmw_ops!(
+        AtomicAnd,
+        i32_atomic_rmw_and,
+        i64_atomic_rmw_and,
+        i32_atomic_rmw8_and_u,
+        i32_atomic_rmw16_and_u,
+        i64_atomic_rmw8_and_u,
+        i64_atomic_rmw16_and_u,
+        i64_atomic_rmw32_and_u
+    );
+    emit_atomic_rmw_ops!(
+        AtomicOr,
+        i32_atomic_rmw_or,
+        i64_atomic_rmw_or,
+        i32_atomic_rmw8_or_u,
+        i32_atomic_rmw16_or_u,
+        i64_atomic_rmw8_or_u,
+        i64_atomic_rmw16_or_u,
+        i64_atomic_rmw32_or_u
+    );
+    emit_atomic_rmw_ops!(
+        AtomicXor,
+        i32_atomic_rmw_xor,
+        i64_atomic_rmw_xor,
+        i32_atomic_rmw8_xor_u,
+        i32_atomic_rmw16_xor_u,
+        i64_atomic_rmw8_xor_u,
+        i64_atomic_rmw16_xor_u,
+        i64_atomic_rmw32_xor_u
+    );
+    emit_atomic_rmw_ops!(
+        AtomicXchg,
+        i32_atomic_rmw_xchg,
+        i64_atomic_rmw_xchg,
+        i32_atomic_rmw8_xchg_u,
+        i32_atomic_rmw16_xchg_u,
+        i64_atomic_rmw8_xchg_u,
+        i64_atomic_rmw16_xchg_u,
+        i64_atomic_rmw32_xchg_u
+    );
+
+    emit_atomic_cmpxchg!(i32_atomic_rmw_cmpxchg, i32_type, 4);
+    emit_atomic_cmpxchg!(i64_atomic_rmw_cmpxchg, i64_type, 8);
+    emit_atomic_cmpxchg!(i32_atomic_rmw8_cmpxchg_u, i8_type, 1, i32_type);
+    emit_atomic_cmpxchg!(i32_atomic_rmw16_cmpxchg_u, i16_type, 2, i32_type);
+    emit_atomic_cmpxchg!(i64_atomic_rmw8_cmpxchg_u, i8_type, 1, i64_type);
+    emit_atomic_cmpxchg!(i64_atomic_rmw16_cmpxchg_u, i16_type, 2, i64_type);
+    emit_atomic_cmpxchg!(i64_atomic_rmw32_cmpxchg_u, i32_type, 4, i64_type);
+
+    fn memory_atomic_notify(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        arg: MemArg,
+    ) {
+        let count = self.pop();
+        let addr = self.emit_atomic_address(ctx, module, &arg, 4);
+        let res = self.emit_runtime_intrinsic(
+            ctx,
+            module,
+            "atomicNotify",
+            FunctionType::new(
+                vec![ValueType::I64, ValueType::I32, ValueType::I32],
+                vec![ValueType::I32],
+            ),
+            vec![addr, count, I32::from(arg.memory as i32).emit_const(ctx)],
+        );
+        self.push(res[0]);
+    }
+
+    fn memory_atomic_wait32(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        arg: MemArg,
+    ) {
+        self.emit_atomic_wait(ctx, module, &arg, "atomicWait32", ValueType::I32, 4);
+    }
+
+    fn memory_atomic_wait64(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        arg: MemArg,
+    ) {
+        self.emit_atomic_wait(ctx, module, &arg, "atomicWait64", ValueType::I64, 8);
+    }
+
+    fn atomic_fence(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+    ) {
+        self.builder.create_fence();
+    }
+}
@@ -1,4 +1,8 @@
-use super::{function::Function, inst::CallInst, BasicBlock, PHINode, Type, Value};
+use super::{
+    function::Function,
+    inst::{CallInst, LandingPadInst},
+    BasicBlock, PHINode, Type, Value,
+};
 use crate::llvm;
 use std::ffi::CString;
 
@@ -37,6 +41,107 @@ impl<'ll> Builder<'ll> {
         unsafe { Value::from(llvm::LLVMBuildAdd(self.0, *lhs, *rhs, c_name.as_ptr())) }
     }
 
+    pub fn create_mul(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildMul(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
+    pub fn create_sub(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildSub(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
+    pub fn create_udiv(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildUDiv(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
+    pub fn create_sdiv(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildSDiv(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
+    pub fn create_urem(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildURem(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
+    pub fn create_srem(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildSRem(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
+    pub fn create_shl(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildShl(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
+    pub fn create_lshr(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildLShr(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
+    pub fn create_ashr(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildAShr(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
+    pub fn create_fadd(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildFAdd(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
+    pub fn create_fsub(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildFSub(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
+    pub fn create_fmul(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildFMul(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
+    pub fn create_fdiv(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildFDiv(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
+    pub fn create_icmp(
+        &self,
+        pred: llvm::IntPredicate,
+        lhs: Value<'ll>,
+        rhs: Value<'ll>,
+    ) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe {
+            Value::from(llvm::LLVMBuildICmp(
+                self.0,
+                pred as u32,
+                *lhs,
+                *rhs,
+                c_name.as_ptr(),
+            ))
+        }
+    }
+
+    pub fn create_fcmp(
+        &self,
+        pred: llvm::RealPredicate,
+        lhs: Value<'ll>,
+        rhs: Value<'ll>,
+    ) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe {
+            Value::from(llvm::LLVMBuildFCmp(
+                self.0,
+                pred as u32,
+                *lhs,
+                *rhs,
+                c_name.as_ptr(),
+            ))
+        }
+    }
+
     pub fn create_store(&self, val: Value<'ll>, ptr: Value<'ll>) -> Value<'ll> {
         unsafe { Value::from(llvm::LLVMBuildStore(self.0, *val, *ptr)) }
     }
@@ -123,6 +228,16 @@ impl<'ll> Builder<'ll> {
         unsafe { Value::from(llvm::LLVMBuildAnd(self.0, *lhs, *rhs, c_name.as_ptr())) }
     }
 
+    pub fn create_or(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildOr(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
+    pub fn create_xor(&self, lhs: Value<'ll>, rhs: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildXor(self.0, *lhs, *rhs, c_name.as_ptr())) }
+    }
+
     pub fn create_ptr_cast(&self, v: Value<'ll>, ty: Type<'ll>) -> Value<'ll> {
         let c_name = CString::new("").unwrap();
         unsafe { Value::from(llvm::LLVMBuildPointerCast(self.0, *v, *ty, c_name.as_ptr())) }
@@ -139,11 +254,59 @@ impl<'ll> Builder<'ll> {
         load
     }
 
+    pub fn store_to_untyped_pointer(&self, value: Value<'ll>, ptr: Value<'ll>, align: u32) {
+        let store = self.create_store(
+            value,
+            self.create_ptr_cast(ptr, value.get_type().ptr_to()),
+        );
+        store.set_alignment(align);
+    }
+
     pub fn create_zext(&self, addr: Value<'ll>, ty: Type<'ll>) -> Value<'ll> {
         let c_name = CString::new("").unwrap();
         unsafe { Value::from(llvm::LLVMBuildZExt(self.0, *addr, *ty, c_name.as_ptr())) }
     }
 
+    pub fn create_sext(&self, v: Value<'ll>, ty: Type<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildSExt(self.0, *v, *ty, c_name.as_ptr())) }
+    }
+
+    pub fn create_trunc(&self, v: Value<'ll>, ty: Type<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildTrunc(self.0, *v, *ty, c_name.as_ptr())) }
+    }
+
+    pub fn create_fp_to_ui(&self, v: Value<'ll>, ty: Type<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildFPToUI(self.0, *v, *ty, c_name.as_ptr())) }
+    }
+
+    pub fn create_fp_to_si(&self, v: Value<'ll>, ty: Type<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildFPToSI(self.0, *v, *ty, c_name.as_ptr())) }
+    }
+
+    pub fn create_ui_to_fp(&self, v: Value<'ll>, ty: Type<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildUIToFP(self.0, *v, *ty, c_name.as_ptr())) }
+    }
+
+    pub fn create_si_to_fp(&self, v: Value<'ll>, ty: Type<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildSIToFP(self.0, *v, *ty, c_name.as_ptr())) }
+    }
+
+    pub fn create_fp_trunc(&self, v: Value<'ll>, ty: Type<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildFPTrunc(self.0, *v, *ty, c_name.as_ptr())) }
+    }
+
+    pub fn create_fp_ext(&self, v: Value<'ll>, ty: Type<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildFPExt(self.0, *v, *ty, c_name.as_ptr())) }
+    }
+
     pub fn create_call(&self, callee: Function<'ll>, args: &[Value]) -> CallInst<'ll> {
         let c_name = CString::new("").unwrap();
         unsafe {
@@ -157,10 +320,162 @@ impl<'ll> Builder<'ll> {
         }
     }
 
+    pub fn create_invoke(
+        &self,
+        callee: Function<'ll>,
+        args: &[Value],
+        then_block: BasicBlock<'ll>,
+        catch_block: BasicBlock<'ll>,
+    ) -> CallInst<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe {
+            CallInst::from(llvm::LLVMBuildInvoke(
+                self.0,
+                *callee,
+                args.as_ptr() as *mut _,
+                args.len() as u32,
+                *then_block,
+                *catch_block,
+                c_name.as_ptr(),
+            ))
+        }
+    }
+
+    pub fn create_landing_pad(
+        &self,
+        ty: Type<'ll>,
+        personality: Function<'ll>,
+        num_clauses: u32,
+    ) -> LandingPadInst<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe {
+            LandingPadInst::from(llvm::LLVMBuildLandingPad(
+                self.0,
+                *ty,
+                *personality,
+                num_clauses,
+                c_name.as_ptr(),
+            ))
+        }
+    }
+
+    // The atomic instructions of wasm are sequentially consistent.
+    pub fn create_atomic_rmw(
+        &self,
+        op: llvm::AtomicRmwBinOp,
+        ptr: Value<'ll>,
+        val: Value<'ll>,
+    ) -> Value<'ll> {
+        unsafe {
+            Value::from(llvm::LLVMBuildAtomicRMW(
+                self.0,
+                op,
+                *ptr,
+                *val,
+                llvm::AtomicOrdering::SequentiallyConsistent,
+                llvm::False,
+            ))
+        }
+    }
+
+    pub fn create_atomic_cmpxchg(
+        &self,
+        ptr: Value<'ll>,
+        cmp: Value<'ll>,
+        new: Value<'ll>,
+    ) -> Value<'ll> {
+        unsafe {
+            Value::from(llvm::LLVMBuildAtomicCmpXchg(
+                self.0,
+                *ptr,
+                *cmp,
+                *new,
+                llvm::AtomicOrdering::SequentiallyConsistent,
+                llvm::AtomicOrdering::SequentiallyConsistent,
+                llvm::False,
+            ))
+        }
+    }
+
+    pub fn create_fence(&self) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe {
+            Value::from(llvm::LLVMBuildFence(
+                self.0,
+                llvm::AtomicOrdering::SequentiallyConsistent,
+                llvm::False,
+                c_name.as_ptr(),
+            ))
+        }
+    }
+
+    pub fn create_extract_value(&self, agg: Value<'ll>, index: u32) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe { Value::from(llvm::LLVMBuildExtractValue(self.0, *agg, index, c_name.as_ptr())) }
+    }
+
+    pub fn create_extract_element(&self, vec: Value<'ll>, index: Value<'ll>) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe {
+            Value::from(llvm::LLVMBuildExtractElement(
+                self.0,
+                *vec,
+                *index,
+                c_name.as_ptr(),
+            ))
+        }
+    }
+
+    pub fn create_insert_element(
+        &self,
+        vec: Value<'ll>,
+        elt: Value<'ll>,
+        index: Value<'ll>,
+    ) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe {
+            Value::from(llvm::LLVMBuildInsertElement(
+                self.0,
+                *vec,
+                *elt,
+                *index,
+                c_name.as_ptr(),
+            ))
+        }
+    }
+
+    pub fn create_shuffle_vector(
+        &self,
+        v1: Value<'ll>,
+        v2: Value<'ll>,
+        mask: Value<'ll>,
+    ) -> Value<'ll> {
+        let c_name = CString::new("").unwrap();
+        unsafe {
+            Value::from(llvm::LLVMBuildShuffleVector(
+                self.0,
+                *v1,
+                *v2,
+                *mask,
+                c_name.as_ptr(),
+            ))
+        }
+    }
+
     pub fn create_ret(&self, ret: Value<'ll>) -> Value<'ll> {
         unsafe { Value::from(llvm::LLVMBuildRet(self.0, *ret)) }
     }
 
+    pub fn create_aggregate_ret(&self, rets: &[Value<'ll>]) -> Value<'ll> {
+        unsafe {
+            Value::from(llvm::LLVMBuildAggregateRet(
+                self.0,
+                rets.as_ptr() as *const _,
+                rets.len() as u32,
+            ))
+        }
+    }
+
     pub fn create_ret_void(&self) -> Value<'ll> {
         unsafe { Value::from(llvm::LLVMBuildRetVoid(self.0)) }
     }
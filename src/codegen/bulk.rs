@@ -0,0 +1,128 @@
+use super::common::Literal;
+use super::{ContextCodeGen, FunctionCodeGen, ModuleCodeGen};
+use crate::wasm::types::I32;
+use crate::wasm::{FunctionType, Module as WASMModule, ValueType};
+
+// The runtime checks the bounds of the whole range before copying or filling anything, and keeps
+// the passive segments of the instance, see `runtime::data::Segments`.
+pub trait BulkInstrEmit<'ll> {
+    declare_bulk_instrs!(declear_op);
+}
+
+impl<'ll> FunctionCodeGen<'ll> {
+    // Pops the operands of a bulk instruction, which are the destination, the source or the
+    // value, and the count, and passes them to the intrinsic `name` followed by the immediates.
+    fn emit_bulk_intrinsic(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        name: &str,
+        immediates: &[u32],
+    ) {
+        let mut args = self.pop_multi(3);
+        args.extend(
+            immediates
+                .iter()
+                .map(|imm| I32::from(*imm as i32).emit_const(ctx)),
+        );
+        self.emit_runtime_intrinsic(
+            ctx,
+            module,
+            name,
+            FunctionType::new(vec![ValueType::I32; args.len()], vec![]),
+            args,
+        );
+    }
+
+    fn emit_segment_drop(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        name: &str,
+        seg_idx: u32,
+    ) {
+        self.emit_runtime_intrinsic(
+            ctx,
+            module,
+            name,
+            FunctionType::new(vec![ValueType::I32], vec![]),
+            vec![I32::from(seg_idx as i32).emit_const(ctx)],
+        );
+    }
+}
+
+impl<'ll> BulkInstrEmit<'ll> for FunctionCodeGen<'ll> {
+    fn memory_init(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        seg_idx: u32,
+        memory_idx: u32,
+    ) {
+        self.emit_bulk_intrinsic(ctx, module, "memoryInit", &[seg_idx, memory_idx]);
+    }
+
+    fn data_drop(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        seg_idx: u32,
+    ) {
+        self.emit_segment_drop(ctx, module, "dataDrop", seg_idx);
+    }
+
+    fn memory_copy(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        dst_memory_idx: u32,
+        src_memory_idx: u32,
+    ) {
+        self.emit_bulk_intrinsic(ctx, module, "memoryCopy", &[dst_memory_idx, src_memory_idx]);
+    }
+
+    fn memory_fill(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        memory_idx: u32,
+    ) {
+        self.emit_bulk_intrinsic(ctx, module, "memoryFill", &[memory_idx]);
+    }
+
+    fn table_init(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        seg_idx: u32,
+        table_idx: u32,
+    ) {
+        self.emit_bulk_intrinsic(ctx, module, "tableInit", &[seg_idx, table_idx]);
+    }
+
+    fn elem_drop(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        seg_idx: u32,
+    ) {
+        self.emit_segment_drop(ctx, module, "elemDrop", seg_idx);
+    }
+
+    fn table_copy(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        dst_table_idx: u32,
+        src_table_idx: u32,
+    ) {
+        self.emit_bulk_intrinsic(ctx, module, "tableCopy", &[dst_table_idx, src_table_idx]);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::llvm::CallConv as LLVMCallConv;
 use crate::wasm::call_conv::CallConv as WASMCallConv;
 
-pub struct CallConv(LLVMCallConv);
+pub struct CallConv(pub(super) LLVMCallConv);
 
 impl From<WASMCallConv> for CallConv {
     fn from(v: WASMCallConv) -> Self {
@@ -23,15 +23,16 @@ impl Literal for I64 {
     }
 }
 
+// Float constants are emitted from their bit patterns, so that NaN payloads are preserved.
 impl Literal for F32 {
     fn emit_const<'ll>(&self, ctx: &ContextCodeGen<'ll>) -> Value<'ll> {
-        unsafe { Value::from(llvm::LLVMConstReal(*ctx.f32_type, self.0 as f64)) }
+        const_bit_cast(const_uint(ctx.i32_type, self.0.to_bits() as u64), ctx.f32_type)
     }
 }
 
 impl Literal for F64 {
     fn emit_const<'ll>(&self, ctx: &ContextCodeGen<'ll>) -> Value<'ll> {
-        unsafe { Value::from(llvm::LLVMConstReal(*ctx.f64_type, self.0)) }
+        const_bit_cast(const_uint(ctx.i64_type, self.0.to_bits()), ctx.f64_type)
     }
 }
 
@@ -73,13 +74,17 @@ pub fn const_double<'ll>(t: Type<'ll>, i: f64) -> Value<'ll> {
 }
 
 pub fn const_f32(ctx: Context, i: f32) -> Value {
-    const_double(Type::f64(ctx), i as f64)
+    const_double(Type::f32(ctx), i as f64)
 }
 
 pub fn const_f64(ctx: Context, i: f64) -> Value {
     const_double(Type::f64(ctx), i)
 }
 
+pub fn const_bit_cast<'ll>(v: Value<'ll>, ty: Type<'ll>) -> Value<'ll> {
+    unsafe { Value::from(llvm::LLVMConstBitCast(*v, *ty)) }
+}
+
 pub fn const_vector<'ll>(elts: &[Value]) -> Value<'ll> {
     unsafe {
         Value::from(llvm::LLVMConstVector(
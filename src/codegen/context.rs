@@ -26,20 +26,22 @@ define_type_wrapper!(pub Context, llvm::Context);
 pub struct ContextCodeGen<'ll> {
     pub ctx: Context<'ll>,
     pub i8_type: Type<'ll>,
-    i16_type: Type<'ll>,
+    pub i16_type: Type<'ll>,
     pub i32_type: Type<'ll>,
     pub i64_type: Type<'ll>,
     pub f32_type: Type<'ll>,
     pub f64_type: Type<'ll>,
     pub i8_ptr_type: Type<'ll>,
     pub iptr_type: Type<'ll>,
-    i8x16_type: Type<'ll>,
-    i16x8_type: Type<'ll>,
-    i32x4_type: Type<'ll>,
-    i64x2_type: Type<'ll>,
-    f32x4_type: Type<'ll>,
-    f64x2_type: Type<'ll>,
+    pub i8x16_type: Type<'ll>,
+    pub i16x8_type: Type<'ll>,
+    pub i32x4_type: Type<'ll>,
+    pub i64x2_type: Type<'ll>,
+    pub f32x4_type: Type<'ll>,
+    pub f64x2_type: Type<'ll>,
     exception_pointer_struct_type: Type<'ll>,
+    // The exception pointer and the selector which a landing pad receives.
+    pub landing_pad_type: Type<'ll>,
     anyref_type: Type<'ll>,
     pub typed_zero_constants: [Value<'ll>; ValueType::LENGTH],
     value_types: [Type<'ll>; ValueType::LENGTH],
@@ -77,7 +79,11 @@ impl<'ll> ContextCodeGen<'ll> {
             false,
         );
 
+        let landing_pad_type = Type::struct_(ctx, &[i8_ptr_type, i32_type], false);
+
+        // A reference is a pointer to an opaque object, which is null for `ref.null`.
         let anyref_type = Type::named_struct(ctx, "Object");
+        let ref_type = anyref_type.ptr_to();
 
         let i8x16_type = i8_type.vector(16);
         let i16x8_type = i16_type.vector(8);
@@ -94,9 +100,9 @@ impl<'ll> ContextCodeGen<'ll> {
             f32_type,
             f64_type,
             i64x2_type,
-            anyref_type,
-            anyref_type,
-            Type::void(ctx),
+            ref_type,
+            ref_type,
+            ref_type,
         ];
         let typed_zero_constants = [
             common::const_null(i32_type),
@@ -106,9 +112,9 @@ impl<'ll> ContextCodeGen<'ll> {
             common::const_f32(ctx, 0.0),
             common::const_f64(ctx, 0.0),
             common::const_v128(ctx, V128::zero()),
-            common::const_null(anyref_type),
-            common::const_null(anyref_type),
-            common::const_null(anyref_type),
+            common::const_null(ref_type),
+            common::const_null(ref_type),
+            common::const_null(ref_type),
         ];
         Self {
             ctx,
@@ -127,6 +133,7 @@ impl<'ll> ContextCodeGen<'ll> {
             f32x4_type,
             f64x2_type,
             exception_pointer_struct_type,
+            landing_pad_type,
             anyref_type,
             typed_zero_constants,
             value_types,
@@ -134,7 +141,7 @@ impl<'ll> ContextCodeGen<'ll> {
         }
     }
 
-    pub fn get_llvm_wrapper(&self) -> Context {
+    pub fn get_llvm_wrapper(&self) -> Context<'ll> {
         self.ctx
     }
 
@@ -154,11 +161,15 @@ impl<'ll> ContextCodeGen<'ll> {
 
     // Append a basic block to the end of a function.
     pub fn create_basic_block(&self, name: &str, func: &FunctionCodeGen<'ll>) -> BasicBlock<'ll> {
+        self.append_basic_block(name, func.get_llvm_func())
+    }
+
+    pub fn append_basic_block(&self, name: &str, func: Function<'ll>) -> BasicBlock<'ll> {
         let c_name = CString::new(name).unwrap();
         unsafe {
             BasicBlock::from(llvm::LLVMAppendBasicBlockInContext(
                 *self.ctx,
-                *func.get_llvm_func(),
+                *func,
                 c_name.as_ptr(),
             ))
         }
@@ -191,14 +202,26 @@ impl<'ll> ContextCodeGen<'ll> {
         }
     }
 
+    // Calls inside a try block are emitted as invokes, which unwind to the landing pad of the
+    // try block, and continue in a new block if the callee returns.
     pub fn emit_call_or_invoke(
         &self,
         callee: Function<'ll>,
         args: Vec<Value<'ll>>,
         call_conv: WASMCallConv,
         builder: Builder<'ll>,
+        landing_pad: Option<BasicBlock<'ll>>,
     ) -> Value<'ll> {
-        let call = builder.create_call(callee, &args);
+        let call = match landing_pad {
+            None => builder.create_call(callee, &args),
+            Some(landing_pad) => {
+                let func = builder.get_insert_block().get_parent();
+                let then_block = self.append_basic_block("invokeContinue", func);
+                let invoke = builder.create_invoke(callee, &args, then_block, landing_pad);
+                builder.set_insert_block(then_block);
+                invoke
+            }
+        };
         call.set_call_conv(call_conv);
         Value::from(*call)
     }
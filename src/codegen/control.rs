@@ -1,9 +1,10 @@
 use super::common;
-use super::function::BranchTarget;
+use super::function::{BranchTarget, Function};
 use super::{
-    value::Value, ContextCodeGen, ContorlContextType, ControlContext, FunctionCodeGen,
-    ModuleCodeGen,
+    value::Value, BasicBlock, ContextCodeGen, ContorlContextType, ControlContext,
+    FunctionCodeGen, ModuleCodeGen, PHINode, Type,
 };
+use crate::llvm;
 use std::rc::Rc;
 use crate::wasm::{
     call_conv::CallConv as WASMCallConv, BlockType, BrTableData, FunctionType,
@@ -11,9 +12,34 @@ use crate::wasm::{
 };
 
 pub trait ControlInstrEmit<'ll> {
+    declare_block_instrs!(declear_op);
     declare_control_instrs!(declear_op);
 }
 
+impl<'ll> FunctionCodeGen<'ll> {
+    // Pushes the control context of a block, whose params are moved from the enclosing block,
+    // and returns the params.
+    pub(super) fn push_block_context(
+        &mut self,
+        ty: ContorlContextType,
+        block_type: &FunctionType,
+        end_block: BasicBlock<'ll>,
+        end_PHIs: Vec<PHINode<'ll>>,
+        else_block: Option<BasicBlock<'ll>>,
+    ) -> Vec<Value<'ll>> {
+        let params = self.pop_multi(block_type.params().len());
+        self.push_control_stack(
+            ty,
+            block_type.results().to_vec(),
+            end_block,
+            end_PHIs,
+            else_block,
+        );
+        params.iter().for_each(|v| self.push(*v));
+        params
+    }
+}
+
 impl<'ll> ControlInstrEmit<'ll> for FunctionCodeGen<'ll> {
     fn block(
         &mut self,
@@ -22,25 +48,22 @@ impl<'ll> ControlInstrEmit<'ll> for FunctionCodeGen<'ll> {
         module: &ModuleCodeGen<'ll>,
         ty: BlockType,
     ) {
-        let res_type = match ty {
-            BlockType::Value(v) => Some(ValueType::from(v)),
-            _ => None,
-        };
+        let ty = wasm_module.get_block_type(ty);
 
         let end_block = ctx.create_basic_block("blockEnd", self);
-        let end_PHIs = res_type.map(|ty| self.create_PHIs(ctx, end_block, ty));
+        let end_PHIs = self.create_PHIs(ctx, end_block, ty.results());
 
-        self.push_control_stack(
+        self.push_block_context(
             ContorlContextType::Block,
-            res_type,
+            &ty,
             end_block,
-            end_PHIs,
+            end_PHIs.clone(),
             None,
         );
 
         self.branch_target_stack.push(BranchTarget {
             block: end_block,
-            type_PHIs: res_type.map(|ty| (ty, end_PHIs.unwrap())),
+            type_PHIs: ty.results().iter().cloned().zip(end_PHIs).collect(),
         })
     }
 
@@ -51,26 +74,37 @@ impl<'ll> ControlInstrEmit<'ll> for FunctionCodeGen<'ll> {
         module: &ModuleCodeGen<'ll>,
         ty: BlockType,
     ) {
-        let res_type = match ty {
-            BlockType::Value(v) => Some(ValueType::from(v)),
-            _ => None,
-        };
-        // let enrty_block = self.builder.get_insert_block();
+        let ty = wasm_module.get_block_type(ty);
 
         let loop_body_block = ctx.create_basic_block("loopBody", self);
         let end_block = ctx.create_basic_block("loopEnd", self);
-        let end_PHIs = res_type.map(|ty| self.create_PHIs(ctx, end_block, ty));
+        let end_PHIs = self.create_PHIs(ctx, end_block, ty.results());
+
+        // The params of the loop are passed to its first iteration, and the branches to the loop
+        // pass the params of the next one.
+        let param_PHIs = self.create_PHIs(ctx, loop_body_block, ty.params());
+        let params = self.pop_multi(ty.params().len());
+        self.add_PHI_incomings(ctx, param_PHIs.iter().cloned(), &params);
 
         self.builder.create_br_instr(loop_body_block);
         self.builder.set_insert_block(loop_body_block);
 
         self.push_control_stack(
             ContorlContextType::Loop,
-            res_type,
+            ty.results().to_vec(),
             end_block,
             end_PHIs,
             None,
         );
+        param_PHIs
+            .iter()
+            .for_each(|PHI| self.push(Value::from(**PHI)));
+
+        // A branch to a loop continues with its next iteration.
+        self.branch_target_stack.push(BranchTarget {
+            block: loop_body_block,
+            type_PHIs: ty.params().iter().cloned().zip(param_PHIs).collect(),
+        });
     }
 
     fn if_(
@@ -80,15 +114,12 @@ impl<'ll> ControlInstrEmit<'ll> for FunctionCodeGen<'ll> {
         module: &ModuleCodeGen<'ll>,
         ty: BlockType,
     ) {
-        let res_type = match ty {
-            BlockType::Value(v) => Some(ValueType::from(v)),
-            _ => None,
-        };
+        let ty = wasm_module.get_block_type(ty);
 
         let then_block = ctx.create_basic_block("ifThen", self);
         let else_block = ctx.create_basic_block("ifElse", self);
         let end_block = ctx.create_basic_block("ifElseEnd", self);
-        let end_PHIs = res_type.map(|ty| self.create_PHIs(ctx, end_block, ty));
+        let end_PHIs = self.create_PHIs(ctx, end_block, ty.results());
 
         let cond = self.pop();
         let cond_bool = ctx.coerce_i32_to_bool(self.builder, cond);
@@ -97,18 +128,19 @@ impl<'ll> ControlInstrEmit<'ll> for FunctionCodeGen<'ll> {
 
         self.builder.set_insert_block(then_block);
 
-        self.push_control_stack(
-            ContorlContextType::IfElse,
-            res_type,
+        // The params are passed to the else branch too.
+        let params = self.push_block_context(
+            ContorlContextType::IfThen,
+            &ty,
             end_block,
-            end_PHIs,
+            end_PHIs.clone(),
             Some(else_block),
         );
+        self.control_stack.last_mut().unwrap().else_args = params;
 
         self.branch_target_stack.push(BranchTarget {
-            // param_types: res_ty,
             block: end_block,
-            type_PHIs: res_type.map(|ty| (ty, end_PHIs.unwrap())),
+            type_PHIs: ty.results().iter().cloned().zip(end_PHIs).collect(),
         });
     }
 
@@ -130,12 +162,15 @@ impl<'ll> ControlInstrEmit<'ll> for FunctionCodeGen<'ll> {
         let else_block = cur_ctx.else_block.unwrap();
         else_block.move_after(self.builder.get_insert_block());
 
-        // TODO: check whether need else arguments.
-        // cur_ctx.else_args.clone().into_iter().for_each(|t| {});
-
         cur_ctx.ty = ContorlContextType::IfElse;
         cur_ctx.is_reachable = true;
         cur_ctx.else_block = None;
+        let else_args = std::mem::replace(&mut cur_ctx.else_args, Vec::new());
+        let outer_stack_size = cur_ctx.outer_stack_size;
+
+        self.builder.set_insert_block(else_block);
+        self.stack.truncate(outer_stack_size);
+        else_args.into_iter().for_each(|v| self.push(v));
     }
 
     fn end(
@@ -144,53 +179,45 @@ impl<'ll> ControlInstrEmit<'ll> for FunctionCodeGen<'ll> {
         wasm_module: &WASMModule,
         module: &ModuleCodeGen<'ll>,
     ) {
-        let (PHI, res_type) = {
-            assert!(self.control_stack.len() > 0);
-
-            self.branch_to_end_of_control_context(ctx);
-
-            let cur_ctx = self.control_stack.last().unwrap();
-            if let Some(else_block) = cur_ctx.else_block {
-                else_block.move_after(self.builder.get_insert_block());
-                self.builder.set_insert_block(else_block);
-                self.builder.create_br_instr(cur_ctx.end_block);
-
-                // assert!(cur_ctx.else_args.len() == cur_ctx.end_PHIs.len());
-
-                // TODO
-                // (0..cur_ctx.else_args.len())
-                //     .for_each(|t| cur_ctx.end_PHIs[t].add_incoming(cur_ctx.else_args[t], else_block));
-            }
-
-            match cur_ctx.ty {
-                ContorlContextType::Try => { /* TODO: Add end_try */ }
-                ContorlContextType::Catch => { /* TODO: Add end_catch */ }
-                _ => {}
-            };
-
-            cur_ctx
-                .end_block
-                .move_after(self.builder.get_insert_block());
-            self.builder.set_insert_block(cur_ctx.end_block);
-
-            assert!(
-                (cur_ctx.end_PHIs.is_some() && cur_ctx.res_types.is_some())
-                    || (cur_ctx.end_PHIs.is_none() && cur_ctx.res_types.is_none())
-            );
-            if cur_ctx.end_PHIs.is_none() {
-                return;
-            }
-            (cur_ctx.end_PHIs.unwrap(), cur_ctx.res_types.unwrap())
-        };
+        assert!(self.control_stack.len() > 0);
+        if self.control_stack.last().unwrap().try_ctx.is_some() {
+            self.end_try(ctx, module);
+        }
+        self.branch_to_end_of_control_context(ctx);
+
+        let cur_ctx = self.control_stack.pop().unwrap();
+        self.branch_target_stack
+            .truncate(cur_ctx.outer_branch_target_stack_size);
+        self.stack.truncate(cur_ctx.outer_stack_size);
 
-        // if let Some(PHI) = cur_ctx.end_PHIs {
-        if PHI.count_incoming() == 0 {
-            PHI.erase_from_parent();
-            self.push(ctx.typed_zero_constants[res_type as usize]);
-        } else {
-            self.push(Value::from(*PHI));
+        // An if without else passes its params to the end.
+        if let Some(else_block) = cur_ctx.else_block {
+            else_block.move_after(self.builder.get_insert_block());
+            self.builder.set_insert_block(else_block);
+
+            assert!(cur_ctx.else_args.len() == cur_ctx.end_PHIs.len());
+            self.add_PHI_incomings(ctx, cur_ctx.end_PHIs.iter().cloned(), &cur_ctx.else_args);
+            self.builder.create_br_instr(cur_ctx.end_block);
         }
-        // }
+
+        cur_ctx
+            .end_block
+            .move_after(self.builder.get_insert_block());
+        self.builder.set_insert_block(cur_ctx.end_block);
+
+        assert!(cur_ctx.end_PHIs.len() == cur_ctx.res_types.len());
+        cur_ctx
+            .end_PHIs
+            .iter()
+            .zip(cur_ctx.res_types.iter())
+            .for_each(|(PHI, res_type)| {
+                if PHI.count_incoming() == 0 {
+                    PHI.erase_from_parent();
+                    self.push(ctx.typed_zero_constants[*res_type as usize]);
+                } else {
+                    self.push(Value::from(**PHI));
+                }
+            });
     }
 
     fn br(
@@ -200,16 +227,13 @@ impl<'ll> ControlInstrEmit<'ll> for FunctionCodeGen<'ll> {
         module: &ModuleCodeGen<'ll>,
         depth: u32,
     ) {
-        if let Some((ty, PHI)) = self.get_branch_target(depth).type_PHIs {
-            let res = self.pop();
-            PHI.add_incoming(
-                ctx.coerce_to_canonical_type(self.builder, res),
-                self.builder.get_insert_block(),
-            )
-        }
+        let num_args = self.get_branch_target(depth).type_PHIs.len();
+        let args = self.pop_multi(num_args);
+        let target = self.get_branch_target(depth);
+        self.add_PHI_incomings(ctx, target.type_PHIs.iter().map(|t| t.1), &args);
 
-        self.builder
-            .create_br_instr(self.get_branch_target(depth).block);
+        self.builder.create_br_instr(target.block);
+        self.enter_unreachable();
     }
 
     fn br_if(
@@ -221,13 +245,9 @@ impl<'ll> ControlInstrEmit<'ll> for FunctionCodeGen<'ll> {
     ) {
         let cond = self.pop();
         let target = self.get_branch_target(depth);
-        if let Some((ty, PHI)) = target.type_PHIs {
-            let arg = self.get_value_from_stack(0);
-            PHI.add_incoming(
-                ctx.coerce_to_canonical_type(self.builder, arg),
-                self.builder.get_insert_block(),
-            );
-        }
+        // The args stay on the stack for the fall-through.
+        let args = &self.stack[self.stack.len() - target.type_PHIs.len()..];
+        self.add_PHI_incomings(ctx, target.type_PHIs.iter().map(|t| t.1), args);
 
         let false_block = ctx.create_basic_block("br_ifElse", self);
         self.builder.create_cond_br_instr(
@@ -244,17 +264,9 @@ impl<'ll> ControlInstrEmit<'ll> for FunctionCodeGen<'ll> {
         wasm_module: &WASMModule,
         module: &ModuleCodeGen<'ll>,
     ) {
-        match self.func_ty.res() {
-            None => {}
-            _ => {
-                let v = self.pop();
-                let cur_ctx = self.control_stack.first().unwrap();
-                cur_ctx.end_PHIs.unwrap().add_incoming(
-                    ctx.coerce_to_canonical_type(self.builder, v),
-                    self.builder.get_insert_block(),
-                );
-            }
-        }
+        let end_PHIs = self.control_stack.first().unwrap().end_PHIs.clone();
+        let results = self.pop_multi(end_PHIs.len());
+        self.add_PHI_incomings(ctx, end_PHIs.into_iter(), &results);
 
         self.builder
             .create_br_instr(self.control_stack.first().unwrap().end_block);
@@ -269,18 +281,12 @@ impl<'ll> ControlInstrEmit<'ll> for FunctionCodeGen<'ll> {
         data: Box<BrTableData>,
     ) {
         let index = self.pop();
-        // let num_args = self.get_branch_target(data.default).type_PHIs.len();
-        // let args = (0..num_args).map(|_| self.pop()).rev().collect::<Vec<_>>();
+        let num_args = self.get_branch_target(data.default).type_PHIs.len();
+        let args = self.pop_multi(num_args);
 
         let ll_switch = {
             let default_target = self.get_branch_target(data.default);
-            // TODO
-            // args.iter().enumerate().for_each(|(t, v)| {
-            //     default_target.type_PHIs[t].1.add_incoming(
-            //         ctx.coerce_to_canonical_type(self.builder, *v),
-            //         self.builder.get_insert_block(),
-            //     );
-            // });
+            self.add_PHI_incomings(ctx, default_target.type_PHIs.iter().map(|t| t.1), &args);
 
             self.builder
                 .create_switch(index, default_target.block, data.table.len())
@@ -295,15 +301,9 @@ impl<'ll> ControlInstrEmit<'ll> for FunctionCodeGen<'ll> {
                 target.block,
             );
 
-            // TODO
-
-            // assert!(target.type_PHIs.len() == num_args);
-            // args.iter().enumerate().for_each(|(t, v)| {
-            //     target.type_PHIs[t].1.add_incoming(
-            //         ctx.coerce_to_canonical_type(self.builder, *v),
-            //         self.builder.get_insert_block(),
-            //     );
-            // });
+            // Every case is an edge of the switch, so the args are passed once for each.
+            assert!(target.type_PHIs.len() == num_args);
+            self.add_PHI_incomings(ctx, target.type_PHIs.iter().map(|t| t.1), &args);
         });
 
         self.enter_unreachable();
@@ -326,9 +326,16 @@ impl<'ll> ControlInstrEmit<'ll> for FunctionCodeGen<'ll> {
                 .map(|t| ctx.coerce_to_canonical_type(self.builder, *t)),
         );
 
-        let res = ctx.emit_call_or_invoke(callee, args, WASMCallConv::Wasm, self.builder);
+        let res = ctx.emit_call_or_invoke(
+            callee,
+            args,
+            WASMCallConv::Wasm,
+            self.builder,
+            self.get_landing_pad(),
+        );
+        self.reload_memory_base(ctx, module);
 
-        self.push(res);
+        self.push_call_results(res, callee_type.results());
     }
 
     fn unreachable_(
@@ -337,28 +344,10 @@ impl<'ll> ControlInstrEmit<'ll> for FunctionCodeGen<'ll> {
         wasm_module: &WASMModule,
         module: &ModuleCodeGen<'ll>,
     ) {
-        self.emit_runtime_intrinsic("unreachableTrap", FunctionType::default(), Vec::new());
-        self.builder.create_unreachable();
+        self.emit_trap(ctx, module, "unreachableTrap");
         self.enter_unreachable();
     }
 
-    fn call_indirect(
-        &mut self,
-        ctx: &ContextCodeGen<'ll>,
-        wasm_module: &WASMModule,
-        module: &ModuleCodeGen<'ll>,
-        ty_index: u32,
-        table_index: u8,
-    ) {
-        // let index = self.pop();
-        // let func_ty = match self.module.get_wasm_module().type_section() {
-        //     Some(section) => section.types()[ty_index as usize].clone(),
-        //     None => panic!(),
-        // };
-
-        // TODO
-    }
-
     fn nop(
         &mut self,
         ctx: &ContextCodeGen<'ll>,
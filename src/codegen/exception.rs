@@ -0,0 +1,327 @@
+use super::common;
+use super::control::ControlInstrEmit;
+use super::function::BranchTarget;
+use super::{
+    BasicBlock, ContextCodeGen, ContorlContextType, FunctionCodeGen, ModuleCodeGen, TryContext,
+    Type, Value,
+};
+use crate::llvm;
+use crate::wasm::{
+    call_conv::CallConv as WASMCallConv, BlockType, Entry, FunctionType, Module as WASMModule,
+    ValueType,
+};
+
+// The exceptions are created by the runtime and raised with the unwinder of the platform, which
+// runs the landing pads of the try blocks. The compiled code refers to an exception by its
+// address, and to its type by the address of the runtime type of its tag, `exceptionTypeId{i}`.
+// The arguments of an exception are stored as untyped 16-byte values.
+pub trait ExceptionInstrEmit<'ll> {
+    declare_exception_instrs!(declear_op);
+}
+
+impl<'ll> FunctionCodeGen<'ll> {
+    // Throws `exception` to `landing_pad`, or to the caller if it's `None`.
+    fn emit_raise_exception(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        exception: Value<'ll>,
+        landing_pad: Option<BasicBlock<'ll>>,
+    ) {
+        let raise_exception = module.get_llvm_intrinsic(
+            "raiseException",
+            Type::function(ctx.get_basic_type(ValueType::None), &[ctx.i8_ptr_type]),
+        );
+        ctx.emit_call_or_invoke(
+            raise_exception,
+            vec![self.builder.create_int_to_ptr(exception, ctx.i8_ptr_type)],
+            WASMCallConv::C,
+            self.builder,
+            landing_pad,
+        );
+        // The unwinder only returns if no frame catches the exception, which can't happen since
+        // the invoke thunks catch all of them.
+        self.emit_trap(ctx, module, "uncaughtExceptionTrap");
+    }
+
+    // The address of the arguments of `exception`.
+    fn emit_exception_args(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        exception: Value<'ll>,
+    ) -> Value<'ll> {
+        let args = self.emit_runtime_intrinsic(
+            ctx,
+            module,
+            "exceptionArgs",
+            FunctionType::new(vec![ValueType::I64], vec![ValueType::I64]),
+            vec![exception],
+        );
+        self.builder.create_int_to_ptr(args[0], ctx.i8_ptr_type)
+    }
+
+    fn emit_destroy_exception(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        exception: Value<'ll>,
+    ) {
+        self.emit_runtime_intrinsic(
+            ctx,
+            module,
+            "destroyException",
+            FunctionType::new(vec![ValueType::I64], vec![]),
+            vec![exception],
+        );
+    }
+
+    fn try_context(&mut self) -> &mut TryContext<'ll> {
+        self.control_stack
+            .last_mut()
+            .unwrap()
+            .try_ctx
+            .as_mut()
+            .unwrap()
+    }
+
+    // Leaves the body of the current try block or its current catch clause. The exception of a
+    // catch clause is destroyed when the clause ends normally, and otherwise when the runtime
+    // returns to the host.
+    fn leave_try_part(&mut self, ctx: &ContextCodeGen<'ll>, module: &ModuleCodeGen<'ll>) {
+        let cur_ctx = self.control_stack.last().unwrap();
+        if cur_ctx.ty == ContorlContextType::Catch && cur_ctx.is_reachable() {
+            let exception = cur_ctx.try_ctx.as_ref().unwrap().exception;
+            self.emit_destroy_exception(ctx, module, exception);
+        }
+        self.branch_to_end_of_control_context(ctx);
+    }
+
+    // Starts a catch clause of the current try block, and returns its first block.
+    fn begin_catch(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+    ) -> BasicBlock<'ll> {
+        self.leave_try_part(ctx, module);
+
+        let cur_ctx = self.control_stack.last_mut().unwrap();
+        cur_ctx.ty = ContorlContextType::Catch;
+        cur_ctx.is_reachable = true;
+        let outer_stack_size = cur_ctx.outer_stack_size;
+        self.stack.truncate(outer_stack_size);
+
+        ctx.create_basic_block("catch", self)
+    }
+
+    // Called by `end` before the try block is popped. The exceptions which no catch clause
+    // matches are thrown to the enclosing try block.
+    pub fn end_try(&mut self, ctx: &ContextCodeGen<'ll>, module: &ModuleCodeGen<'ll>) {
+        let cur_ctx = self.control_stack.last().unwrap();
+        if cur_ctx.ty == ContorlContextType::Catch && cur_ctx.is_reachable() {
+            let exception = cur_ctx.try_ctx.as_ref().unwrap().exception;
+            self.emit_destroy_exception(ctx, module, exception);
+        }
+
+        let exception = self.try_context().exception;
+        if let Some(dispatch_block) = self.try_context().dispatch_block.take() {
+            let insert_block = self.builder.get_insert_block();
+            self.builder.set_insert_block(dispatch_block);
+            let landing_pad = self.find_landing_pad(self.control_stack.len() - 1);
+            self.emit_raise_exception(ctx, module, exception, landing_pad);
+            self.builder.set_insert_block(insert_block);
+        }
+    }
+}
+
+impl<'ll> ExceptionInstrEmit<'ll> for FunctionCodeGen<'ll> {
+    fn try_(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        ty: BlockType,
+    ) {
+        let ty = wasm_module.get_block_type(ty);
+
+        let end_block = ctx.create_basic_block("tryEnd", self);
+        let end_PHIs = self.create_PHIs(ctx, end_block, ty.results());
+
+        // The landing pad catches any exception, including the foreign ones, whose type id is 0.
+        let landing_pad = ctx.create_basic_block("tryLandingPad", self);
+        let body_block = self.builder.get_insert_block();
+        self.builder.set_insert_block(landing_pad);
+        let landing_pad_inst =
+            self.builder
+                .create_landing_pad(ctx.landing_pad_type, module.personality_function(), 1);
+        landing_pad_inst.add_clause(common::const_null(ctx.i8_ptr_type));
+        let exception = self.builder.create_ptr_to_int(
+            self.builder
+                .create_extract_value(Value::from(*landing_pad_inst), 0),
+            ctx.i64_type,
+        );
+        let type_id = self.emit_runtime_intrinsic(
+            ctx,
+            module,
+            "getExceptionTypeId",
+            FunctionType::new(vec![ValueType::I64], vec![ValueType::I64]),
+            vec![exception],
+        )[0];
+        // The code which threw may have grown the memory.
+        self.reload_memory_base(ctx, module);
+        let dispatch_block = self.builder.get_insert_block();
+        self.builder.set_insert_block(body_block);
+
+        self.push_block_context(
+            ContorlContextType::Try,
+            &ty,
+            end_block,
+            end_PHIs.clone(),
+            None,
+        );
+        self.control_stack.last_mut().unwrap().try_ctx = Some(TryContext {
+            landing_pad,
+            exception,
+            type_id,
+            dispatch_block: Some(dispatch_block),
+        });
+
+        self.branch_target_stack.push(BranchTarget {
+            block: end_block,
+            type_PHIs: ty.results().iter().cloned().zip(end_PHIs).collect(),
+        });
+    }
+
+    fn catch(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        tag_index: u32,
+    ) {
+        let catch_block = self.begin_catch(ctx, module);
+
+        let next_dispatch_block = ctx.create_basic_block("catchDispatch", self);
+        let (exception, type_id, dispatch_block) = {
+            let try_ctx = self.try_context();
+            let dispatch_block = try_ctx.dispatch_block.replace(next_dispatch_block);
+            (try_ctx.exception, try_ctx.type_id, dispatch_block.unwrap())
+        };
+        self.builder.set_insert_block(dispatch_block);
+        self.builder.create_cond_br_instr(
+            self.builder.create_icmp(
+                llvm::IntPredicate::IntEQ,
+                type_id,
+                module.exception_type_ids()[tag_index as usize],
+            ),
+            catch_block,
+            next_dispatch_block,
+        );
+
+        self.builder.set_insert_block(catch_block);
+        let params = wasm_module.tags()[tag_index as usize].get_type().params();
+        if params.is_empty() {
+            return;
+        }
+        let args = self.emit_exception_args(ctx, module, exception);
+        params.iter().enumerate().for_each(|(idx, ty)| {
+            let arg_ptr = self.builder.create_in_bounds_GEP(
+                args,
+                &[common::const_uint(ctx.iptr_type, (idx * 16) as u64)],
+            );
+            let arg = self
+                .builder
+                .load_from_untyped_pointer(arg_ptr, ctx.get_basic_type(*ty), 16);
+            self.push(arg);
+        });
+    }
+
+    fn catch_all(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+    ) {
+        let catch_block = self.begin_catch(ctx, module);
+
+        let dispatch_block = self.try_context().dispatch_block.take().unwrap();
+        self.builder.set_insert_block(dispatch_block);
+        self.builder.create_br_instr(catch_block);
+        self.builder.set_insert_block(catch_block);
+    }
+
+    fn throw(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        tag_index: u32,
+    ) {
+        let num_params = wasm_module.tags()[tag_index as usize]
+            .get_type()
+            .params()
+            .len();
+        let params = self.pop_multi(num_params);
+
+        let exception = self.emit_runtime_intrinsic(
+            ctx,
+            module,
+            "createException",
+            FunctionType::new(vec![ValueType::I64], vec![ValueType::I64]),
+            vec![module.exception_type_ids()[tag_index as usize]],
+        )[0];
+        if !params.is_empty() {
+            let args = self.emit_exception_args(ctx, module, exception);
+            params.iter().enumerate().for_each(|(idx, param)| {
+                let arg_ptr = self.builder.create_in_bounds_GEP(
+                    args,
+                    &[common::const_uint(ctx.iptr_type, (idx * 16) as u64)],
+                );
+                self.builder.store_to_untyped_pointer(
+                    ctx.coerce_to_canonical_type(self.builder, *param),
+                    arg_ptr,
+                    16,
+                );
+            });
+        }
+
+        self.emit_raise_exception(ctx, module, exception, self.get_landing_pad());
+        self.enter_unreachable();
+    }
+
+    fn rethrow(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        depth: u32,
+    ) {
+        let exception = self.control_stack[self.control_stack.len() - 1 - depth as usize]
+            .try_ctx
+            .as_ref()
+            .unwrap()
+            .exception;
+        self.emit_raise_exception(ctx, module, exception, self.get_landing_pad());
+        self.enter_unreachable();
+    }
+
+    fn delegate(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        depth: u32,
+    ) {
+        // The exceptions thrown in the body go to the try block of the label `depth`, which is
+        // relative to the blocks enclosing this one, or to its enclosing try block.
+        let exception = self.try_context().exception;
+        let dispatch_block = self.try_context().dispatch_block.take().unwrap();
+        let insert_block = self.builder.get_insert_block();
+        self.builder.set_insert_block(dispatch_block);
+        let landing_pad = self.find_landing_pad(self.control_stack.len() - 1 - depth as usize);
+        self.emit_raise_exception(ctx, module, exception, landing_pad);
+        self.builder.set_insert_block(insert_block);
+
+        self.end(ctx, wasm_module, module);
+    }
+}
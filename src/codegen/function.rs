@@ -1,7 +1,10 @@
+use super::common::Literal;
 use super::{
-    context::ContextCodeGen, control::ControlInstrEmit, memory::MemoryInstrEmit,
-    module::ModuleCodeGen, numeric::NumericInstrEmit, variable::VariableInstrEmit, BasicBlock,
-    Builder, CodeGen, ContorlContextType, ControlContext, PHINode, Type, Value,
+    atomic::AtomicInstrEmit, bulk::BulkInstrEmit, context::ContextCodeGen,
+    control::ControlInstrEmit, exception::ExceptionInstrEmit, memory::MemoryInstrEmit,
+    module::ModuleCodeGen, numeric::NumericInstrEmit, reference::RefInstrEmit,
+    simd::SimdInstrEmit, variable::VariableInstrEmit, BasicBlock, Builder,
+    CodeGen, ContorlContextType, ControlContext, PHINode, Type, Value,
 };
 use libc::c_uint;
 use crate::llvm;
@@ -11,7 +14,10 @@ use std::ffi::{CStr, CString};
 use std::ops::Deref;
 use std::ptr::null;
 use std::rc::Rc;
-use crate::wasm::{Function as WASMFunction, FunctionType, Instruction, Module as WASMModule, ValueType};
+use crate::wasm::{
+    call_conv::CallConv as WASMCallConv, types::{I32, I64}, Function as WASMFunction, FunctionType,
+    Instruction, Module as WASMModule, Operator, ValueType,
+};
 
 define_type_wrapper!(pub Function, llvm::Value);
 
@@ -35,12 +41,14 @@ impl<'ll> Function<'ll> {
 pub struct BranchTarget<'ll> {
     // pub(in crate::codegen) param_types: Vec<ValueType>,
     pub(in crate::codegen) block: BasicBlock<'ll>,
-    pub(in crate::codegen) type_PHIs: Option<(ValueType, PHINode<'ll>)>,
+    pub(in crate::codegen) type_PHIs: Vec<(ValueType, PHINode<'ll>)>,
 }
 
 pub struct FunctionCodeGen<'ll> {
     pub(in crate::codegen) func: Function<'ll>,
     pub(in crate::codegen) func_ty: FunctionType,
+    // The index of the function in the wasm module, which is reported with its traps.
+    pub(in crate::codegen) index: u32,
     // pub(in crate::codegen) module: Rc<ModuleCodeGen>,
     // pub(in crate::codegen) ctx: Rc<ContextCodeGen>,
     pub(in crate::codegen) builder: Builder<'ll>,
@@ -49,7 +57,9 @@ pub struct FunctionCodeGen<'ll> {
     pub(in crate::codegen) stack: Vec<Value<'ll>>,
     pub(in crate::codegen) local_pointers: Vec<Value<'ll>>,
     // ll_params: Vec<Value>,
-    pub memory_base_ptr: Option<Value<'ll>>,
+    // The base address and, in the explicit bounds-checking mode, the number of bytes of each
+    // memory, indexed by the memory index.
+    pub memory_base_ptrs: Vec<Value<'ll>>,
     pub ctx_ptr: Option<Value<'ll>>,
 }
 
@@ -62,12 +72,14 @@ impl<'ll> FunctionCodeGen<'ll> {
         module: &ModuleCodeGen,
         func: Function<'ll>,
         func_ty: FunctionType,
+        index: u32,
     ) -> Self {
         let builder = ctx.create_builder();
 
         Self {
             func,
             func_ty,
+            index,
             // module,
             // ctx,
             builder,
@@ -76,13 +88,54 @@ impl<'ll> FunctionCodeGen<'ll> {
             stack: Vec::new(),
             local_pointers: Vec::new(),
             // None,
-            memory_base_ptr: None,
+            memory_base_ptrs: Vec::new(),
             ctx_ptr: None,
         }
     }
 
-    fn reload_memory_base(&mut self) {
-        // TODO
+    // Loads the base addresses of the memories from the compartment runtime data into
+    // `memory_base_ptrs`, which must be done again whenever a memory may have been moved, i.e.
+    // after `memory.grow` and after any call, since the callee may grow it. The runtime data of
+    // a memory is laid out as [base address, number of bytes].
+    pub fn reload_memory_base(&self, ctx: &ContextCodeGen<'ll>, module: &ModuleCodeGen<'ll>) {
+        if module.memory_offsets().is_empty() {
+            return;
+        }
+        let compartment_addr =
+            super::get_compartment_address(ctx, self.builder, self.ctx_ptr.unwrap());
+        for (idx, offset) in module.memory_offsets().iter().enumerate() {
+            self.builder.create_store(
+                self.builder.load_from_untyped_pointer(
+                    self.builder
+                        .create_in_bounds_GEP(compartment_addr, &[*offset]),
+                    ctx.i8_ptr_type,
+                    std::mem::size_of::<usize>() as u32,
+                ),
+                self.memory_base_ptrs[idx],
+            );
+        }
+    }
+
+    // The current size of a memory is loaded at every explicit bounds check rather than cached,
+    // since another thread may grow a shared memory at any time.
+    pub fn load_memory_num_bytes(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        memory_idx: usize,
+    ) -> Value<'ll> {
+        let compartment_addr =
+            super::get_compartment_address(ctx, self.builder, self.ctx_ptr.unwrap());
+        let num_bytes_offset = self.builder.create_add(
+            module.memory_offsets()[memory_idx],
+            I64::from(std::mem::size_of::<usize>() as i64).emit_const(ctx),
+        );
+        self.builder.load_from_untyped_pointer(
+            self.builder
+                .create_in_bounds_GEP(compartment_addr, &[num_bytes_offset]),
+            ctx.i64_type,
+            std::mem::size_of::<usize>() as u32,
+        )
     }
 
     pub fn create_entry_block(&self, ctx: &ContextCodeGen<'ll>) {
@@ -94,32 +147,47 @@ impl<'ll> FunctionCodeGen<'ll> {
         &self,
         ctx: &ContextCodeGen<'ll>,
         block: BasicBlock<'ll>,
-        res_type: ValueType,
-    ) -> PHINode<'ll> {
+        types: &[ValueType],
+    ) -> Vec<PHINode<'ll>> {
         let origin_block = self.builder.get_insert_block();
         self.builder.set_insert_block(block);
 
-        let ret = self.builder.create_phi(ctx.get_basic_type(res_type));
+        let ret = types
+            .iter()
+            .map(|ty| self.builder.create_phi(ctx.get_basic_type(*ty)))
+            .collect();
         self.builder.set_insert_block(origin_block);
         ret
     }
 
+    // Adds the values which the current block passes to `PHIs`.
+    pub fn add_PHI_incomings<I: Iterator<Item = PHINode<'ll>>>(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        PHIs: I,
+        args: &[Value<'ll>],
+    ) {
+        let block = self.builder.get_insert_block();
+        PHIs.zip(args.iter()).for_each(|(PHI, arg)| {
+            PHI.add_incoming(ctx.coerce_to_canonical_type(self.builder, *arg), block)
+        });
+    }
+
     fn create_ret_block(&mut self, ctx: &ContextCodeGen<'ll>) -> BasicBlock<'ll> {
         let ret_block = ctx.create_basic_block("return", self);
-        let res_type = self.func_ty.res().map(From::from);
-        let end_PHIs = res_type.map(|ty| self.create_PHIs(ctx, ret_block, ty));
+        let res_types = self.func_ty.results().to_vec();
+        let end_PHIs = self.create_PHIs(ctx, ret_block, &res_types);
 
         self.push_control_stack(
             ContorlContextType::Function,
-            res_type,
+            res_types.clone(),
             ret_block,
-            end_PHIs,
+            end_PHIs.clone(),
             None,
         );
         self.branch_target_stack.push(BranchTarget {
-            // param_types: vec![ret_ty],
             block: ret_block,
-            type_PHIs: res_type.map(|ty| (ty, end_PHIs.unwrap())),
+            type_PHIs: res_types.into_iter().zip(end_PHIs).collect(),
         });
         ret_block
     }
@@ -178,31 +246,16 @@ impl<'ll> FunctionCodeGen<'ll> {
         let init_ctx_ptr = ll_params.remove(0);
         assert!(ll_params.len() == self.func_ty.params().len());
 
-        let (memory_base_ptr, ctx_ptr) = {
-            let memory_base_ptr = self.builder.create_alloca(ctx.i8_ptr_type, "memoryBase");
-            let ctx_ptr = self.builder.create_alloca(ctx.i8_ptr_type, "context");
-            self.builder.create_store(init_ctx_ptr, ctx_ptr);
-            {
-                let compartment_addr = super::get_compartment_address(ctx, self.builder, ctx_ptr);
-
-                if let Some(offset) = module.default_memory_offset() {
-                    self.builder.create_store(
-                        self.builder.load_from_untyped_pointer(
-                            self.builder
-                                .create_in_bounds_GEP(compartment_addr, &[offset]),
-                            ctx.i8_ptr_type,
-                            std::mem::size_of::<usize>() as u32,
-                        ),
-                        memory_base_ptr,
-                    );
-                }
-            }
-            (memory_base_ptr, ctx_ptr)
-        };
-
-        self.memory_base_ptr = Some(memory_base_ptr);
+        let ctx_ptr = self.builder.create_alloca(ctx.i8_ptr_type, "context");
+        self.builder.create_store(init_ctx_ptr, ctx_ptr);
         self.ctx_ptr = Some(ctx_ptr);
 
+        let num_memorys = module.memory_offsets().len();
+        self.memory_base_ptrs = (0..num_memorys)
+            .map(|_| self.builder.create_alloca(ctx.i8_ptr_type, "memoryBase"))
+            .collect();
+        self.reload_memory_base(ctx, module);
+
         self.func_ty
             .clone()
             .params()
@@ -221,9 +274,23 @@ impl<'ll> FunctionCodeGen<'ll> {
             self.local_pointers.push(local);
         });
 
-        wasm_func.instructions().iter().for_each(|t| {
-            declear_instrs!(decode_instr, (self, ctx, wasm_module, module, t.clone()));
-            unimplemented!()
+        wasm_func.operators().iter().for_each(|op| match op {
+            Operator::Instr(t) => {
+                declear_instrs!(decode_instr, (self, ctx, wasm_module, module, t.clone()));
+                unimplemented!()
+            }
+            Operator::Simd(op) => {
+                declare_simd_instrs!(decode_simd, (self, ctx, wasm_module, module, op.clone()));
+                unimplemented!()
+            }
+            Operator::AtomicFence => self.atomic_fence(ctx, wasm_module, module),
+            _ => {
+                declare_block_instrs!(decode_operator, (self, ctx, wasm_module, module, op.clone()));
+                declare_exception_instrs!(decode_operator, (self, ctx, wasm_module, module, op.clone()));
+                declare_ref_instrs!(decode_operator, (self, ctx, wasm_module, module, op.clone()));
+                declare_bulk_instrs!(decode_operator, (self, ctx, wasm_module, module, op.clone()));
+                unimplemented!()
+            }
         });
         assert!(self.builder.get_insert_block() == ret_block);
 
@@ -232,12 +299,23 @@ impl<'ll> FunctionCodeGen<'ll> {
     }
 
     fn emit_return(&self) {
-        if let Some(_) = self.func_ty.res() {
-            assert!(self.stack.len() == 1);
-            self.builder.create_ret(self.stack[0]);
-        } else {
-            assert!(self.stack.len() == 0);
-            self.builder.create_ret_void();
+        assert!(self.stack.len() == self.func_ty.results().len());
+        match self.stack.as_slice() {
+            [] => self.builder.create_ret_void(),
+            [res] => self.builder.create_ret(*res),
+            results => self.builder.create_aggregate_ret(results),
+        };
+    }
+
+    // Pushes the results of a call, which returns multiple results as a struct.
+    pub fn push_call_results(&mut self, res: Value<'ll>, results: &[ValueType]) {
+        match results.len() {
+            0 => {}
+            1 => self.push(res),
+            n => (0..n).for_each(|idx| {
+                let v = self.builder.create_extract_value(res, idx as u32);
+                self.push(v);
+            }),
         }
     }
 
@@ -276,9 +354,9 @@ impl<'ll> FunctionCodeGen<'ll> {
     pub fn push_control_stack(
         &mut self,
         ty: ContorlContextType,
-        res_types: Option<ValueType>,
+        res_types: Vec<ValueType>,
         end_block: BasicBlock<'ll>,
-        end_PHIs: Option<PHINode<'ll>>,
+        end_PHIs: Vec<PHINode<'ll>>,
         else_block: Option<BasicBlock<'ll>>,
     ) {
         self.control_stack.push(ControlContext::new(
@@ -297,20 +375,14 @@ impl<'ll> FunctionCodeGen<'ll> {
             let cur_ctx = self.control_stack.last().unwrap();
 
             if cur_ctx.is_reachable() {
-                (cur_ctx.end_block, cur_ctx.end_PHIs)
+                (cur_ctx.end_block, cur_ctx.end_PHIs.clone())
             } else {
                 return;
             }
         };
 
-        if let Some(PHI) = end_PHIs {
-            let res = self.stack.pop().unwrap();
-            PHI.add_incoming(
-                ctx.coerce_to_canonical_type(self.builder, res),
-                // TODO: handle the None value of insert block
-                self.builder.get_insert_block(),
-            );
-        }
+        let results = self.pop_multi(end_PHIs.len());
+        self.add_PHI_incomings(ctx, end_PHIs.into_iter(), &results);
 
         self.builder.create_br_instr(end_block);
     }
@@ -319,6 +391,21 @@ impl<'ll> FunctionCodeGen<'ll> {
         &self.branch_target_stack[self.branch_target_stack.len() - depth as usize - 1]
     }
 
+    // The landing pad which the calls emitted now unwind to, which is the one of the innermost
+    // try block whose body is being emitted, if any.
+    pub fn get_landing_pad(&self) -> Option<BasicBlock<'ll>> {
+        self.find_landing_pad(self.control_stack.len())
+    }
+
+    // Like `get_landing_pad`, but only looks at the first `num_contexts` control contexts.
+    pub fn find_landing_pad(&self, num_contexts: usize) -> Option<BasicBlock<'ll>> {
+        self.control_stack[..num_contexts]
+            .iter()
+            .rev()
+            .find(|t| t.ty == ContorlContextType::Try)
+            .map(|t| t.try_ctx.as_ref().unwrap().landing_pad)
+    }
+
     pub fn enter_unreachable(&mut self) {
         let cur_ctx = self.control_stack.last_mut().unwrap();
         assert!(cur_ctx.outer_stack_size <= self.stack.len());
@@ -329,10 +416,72 @@ impl<'ll> FunctionCodeGen<'ll> {
 
     pub fn emit_runtime_intrinsic(
         &self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
         name: &str,
         ty: FunctionType,
-        args: Vec<Value>,
-    ) -> Vec<Value> {
-        unimplemented!()
+        args: Vec<Value<'ll>>,
+    ) -> Vec<Value<'ll>> {
+        let intrinsic = module.get_intrinsic(ctx, name, &ty);
+        let mut call_args = vec![self.builder.create_load(self.ctx_ptr.unwrap())];
+        call_args.extend(args);
+        let res = ctx.emit_call_or_invoke(
+            intrinsic,
+            call_args,
+            WASMCallConv::Wasm,
+            self.builder,
+            None,
+        );
+        match ty.results().len() {
+            0 => Vec::new(),
+            1 => vec![res],
+            n => (0..n)
+                .map(|idx| self.builder.create_extract_value(res, idx as u32))
+                .collect(),
+        }
+    }
+
+    pub fn emit_llvm_intrinsic(
+        &self,
+        module: &ModuleCodeGen<'ll>,
+        name: &str,
+        res_type: Type<'ll>,
+        args: &[Value<'ll>],
+    ) -> Value<'ll> {
+        let param_types = args.iter().map(|v| v.get_type()).collect::<Vec<_>>();
+        let intrinsic = module.get_llvm_intrinsic(name, Type::function(res_type, &param_types));
+        Value::from(*self.builder.create_call(intrinsic, args))
+    }
+
+    // Branches to a block calling the trap intrinsic `name` when `cond` is true, and continues
+    // emitting code in the fall-through block otherwise.
+    pub fn emit_conditional_trap(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        cond: Value<'ll>,
+        name: &str,
+    ) {
+        let trap_block = ctx.create_basic_block(name, self);
+        let continue_block = ctx.create_basic_block("", self);
+        self.builder
+            .create_cond_br_instr(cond, trap_block, continue_block);
+
+        self.builder.set_insert_block(trap_block);
+        self.emit_trap(ctx, module, name);
+
+        self.builder.set_insert_block(continue_block);
+    }
+
+    // Calls the trap intrinsic `name`, which never returns.
+    pub fn emit_trap(&self, ctx: &ContextCodeGen<'ll>, module: &ModuleCodeGen<'ll>, name: &str) {
+        self.emit_runtime_intrinsic(
+            ctx,
+            module,
+            name,
+            FunctionType::new(vec![ValueType::I32], vec![]),
+            vec![I32::from(self.index as i32).emit_const(ctx)],
+        );
+        self.builder.create_unreachable();
     }
 }
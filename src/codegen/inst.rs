@@ -1,4 +1,6 @@
 // use llvm_sys::prelude::LLVMValueRef;
+use super::call_conv::CallConv;
+use super::Value;
 use crate::llvm;
 use crate::wasm::call_conv::CallConv as WASMCallConv;
 
@@ -6,12 +8,19 @@ define_type_wrapper!(pub CallInst, llvm::Value);
 
 impl<'ll> CallInst<'ll> {
     pub fn set_call_conv(&self, call_conv: WASMCallConv) {
-        let cc = match call_conv {
-            WASMCallConv::Wasm => llvm::CallConv::FastCallConv,
-            _ => llvm::CallConv::CCallConv,
-        };
+        // The calling convention must match the one of the callee's declaration, which is
+        // always the default one for now.
+        let CallConv(cc) = CallConv::from(call_conv);
         unsafe {
             llvm::LLVMSetInstructionCallConv(self.0, cc as u32);
         }
     }
 }
+
+define_type_wrapper!(pub LandingPadInst, llvm::Value);
+
+impl<'ll> LandingPadInst<'ll> {
+    pub fn add_clause(&self, clause: Value<'ll>) {
+        unsafe { llvm::LLVMAddClause(self.0, *clause) }
+    }
+}
@@ -19,6 +19,54 @@ macro_rules! decode_instr {
     };
 }
 
+macro_rules! decode_operator {
+    (($self:ident, $ctx:expr, $wasm:expr, $mod:expr, $var:expr), $instr:ident, $name:ident) => {
+        if let $crate::wasm::Operator::$instr = $var {
+            $self.$name($ctx, $wasm, $mod);
+            return;
+        };
+    };
+    (($self:ident, $ctx:expr, $wasm:expr, $mod:expr, $var:expr), $instr:ident, $name:ident, $arg1:ty) => {
+        if let $crate::wasm::Operator::$instr(_arg1) = $var {
+            $self.$name($ctx, $wasm, $mod, _arg1);
+            return;
+        };
+    };
+    (($self:ident, $ctx:expr, $wasm:expr, $mod:expr, $var:expr), $instr:ident, $name:ident, $arg1:ty, $arg2:ty) => {
+        if let $crate::wasm::Operator::$instr(_arg1, _arg2) = $var {
+            $self.$name($ctx, $wasm, $mod, _arg1, _arg2);
+            return;
+        };
+    };
+}
+
+macro_rules! decode_simd {
+    (($self:ident, $ctx:expr, $wasm:expr, $mod:expr, $var:expr), $instr:ident, $name:ident) => {
+        if let $crate::wasm::SimdOperator::$instr = $var {
+            $self.$name($ctx, $wasm, $mod);
+            return;
+        };
+    };
+    (($self:ident, $ctx:expr, $wasm:expr, $mod:expr, $var:expr), $instr:ident, $name:ident, $arg1:ty) => {
+        if let $crate::wasm::SimdOperator::$instr(_arg1) = $var {
+            $self.$name($ctx, $wasm, $mod, _arg1);
+            return;
+        };
+    };
+    (($self:ident, $ctx:expr, $wasm:expr, $mod:expr, $var:expr), $instr:ident, $name:ident, $arg1:ty, $arg2:ty) => {
+        if let $crate::wasm::SimdOperator::$instr(_arg1, _arg2) = $var {
+            $self.$name($ctx, $wasm, $mod, _arg1, _arg2);
+            return;
+        };
+    };
+    (($self:ident, $ctx:expr, $wasm:expr, $mod:expr, $var:expr), $instr:ident, $name:ident, $arg1:ty, $arg2:ty, $arg3:ty) => {
+        if let $crate::wasm::SimdOperator::$instr(_arg1, _arg2, _arg3) = $var {
+            $self.$name($ctx, $wasm, $mod, _arg1, _arg2, _arg3);
+            return;
+        };
+    };
+}
+
 macro_rules! declear_op {
     ($var:tt, $instr:ident, $name:ident) => {
         fn $name(&mut self,
@@ -40,6 +88,13 @@ macro_rules! declear_op {
             _: &$crate::codegen::ModuleCodeGen<'ll>,
             _: $arg1, _: $arg2);
     };
+    ($var:tt, $instr:ident, $name:ident, $arg1:ty, $arg2:ty, $arg3:ty) => {
+        fn $name(&mut self,
+            _: &$crate::codegen::ContextCodeGen<'ll>,
+            _: &$crate::wasm::Module,
+            _: &$crate::codegen::ModuleCodeGen<'ll>,
+            _: $arg1, _: $arg2, _: $arg3);
+    };
 }
 
 macro_rules! declare_variable_instrs {
@@ -49,7 +104,9 @@ macro_rules! declare_variable_instrs {
     ($op:ident, $var:tt) => {
         $op!($var, GetLocal, get_local, u32);
         $op!($var, SetLocal, set_local, u32);
+        $op!($var, TeeLocal, tee_local, u32);
         $op!($var, GetGlobal, get_global, u32);
+        $op!($var, SetGlobal, set_global, u32);
     };
 }
 
@@ -63,6 +120,129 @@ macro_rules! declare_numeric_instrs {
         $op!($var, F32Const, f32_const, u32);
         $op!($var, F64Const, f64_const, u64);
         $op!($var, V128Const, v128_const, Box<[u8; 16]>);
+        $op!($var, I32Add, i32_add);
+        $op!($var, I32Sub, i32_sub);
+        $op!($var, I32Mul, i32_mul);
+        $op!($var, I32DivS, i32_div_s);
+        $op!($var, I32DivU, i32_div_u);
+        $op!($var, I32RemS, i32_rem_s);
+        $op!($var, I32RemU, i32_rem_u);
+        $op!($var, I32And, i32_and);
+        $op!($var, I32Or, i32_or);
+        $op!($var, I32Xor, i32_xor);
+        $op!($var, I32Shl, i32_shl);
+        $op!($var, I32ShrS, i32_shr_s);
+        $op!($var, I32ShrU, i32_shr_u);
+        $op!($var, I32Rotl, i32_rotl);
+        $op!($var, I32Rotr, i32_rotr);
+        $op!($var, I32Clz, i32_clz);
+        $op!($var, I32Ctz, i32_ctz);
+        $op!($var, I32Popcnt, i32_popcnt);
+        $op!($var, I32Eqz, i32_eqz);
+        $op!($var, I32Eq, i32_eq);
+        $op!($var, I32Ne, i32_ne);
+        $op!($var, I32LtS, i32_lt_s);
+        $op!($var, I32LtU, i32_lt_u);
+        $op!($var, I32GtS, i32_gt_s);
+        $op!($var, I32GtU, i32_gt_u);
+        $op!($var, I32LeS, i32_le_s);
+        $op!($var, I32LeU, i32_le_u);
+        $op!($var, I32GeS, i32_ge_s);
+        $op!($var, I32GeU, i32_ge_u);
+        $op!($var, I64Add, i64_add);
+        $op!($var, I64Sub, i64_sub);
+        $op!($var, I64Mul, i64_mul);
+        $op!($var, I64DivS, i64_div_s);
+        $op!($var, I64DivU, i64_div_u);
+        $op!($var, I64RemS, i64_rem_s);
+        $op!($var, I64RemU, i64_rem_u);
+        $op!($var, I64And, i64_and);
+        $op!($var, I64Or, i64_or);
+        $op!($var, I64Xor, i64_xor);
+        $op!($var, I64Shl, i64_shl);
+        $op!($var, I64ShrS, i64_shr_s);
+        $op!($var, I64ShrU, i64_shr_u);
+        $op!($var, I64Rotl, i64_rotl);
+        $op!($var, I64Rotr, i64_rotr);
+        $op!($var, I64Clz, i64_clz);
+        $op!($var, I64Ctz, i64_ctz);
+        $op!($var, I64Popcnt, i64_popcnt);
+        $op!($var, I64Eqz, i64_eqz);
+        $op!($var, I64Eq, i64_eq);
+        $op!($var, I64Ne, i64_ne);
+        $op!($var, I64LtS, i64_lt_s);
+        $op!($var, I64LtU, i64_lt_u);
+        $op!($var, I64GtS, i64_gt_s);
+        $op!($var, I64GtU, i64_gt_u);
+        $op!($var, I64LeS, i64_le_s);
+        $op!($var, I64LeU, i64_le_u);
+        $op!($var, I64GeS, i64_ge_s);
+        $op!($var, I64GeU, i64_ge_u);
+        $op!($var, F32Abs, f32_abs);
+        $op!($var, F32Neg, f32_neg);
+        $op!($var, F32Ceil, f32_ceil);
+        $op!($var, F32Floor, f32_floor);
+        $op!($var, F32Trunc, f32_trunc);
+        $op!($var, F32Nearest, f32_nearest);
+        $op!($var, F32Sqrt, f32_sqrt);
+        $op!($var, F32Add, f32_add);
+        $op!($var, F32Sub, f32_sub);
+        $op!($var, F32Mul, f32_mul);
+        $op!($var, F32Div, f32_div);
+        $op!($var, F32Min, f32_min);
+        $op!($var, F32Max, f32_max);
+        $op!($var, F32Copysign, f32_copysign);
+        $op!($var, F32Eq, f32_eq);
+        $op!($var, F32Ne, f32_ne);
+        $op!($var, F32Lt, f32_lt);
+        $op!($var, F32Gt, f32_gt);
+        $op!($var, F32Le, f32_le);
+        $op!($var, F32Ge, f32_ge);
+        $op!($var, F64Abs, f64_abs);
+        $op!($var, F64Neg, f64_neg);
+        $op!($var, F64Ceil, f64_ceil);
+        $op!($var, F64Floor, f64_floor);
+        $op!($var, F64Trunc, f64_trunc);
+        $op!($var, F64Nearest, f64_nearest);
+        $op!($var, F64Sqrt, f64_sqrt);
+        $op!($var, F64Add, f64_add);
+        $op!($var, F64Sub, f64_sub);
+        $op!($var, F64Mul, f64_mul);
+        $op!($var, F64Div, f64_div);
+        $op!($var, F64Min, f64_min);
+        $op!($var, F64Max, f64_max);
+        $op!($var, F64Copysign, f64_copysign);
+        $op!($var, F64Eq, f64_eq);
+        $op!($var, F64Ne, f64_ne);
+        $op!($var, F64Lt, f64_lt);
+        $op!($var, F64Gt, f64_gt);
+        $op!($var, F64Le, f64_le);
+        $op!($var, F64Ge, f64_ge);
+        $op!($var, I32WrapI64, i32_wrap_i64);
+        $op!($var, I32TruncSF32, i32_trunc_s_f32);
+        $op!($var, I32TruncUF32, i32_trunc_u_f32);
+        $op!($var, I32TruncSF64, i32_trunc_s_f64);
+        $op!($var, I32TruncUF64, i32_trunc_u_f64);
+        $op!($var, I64ExtendSI32, i64_extend_s_i32);
+        $op!($var, I64ExtendUI32, i64_extend_u_i32);
+        $op!($var, I64TruncSF32, i64_trunc_s_f32);
+        $op!($var, I64TruncUF32, i64_trunc_u_f32);
+        $op!($var, I64TruncSF64, i64_trunc_s_f64);
+        $op!($var, I64TruncUF64, i64_trunc_u_f64);
+        $op!($var, F32ConvertSI32, f32_convert_s_i32);
+        $op!($var, F32ConvertUI32, f32_convert_u_i32);
+        $op!($var, F32ConvertSI64, f32_convert_s_i64);
+        $op!($var, F32ConvertUI64, f32_convert_u_i64);
+        $op!($var, F32DemoteF64, f32_demote_f64);
+        $op!($var, F64ConvertSI32, f64_convert_s_i32);
+        $op!($var, F64ConvertUI32, f64_convert_u_i32);
+        $op!($var, F64ConvertSI64, f64_convert_s_i64);
+        $op!($var, F64ConvertUI64, f64_convert_u_i64);
+        $op!($var, F64PromoteF32, f64_promote_f32);
+        $op!($var, I32ReinterpretF32, i32_reinterpret_f32);
+        $op!($var, I64ReinterpretF64, i64_reinterpret_f64);
+        $op!($var, F32ReinterpretI32, f32_reinterpret_i32);
+        $op!($var, F64ReinterpretI64, f64_reinterpret_i64);
     };
 }
 
@@ -71,9 +251,6 @@ macro_rules! declare_control_instrs {
         declare_control_instrs!($op, _);
     };
     ($op:ident, $var:tt) => {
-        $op!($var, Block, block, BlockType);
-        $op!($var, Loop, loop_, BlockType);
-        $op!($var, If, if_, BlockType);
         $op!($var, Else, else_);
         $op!($var, End, end);
         $op!($var, Br, br, u32);
@@ -82,19 +259,426 @@ macro_rules! declare_control_instrs {
         $op!($var, BrTable, br_table, Box<BrTableData>);
         $op!($var, Call, call, u32);
         $op!($var, Unreachable, unreachable_);
-        $op!($var, CallIndirect, call_indirect, u32, u8);
         $op!($var, Nop, nop);
         $op!($var, Drop, drop);
         $op!($var, Select, select_);
     };
 }
 
+// The instructions which begin a block, which are decoded as `wasm::Operator`s since their
+// block types may be type indices.
+macro_rules! declare_block_instrs {
+    ($op:ident) => {
+        declare_block_instrs!($op, _);
+    };
+    ($op:ident, $var:tt) => {
+        $op!($var, Block, block, BlockType);
+        $op!($var, Loop, loop_, BlockType);
+        $op!($var, If, if_, BlockType);
+    };
+}
+
+// The instructions of the exception handling proposal, which are decoded as `wasm::Operator`s
+// rather than `wasm::Instruction`s.
+macro_rules! declare_exception_instrs {
+    ($op:ident) => {
+        declare_exception_instrs!($op, _);
+    };
+    ($op:ident, $var:tt) => {
+        $op!($var, Try, try_, BlockType);
+        $op!($var, Catch, catch, u32);
+        $op!($var, CatchAll, catch_all);
+        $op!($var, Throw, throw, u32);
+        $op!($var, Rethrow, rethrow, u32);
+        $op!($var, Delegate, delegate, u32);
+    };
+}
+
+// The instructions of the reference types proposal, and `call_indirect` whose table index it
+// allows, which are decoded as `wasm::Operator`s.
+macro_rules! declare_ref_instrs {
+    ($op:ident) => {
+        declare_ref_instrs!($op, _);
+    };
+    ($op:ident, $var:tt) => {
+        $op!($var, CallIndirect, call_indirect, u32, u32);
+        $op!($var, SelectTyped, select_typed, Vec<ValueType>);
+        $op!($var, RefNull, ref_null, ValueType);
+        $op!($var, RefIsNull, ref_is_null);
+        $op!($var, RefFunc, ref_func, u32);
+        $op!($var, TableGet, table_get, u32);
+        $op!($var, TableSet, table_set, u32);
+        $op!($var, TableSize, table_size, u32);
+        $op!($var, TableGrow, table_grow, u32);
+        $op!($var, TableFill, table_fill, u32);
+    };
+}
+
+// The instructions of the bulk memory proposal, which are decoded as `wasm::Operator`s.
+macro_rules! declare_bulk_instrs {
+    ($op:ident) => {
+        declare_bulk_instrs!($op, _);
+    };
+    ($op:ident, $var:tt) => {
+        $op!($var, MemoryInit, memory_init, u32, u32);
+        $op!($var, DataDrop, data_drop, u32);
+        $op!($var, MemoryCopy, memory_copy, u32, u32);
+        $op!($var, MemoryFill, memory_fill, u32);
+        $op!($var, TableInit, table_init, u32, u32);
+        $op!($var, ElemDrop, elem_drop, u32);
+        $op!($var, TableCopy, table_copy, u32, u32);
+    };
+}
+
+// The instructions of the SIMD proposal, which are decoded as `wasm::SimdOperator`s.
+macro_rules! declare_simd_instrs {
+    ($op:ident) => {
+        declare_simd_instrs!($op, _);
+    };
+    ($op:ident, $var:tt) => {
+        $op!($var, V128Load, v128_load, $crate::wasm::MemArg);
+        $op!($var, V128Load8x8S, v128_load8x8_s, $crate::wasm::MemArg);
+        $op!($var, V128Load8x8U, v128_load8x8_u, $crate::wasm::MemArg);
+        $op!($var, V128Load16x4S, v128_load16x4_s, $crate::wasm::MemArg);
+        $op!($var, V128Load16x4U, v128_load16x4_u, $crate::wasm::MemArg);
+        $op!($var, V128Load32x2S, v128_load32x2_s, $crate::wasm::MemArg);
+        $op!($var, V128Load32x2U, v128_load32x2_u, $crate::wasm::MemArg);
+        $op!($var, V128Load8Splat, v128_load8_splat, $crate::wasm::MemArg);
+        $op!($var, V128Load16Splat, v128_load16_splat, $crate::wasm::MemArg);
+        $op!($var, V128Load32Splat, v128_load32_splat, $crate::wasm::MemArg);
+        $op!($var, V128Load64Splat, v128_load64_splat, $crate::wasm::MemArg);
+        $op!($var, V128Store, v128_store, $crate::wasm::MemArg);
+        $op!($var, I8x16Shuffle, i8x16_shuffle, Box<[u8; 16]>);
+        $op!($var, I8x16Swizzle, i8x16_swizzle);
+        $op!($var, I8x16Splat, i8x16_splat);
+        $op!($var, I16x8Splat, i16x8_splat);
+        $op!($var, I32x4Splat, i32x4_splat);
+        $op!($var, I64x2Splat, i64x2_splat);
+        $op!($var, F32x4Splat, f32x4_splat);
+        $op!($var, F64x2Splat, f64x2_splat);
+        $op!($var, I8x16ExtractLaneS, i8x16_extract_lane_s, u8);
+        $op!($var, I8x16ExtractLaneU, i8x16_extract_lane_u, u8);
+        $op!($var, I8x16ReplaceLane, i8x16_replace_lane, u8);
+        $op!($var, I16x8ExtractLaneS, i16x8_extract_lane_s, u8);
+        $op!($var, I16x8ExtractLaneU, i16x8_extract_lane_u, u8);
+        $op!($var, I16x8ReplaceLane, i16x8_replace_lane, u8);
+        $op!($var, I32x4ExtractLane, i32x4_extract_lane, u8);
+        $op!($var, I32x4ReplaceLane, i32x4_replace_lane, u8);
+        $op!($var, I64x2ExtractLane, i64x2_extract_lane, u8);
+        $op!($var, I64x2ReplaceLane, i64x2_replace_lane, u8);
+        $op!($var, F32x4ExtractLane, f32x4_extract_lane, u8);
+        $op!($var, F32x4ReplaceLane, f32x4_replace_lane, u8);
+        $op!($var, F64x2ExtractLane, f64x2_extract_lane, u8);
+        $op!($var, F64x2ReplaceLane, f64x2_replace_lane, u8);
+        $op!($var, I8x16Eq, i8x16_eq);
+        $op!($var, I8x16Ne, i8x16_ne);
+        $op!($var, I8x16LtS, i8x16_lt_s);
+        $op!($var, I8x16LtU, i8x16_lt_u);
+        $op!($var, I8x16GtS, i8x16_gt_s);
+        $op!($var, I8x16GtU, i8x16_gt_u);
+        $op!($var, I8x16LeS, i8x16_le_s);
+        $op!($var, I8x16LeU, i8x16_le_u);
+        $op!($var, I8x16GeS, i8x16_ge_s);
+        $op!($var, I8x16GeU, i8x16_ge_u);
+        $op!($var, I16x8Eq, i16x8_eq);
+        $op!($var, I16x8Ne, i16x8_ne);
+        $op!($var, I16x8LtS, i16x8_lt_s);
+        $op!($var, I16x8LtU, i16x8_lt_u);
+        $op!($var, I16x8GtS, i16x8_gt_s);
+        $op!($var, I16x8GtU, i16x8_gt_u);
+        $op!($var, I16x8LeS, i16x8_le_s);
+        $op!($var, I16x8LeU, i16x8_le_u);
+        $op!($var, I16x8GeS, i16x8_ge_s);
+        $op!($var, I16x8GeU, i16x8_ge_u);
+        $op!($var, I32x4Eq, i32x4_eq);
+        $op!($var, I32x4Ne, i32x4_ne);
+        $op!($var, I32x4LtS, i32x4_lt_s);
+        $op!($var, I32x4LtU, i32x4_lt_u);
+        $op!($var, I32x4GtS, i32x4_gt_s);
+        $op!($var, I32x4GtU, i32x4_gt_u);
+        $op!($var, I32x4LeS, i32x4_le_s);
+        $op!($var, I32x4LeU, i32x4_le_u);
+        $op!($var, I32x4GeS, i32x4_ge_s);
+        $op!($var, I32x4GeU, i32x4_ge_u);
+        $op!($var, F32x4Eq, f32x4_eq);
+        $op!($var, F32x4Ne, f32x4_ne);
+        $op!($var, F32x4Lt, f32x4_lt);
+        $op!($var, F32x4Gt, f32x4_gt);
+        $op!($var, F32x4Le, f32x4_le);
+        $op!($var, F32x4Ge, f32x4_ge);
+        $op!($var, F64x2Eq, f64x2_eq);
+        $op!($var, F64x2Ne, f64x2_ne);
+        $op!($var, F64x2Lt, f64x2_lt);
+        $op!($var, F64x2Gt, f64x2_gt);
+        $op!($var, F64x2Le, f64x2_le);
+        $op!($var, F64x2Ge, f64x2_ge);
+        $op!($var, V128Not, v128_not);
+        $op!($var, V128And, v128_and);
+        $op!($var, V128AndNot, v128_andnot);
+        $op!($var, V128Or, v128_or);
+        $op!($var, V128Xor, v128_xor);
+        $op!($var, V128Bitselect, v128_bitselect);
+        $op!($var, V128AnyTrue, v128_any_true);
+        $op!($var, V128Load8Lane, v128_load8_lane, $crate::wasm::MemArg, u8);
+        $op!($var, V128Load16Lane, v128_load16_lane, $crate::wasm::MemArg, u8);
+        $op!($var, V128Load32Lane, v128_load32_lane, $crate::wasm::MemArg, u8);
+        $op!($var, V128Load64Lane, v128_load64_lane, $crate::wasm::MemArg, u8);
+        $op!($var, V128Store8Lane, v128_store8_lane, $crate::wasm::MemArg, u8);
+        $op!($var, V128Store16Lane, v128_store16_lane, $crate::wasm::MemArg, u8);
+        $op!($var, V128Store32Lane, v128_store32_lane, $crate::wasm::MemArg, u8);
+        $op!($var, V128Store64Lane, v128_store64_lane, $crate::wasm::MemArg, u8);
+        $op!($var, V128Load32Zero, v128_load32_zero, $crate::wasm::MemArg);
+        $op!($var, V128Load64Zero, v128_load64_zero, $crate::wasm::MemArg);
+        $op!($var, F32x4DemoteF64x2Zero, f32x4_demote_f64x2_zero);
+        $op!($var, F64x2PromoteLowF32x4, f64x2_promote_low_f32x4);
+        $op!($var, I8x16Abs, i8x16_abs);
+        $op!($var, I8x16Neg, i8x16_neg);
+        $op!($var, I8x16Popcnt, i8x16_popcnt);
+        $op!($var, I8x16AllTrue, i8x16_all_true);
+        $op!($var, I8x16Bitmask, i8x16_bitmask);
+        $op!($var, I8x16NarrowI16x8S, i8x16_narrow_i16x8_s);
+        $op!($var, I8x16NarrowI16x8U, i8x16_narrow_i16x8_u);
+        $op!($var, F32x4Ceil, f32x4_ceil);
+        $op!($var, F32x4Floor, f32x4_floor);
+        $op!($var, F32x4Trunc, f32x4_trunc);
+        $op!($var, F32x4Nearest, f32x4_nearest);
+        $op!($var, I8x16Shl, i8x16_shl);
+        $op!($var, I8x16ShrS, i8x16_shr_s);
+        $op!($var, I8x16ShrU, i8x16_shr_u);
+        $op!($var, I8x16Add, i8x16_add);
+        $op!($var, I8x16AddSatS, i8x16_add_sat_s);
+        $op!($var, I8x16AddSatU, i8x16_add_sat_u);
+        $op!($var, I8x16Sub, i8x16_sub);
+        $op!($var, I8x16SubSatS, i8x16_sub_sat_s);
+        $op!($var, I8x16SubSatU, i8x16_sub_sat_u);
+        $op!($var, F64x2Ceil, f64x2_ceil);
+        $op!($var, F64x2Floor, f64x2_floor);
+        $op!($var, I8x16MinS, i8x16_min_s);
+        $op!($var, I8x16MinU, i8x16_min_u);
+        $op!($var, I8x16MaxS, i8x16_max_s);
+        $op!($var, I8x16MaxU, i8x16_max_u);
+        $op!($var, F64x2Trunc, f64x2_trunc);
+        $op!($var, I8x16AvgrU, i8x16_avgr_u);
+        $op!($var, I16x8ExtaddPairwiseI8x16S, i16x8_extadd_pairwise_i8x16_s);
+        $op!($var, I16x8ExtaddPairwiseI8x16U, i16x8_extadd_pairwise_i8x16_u);
+        $op!($var, I32x4ExtaddPairwiseI16x8S, i32x4_extadd_pairwise_i16x8_s);
+        $op!($var, I32x4ExtaddPairwiseI16x8U, i32x4_extadd_pairwise_i16x8_u);
+        $op!($var, I16x8Abs, i16x8_abs);
+        $op!($var, I16x8Neg, i16x8_neg);
+        $op!($var, I16x8Q15MulrSatS, i16x8_q15mulr_sat_s);
+        $op!($var, I16x8AllTrue, i16x8_all_true);
+        $op!($var, I16x8Bitmask, i16x8_bitmask);
+        $op!($var, I16x8NarrowI32x4S, i16x8_narrow_i32x4_s);
+        $op!($var, I16x8NarrowI32x4U, i16x8_narrow_i32x4_u);
+        $op!($var, I16x8ExtendLowI8x16S, i16x8_extend_low_i8x16_s);
+        $op!($var, I16x8ExtendHighI8x16S, i16x8_extend_high_i8x16_s);
+        $op!($var, I16x8ExtendLowI8x16U, i16x8_extend_low_i8x16_u);
+        $op!($var, I16x8ExtendHighI8x16U, i16x8_extend_high_i8x16_u);
+        $op!($var, I16x8Shl, i16x8_shl);
+        $op!($var, I16x8ShrS, i16x8_shr_s);
+        $op!($var, I16x8ShrU, i16x8_shr_u);
+        $op!($var, I16x8Add, i16x8_add);
+        $op!($var, I16x8AddSatS, i16x8_add_sat_s);
+        $op!($var, I16x8AddSatU, i16x8_add_sat_u);
+        $op!($var, I16x8Sub, i16x8_sub);
+        $op!($var, I16x8SubSatS, i16x8_sub_sat_s);
+        $op!($var, I16x8SubSatU, i16x8_sub_sat_u);
+        $op!($var, F64x2Nearest, f64x2_nearest);
+        $op!($var, I16x8Mul, i16x8_mul);
+        $op!($var, I16x8MinS, i16x8_min_s);
+        $op!($var, I16x8MinU, i16x8_min_u);
+        $op!($var, I16x8MaxS, i16x8_max_s);
+        $op!($var, I16x8MaxU, i16x8_max_u);
+        $op!($var, I16x8AvgrU, i16x8_avgr_u);
+        $op!($var, I16x8ExtmulLowI8x16S, i16x8_extmul_low_i8x16_s);
+        $op!($var, I16x8ExtmulHighI8x16S, i16x8_extmul_high_i8x16_s);
+        $op!($var, I16x8ExtmulLowI8x16U, i16x8_extmul_low_i8x16_u);
+        $op!($var, I16x8ExtmulHighI8x16U, i16x8_extmul_high_i8x16_u);
+        $op!($var, I32x4Abs, i32x4_abs);
+        $op!($var, I32x4Neg, i32x4_neg);
+        $op!($var, I32x4AllTrue, i32x4_all_true);
+        $op!($var, I32x4Bitmask, i32x4_bitmask);
+        $op!($var, I32x4ExtendLowI16x8S, i32x4_extend_low_i16x8_s);
+        $op!($var, I32x4ExtendHighI16x8S, i32x4_extend_high_i16x8_s);
+        $op!($var, I32x4ExtendLowI16x8U, i32x4_extend_low_i16x8_u);
+        $op!($var, I32x4ExtendHighI16x8U, i32x4_extend_high_i16x8_u);
+        $op!($var, I32x4Shl, i32x4_shl);
+        $op!($var, I32x4ShrS, i32x4_shr_s);
+        $op!($var, I32x4ShrU, i32x4_shr_u);
+        $op!($var, I32x4Add, i32x4_add);
+        $op!($var, I32x4Sub, i32x4_sub);
+        $op!($var, I32x4Mul, i32x4_mul);
+        $op!($var, I32x4MinS, i32x4_min_s);
+        $op!($var, I32x4MinU, i32x4_min_u);
+        $op!($var, I32x4MaxS, i32x4_max_s);
+        $op!($var, I32x4MaxU, i32x4_max_u);
+        $op!($var, I32x4DotI16x8S, i32x4_dot_i16x8_s);
+        $op!($var, I32x4ExtmulLowI16x8S, i32x4_extmul_low_i16x8_s);
+        $op!($var, I32x4ExtmulHighI16x8S, i32x4_extmul_high_i16x8_s);
+        $op!($var, I32x4ExtmulLowI16x8U, i32x4_extmul_low_i16x8_u);
+        $op!($var, I32x4ExtmulHighI16x8U, i32x4_extmul_high_i16x8_u);
+        $op!($var, I64x2Abs, i64x2_abs);
+        $op!($var, I64x2Neg, i64x2_neg);
+        $op!($var, I64x2AllTrue, i64x2_all_true);
+        $op!($var, I64x2Bitmask, i64x2_bitmask);
+        $op!($var, I64x2ExtendLowI32x4S, i64x2_extend_low_i32x4_s);
+        $op!($var, I64x2ExtendHighI32x4S, i64x2_extend_high_i32x4_s);
+        $op!($var, I64x2ExtendLowI32x4U, i64x2_extend_low_i32x4_u);
+        $op!($var, I64x2ExtendHighI32x4U, i64x2_extend_high_i32x4_u);
+        $op!($var, I64x2Shl, i64x2_shl);
+        $op!($var, I64x2ShrS, i64x2_shr_s);
+        $op!($var, I64x2ShrU, i64x2_shr_u);
+        $op!($var, I64x2Add, i64x2_add);
+        $op!($var, I64x2Sub, i64x2_sub);
+        $op!($var, I64x2Mul, i64x2_mul);
+        $op!($var, I64x2Eq, i64x2_eq);
+        $op!($var, I64x2Ne, i64x2_ne);
+        $op!($var, I64x2LtS, i64x2_lt_s);
+        $op!($var, I64x2GtS, i64x2_gt_s);
+        $op!($var, I64x2LeS, i64x2_le_s);
+        $op!($var, I64x2GeS, i64x2_ge_s);
+        $op!($var, I64x2ExtmulLowI32x4S, i64x2_extmul_low_i32x4_s);
+        $op!($var, I64x2ExtmulHighI32x4S, i64x2_extmul_high_i32x4_s);
+        $op!($var, I64x2ExtmulLowI32x4U, i64x2_extmul_low_i32x4_u);
+        $op!($var, I64x2ExtmulHighI32x4U, i64x2_extmul_high_i32x4_u);
+        $op!($var, F32x4Abs, f32x4_abs);
+        $op!($var, F32x4Neg, f32x4_neg);
+        $op!($var, F32x4Sqrt, f32x4_sqrt);
+        $op!($var, F32x4Add, f32x4_add);
+        $op!($var, F32x4Sub, f32x4_sub);
+        $op!($var, F32x4Mul, f32x4_mul);
+        $op!($var, F32x4Div, f32x4_div);
+        $op!($var, F32x4Min, f32x4_min);
+        $op!($var, F32x4Max, f32x4_max);
+        $op!($var, F32x4Pmin, f32x4_pmin);
+        $op!($var, F32x4Pmax, f32x4_pmax);
+        $op!($var, F64x2Abs, f64x2_abs);
+        $op!($var, F64x2Neg, f64x2_neg);
+        $op!($var, F64x2Sqrt, f64x2_sqrt);
+        $op!($var, F64x2Add, f64x2_add);
+        $op!($var, F64x2Sub, f64x2_sub);
+        $op!($var, F64x2Mul, f64x2_mul);
+        $op!($var, F64x2Div, f64x2_div);
+        $op!($var, F64x2Min, f64x2_min);
+        $op!($var, F64x2Max, f64x2_max);
+        $op!($var, F64x2Pmin, f64x2_pmin);
+        $op!($var, F64x2Pmax, f64x2_pmax);
+        $op!($var, I32x4TruncSatF32x4S, i32x4_trunc_sat_f32x4_s);
+        $op!($var, I32x4TruncSatF32x4U, i32x4_trunc_sat_f32x4_u);
+        $op!($var, F32x4ConvertI32x4S, f32x4_convert_i32x4_s);
+        $op!($var, F32x4ConvertI32x4U, f32x4_convert_i32x4_u);
+        $op!($var, I32x4TruncSatF64x2SZero, i32x4_trunc_sat_f64x2_s_zero);
+        $op!($var, I32x4TruncSatF64x2UZero, i32x4_trunc_sat_f64x2_u_zero);
+        $op!($var, F64x2ConvertLowI32x4S, f64x2_convert_low_i32x4_s);
+        $op!($var, F64x2ConvertLowI32x4U, f64x2_convert_low_i32x4_u);
+    };
+}
+
 macro_rules! declare_memory_instrs {
     ($op:ident) => {
         declare_memory_instrs!($op, _);
     };
     ($op:ident, $var:tt) => {
-        $op!($var, I32Load, i32_load, u32, u32);
+        $op!($var, I32Load, i32_load, $crate::wasm::MemArg);
+        $op!($var, I64Load, i64_load, $crate::wasm::MemArg);
+        $op!($var, F32Load, f32_load, $crate::wasm::MemArg);
+        $op!($var, F64Load, f64_load, $crate::wasm::MemArg);
+        $op!($var, I32Load8S, i32_load8_s, $crate::wasm::MemArg);
+        $op!($var, I32Load8U, i32_load8_u, $crate::wasm::MemArg);
+        $op!($var, I32Load16S, i32_load16_s, $crate::wasm::MemArg);
+        $op!($var, I32Load16U, i32_load16_u, $crate::wasm::MemArg);
+        $op!($var, I64Load8S, i64_load8_s, $crate::wasm::MemArg);
+        $op!($var, I64Load8U, i64_load8_u, $crate::wasm::MemArg);
+        $op!($var, I64Load16S, i64_load16_s, $crate::wasm::MemArg);
+        $op!($var, I64Load16U, i64_load16_u, $crate::wasm::MemArg);
+        $op!($var, I64Load32S, i64_load32_s, $crate::wasm::MemArg);
+        $op!($var, I64Load32U, i64_load32_u, $crate::wasm::MemArg);
+        $op!($var, I32Store, i32_store, $crate::wasm::MemArg);
+        $op!($var, I64Store, i64_store, $crate::wasm::MemArg);
+        $op!($var, F32Store, f32_store, $crate::wasm::MemArg);
+        $op!($var, F64Store, f64_store, $crate::wasm::MemArg);
+        $op!($var, I32Store8, i32_store8, $crate::wasm::MemArg);
+        $op!($var, I32Store16, i32_store16, $crate::wasm::MemArg);
+        $op!($var, I64Store8, i64_store8, $crate::wasm::MemArg);
+        $op!($var, I64Store16, i64_store16, $crate::wasm::MemArg);
+        $op!($var, I64Store32, i64_store32, $crate::wasm::MemArg);
+        $op!($var, CurrentMemory, current_memory, u32);
+        $op!($var, GrowMemory, grow_memory, u32);
+    };
+}
+
+// The instructions of the threads proposal except `atomic.fence`, which is an `Operator`.
+macro_rules! declare_atomic_instrs {
+    ($op:ident) => {
+        declare_atomic_instrs!($op, _);
+    };
+    ($op:ident, $var:tt) => {
+        $op!($var, AtomicWake, memory_atomic_notify, $crate::wasm::MemArg);
+        $op!($var, I32AtomicWait, memory_atomic_wait32, $crate::wasm::MemArg);
+        $op!($var, I64AtomicWait, memory_atomic_wait64, $crate::wasm::MemArg);
+        $op!($var, I32AtomicLoad, i32_atomic_load, $crate::wasm::MemArg);
+        $op!($var, I64AtomicLoad, i64_atomic_load, $crate::wasm::MemArg);
+        $op!($var, I32AtomicLoad8u, i32_atomic_load8_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicLoad16u, i32_atomic_load16_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicLoad8u, i64_atomic_load8_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicLoad16u, i64_atomic_load16_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicLoad32u, i64_atomic_load32_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicStore, i32_atomic_store, $crate::wasm::MemArg);
+        $op!($var, I64AtomicStore, i64_atomic_store, $crate::wasm::MemArg);
+        $op!($var, I32AtomicStore8u, i32_atomic_store8, $crate::wasm::MemArg);
+        $op!($var, I32AtomicStore16u, i32_atomic_store16, $crate::wasm::MemArg);
+        $op!($var, I64AtomicStore8u, i64_atomic_store8, $crate::wasm::MemArg);
+        $op!($var, I64AtomicStore16u, i64_atomic_store16, $crate::wasm::MemArg);
+        $op!($var, I64AtomicStore32u, i64_atomic_store32, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwAdd, i32_atomic_rmw_add, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwAdd, i64_atomic_rmw_add, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwAdd8u, i32_atomic_rmw8_add_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwAdd16u, i32_atomic_rmw16_add_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwAdd8u, i64_atomic_rmw8_add_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwAdd16u, i64_atomic_rmw16_add_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwAdd32u, i64_atomic_rmw32_add_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwSub, i32_atomic_rmw_sub, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwSub, i64_atomic_rmw_sub, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwSub8u, i32_atomic_rmw8_sub_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwSub16u, i32_atomic_rmw16_sub_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwSub8u, i64_atomic_rmw8_sub_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwSub16u, i64_atomic_rmw16_sub_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwSub32u, i64_atomic_rmw32_sub_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwAnd, i32_atomic_rmw_and, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwAnd, i64_atomic_rmw_and, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwAnd8u, i32_atomic_rmw8_and_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwAnd16u, i32_atomic_rmw16_and_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwAnd8u, i64_atomic_rmw8_and_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwAnd16u, i64_atomic_rmw16_and_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwAnd32u, i64_atomic_rmw32_and_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwOr, i32_atomic_rmw_or, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwOr, i64_atomic_rmw_or, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwOr8u, i32_atomic_rmw8_or_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwOr16u, i32_atomic_rmw16_or_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwOr8u, i64_atomic_rmw8_or_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwOr16u, i64_atomic_rmw16_or_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwOr32u, i64_atomic_rmw32_or_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwXor, i32_atomic_rmw_xor, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwXor, i64_atomic_rmw_xor, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwXor8u, i32_atomic_rmw8_xor_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwXor16u, i32_atomic_rmw16_xor_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwXor8u, i64_atomic_rmw8_xor_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwXor16u, i64_atomic_rmw16_xor_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwXor32u, i64_atomic_rmw32_xor_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwXchg, i32_atomic_rmw_xchg, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwXchg, i64_atomic_rmw_xchg, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwXchg8u, i32_atomic_rmw8_xchg_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwXchg16u, i32_atomic_rmw16_xchg_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwXchg8u, i64_atomic_rmw8_xchg_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwXchg16u, i64_atomic_rmw16_xchg_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwXchg32u, i64_atomic_rmw32_xchg_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwCmpxchg, i32_atomic_rmw_cmpxchg, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwCmpxchg, i64_atomic_rmw_cmpxchg, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwCmpxchg8u, i32_atomic_rmw8_cmpxchg_u, $crate::wasm::MemArg);
+        $op!($var, I32AtomicRmwCmpxchg16u, i32_atomic_rmw16_cmpxchg_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwCmpxchg8u, i64_atomic_rmw8_cmpxchg_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwCmpxchg16u, i64_atomic_rmw16_cmpxchg_u, $crate::wasm::MemArg);
+        $op!($var, I64AtomicRmwCmpxchg32u, i64_atomic_rmw32_cmpxchg_u, $crate::wasm::MemArg);
     };
 }
 
@@ -107,5 +691,6 @@ macro_rules! declear_instrs {
         declare_numeric_instrs!($op, $var);
         declare_variable_instrs!($op, $var);
         declare_memory_instrs!($op, $var);
+        declare_atomic_instrs!($op, $var);
     };
 }
@@ -1,28 +1,12 @@
 use super::_type::Type;
 use super::common::Literal;
 use super::value::Value;
-use super::{Builder, ContextCodeGen, FunctionCodeGen, ModuleCodeGen};
-use crate::wasm::types::I32;
-use crate::wasm::Module as WASMModule;
+use super::{BoundsCheckMode, Builder, ContextCodeGen, FunctionCodeGen, ModuleCodeGen};
+use crate::llvm::IntPredicate;
+use crate::wasm::types::{I32, I64};
+use crate::wasm::{FunctionType, MemArg, Module as WASMModule, ValueType};
 
-fn get_offset_and_bounded_addr<'ll>(
-    ctx: &ContextCodeGen<'ll>,
-    builder: Builder<'ll>,
-    addr: Value<'ll>,
-    offset: u32,
-) -> Value<'ll> {
-    let addr_64bit = builder.create_zext(addr, ctx.i64_type);
-    if offset != 0 {
-        builder.create_add(
-            addr_64bit,
-            builder.create_zext(I32::from(offset as i32).emit_const(ctx), ctx.i64_type),
-        )
-    } else {
-        addr_64bit
-    }
-}
-
-fn coerce_address_to_ptr<'ll>(
+pub(super) fn coerce_address_to_ptr<'ll>(
     builder: Builder<'ll>,
     mem_base_ptr_var: Value<'ll>,
     addr: Value<'ll>,
@@ -33,36 +17,189 @@ fn coerce_address_to_ptr<'ll>(
     builder.create_ptr_cast(byte_ptr, mem_ty.ptr_to())
 }
 
+impl<'ll> FunctionCodeGen<'ll> {
+    // Adds the offset to the address in 64 bits, so that the sum can't wrap around. In the
+    // explicit bounds-checking mode, the end of the access of `num_bytes` bytes is compared
+    // against the current size of the memory.
+    pub(super) fn get_offset_and_bounded_addr(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        memory_idx: usize,
+        addr: Value<'ll>,
+        offset: u32,
+        num_bytes: u32,
+    ) -> Value<'ll> {
+        let addr_64bit = self.builder.create_zext(addr, ctx.i64_type);
+        let addr_64bit = if offset != 0 {
+            self.builder.create_add(
+                addr_64bit,
+                self.builder
+                    .create_zext(I32::from(offset as i32).emit_const(ctx), ctx.i64_type),
+            )
+        } else {
+            addr_64bit
+        };
+
+        if module.options().bounds_check == BoundsCheckMode::Explicit {
+            let end_addr = self
+                .builder
+                .create_add(addr_64bit, I64::from(num_bytes as i64).emit_const(ctx));
+            let memory_num_bytes = self.load_memory_num_bytes(ctx, module, memory_idx);
+            let is_out_of_bounds =
+                self.builder
+                    .create_icmp(IntPredicate::IntUGT, end_addr, memory_num_bytes);
+            self.emit_conditional_trap(ctx, module, is_out_of_bounds, "memoryOutOfBoundsTrap");
+        }
+        addr_64bit
+    }
+
+    // The alignment immediate is only a hint and wasm allows any address, so the accesses are
+    // emitted with alignment 1; a larger alignment would let LLVM pick instructions which fault
+    // on misaligned addresses. Only the atomic accesses, whose alignment is checked, use their
+    // natural alignment. The accesses aren't volatile, so LLVM may combine them, and only the
+    // atomic instructions order the accesses to a shared memory.
+    pub(super) fn emit_load_from_memory(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        mem_ty: Type<'ll>,
+        num_bytes: u32,
+        arg: &MemArg,
+    ) -> Value<'ll> {
+        let addr = self.pop();
+        let memory_idx = arg.memory as usize;
+        let bounded_addr =
+            self.get_offset_and_bounded_addr(ctx, module, memory_idx, addr, arg.offset, num_bytes);
+        let ptr = coerce_address_to_ptr(
+            self.builder,
+            self.memory_base_ptrs[memory_idx],
+            bounded_addr,
+            mem_ty,
+        );
+        let load = self.builder.create_load(ptr);
+        load.set_alignment(1);
+        load
+    }
+
+    pub(super) fn emit_store_to_memory(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        value: Value<'ll>,
+        num_bytes: u32,
+        arg: &MemArg,
+    ) {
+        let addr = self.pop();
+        let memory_idx = arg.memory as usize;
+        let bounded_addr =
+            self.get_offset_and_bounded_addr(ctx, module, memory_idx, addr, arg.offset, num_bytes);
+        let ptr = coerce_address_to_ptr(
+            self.builder,
+            self.memory_base_ptrs[memory_idx],
+            bounded_addr,
+            value.get_type(),
+        );
+        let store = self.builder.create_store(value, ptr);
+        store.set_alignment(1);
+    }
+}
+
 pub trait MemoryInstrEmit<'ll> {
     declare_memory_instrs!(declear_op, _);
 }
 
 macro_rules! emit_load {
-    ($name:ident, $offset:ty, $align:ty, $mem_type:ident) => {
-        fn $name(&mut self, ctx: &$crate::codegen::ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, offset: $offset, align: $align) {
-
-            let addr = self.pop();
-            let bounded_addr = get_offset_and_bounded_addr(ctx, self.builder, addr, offset);
-            let ptr = coerce_address_to_ptr(
-                self.builder,
-                self.memory_base_ptr.unwrap(),
-                bounded_addr,
-                ctx.$mem_type
-            );
-            let load = self.builder.create_load(ptr);
-            load.set_alignment(1);
-            load.set_volatile(true);
-
+    ($name:ident, $mem_type:ident, $num_bytes:expr) => {
+        fn $name(&mut self, ctx: &$crate::codegen::ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let load = self.emit_load_from_memory(ctx, module, ctx.$mem_type, $num_bytes, &arg);
             self.push(load);
         }
     };
+    ($name:ident, $mem_type:ident, $num_bytes:expr, $extend:ident, $res_type:ident) => {
+        fn $name(&mut self, ctx: &$crate::codegen::ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let load = self.emit_load_from_memory(ctx, module, ctx.$mem_type, $num_bytes, &arg);
+            self.push(self.builder.$extend(load, ctx.$res_type));
+        }
+    };
+}
+
+macro_rules! emit_store {
+    ($name:ident, $num_bytes:expr) => {
+        fn $name(&mut self, ctx: &$crate::codegen::ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let value = self.pop();
+            self.emit_store_to_memory(ctx, module, value, $num_bytes, &arg);
+        }
+    };
+    ($name:ident, $mem_type:ident, $num_bytes:expr) => {
+        fn $name(&mut self, ctx: &$crate::codegen::ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let value = self.pop();
+            let value = self.builder.create_trunc(value, ctx.$mem_type);
+            self.emit_store_to_memory(ctx, module, value, $num_bytes, &arg);
+        }
+    };
 }
 
 impl<'ll> MemoryInstrEmit<'ll> for FunctionCodeGen<'ll> {
-    emit_load!(i32_load, u32, u32, i32_type);
-    // emit_const!(i32_const, i32, I32);
-    // emit_const!(i64_const, i64, I64);
-    // emit_const!(f32_const, u32, F32);
-    // emit_const!(f64_const, u64, F64);
-    // emit_const!(v128_const, Box<[u8; 16]>, V128);
+    emit_load!(i32_load, i32_type, 4);
+    emit_load!(i64_load, i64_type, 8);
+    emit_load!(f32_load, f32_type, 4);
+    emit_load!(f64_load, f64_type, 8);
+    emit_load!(i32_load8_s, i8_type, 1, create_sext, i32_type);
+    emit_load!(i32_load8_u, i8_type, 1, create_zext, i32_type);
+    emit_load!(i32_load16_s, i16_type, 2, create_sext, i32_type);
+    emit_load!(i32_load16_u, i16_type, 2, create_zext, i32_type);
+    emit_load!(i64_load8_s, i8_type, 1, create_sext, i64_type);
+    emit_load!(i64_load8_u, i8_type, 1, create_zext, i64_type);
+    emit_load!(i64_load16_s, i16_type, 2, create_sext, i64_type);
+    emit_load!(i64_load16_u, i16_type, 2, create_zext, i64_type);
+    emit_load!(i64_load32_s, i32_type, 4, create_sext, i64_type);
+    emit_load!(i64_load32_u, i32_type, 4, create_zext, i64_type);
+
+    emit_store!(i32_store, 4);
+    emit_store!(i64_store, 8);
+    emit_store!(f32_store, 4);
+    emit_store!(f64_store, 8);
+    emit_store!(i32_store8, i8_type, 1);
+    emit_store!(i32_store16, i16_type, 2);
+    emit_store!(i64_store8, i8_type, 1);
+    emit_store!(i64_store16, i16_type, 2);
+    emit_store!(i64_store32, i32_type, 4);
+
+    fn current_memory(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        memory_idx: u32,
+    ) {
+        let res = self.emit_runtime_intrinsic(
+            ctx,
+            module,
+            "currentMemory",
+            FunctionType::new(vec![ValueType::I32], vec![ValueType::I32]),
+            vec![I32::from(memory_idx as i32).emit_const(ctx)],
+        );
+        self.push(res[0]);
+    }
+
+    fn grow_memory(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        memory_idx: u32,
+    ) {
+        let delta_pages = self.pop();
+        let res = self.emit_runtime_intrinsic(
+            ctx,
+            module,
+            "growMemory",
+            FunctionType::new(vec![ValueType::I32, ValueType::I32], vec![ValueType::I32]),
+            vec![delta_pages, I32::from(memory_idx as i32).emit_const(ctx)],
+        );
+        // Growing the memory may move its base address.
+        self.reload_memory_base(ctx, module);
+        self.push(res[0]);
+    }
 }
@@ -1,10 +1,13 @@
 #[macro_use]
 mod macros;
 mod _type;
+mod atomic;
+mod bulk;
 mod common;
 mod context;
 mod control;
 // mod debuginfo;
+mod exception;
 mod builder;
 mod call_conv;
 mod function;
@@ -12,6 +15,8 @@ mod inst;
 mod memory;
 mod module;
 mod numeric;
+mod reference;
+mod simd;
 mod value;
 mod variable;
 
@@ -77,24 +82,37 @@ pub enum ContorlContextType {
     Catch,
 }
 
+// The state of a try block. Its landing pad catches every exception thrown by the calls in its
+// body, and the catch clauses dispatch on the type id of the exception.
+pub(in crate::codegen) struct TryContext<'ll> {
+    pub landing_pad: BasicBlock<'ll>,
+    // The caught exception and its type id, which are defined in the landing pad.
+    pub exception: Value<'ll>,
+    pub type_id: Value<'ll>,
+    // Where the exceptions which the catch clauses so far don't match go, which is `None` after
+    // a `catch_all`.
+    pub dispatch_block: Option<BasicBlock<'ll>>,
+}
+
 pub(in crate::codegen) struct ControlContext<'ll> {
     pub ty: ContorlContextType,
     pub end_block: BasicBlock<'ll>,
-    pub end_PHIs: Option<PHINode<'ll>>,
+    pub end_PHIs: Vec<PHINode<'ll>>,
     else_block: Option<BasicBlock<'ll>>,
     pub else_args: Vec<Value<'ll>>,
-    pub res_types: Option<ValueType>,
+    pub res_types: Vec<ValueType>,
     pub(in crate::codegen) outer_stack_size: usize,
     outer_branch_target_stack_size: usize,
     pub is_reachable: bool,
+    pub try_ctx: Option<TryContext<'ll>>,
 }
 
 impl<'ll> ControlContext<'ll> {
     pub fn new(
         ty: ContorlContextType,
-        res_types: Option<ValueType>,
+        res_types: Vec<ValueType>,
         end_block: BasicBlock<'ll>,
-        end_PHIs: Option<PHINode<'ll>>,
+        end_PHIs: Vec<PHINode<'ll>>,
         else_block: Option<BasicBlock<'ll>>,
         stack_size: usize,
         branch_target_stack_size: usize,
@@ -109,6 +127,7 @@ impl<'ll> ControlContext<'ll> {
             outer_stack_size: stack_size,
             outer_branch_target_stack_size: branch_target_stack_size,
             is_reachable: true,
+            try_ctx: None,
         }
     }
 
@@ -130,6 +149,10 @@ impl<'ll> BasicBlock<'ll> {
     pub fn erase_from_parent(self) {
         unsafe { llvm::LLVMDeleteBasicBlock(self.0) }
     }
+
+    pub fn get_parent(&self) -> function::Function<'ll> {
+        unsafe { function::Function::from(llvm::LLVMGetBasicBlockParent(self.0)) }
+    }
 }
 
 pub fn get_compartment_address<'ll>(
@@ -148,9 +171,30 @@ pub fn get_compartment_address<'ll>(
     )
 }
 
-pub fn compile_module(wasm_module: &WASMModule) -> Vec<u8> {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoundsCheckMode {
+    // The runtime reserves the whole 8GiB range which can be addressed by a 32-bit address
+    // plus a 32-bit offset, so an out-of-bounds access always hits a guard page.
+    GuardPages,
+    // Every access is compared against the current size of the memory, for hosts which don't
+    // allow such large virtual reservations.
+    Explicit,
+}
+
+impl Default for BoundsCheckMode {
+    fn default() -> Self {
+        BoundsCheckMode::GuardPages
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CompileOptions {
+    pub bounds_check: BoundsCheckMode,
+}
+
+pub fn compile_module(wasm_module: &WASMModule, options: &CompileOptions) -> Vec<u8> {
     let ctx = context::ContextCodeGen::new();
-    let module = ModuleCodeGen::new(&ctx, wasm_module);
+    let module = ModuleCodeGen::new(&ctx, wasm_module, options);
     let llvm_module = module.emit(&ctx, wasm_module);
 
     module.compile(wasm_module)
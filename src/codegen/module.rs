@@ -1,6 +1,7 @@
 use super::function::Function;
 use super::{
-    common, ContextCodeGen, FunctionCodeGen, MemoryBuffer, Metadata, TargetMachine, Type, Value,
+    common, CompileOptions, ContextCodeGen, FunctionCodeGen, MemoryBuffer, Metadata,
+    TargetMachine, Type, Value,
 };
 use crate::llvm;
 // use llvm_sys::prelude::{LLVMDIBuilderRef, LLVMMetadataRef, LLVMModuleRef, LLVMPassManagerRef};
@@ -67,6 +68,11 @@ impl<'ll> Module<'ll> {
         unsafe { Function::from(llvm::LLVMAddFunction(self.0, c_name.as_ptr(), *ty)) }
     }
 
+    pub fn get_named_function(&self, name: &str) -> Option<Function<'ll>> {
+        let c_name = CString::new(name).unwrap();
+        unsafe { llvm::LLVMGetNamedFunction(self.0, c_name.as_ptr()).map(Function::from) }
+    }
+
     pub fn create_imported_constant(&self, name: &str, ty: Type<'ll>) -> Value<'ll> {
         let c_name = CString::new(name).unwrap();
         unsafe { Value::from(llvm::LLVMAddGlobal(self.0, *ty, c_name.as_ptr())) }
@@ -115,19 +121,26 @@ pub struct ModuleCodeGen<'ll> {
     module: Module<'ll>,
     type_ids: Vec<Value<'ll>>,
     table_offsets: Vec<Value<'ll>>,
+    function_refs: Vec<Value<'ll>>,
     memory_offsets: Vec<Value<'ll>>,
     globals: Vec<Value<'ll>>,
     exception_type_ids: Vec<Value<'ll>>,
+    personality_func: Function<'ll>,
     functions: Vec<Function<'ll>>,
     // pub dibuilder: DIBuilder,
     default_table_offset: Option<Value<'ll>>,
     default_memory_offset: Option<Value<'ll>>,
+    options: CompileOptions,
     // di_value_types: [Option<Metadata>; ValueType::LENGTH],
     // pub di_module_scope: DIDescriptor,
 }
 
 impl<'ll> ModuleCodeGen<'ll> {
-    pub(super) fn new(ctx: &ContextCodeGen<'ll>, wasm_module: &wasm::Module) -> Self {
+    pub(super) fn new(
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &wasm::Module,
+        options: &CompileOptions,
+    ) -> Self {
         let module = ctx.create_module("");
 
         let type_ids = (0..wasm_module.types_count())
@@ -139,35 +152,32 @@ impl<'ll> ModuleCodeGen<'ll> {
             })
             .collect();
 
-        // let table_offsets = {
-        //     if let Some(tables) = wasm_module.table_section() {
-        //         (0..tables.entries().len())
-        //             .map(|t| {
-        //                 let s = format!("tableOffset{}", t);
-        //                 llmod
-        //                     .create_imported_constant(s.as_str(), ctx.i8_type)
-        //                     .get_ptr_to_int(ctx.iptr_type)
-        //             })
-        //             .collect()
-        //     } else {
-        //         Vec::new()
-        //     }
-        // };
+        let table_offsets = (0..wasm_module.tables().len())
+            .map(|t| {
+                let s = format!("tableOffset{}", t);
+                module
+                    .create_imported_constant(s.as_str(), ctx.i8_type)
+                    .get_ptr_to_int(ctx.iptr_type)
+            })
+            .collect();
 
-        // let memory_offsets = {
-        //     if let Some(memorys) = wasm_module.memory_section() {
-        //         (0..memorys.entries().len())
-        //             .map(|t| {
-        //                 let s = format!("memoryOffset{}", t);
-        //                 llmod
-        //                     .create_imported_constant(s.as_str(), ctx.i8_type)
-        //                     .get_ptr_to_int(ctx.iptr_type)
-        //             })
-        //             .collect()
-        //     } else {
-        //         Vec::new()
-        //     }
-        // };
+        // The reference to a function is the address of its type id and function pointer, which
+        // the runtime allocates for every function of the instance.
+        let function_refs = (0..wasm_module.functions().len())
+            .map(|t| {
+                let s = format!("functionRef{}", t);
+                module.create_imported_constant(s.as_str(), ctx.i8_type)
+            })
+            .collect();
+
+        let memory_offsets = (0..wasm_module.memorys_count())
+            .map(|t| {
+                let s = format!("memoryOffset{}", t);
+                module
+                    .create_imported_constant(s.as_str(), ctx.i8_type)
+                    .get_ptr_to_int(ctx.iptr_type)
+            })
+            .collect();
 
         let globals = (0..wasm_module.globals().len())
             .map(|t| {
@@ -180,7 +190,7 @@ impl<'ll> ModuleCodeGen<'ll> {
             "__gxx_personality_v0",
             Type::func(
                 ctx,
-                &WASMFunctionType::new(vec![], Some(ValueType::I32)),
+                &WASMFunctionType::new(vec![], vec![ValueType::I32]),
                 WASMCallConv::C,
             ),
         );
@@ -192,10 +202,6 @@ impl<'ll> ModuleCodeGen<'ll> {
                 } else {
                     format!("functionImport{}", i)
                 };
-                module
-                    .create_imported_constant(s.as_str(), ctx.i8_type)
-                    .get_ptr_to_int(ctx.iptr_type);
-
                 let func_type = wasm_module.functions().get_type(i);
                 let llvm_type = get_function_llvm_type(ctx, func_type, WASMCallConv::Wasm);
                 let ll_func = module.add_function(s.as_str(), llvm_type);
@@ -210,7 +216,14 @@ impl<'ll> ModuleCodeGen<'ll> {
             })
             .collect();
 
-        // TODO: exception globals
+        let exception_type_ids = (0..wasm_module.tags().len())
+            .map(|t| {
+                let s = format!("exceptionTypeId{}", t);
+                module
+                    .create_imported_constant(s.as_str(), ctx.i8_type)
+                    .get_ptr_to_int(ctx.iptr_type)
+            })
+            .collect();
 
         // let dibuilder = llmod.create_dibuilder();
         // let di_value_types = [
@@ -234,14 +247,17 @@ impl<'ll> ModuleCodeGen<'ll> {
             module,
             // wasm_module,
             type_ids,
-            table_offsets: Vec::new(),
-            memory_offsets: Vec::new(),
+            table_offsets,
+            function_refs,
+            memory_offsets,
             globals,
             functions,
             // dibuilder,
             default_memory_offset: None,
             default_table_offset: None,
-            exception_type_ids: Vec::new(),
+            options: *options,
+            exception_type_ids,
+            personality_func,
             // di_value_types,
             // di_module_scope: dibuilder.create_file("unknown", "unknown"),
         }
@@ -269,6 +285,7 @@ impl<'ll> ModuleCodeGen<'ll> {
     pub fn emit(&self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule) -> Module<'ll> {
         (0..wasm_module.functions().len()).for_each(|i| {
             if !wasm_module.functions().is_define(i) {
+                self.emit_import_stub(ctx, wasm_module, i);
                 return;
             }
             FunctionCodeGen::new(
@@ -276,6 +293,7 @@ impl<'ll> ModuleCodeGen<'ll> {
                 self,
                 self.functions[i],
                 wasm_module.functions().get_type(i).clone(),
+                i as u32,
             )
             .codegen(
                 ctx,
@@ -284,17 +302,234 @@ impl<'ll> ModuleCodeGen<'ll> {
                 wasm_module.functions().get_define(i).unwrap(),
             );
         });
+
+        let mut entry_functions = wasm_module
+            .exports()
+            .values()
+            .filter_map(|export| match export {
+                wasm::Export::Function(idx) => Some(*idx as usize),
+                _ => None,
+            })
+            .chain(wasm_module.start_function().map(|idx| idx as usize))
+            .collect::<Vec<_>>();
+        entry_functions.sort();
+        entry_functions.dedup();
+        entry_functions
+            .into_iter()
+            .for_each(|i| self.emit_invoke_thunk(ctx, wasm_module, i));
         self.module
     }
 
+    // An imported function is defined as a stub, which stores the arguments into an array of
+    // untyped 16-byte values and passes it to the runtime with the host function
+    // `hostFunction{i}`. The runtime stores the results into the first elements of the array.
+    fn emit_import_stub(&self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, i: usize) {
+        let func_type = wasm_module.functions().get_type(i);
+        let stub = self.functions[i];
+        let params = stub.get_params();
+        let builder = ctx.create_builder();
+        builder.set_insert_block(ctx.append_basic_block("entry", stub));
+
+        let num_values = func_type
+            .params()
+            .len()
+            .max(func_type.results().len())
+            .max(1) as u32;
+        let values = builder.create_alloca(ctx.i8_type.array(num_values * 16), "values");
+        values.set_alignment(16);
+        let values = builder.create_ptr_cast(values, ctx.i8_ptr_type);
+        params[1..].iter().enumerate().for_each(|(idx, param)| {
+            let value_ptr = builder.create_in_bounds_GEP(
+                values,
+                &[common::const_uint(ctx.iptr_type, (idx * 16) as u64)],
+            );
+            builder.store_to_untyped_pointer(*param, value_ptr, 16);
+        });
+
+        let host_function = self
+            .module
+            .create_imported_constant(format!("hostFunction{}", i).as_str(), ctx.i8_type);
+        let call_host_function = self.get_llvm_intrinsic(
+            "callHostFunction",
+            Type::function(
+                ctx.get_basic_type(ValueType::None),
+                &[ctx.i8_ptr_type, ctx.i8_ptr_type, ctx.i8_ptr_type],
+            ),
+        );
+        builder.create_call(call_host_function, &[params[0], host_function, values]);
+        let results = func_type
+            .results()
+            .iter()
+            .enumerate()
+            .map(|(idx, ty)| {
+                let value_ptr = builder.create_in_bounds_GEP(
+                    values,
+                    &[common::const_uint(ctx.iptr_type, (idx * 16) as u64)],
+                );
+                builder.load_from_untyped_pointer(value_ptr, ctx.get_basic_type(*ty), 16)
+            })
+            .collect::<Vec<_>>();
+        match results.as_slice() {
+            [] => builder.create_ret_void(),
+            [res] => builder.create_ret(*res),
+            results => builder.create_aggregate_ret(results),
+        };
+    }
+
+    // The runtime calls the exported functions through `invokeThunk{i}`, which takes the
+    // context pointer and a pointer to an array of untyped 16-byte values. The arguments are
+    // loaded from the array, and the results are stored into its first elements, so that the
+    // runtime can call a function of any type in the same way.
+    fn emit_invoke_thunk(&self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, i: usize) {
+        let func_type = wasm_module.functions().get_type(i);
+        let thunk = self.add_function(
+            format!("invokeThunk{}", i).as_str(),
+            Type::function(
+                ctx.get_basic_type(ValueType::None),
+                &[ctx.i8_ptr_type, ctx.i8_ptr_type],
+            ),
+        );
+        let params = thunk.get_params();
+        let builder = ctx.create_builder();
+        builder.set_insert_block(ctx.append_basic_block("entry", thunk));
+
+        let mut args = vec![params[0]];
+        args.extend(func_type.params().iter().enumerate().map(|(idx, ty)| {
+            let arg_ptr = builder.create_in_bounds_GEP(
+                params[1],
+                &[common::const_uint(ctx.iptr_type, (idx * 16) as u64)],
+            );
+            builder.load_from_untyped_pointer(arg_ptr, ctx.get_basic_type(*ty), 16)
+        }));
+        // The exceptions which the function doesn't catch are destroyed, and returned to the
+        // runtime as a trap.
+        let landing_pad = if wasm_module.tags().is_empty() {
+            None
+        } else {
+            thunk.set_personality_function(self.personality_func);
+            Some(ctx.append_basic_block("uncaughtException", thunk))
+        };
+        let res = ctx.emit_call_or_invoke(
+            self.functions[i],
+            args,
+            WASMCallConv::Wasm,
+            builder,
+            landing_pad,
+        );
+        let num_results = func_type.results().len();
+        (0..num_results).for_each(|idx| {
+            let res = if num_results == 1 {
+                res
+            } else {
+                builder.create_extract_value(res, idx as u32)
+            };
+            let res_ptr = builder.create_in_bounds_GEP(
+                params[1],
+                &[common::const_uint(ctx.iptr_type, (idx * 16) as u64)],
+            );
+            builder.store_to_untyped_pointer(res, res_ptr, 16);
+        });
+        builder.create_ret_void();
+
+        if let Some(landing_pad) = landing_pad {
+            builder.set_insert_block(landing_pad);
+            let landing_pad_inst =
+                builder.create_landing_pad(ctx.landing_pad_type, self.personality_func, 1);
+            landing_pad_inst.add_clause(common::const_null(ctx.i8_ptr_type));
+            let exception = builder.create_ptr_to_int(
+                builder.create_extract_value(Value::from(*landing_pad_inst), 0),
+                ctx.i64_type,
+            );
+            let destroy_exception = self.get_intrinsic(
+                ctx,
+                "destroyException",
+                &WASMFunctionType::new(vec![ValueType::I64], vec![]),
+            );
+            builder
+                .create_call(destroy_exception, &[params[0], exception])
+                .set_call_conv(WASMCallConv::Wasm);
+            let uncaught_exception_trap = self.get_intrinsic(
+                ctx,
+                "uncaughtExceptionTrap",
+                &WASMFunctionType::new(vec![ValueType::I32], vec![]),
+            );
+            builder
+                .create_call(
+                    uncaught_exception_trap,
+                    &[params[0], common::const_int(ctx.i32_type, i as i64)],
+                )
+                .set_call_conv(WASMCallConv::Wasm);
+            builder.create_unreachable();
+        }
+    }
+
     pub fn functions(&self) -> &[Function<'ll>] {
         &self.functions
     }
 
+    #[inline]
+    pub fn options(&self) -> &CompileOptions {
+        &self.options
+    }
+
+    #[inline]
+    pub fn type_ids(&self) -> &[Value<'ll>] {
+        &self.type_ids
+    }
+
+    #[inline]
+    pub fn exception_type_ids(&self) -> &[Value<'ll>] {
+        &self.exception_type_ids
+    }
+
+    #[inline]
+    pub fn personality_function(&self) -> Function<'ll> {
+        self.personality_func
+    }
+
+    #[inline]
+    pub fn table_offsets(&self) -> &[Value<'ll>] {
+        &self.table_offsets
+    }
+
+    #[inline]
+    pub fn function_refs(&self) -> &[Value<'ll>] {
+        &self.function_refs
+    }
+
+    // Runtime intrinsics are declared lazily, the first time a function calls them, and are
+    // resolved by name when the compiled module is loaded.
+    pub fn get_intrinsic(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        name: &str,
+        ty: &WASMFunctionType,
+    ) -> Function<'ll> {
+        match self.module.get_named_function(name) {
+            Some(func) => func,
+            None => self.add_function(name, get_function_llvm_type(ctx, ty, WASMCallConv::Wasm)),
+        }
+    }
+
+    // LLVM intrinsics like `llvm.ctlz.i32` are declared in the same way, but they don't take
+    // the context pointer and use the C calling convention. So are the runtime functions which
+    // don't follow the calling convention of wasm functions, like `callHostFunction`.
+    pub fn get_llvm_intrinsic(&self, name: &str, ty: Type<'ll>) -> Function<'ll> {
+        match self.module.get_named_function(name) {
+            Some(func) => func,
+            None => self.add_function(name, ty),
+        }
+    }
+
     // pub fn create_dibuilder(self) -> mut DIBuilder {
     //     unsafe { llvm::LLVMRustDIBuilderCreate(self) }
     // }
 
+    #[inline]
+    pub fn memory_offsets(&self) -> &[Value<'ll>] {
+        &self.memory_offsets
+    }
+
     pub fn default_memory_offset(&self) -> Option<Value<'ll>> {
         if self.memory_offsets.is_empty() {
             None
@@ -351,7 +586,9 @@ impl<'ll> ModuleCodeGen<'ll> {
                 llvm::LLVMGetDefaultTargetTriple(),
                 llvm::LLVMGetHostCPUName(),
                 llvm::LLVMGetHostCPUFeatures(),
-                llvm::CodeModel::Tiny,
+                // The compiled code is loaded at an arbitrary address, and refers to the runtime
+                // and the imported constants by absolute 64-bit addresses.
+                llvm::CodeModel::Large,
                 llvm::RelocMode::Static,
                 llvm::CodeGenOptLevel::Default,
             )
@@ -383,8 +620,6 @@ impl<'ll> ModuleCodeGen<'ll> {
 
         self.optimize(wasm_module);
 
-        self.module.print();
-
         let mem_buf = self.module.emit_to_memory_buffer(target_machine);
         unsafe { std::slice::from_raw_parts(mem_buf.get_data(), mem_buf.get_len()).to_vec() }
     }
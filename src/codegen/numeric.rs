@@ -1,5 +1,6 @@
-use super::common::Literal;
-use super::{FunctionCodeGen, ModuleCodeGen};
+use super::common::{self, Literal};
+use super::{Builder, ContextCodeGen, FunctionCodeGen, ModuleCodeGen, Type as LLVMType, Value};
+use crate::llvm::{IntPredicate, RealPredicate};
 use crate::wasm::types::*;
 use crate::wasm::Module as WASMModule;
 
@@ -19,10 +20,451 @@ macro_rules! emit_const {
     };
 }
 
+macro_rules! emit_binop {
+    ($name:ident, $create:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop();
+            let left = self.pop();
+            let res = self.builder.$create(left, right);
+            self.push(res);
+        }
+    };
+}
+
+// WebAssembly takes the shift count modulo the bit width, while LLVM yields a poison value
+// for a count which is not less than the bit width.
+macro_rules! emit_shift {
+    ($name:ident, $create:ident, $type:tt, $mask:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop();
+            let left = self.pop();
+            let count = self.builder.create_and(right, $type::from($mask).emit_const(ctx));
+            let res = self.builder.$create(left, count);
+            self.push(res);
+        }
+    };
+}
+
+macro_rules! emit_rotate {
+    ($name:ident, $create:ident, $create_rev:ident, $type:tt, $bits:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop();
+            let left = self.pop();
+            let mask = $type::from($bits - 1).emit_const(ctx);
+            let count = self.builder.create_and(right, mask);
+            let rev_count = self.builder.create_and(
+                self.builder.create_sub($type::from($bits).emit_const(ctx), right),
+                mask,
+            );
+            let res = self.builder.create_or(
+                self.builder.$create(left, count),
+                self.builder.$create_rev(left, rev_count),
+            );
+            self.push(res);
+        }
+    };
+}
+
+macro_rules! emit_icmp {
+    ($name:ident, $pred:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop();
+            let left = self.pop();
+            let cond = self.builder.create_icmp(IntPredicate::$pred, left, right);
+            self.push(self.builder.create_zext(cond, ctx.i32_type));
+        }
+    };
+}
+
+macro_rules! emit_eqz {
+    ($name:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop();
+            let cond = self.builder.create_icmp(
+                IntPredicate::IntEQ,
+                operand,
+                ctx.typed_zero_constants[ValueType::$type as usize],
+            );
+            self.push(self.builder.create_zext(cond, ctx.i32_type));
+        }
+    };
+}
+
+// `llvm.ctlz` and `llvm.cttz` take an extra flag telling whether the result is undefined for
+// zero, which must be false since WebAssembly defines it as the bit width.
+macro_rules! emit_intrinsic_unop {
+    ($name:ident, $intrinsic:expr, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop();
+            let res = self.emit_llvm_intrinsic(module, $intrinsic, ctx.$type, &[operand]);
+            self.push(res);
+        }
+    };
+    ($name:ident, $intrinsic:expr, $type:ident, zero_undef) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop();
+            let is_zero_undef = common::const_uint(LLVMType::i1(ctx.ctx), 0);
+            let res = self.emit_llvm_intrinsic(
+                module,
+                $intrinsic,
+                ctx.$type,
+                &[operand, is_zero_undef],
+            );
+            self.push(res);
+        }
+    };
+}
+
+macro_rules! emit_div {
+    ($name:ident, $create:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop();
+            let left = self.pop();
+            self.emit_divide_by_zero_trap(ctx, module, right);
+            let res = self.builder.$create(left, right);
+            self.push(res);
+        }
+    };
+    ($name:ident, $create:ident, $min:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop();
+            let left = self.pop();
+            self.emit_divide_by_zero_trap(ctx, module, right);
+
+            // The result of dividing the minimum signed integer by -1 isn't representable.
+            let ty = left.get_type();
+            let is_overflow = self.builder.create_and(
+                self.builder.create_icmp(IntPredicate::IntEQ, left, common::const_int(ty, $min)),
+                self.builder.create_icmp(IntPredicate::IntEQ, right, common::const_int(ty, -1)),
+            );
+            self.emit_conditional_trap(ctx, module, is_overflow, "integerOverflowTrap");
+
+            let res = self.builder.$create(left, right);
+            self.push(res);
+        }
+    };
+}
+
+macro_rules! emit_rem {
+    ($name:ident, create_urem) => {
+        emit_div!($name, create_urem);
+    };
+    ($name:ident, create_srem) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop();
+            let left = self.pop();
+            self.emit_divide_by_zero_trap(ctx, module, right);
+
+            // The remainder of the minimum signed integer by -1 is 0 in WebAssembly, but it's
+            // undefined behavior for LLVM. Since `x % -1 == x % 1` for other values, use 1 as
+            // the divisor instead.
+            let ty = right.get_type();
+            let divisor = self.builder.create_select(
+                self.builder.create_icmp(IntPredicate::IntEQ, right, common::const_int(ty, -1)),
+                common::const_int(ty, 1),
+                right,
+            );
+            let res = self.builder.create_srem(left, divisor);
+            self.push(res);
+        }
+    };
+}
+
+macro_rules! emit_intrinsic_binop {
+    ($name:ident, $intrinsic:expr, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop();
+            let left = self.pop();
+            let res = self.emit_llvm_intrinsic(module, $intrinsic, ctx.$type, &[left, right]);
+            self.push(res);
+        }
+    };
+}
+
+// Negation only flips the sign bit, even for NaNs.
+macro_rules! emit_fneg {
+    ($name:ident, $int_type:ident, $float_type:ident, $sign_mask:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop();
+            let bits = self.builder.create_bit_cast(operand, ctx.$int_type);
+            let res = self
+                .builder
+                .create_xor(bits, common::const_uint(ctx.$int_type, $sign_mask));
+            self.push(self.builder.create_bit_cast(res, ctx.$float_type));
+        }
+    };
+}
+
+macro_rules! emit_fmin_max {
+    ($name:ident, $int_type:ident, $pred:ident, $combine_zeros:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop();
+            let left = self.pop();
+            let res = self.emit_fmin_max(ctx.$int_type, left, right, RealPredicate::$pred, |builder, l, r| {
+                builder.$combine_zeros(l, r)
+            });
+            self.push(res);
+        }
+    };
+}
+
+macro_rules! emit_fcmp {
+    ($name:ident, $pred:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop();
+            let left = self.pop();
+            let cond = self.builder.create_fcmp(RealPredicate::$pred, left, right);
+            self.push(self.builder.create_zext(cond, ctx.i32_type));
+        }
+    };
+}
+
+macro_rules! emit_cast {
+    ($name:ident, $create:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop();
+            let res = self.builder.$create(operand, ctx.$type);
+            self.push(res);
+        }
+    };
+}
+
+// The conversion traps if the operand is NaN, or its truncated value is out of the range of
+// the result type. `$min` and `$max` are the nearest values outside of the range which are
+// representable by the operand type.
+macro_rules! emit_trunc {
+    ($name:ident, $create:ident, $type:ident, $min:expr, $max:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop();
+            let ty = operand.get_type();
+
+            let is_nan = self
+                .builder
+                .create_fcmp(RealPredicate::RealUNO, operand, operand);
+            self.emit_conditional_trap(ctx, module, is_nan, "invalidFloatOperationTrap");
+
+            let is_overflow = self.builder.create_or(
+                self.builder.create_fcmp(
+                    RealPredicate::RealOLE,
+                    operand,
+                    common::const_double(ty, $min),
+                ),
+                self.builder.create_fcmp(
+                    RealPredicate::RealOGE,
+                    operand,
+                    common::const_double(ty, $max),
+                ),
+            );
+            self.emit_conditional_trap(ctx, module, is_overflow, "integerOverflowTrap");
+
+            let res = self.builder.$create(operand, ctx.$type);
+            self.push(res);
+        }
+    };
+}
+
+impl<'ll> FunctionCodeGen<'ll> {
+    // Unlike `llvm.minnum` and `llvm.maxnum`, WebAssembly propagates NaN operands and orders
+    // -0.0 below +0.0. `int_type` has the width of the operands, which are scalars or vectors.
+    pub(super) fn emit_fmin_max(
+        &self,
+        int_type: LLVMType<'ll>,
+        left: Value<'ll>,
+        right: Value<'ll>,
+        pred: RealPredicate,
+        combine_zeros: impl Fn(Builder<'ll>, Value<'ll>, Value<'ll>) -> Value<'ll>,
+    ) -> Value<'ll> {
+        // Adding the operands yields a quiet NaN if any of them is NaN.
+        let is_nan = self
+            .builder
+            .create_fcmp(RealPredicate::RealUNO, left, right);
+        let nan = self.builder.create_fadd(left, right);
+
+        // The operands only compare equal with different bits if they are zeros of different
+        // signs, in which case the sign bits are combined.
+        let is_equal = self
+            .builder
+            .create_fcmp(RealPredicate::RealOEQ, left, right);
+        let zero = self.builder.create_bit_cast(
+            combine_zeros(
+                self.builder,
+                self.builder.create_bit_cast(left, int_type),
+                self.builder.create_bit_cast(right, int_type),
+            ),
+            left.get_type(),
+        );
+
+        let ordered = self.builder.create_select(
+            self.builder.create_fcmp(pred, left, right),
+            left,
+            right,
+        );
+        self.builder.create_select(
+            is_nan,
+            nan,
+            self.builder.create_select(is_equal, zero, ordered),
+        )
+    }
+
+    fn emit_divide_by_zero_trap(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        divisor: Value<'ll>,
+    ) {
+        let is_zero = self.builder.create_icmp(
+            IntPredicate::IntEQ,
+            divisor,
+            common::const_null(divisor.get_type()),
+        );
+        self.emit_conditional_trap(ctx, module, is_zero, "divideByZeroTrap");
+    }
+}
+
 impl<'ll> NumericInstrEmit<'ll> for FunctionCodeGen<'ll> {
     emit_const!(i32_const, i32, I32);
     emit_const!(i64_const, i64, I64);
     emit_const!(f32_const, u32, F32);
     emit_const!(f64_const, u64, F64);
     emit_const!(v128_const, Box<[u8; 16]>, V128);
+
+    emit_binop!(i32_add, create_add);
+    emit_binop!(i32_sub, create_sub);
+    emit_binop!(i32_mul, create_mul);
+    emit_div!(i32_div_s, create_sdiv, std::i32::MIN as i64);
+    emit_div!(i32_div_u, create_udiv);
+    emit_rem!(i32_rem_s, create_srem);
+    emit_rem!(i32_rem_u, create_urem);
+    emit_binop!(i32_and, create_and);
+    emit_binop!(i32_or, create_or);
+    emit_binop!(i32_xor, create_xor);
+    emit_shift!(i32_shl, create_shl, I32, 31);
+    emit_shift!(i32_shr_s, create_ashr, I32, 31);
+    emit_shift!(i32_shr_u, create_lshr, I32, 31);
+    emit_rotate!(i32_rotl, create_shl, create_lshr, I32, 32);
+    emit_rotate!(i32_rotr, create_lshr, create_shl, I32, 32);
+    emit_intrinsic_unop!(i32_clz, "llvm.ctlz.i32", i32_type, zero_undef);
+    emit_intrinsic_unop!(i32_ctz, "llvm.cttz.i32", i32_type, zero_undef);
+    emit_intrinsic_unop!(i32_popcnt, "llvm.ctpop.i32", i32_type);
+    emit_eqz!(i32_eqz, I32);
+    emit_icmp!(i32_eq, IntEQ);
+    emit_icmp!(i32_ne, IntNE);
+    emit_icmp!(i32_lt_s, IntSLT);
+    emit_icmp!(i32_lt_u, IntULT);
+    emit_icmp!(i32_gt_s, IntSGT);
+    emit_icmp!(i32_gt_u, IntUGT);
+    emit_icmp!(i32_le_s, IntSLE);
+    emit_icmp!(i32_le_u, IntULE);
+    emit_icmp!(i32_ge_s, IntSGE);
+    emit_icmp!(i32_ge_u, IntUGE);
+
+    emit_binop!(i64_add, create_add);
+    emit_binop!(i64_sub, create_sub);
+    emit_binop!(i64_mul, create_mul);
+    emit_div!(i64_div_s, create_sdiv, std::i64::MIN);
+    emit_div!(i64_div_u, create_udiv);
+    emit_rem!(i64_rem_s, create_srem);
+    emit_rem!(i64_rem_u, create_urem);
+    emit_binop!(i64_and, create_and);
+    emit_binop!(i64_or, create_or);
+    emit_binop!(i64_xor, create_xor);
+    emit_shift!(i64_shl, create_shl, I64, 63);
+    emit_shift!(i64_shr_s, create_ashr, I64, 63);
+    emit_shift!(i64_shr_u, create_lshr, I64, 63);
+    emit_rotate!(i64_rotl, create_shl, create_lshr, I64, 64);
+    emit_rotate!(i64_rotr, create_lshr, create_shl, I64, 64);
+    emit_intrinsic_unop!(i64_clz, "llvm.ctlz.i64", i64_type, zero_undef);
+    emit_intrinsic_unop!(i64_ctz, "llvm.cttz.i64", i64_type, zero_undef);
+    emit_intrinsic_unop!(i64_popcnt, "llvm.ctpop.i64", i64_type);
+    emit_eqz!(i64_eqz, I64);
+    emit_icmp!(i64_eq, IntEQ);
+    emit_icmp!(i64_ne, IntNE);
+    emit_icmp!(i64_lt_s, IntSLT);
+    emit_icmp!(i64_lt_u, IntULT);
+    emit_icmp!(i64_gt_s, IntSGT);
+    emit_icmp!(i64_gt_u, IntUGT);
+    emit_icmp!(i64_le_s, IntSLE);
+    emit_icmp!(i64_le_u, IntULE);
+    emit_icmp!(i64_ge_s, IntSGE);
+    emit_icmp!(i64_ge_u, IntUGE);
+
+    emit_intrinsic_unop!(f32_abs, "llvm.fabs.f32", f32_type);
+    emit_fneg!(f32_neg, i32_type, f32_type, 0x8000_0000);
+    emit_intrinsic_unop!(f32_ceil, "llvm.ceil.f32", f32_type);
+    emit_intrinsic_unop!(f32_floor, "llvm.floor.f32", f32_type);
+    emit_intrinsic_unop!(f32_trunc, "llvm.trunc.f32", f32_type);
+    emit_intrinsic_unop!(f32_nearest, "llvm.nearbyint.f32", f32_type);
+    emit_intrinsic_unop!(f32_sqrt, "llvm.sqrt.f32", f32_type);
+    emit_binop!(f32_add, create_fadd);
+    emit_binop!(f32_sub, create_fsub);
+    emit_binop!(f32_mul, create_fmul);
+    emit_binop!(f32_div, create_fdiv);
+    emit_fmin_max!(f32_min, i32_type, RealOLT, create_or);
+    emit_fmin_max!(f32_max, i32_type, RealOGT, create_and);
+    emit_intrinsic_binop!(f32_copysign, "llvm.copysign.f32", f32_type);
+    emit_fcmp!(f32_eq, RealOEQ);
+    emit_fcmp!(f32_ne, RealUNE);
+    emit_fcmp!(f32_lt, RealOLT);
+    emit_fcmp!(f32_gt, RealOGT);
+    emit_fcmp!(f32_le, RealOLE);
+    emit_fcmp!(f32_ge, RealOGE);
+
+    emit_intrinsic_unop!(f64_abs, "llvm.fabs.f64", f64_type);
+    emit_fneg!(f64_neg, i64_type, f64_type, 0x8000_0000_0000_0000);
+    emit_intrinsic_unop!(f64_ceil, "llvm.ceil.f64", f64_type);
+    emit_intrinsic_unop!(f64_floor, "llvm.floor.f64", f64_type);
+    emit_intrinsic_unop!(f64_trunc, "llvm.trunc.f64", f64_type);
+    emit_intrinsic_unop!(f64_nearest, "llvm.nearbyint.f64", f64_type);
+    emit_intrinsic_unop!(f64_sqrt, "llvm.sqrt.f64", f64_type);
+    emit_binop!(f64_add, create_fadd);
+    emit_binop!(f64_sub, create_fsub);
+    emit_binop!(f64_mul, create_fmul);
+    emit_binop!(f64_div, create_fdiv);
+    emit_fmin_max!(f64_min, i64_type, RealOLT, create_or);
+    emit_fmin_max!(f64_max, i64_type, RealOGT, create_and);
+    emit_intrinsic_binop!(f64_copysign, "llvm.copysign.f64", f64_type);
+    emit_fcmp!(f64_eq, RealOEQ);
+    emit_fcmp!(f64_ne, RealUNE);
+    emit_fcmp!(f64_lt, RealOLT);
+    emit_fcmp!(f64_gt, RealOGT);
+    emit_fcmp!(f64_le, RealOLE);
+    emit_fcmp!(f64_ge, RealOGE);
+
+    emit_cast!(i32_wrap_i64, create_trunc, i32_type);
+    emit_trunc!(i32_trunc_s_f32, create_fp_to_si, i32_type, -2147483904.0, 2147483648.0);
+    emit_trunc!(i32_trunc_u_f32, create_fp_to_ui, i32_type, -1.0, 4294967296.0);
+    emit_trunc!(i32_trunc_s_f64, create_fp_to_si, i32_type, -2147483649.0, 2147483648.0);
+    emit_trunc!(i32_trunc_u_f64, create_fp_to_ui, i32_type, -1.0, 4294967296.0);
+    emit_cast!(i64_extend_s_i32, create_sext, i64_type);
+    emit_cast!(i64_extend_u_i32, create_zext, i64_type);
+    emit_trunc!(
+        i64_trunc_s_f32,
+        create_fp_to_si,
+        i64_type,
+        -9223373136366403584.0,
+        9223372036854775808.0
+    );
+    emit_trunc!(i64_trunc_u_f32, create_fp_to_ui, i64_type, -1.0, 18446744073709551616.0);
+    emit_trunc!(
+        i64_trunc_s_f64,
+        create_fp_to_si,
+        i64_type,
+        -9223372036854777856.0,
+        9223372036854775808.0
+    );
+    emit_trunc!(i64_trunc_u_f64, create_fp_to_ui, i64_type, -1.0, 18446744073709551616.0);
+    emit_cast!(f32_convert_s_i32, create_si_to_fp, f32_type);
+    emit_cast!(f32_convert_u_i32, create_ui_to_fp, f32_type);
+    emit_cast!(f32_convert_s_i64, create_si_to_fp, f32_type);
+    emit_cast!(f32_convert_u_i64, create_ui_to_fp, f32_type);
+    emit_cast!(f32_demote_f64, create_fp_trunc, f32_type);
+    emit_cast!(f64_convert_s_i32, create_si_to_fp, f64_type);
+    emit_cast!(f64_convert_u_i32, create_ui_to_fp, f64_type);
+    emit_cast!(f64_convert_s_i64, create_si_to_fp, f64_type);
+    emit_cast!(f64_convert_u_i64, create_ui_to_fp, f64_type);
+    emit_cast!(f64_promote_f32, create_fp_ext, f64_type);
+    emit_cast!(i32_reinterpret_f32, create_bit_cast, i32_type);
+    emit_cast!(i64_reinterpret_f64, create_bit_cast, i64_type);
+    emit_cast!(f32_reinterpret_i32, create_bit_cast, f32_type);
+    emit_cast!(f64_reinterpret_i64, create_bit_cast, f64_type);
 }
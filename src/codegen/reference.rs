@@ -0,0 +1,329 @@
+use super::_type::Type;
+use super::common::{self, Literal};
+use super::control::ControlInstrEmit;
+use super::function::Function;
+use super::value::Value;
+use super::{ContextCodeGen, FunctionCodeGen, ModuleCodeGen};
+use crate::llvm;
+use crate::wasm::call_conv::CallConv as WASMCallConv;
+use crate::wasm::types::I32;
+use crate::wasm::{FunctionType, Module as WASMModule, ValueType};
+
+// A reference is a pointer, which is null for `ref.null`. A `funcref` points to the type id and
+// the function pointer of the function, which the runtime allocates for every function of an
+// instance, and an `anyref` is opaque. The elements of the tables are references.
+pub trait RefInstrEmit<'ll> {
+    declare_ref_instrs!(declear_op);
+}
+
+const REF_SIZE: u32 = 8;
+
+impl<'ll> FunctionCodeGen<'ll> {
+    // Each table is described in the compartment runtime data by its base pointer followed by its
+    // current number of elements, see `runtime::table::TableRuntimeData`.
+    fn load_table_runtime_data(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        table_index: u32,
+    ) -> (Value<'ll>, Value<'ll>) {
+        let compartment_addr =
+            super::get_compartment_address(ctx, self.builder, self.ctx_ptr.unwrap());
+        let table_offset = module.table_offsets()[table_index as usize];
+        let table_base = self.builder.load_from_untyped_pointer(
+            self.builder
+                .create_in_bounds_GEP(compartment_addr, &[table_offset]),
+            ctx.i8_ptr_type,
+            std::mem::size_of::<usize>() as u32,
+        );
+        let num_elements = self.builder.load_from_untyped_pointer(
+            self.builder.create_in_bounds_GEP(
+                compartment_addr,
+                &[self.builder.create_add(
+                    table_offset,
+                    common::const_uint(ctx.iptr_type, std::mem::size_of::<usize>() as u64),
+                )],
+            ),
+            ctx.iptr_type,
+            std::mem::size_of::<usize>() as u32,
+        );
+        (table_base, num_elements)
+    }
+
+    // The pointer to the element `index` of a table, which calls the trap intrinsic `trap` if
+    // the index is out of bounds.
+    fn emit_table_elem_ptr(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        table_index: u32,
+        index: Value<'ll>,
+        trap: &str,
+    ) -> Value<'ll> {
+        let (table_base, num_elements) = self.load_table_runtime_data(ctx, module, table_index);
+        let elem_index = self.builder.create_zext(index, ctx.iptr_type);
+        self.emit_conditional_trap(
+            ctx,
+            module,
+            self.builder
+                .create_icmp(llvm::IntPredicate::IntUGE, elem_index, num_elements),
+            trap,
+        );
+        self.builder.create_in_bounds_GEP(
+            table_base,
+            &[self.builder.create_mul(
+                elem_index,
+                common::const_uint(ctx.iptr_type, REF_SIZE as u64),
+            )],
+        )
+    }
+}
+
+impl<'ll> RefInstrEmit<'ll> for FunctionCodeGen<'ll> {
+    fn call_indirect(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        ty_index: u32,
+        table_index: u32,
+    ) {
+        let index = self.pop();
+        let callee_type = wasm_module.get_func_type(ty_index);
+
+        let elem_ptr = self.emit_table_elem_ptr(
+            ctx,
+            module,
+            table_index,
+            index,
+            "indirectCallIndexOutOfBoundsTrap",
+        );
+        let func_ref = self
+            .builder
+            .load_from_untyped_pointer(elem_ptr, ctx.i8_ptr_type, REF_SIZE);
+        self.emit_conditional_trap(
+            ctx,
+            module,
+            self.builder.create_icmp(
+                llvm::IntPredicate::IntEQ,
+                func_ref,
+                common::const_null(ctx.i8_ptr_type),
+            ),
+            "uninitializedElementTrap",
+        );
+        let elem_type_id = self.builder.load_from_untyped_pointer(
+            func_ref,
+            ctx.iptr_type,
+            std::mem::size_of::<usize>() as u32,
+        );
+        self.emit_conditional_trap(
+            ctx,
+            module,
+            self.builder.create_icmp(
+                llvm::IntPredicate::IntNE,
+                elem_type_id,
+                module.type_ids()[ty_index as usize],
+            ),
+            "indirectCallSignatureMismatchTrap",
+        );
+
+        let callee_llvm_type = Type::func(ctx, callee_type, WASMCallConv::Wasm);
+        let callee = self.builder.load_from_untyped_pointer(
+            self.builder.create_in_bounds_GEP(
+                func_ref,
+                &[common::const_uint(
+                    ctx.iptr_type,
+                    std::mem::size_of::<usize>() as u64,
+                )],
+            ),
+            callee_llvm_type.ptr_to(),
+            std::mem::size_of::<usize>() as u32,
+        );
+
+        // The function may belong to another instance which shares the table.
+        let callee_ctx = self.builder.load_from_untyped_pointer(
+            self.builder.create_in_bounds_GEP(
+                func_ref,
+                &[common::const_uint(
+                    ctx.iptr_type,
+                    2 * std::mem::size_of::<usize>() as u64,
+                )],
+            ),
+            ctx.i8_ptr_type,
+            std::mem::size_of::<usize>() as u32,
+        );
+
+        let mut args = vec![callee_ctx];
+        args.extend(
+            self.pop_multi(callee_type.params().len())
+                .iter()
+                .map(|t| ctx.coerce_to_canonical_type(self.builder, *t)),
+        );
+
+        let res = ctx.emit_call_or_invoke(
+            Function::from(*callee),
+            args,
+            WASMCallConv::Wasm,
+            self.builder,
+            self.get_landing_pad(),
+        );
+        self.reload_memory_base(ctx, module);
+
+        self.push_call_results(res, callee_type.results());
+    }
+
+    // The type only matters to the validation.
+    fn select_typed(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        _types: Vec<ValueType>,
+    ) {
+        self.select_(ctx, wasm_module, module);
+    }
+
+    fn ref_null(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        ty: ValueType,
+    ) {
+        self.push(ctx.typed_zero_constants[ty as usize]);
+    }
+
+    fn ref_is_null(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+    ) {
+        let v = self.pop();
+        let is_null = self.builder.create_icmp(
+            llvm::IntPredicate::IntEQ,
+            v,
+            common::const_null(v.get_type()),
+        );
+        let res = self.builder.create_zext(is_null, ctx.i32_type);
+        self.push(res);
+    }
+
+    fn ref_func(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        index: u32,
+    ) {
+        let func_ref = common::const_bit_cast(
+            module.function_refs()[index as usize],
+            ctx.get_basic_type(ValueType::AnyFunc),
+        );
+        self.push(func_ref);
+    }
+
+    fn table_get(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        table_index: u32,
+    ) {
+        let index = self.pop();
+        let elem_ptr =
+            self.emit_table_elem_ptr(ctx, module, table_index, index, "tableOutOfBoundsTrap");
+        let elem_type = wasm_module
+            .tables()
+            .get_type(table_index as usize)
+            .elem_type();
+        let elem = self.builder.load_from_untyped_pointer(
+            elem_ptr,
+            ctx.get_basic_type(elem_type),
+            REF_SIZE,
+        );
+        self.push(elem);
+    }
+
+    fn table_set(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        table_index: u32,
+    ) {
+        let value = self.pop();
+        let index = self.pop();
+        let elem_ptr =
+            self.emit_table_elem_ptr(ctx, module, table_index, index, "tableOutOfBoundsTrap");
+        self.builder
+            .store_to_untyped_pointer(value, elem_ptr, REF_SIZE);
+    }
+
+    fn table_size(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        table_index: u32,
+    ) {
+        let (_, num_elements) = self.load_table_runtime_data(ctx, module, table_index);
+        let size = self.builder.create_trunc(num_elements, ctx.i32_type);
+        self.push(size);
+    }
+
+    // Growing a table may move its elements, so the runtime grows it.
+    fn table_grow(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        table_index: u32,
+    ) {
+        let delta = self.pop();
+        let init = self.pop();
+        let res = self.emit_runtime_intrinsic(
+            ctx,
+            module,
+            "tableGrow",
+            FunctionType::new(
+                vec![ValueType::AnyRef, ValueType::I32, ValueType::I32],
+                vec![ValueType::I32],
+            ),
+            vec![init, delta, I32::from(table_index as i32).emit_const(ctx)],
+        );
+        self.push(res[0]);
+    }
+
+    fn table_fill(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        table_index: u32,
+    ) {
+        let count = self.pop();
+        let value = self.pop();
+        let index = self.pop();
+        self.emit_runtime_intrinsic(
+            ctx,
+            module,
+            "tableFill",
+            FunctionType::new(
+                vec![
+                    ValueType::I32,
+                    ValueType::AnyRef,
+                    ValueType::I32,
+                    ValueType::I32,
+                ],
+                vec![],
+            ),
+            vec![
+                index,
+                value,
+                count,
+                I32::from(table_index as i32).emit_const(ctx),
+            ],
+        );
+    }
+}
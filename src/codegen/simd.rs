@@ -0,0 +1,1077 @@
+use super::common;
+use super::{ContextCodeGen, FunctionCodeGen, ModuleCodeGen, Type, Value};
+use crate::llvm::{IntPredicate, RealPredicate};
+use crate::wasm::{MemArg, Module as WASMModule};
+
+// The v128 values are `<2 x i64>` on the operand stack and in the locals and globals, and each
+// instruction bitcasts its operands to the vector type of its lanes.
+pub trait SimdInstrEmit<'ll> {
+    declare_simd_instrs!(declear_op, _);
+}
+
+fn const_splat<'ll>(v: Value<'ll>, num_lanes: u32) -> Value<'ll> {
+    common::const_vector(&vec![v; num_lanes as usize])
+}
+
+impl<'ll> FunctionCodeGen<'ll> {
+    fn pop_vector(&mut self, ty: Type<'ll>) -> Value<'ll> {
+        let v = self.pop();
+        self.builder.create_bit_cast(v, ty)
+    }
+
+    fn push_vector(&mut self, ctx: &ContextCodeGen<'ll>, v: Value<'ll>) {
+        self.push(ctx.coerce_to_canonical_type(self.builder, v));
+    }
+
+    fn emit_splat(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        scalar: Value<'ll>,
+        num_lanes: u32,
+    ) -> Value<'ll> {
+        let vec_type = scalar.get_type().vector(num_lanes as u64);
+        let v = self.builder.create_insert_element(
+            common::const_null(vec_type),
+            scalar,
+            common::const_uint(ctx.i32_type, 0),
+        );
+        self.builder.create_shuffle_vector(
+            v,
+            v,
+            common::const_null(ctx.i32_type.vector(num_lanes as u64)),
+        )
+    }
+
+    fn emit_shuffle(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        v1: Value<'ll>,
+        v2: Value<'ll>,
+        lanes: &[u32],
+    ) -> Value<'ll> {
+        let mask = lanes
+            .iter()
+            .map(|lane| common::const_uint(ctx.i32_type, *lane as u64))
+            .collect::<Vec<_>>();
+        self.builder
+            .create_shuffle_vector(v1, v2, common::const_vector(&mask))
+    }
+
+    // The lower or upper half of the lanes of `v`.
+    fn emit_half_vector(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        v: Value<'ll>,
+        is_high: bool,
+    ) -> Value<'ll> {
+        let num_lanes = v.get_type().vector_size() / 2;
+        let first = if is_high { num_lanes } else { 0 };
+        let lanes = (first..first + num_lanes).collect::<Vec<_>>();
+        self.emit_shuffle(ctx, v, v, &lanes)
+    }
+
+    // The even or odd lanes of `v`.
+    fn emit_interleaved_lanes(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        v: Value<'ll>,
+        is_odd: bool,
+    ) -> Value<'ll> {
+        let num_lanes = v.get_type().vector_size();
+        let lanes = (0..num_lanes / 2)
+            .map(|i| i * 2 + is_odd as u32)
+            .collect::<Vec<_>>();
+        self.emit_shuffle(ctx, v, v, &lanes)
+    }
+
+    // The lanes of `left` followed by those of `right`.
+    fn emit_concat(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        left: Value<'ll>,
+        right: Value<'ll>,
+    ) -> Value<'ll> {
+        let num_lanes = left.get_type().vector_size();
+        let lanes = (0..num_lanes * 2).collect::<Vec<_>>();
+        self.emit_shuffle(ctx, left, right, &lanes)
+    }
+
+    // Like the scalar shifts, the count is taken modulo the lane width.
+    fn emit_shift_count(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        count: Value<'ll>,
+        vec_type: Type<'ll>,
+        num_bits: u32,
+    ) -> Value<'ll> {
+        let count = self
+            .builder
+            .create_and(count, common::const_uint(ctx.i32_type, (num_bits - 1) as u64));
+        let lane_type = vec_type.get_element_type();
+        let count = match num_bits {
+            8 | 16 => self.builder.create_trunc(count, lane_type),
+            64 => self.builder.create_zext(count, lane_type),
+            _ => count,
+        };
+        self.emit_splat(ctx, count, vec_type.vector_size())
+    }
+
+    // Clamps the signed lanes of `v` to `[min, max]`.
+    fn emit_clamp(&self, v: Value<'ll>, min: i64, max: i64) -> Value<'ll> {
+        let ty = v.get_type();
+        let num_lanes = ty.vector_size();
+        let min = const_splat(common::const_int(ty.get_element_type(), min), num_lanes);
+        let max = const_splat(common::const_int(ty.get_element_type(), max), num_lanes);
+        let v = self.builder.create_select(
+            self.builder.create_icmp(IntPredicate::IntSLT, v, min),
+            min,
+            v,
+        );
+        self.builder.create_select(
+            self.builder.create_icmp(IntPredicate::IntSGT, v, max),
+            max,
+            v,
+        )
+    }
+
+    // Converts the float lanes of `operand` to the integer lanes of `res_type`, saturating the
+    // values out of range and converting NaNs to 0. `min` and `max` are the nearest values
+    // outside of the range of the result, like the operands of `emit_trunc!`.
+    fn emit_trunc_sat(
+        &self,
+        operand: Value<'ll>,
+        res_type: Type<'ll>,
+        is_signed: bool,
+        min: f64,
+        max: f64,
+    ) -> Value<'ll> {
+        let float_type = operand.get_type().get_element_type();
+        let int_type = res_type.get_element_type();
+        let num_lanes = res_type.vector_size();
+        let float_splat = |v: f64| const_splat(common::const_double(float_type, v), num_lanes);
+        let int_splat = |v: i64| const_splat(common::const_int(int_type, v), num_lanes);
+
+        let res = if is_signed {
+            self.builder.create_fp_to_si(operand, res_type)
+        } else {
+            self.builder.create_fp_to_ui(operand, res_type)
+        };
+        let (int_min, int_max) = if is_signed {
+            (std::i32::MIN as i64, std::i32::MAX as i64)
+        } else {
+            (0, -1)
+        };
+        let res = self.builder.create_select(
+            self.builder
+                .create_fcmp(RealPredicate::RealOGE, operand, float_splat(max)),
+            int_splat(int_max),
+            res,
+        );
+        // NaNs compare unordered, so they are converted to 0 along with the unsigned values
+        // out of range.
+        let pred = if is_signed {
+            RealPredicate::RealOLE
+        } else {
+            RealPredicate::RealULE
+        };
+        let res = self.builder.create_select(
+            self.builder.create_fcmp(pred, operand, float_splat(min)),
+            int_splat(int_min),
+            res,
+        );
+        if !is_signed {
+            return res;
+        }
+        self.builder.create_select(
+            self.builder
+                .create_fcmp(RealPredicate::RealUNO, operand, operand),
+            common::const_null(res_type),
+            res,
+        )
+    }
+}
+
+macro_rules! emit_simd_load {
+    ($name:ident, $type:ident, $num_bytes:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let load = self.emit_load_from_memory(ctx, module, ctx.$type, $num_bytes, &arg);
+            self.push_vector(ctx, load);
+        }
+    };
+}
+
+// Loads 8 bytes as half as many lanes, and extends them to the lanes of `$type`.
+macro_rules! emit_load_extend {
+    ($name:ident, $lane_type:ident, $extend:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let mem_type = ctx.$lane_type.vector(ctx.$type.vector_size() as u64);
+            let load = self.emit_load_from_memory(ctx, module, mem_type, 8, &arg);
+            let res = self.builder.$extend(load, ctx.$type);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_load_splat {
+    ($name:ident, $lane_type:ident, $num_bytes:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let load = self.emit_load_from_memory(ctx, module, ctx.$lane_type, $num_bytes, &arg);
+            let res = self.emit_splat(ctx, load, 16 / $num_bytes);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_load_zero {
+    ($name:ident, $lane_type:ident, $num_bytes:expr, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg) {
+            let load = self.emit_load_from_memory(ctx, module, ctx.$lane_type, $num_bytes, &arg);
+            let res = self.builder.create_insert_element(
+                common::const_null(ctx.$type),
+                load,
+                common::const_uint(ctx.i32_type, 0),
+            );
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_load_lane {
+    ($name:ident, $type:ident, $num_bytes:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg, lane: u8) {
+            let operand = self.pop_vector(ctx.$type);
+            let lane_type = ctx.$type.get_element_type();
+            let load = self.emit_load_from_memory(ctx, module, lane_type, $num_bytes, &arg);
+            let res = self.builder.create_insert_element(
+                operand,
+                load,
+                common::const_uint(ctx.i32_type, lane as u64),
+            );
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_store_lane {
+    ($name:ident, $type:ident, $num_bytes:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, arg: MemArg, lane: u8) {
+            let operand = self.pop_vector(ctx.$type);
+            let value = self
+                .builder
+                .create_extract_element(operand, common::const_uint(ctx.i32_type, lane as u64));
+            self.emit_store_to_memory(ctx, module, value, $num_bytes, &arg);
+        }
+    };
+}
+
+// The scalar operand of the splats of i8 and i16 lanes is an i32, which is truncated.
+macro_rules! emit_splat {
+    ($name:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let scalar = self.pop();
+            let res = self.emit_splat(ctx, scalar, ctx.$type.vector_size());
+            self.push_vector(ctx, res);
+        }
+    };
+    ($name:ident, $type:ident, $lane_type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let scalar = self.pop();
+            let scalar = self.builder.create_trunc(scalar, ctx.$lane_type);
+            let res = self.emit_splat(ctx, scalar, ctx.$type.vector_size());
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_extract_lane {
+    ($name:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, lane: u8) {
+            let operand = self.pop_vector(ctx.$type);
+            let res = self
+                .builder
+                .create_extract_element(operand, common::const_uint(ctx.i32_type, lane as u64));
+            self.push(res);
+        }
+    };
+    ($name:ident, $type:ident, $extend:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, lane: u8) {
+            let operand = self.pop_vector(ctx.$type);
+            let res = self
+                .builder
+                .create_extract_element(operand, common::const_uint(ctx.i32_type, lane as u64));
+            self.push(self.builder.$extend(res, ctx.i32_type));
+        }
+    };
+}
+
+macro_rules! emit_replace_lane {
+    ($name:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, lane: u8) {
+            let scalar = self.pop();
+            let operand = self.pop_vector(ctx.$type);
+            let res = self.builder.create_insert_element(
+                operand,
+                scalar,
+                common::const_uint(ctx.i32_type, lane as u64),
+            );
+            self.push_vector(ctx, res);
+        }
+    };
+    ($name:ident, $type:ident, $lane_type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>, lane: u8) {
+            let scalar = self.pop();
+            let scalar = self.builder.create_trunc(scalar, ctx.$lane_type);
+            let operand = self.pop_vector(ctx.$type);
+            let res = self.builder.create_insert_element(
+                operand,
+                scalar,
+                common::const_uint(ctx.i32_type, lane as u64),
+            );
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_simd_binop {
+    ($name:ident, $create:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop_vector(ctx.$type);
+            let left = self.pop_vector(ctx.$type);
+            let res = self.builder.$create(left, right);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_simd_intrinsic_unop {
+    ($name:ident, $intrinsic:expr, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop_vector(ctx.$type);
+            let res = self.emit_llvm_intrinsic(module, $intrinsic, ctx.$type, &[operand]);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_simd_intrinsic_binop {
+    ($name:ident, $intrinsic:expr, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop_vector(ctx.$type);
+            let left = self.pop_vector(ctx.$type);
+            let res = self.emit_llvm_intrinsic(module, $intrinsic, ctx.$type, &[left, right]);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_simd_shift {
+    ($name:ident, $create:ident, $type:ident, $num_bits:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let count = self.pop();
+            let operand = self.pop_vector(ctx.$type);
+            let count = self.emit_shift_count(ctx, count, ctx.$type, $num_bits);
+            let res = self.builder.$create(operand, count);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+// The comparisons set all the bits of the lanes which satisfy them.
+macro_rules! emit_simd_icmp {
+    ($name:ident, $pred:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop_vector(ctx.$type);
+            let left = self.pop_vector(ctx.$type);
+            let cond = self.builder.create_icmp(IntPredicate::$pred, left, right);
+            let res = self.builder.create_sext(cond, ctx.$type);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_simd_fcmp {
+    ($name:ident, $pred:ident, $type:ident, $int_type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop_vector(ctx.$type);
+            let left = self.pop_vector(ctx.$type);
+            let cond = self.builder.create_fcmp(RealPredicate::$pred, left, right);
+            let res = self.builder.create_sext(cond, ctx.$int_type);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_simd_min_max {
+    ($name:ident, $pred:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop_vector(ctx.$type);
+            let left = self.pop_vector(ctx.$type);
+            let res = self.builder.create_select(
+                self.builder.create_icmp(IntPredicate::$pred, left, right),
+                left,
+                right,
+            );
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_simd_neg {
+    ($name:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop_vector(ctx.$type);
+            let res = self
+                .builder
+                .create_sub(common::const_null(ctx.$type), operand);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+// The absolute value of the minimum signed integer is itself.
+macro_rules! emit_simd_abs {
+    ($name:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop_vector(ctx.$type);
+            let zero = common::const_null(ctx.$type);
+            let res = self.builder.create_select(
+                self.builder.create_icmp(IntPredicate::IntSLT, operand, zero),
+                self.builder.create_sub(zero, operand),
+                operand,
+            );
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_all_true {
+    ($name:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop_vector(ctx.$type);
+            let num_lanes = ctx.$type.vector_size();
+            let is_nonzero = self.builder.create_icmp(
+                IntPredicate::IntNE,
+                operand,
+                common::const_null(ctx.$type),
+            );
+            let mask_type = Type::int(ctx.ctx, num_lanes);
+            let cond = self.builder.create_icmp(
+                IntPredicate::IntEQ,
+                self.builder.create_bit_cast(is_nonzero, mask_type),
+                common::const_int(mask_type, -1),
+            );
+            self.push(self.builder.create_zext(cond, ctx.i32_type));
+        }
+    };
+}
+
+// Gathers the sign bits of the lanes.
+macro_rules! emit_bitmask {
+    ($name:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop_vector(ctx.$type);
+            let num_lanes = ctx.$type.vector_size();
+            let is_negative = self.builder.create_icmp(
+                IntPredicate::IntSLT,
+                operand,
+                common::const_null(ctx.$type),
+            );
+            let mask = self
+                .builder
+                .create_bit_cast(is_negative, Type::int(ctx.ctx, num_lanes));
+            self.push(self.builder.create_zext(mask, ctx.i32_type));
+        }
+    };
+}
+
+// Both operands are narrowed with signed saturation, and the lanes of the first come first.
+macro_rules! emit_narrow {
+    ($name:ident, $from_type:ident, $type:ident, $min:expr, $max:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop_vector(ctx.$from_type);
+            let left = self.pop_vector(ctx.$from_type);
+            let v = self.emit_concat(ctx, left, right);
+            let v = self.emit_clamp(v, $min, $max);
+            let res = self.builder.create_trunc(v, ctx.$type);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_extend {
+    ($name:ident, $from_type:ident, $is_high:expr, $extend:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop_vector(ctx.$from_type);
+            let half = self.emit_half_vector(ctx, operand, $is_high);
+            let res = self.builder.$extend(half, ctx.$type);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_extmul {
+    ($name:ident, $from_type:ident, $is_high:expr, $extend:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop_vector(ctx.$from_type);
+            let left = self.pop_vector(ctx.$from_type);
+            let left = self.emit_half_vector(ctx, left, $is_high);
+            let right = self.emit_half_vector(ctx, right, $is_high);
+            let res = self.builder.create_mul(
+                self.builder.$extend(left, ctx.$type),
+                self.builder.$extend(right, ctx.$type),
+            );
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+// Adds the extended even and odd lanes.
+macro_rules! emit_extadd_pairwise {
+    ($name:ident, $from_type:ident, $extend:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop_vector(ctx.$from_type);
+            let even = self.emit_interleaved_lanes(ctx, operand, false);
+            let odd = self.emit_interleaved_lanes(ctx, operand, true);
+            let res = self.builder.create_add(
+                self.builder.$extend(even, ctx.$type),
+                self.builder.$extend(odd, ctx.$type),
+            );
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+// The rounding average `(a + b + 1) / 2` is computed in lanes twice as wide.
+macro_rules! emit_avgr {
+    ($name:ident, $type:ident, $wide_lane_type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop_vector(ctx.$type);
+            let left = self.pop_vector(ctx.$type);
+            let num_lanes = ctx.$type.vector_size();
+            let wide_type = ctx.$wide_lane_type.vector(num_lanes as u64);
+            let one = const_splat(common::const_uint(ctx.$wide_lane_type, 1), num_lanes);
+            let sum = self.builder.create_add(
+                self.builder.create_add(
+                    self.builder.create_zext(left, wide_type),
+                    self.builder.create_zext(right, wide_type),
+                ),
+                one,
+            );
+            let res = self
+                .builder
+                .create_trunc(self.builder.create_lshr(sum, one), ctx.$type);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_simd_fneg {
+    ($name:ident, $int_type:ident, $sign_mask:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop_vector(ctx.$int_type);
+            let sign_mask = const_splat(
+                common::const_uint(ctx.$int_type.get_element_type(), $sign_mask),
+                ctx.$int_type.vector_size(),
+            );
+            let res = self.builder.create_xor(operand, sign_mask);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_simd_fmin_max {
+    ($name:ident, $type:ident, $int_type:ident, $pred:ident, $combine_zeros:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop_vector(ctx.$type);
+            let left = self.pop_vector(ctx.$type);
+            let res = self.emit_fmin_max(ctx.$int_type, left, right, RealPredicate::$pred, |builder, l, r| {
+                builder.$combine_zeros(l, r)
+            });
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+// `pmin` is `b < a ? b : a`, and `pmax` is `a < b ? b : a`.
+macro_rules! emit_pmin_max {
+    ($name:ident, $type:ident, $is_max:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let right = self.pop_vector(ctx.$type);
+            let left = self.pop_vector(ctx.$type);
+            let cond = if $is_max {
+                self.builder.create_fcmp(RealPredicate::RealOLT, left, right)
+            } else {
+                self.builder.create_fcmp(RealPredicate::RealOLT, right, left)
+            };
+            let res = self.builder.create_select(cond, right, left);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_simd_cast {
+    ($name:ident, $create:ident, $from_type:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop_vector(ctx.$from_type);
+            let res = self.builder.$create(operand, ctx.$type);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+// Converts the lower two lanes.
+macro_rules! emit_convert_low {
+    ($name:ident, $create:ident, $from_type:ident, $type:ident) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop_vector(ctx.$from_type);
+            let low = self.emit_shuffle(ctx, operand, operand, &[0, 1]);
+            let res = self.builder.$create(low, ctx.$type);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+macro_rules! emit_trunc_sat {
+    ($name:ident, $from_type:ident, $is_signed:expr, $min:expr, $max:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop_vector(ctx.$from_type);
+            let res = self.emit_trunc_sat(operand, ctx.i32x4_type, $is_signed, $min, $max);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+// Converts the two lanes of `f64x2` to the lower lanes of `i32x4`, and zeroes the upper lanes.
+macro_rules! emit_trunc_sat_zero {
+    ($name:ident, $is_signed:expr, $min:expr, $max:expr) => {
+        fn $name(&mut self, ctx: &ContextCodeGen<'ll>, wasm_module: &WASMModule, module: &ModuleCodeGen<'ll>) {
+            let operand = self.pop_vector(ctx.f64x2_type);
+            let res_type = ctx.i32_type.vector(2);
+            let res = self.emit_trunc_sat(operand, res_type, $is_signed, $min, $max);
+            let res = self.emit_shuffle(ctx, res, common::const_null(res_type), &[0, 1, 2, 3]);
+            self.push_vector(ctx, res);
+        }
+    };
+}
+
+impl<'ll> SimdInstrEmit<'ll> for FunctionCodeGen<'ll> {
+    emit_simd_load!(v128_load, i64x2_type, 16);
+    emit_load_extend!(v128_load8x8_s, i8_type, create_sext, i16x8_type);
+    emit_load_extend!(v128_load8x8_u, i8_type, create_zext, i16x8_type);
+    emit_load_extend!(v128_load16x4_s, i16_type, create_sext, i32x4_type);
+    emit_load_extend!(v128_load16x4_u, i16_type, create_zext, i32x4_type);
+    emit_load_extend!(v128_load32x2_s, i32_type, create_sext, i64x2_type);
+    emit_load_extend!(v128_load32x2_u, i32_type, create_zext, i64x2_type);
+    emit_load_splat!(v128_load8_splat, i8_type, 1);
+    emit_load_splat!(v128_load16_splat, i16_type, 2);
+    emit_load_splat!(v128_load32_splat, i32_type, 4);
+    emit_load_splat!(v128_load64_splat, i64_type, 8);
+    emit_load_zero!(v128_load32_zero, i32_type, 4, i32x4_type);
+    emit_load_zero!(v128_load64_zero, i64_type, 8, i64x2_type);
+    emit_load_lane!(v128_load8_lane, i8x16_type, 1);
+    emit_load_lane!(v128_load16_lane, i16x8_type, 2);
+    emit_load_lane!(v128_load32_lane, i32x4_type, 4);
+    emit_load_lane!(v128_load64_lane, i64x2_type, 8);
+    emit_store_lane!(v128_store8_lane, i8x16_type, 1);
+    emit_store_lane!(v128_store16_lane, i16x8_type, 2);
+    emit_store_lane!(v128_store32_lane, i32x4_type, 4);
+    emit_store_lane!(v128_store64_lane, i64x2_type, 8);
+
+    fn v128_store(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        arg: MemArg,
+    ) {
+        let value = self.pop_vector(ctx.i64x2_type);
+        self.emit_store_to_memory(ctx, module, value, 16, &arg);
+    }
+
+    fn i8x16_shuffle(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        lanes: Box<[u8; 16]>,
+    ) {
+        let right = self.pop_vector(ctx.i8x16_type);
+        let left = self.pop_vector(ctx.i8x16_type);
+        let lanes = lanes.iter().map(|lane| *lane as u32).collect::<Vec<_>>();
+        let res = self.emit_shuffle(ctx, left, right, &lanes);
+        self.push_vector(ctx, res);
+    }
+
+    // The lanes whose index is out of range are 0. `pshufb` zeroes the lanes whose index has the
+    // top bit set and takes the low 4 bits of the others, so adding 0x70 with saturation keeps
+    // the indices below 16 and sets the top bit of the rest.
+    fn i8x16_swizzle(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+    ) {
+        let indices = self.pop_vector(ctx.i8x16_type);
+        let operand = self.pop_vector(ctx.i8x16_type);
+        let bias = common::const_vector(&[common::const_uint(ctx.i8_type, 0x70); 16]);
+        let indices = self.emit_llvm_intrinsic(
+            module,
+            "llvm.uadd.sat.v16i8",
+            ctx.i8x16_type,
+            &[indices, bias],
+        );
+        let res = self.emit_llvm_intrinsic(
+            module,
+            "llvm.x86.ssse3.pshuf.b.128",
+            ctx.i8x16_type,
+            &[operand, indices],
+        );
+        self.push_vector(ctx, res);
+    }
+
+    emit_splat!(i8x16_splat, i8x16_type, i8_type);
+    emit_splat!(i16x8_splat, i16x8_type, i16_type);
+    emit_splat!(i32x4_splat, i32x4_type);
+    emit_splat!(i64x2_splat, i64x2_type);
+    emit_splat!(f32x4_splat, f32x4_type);
+    emit_splat!(f64x2_splat, f64x2_type);
+
+    emit_extract_lane!(i8x16_extract_lane_s, i8x16_type, create_sext);
+    emit_extract_lane!(i8x16_extract_lane_u, i8x16_type, create_zext);
+    emit_replace_lane!(i8x16_replace_lane, i8x16_type, i8_type);
+    emit_extract_lane!(i16x8_extract_lane_s, i16x8_type, create_sext);
+    emit_extract_lane!(i16x8_extract_lane_u, i16x8_type, create_zext);
+    emit_replace_lane!(i16x8_replace_lane, i16x8_type, i16_type);
+    emit_extract_lane!(i32x4_extract_lane, i32x4_type);
+    emit_replace_lane!(i32x4_replace_lane, i32x4_type);
+    emit_extract_lane!(i64x2_extract_lane, i64x2_type);
+    emit_replace_lane!(i64x2_replace_lane, i64x2_type);
+    emit_extract_lane!(f32x4_extract_lane, f32x4_type);
+    emit_replace_lane!(f32x4_replace_lane, f32x4_type);
+    emit_extract_lane!(f64x2_extract_lane, f64x2_type);
+    emit_replace_lane!(f64x2_replace_lane, f64x2_type);
+
+    emit_simd_icmp!(i8x16_eq, IntEQ, i8x16_type);
+    emit_simd_icmp!(i8x16_ne, IntNE, i8x16_type);
+    emit_simd_icmp!(i8x16_lt_s, IntSLT, i8x16_type);
+    emit_simd_icmp!(i8x16_lt_u, IntULT, i8x16_type);
+    emit_simd_icmp!(i8x16_gt_s, IntSGT, i8x16_type);
+    emit_simd_icmp!(i8x16_gt_u, IntUGT, i8x16_type);
+    emit_simd_icmp!(i8x16_le_s, IntSLE, i8x16_type);
+    emit_simd_icmp!(i8x16_le_u, IntULE, i8x16_type);
+    emit_simd_icmp!(i8x16_ge_s, IntSGE, i8x16_type);
+    emit_simd_icmp!(i8x16_ge_u, IntUGE, i8x16_type);
+    emit_simd_icmp!(i16x8_eq, IntEQ, i16x8_type);
+    emit_simd_icmp!(i16x8_ne, IntNE, i16x8_type);
+    emit_simd_icmp!(i16x8_lt_s, IntSLT, i16x8_type);
+    emit_simd_icmp!(i16x8_lt_u, IntULT, i16x8_type);
+    emit_simd_icmp!(i16x8_gt_s, IntSGT, i16x8_type);
+    emit_simd_icmp!(i16x8_gt_u, IntUGT, i16x8_type);
+    emit_simd_icmp!(i16x8_le_s, IntSLE, i16x8_type);
+    emit_simd_icmp!(i16x8_le_u, IntULE, i16x8_type);
+    emit_simd_icmp!(i16x8_ge_s, IntSGE, i16x8_type);
+    emit_simd_icmp!(i16x8_ge_u, IntUGE, i16x8_type);
+    emit_simd_icmp!(i32x4_eq, IntEQ, i32x4_type);
+    emit_simd_icmp!(i32x4_ne, IntNE, i32x4_type);
+    emit_simd_icmp!(i32x4_lt_s, IntSLT, i32x4_type);
+    emit_simd_icmp!(i32x4_lt_u, IntULT, i32x4_type);
+    emit_simd_icmp!(i32x4_gt_s, IntSGT, i32x4_type);
+    emit_simd_icmp!(i32x4_gt_u, IntUGT, i32x4_type);
+    emit_simd_icmp!(i32x4_le_s, IntSLE, i32x4_type);
+    emit_simd_icmp!(i32x4_le_u, IntULE, i32x4_type);
+    emit_simd_icmp!(i32x4_ge_s, IntSGE, i32x4_type);
+    emit_simd_icmp!(i32x4_ge_u, IntUGE, i32x4_type);
+    emit_simd_icmp!(i64x2_eq, IntEQ, i64x2_type);
+    emit_simd_icmp!(i64x2_ne, IntNE, i64x2_type);
+    emit_simd_icmp!(i64x2_lt_s, IntSLT, i64x2_type);
+    emit_simd_icmp!(i64x2_gt_s, IntSGT, i64x2_type);
+    emit_simd_icmp!(i64x2_le_s, IntSLE, i64x2_type);
+    emit_simd_icmp!(i64x2_ge_s, IntSGE, i64x2_type);
+    emit_simd_fcmp!(f32x4_eq, RealOEQ, f32x4_type, i32x4_type);
+    emit_simd_fcmp!(f32x4_ne, RealUNE, f32x4_type, i32x4_type);
+    emit_simd_fcmp!(f32x4_lt, RealOLT, f32x4_type, i32x4_type);
+    emit_simd_fcmp!(f32x4_gt, RealOGT, f32x4_type, i32x4_type);
+    emit_simd_fcmp!(f32x4_le, RealOLE, f32x4_type, i32x4_type);
+    emit_simd_fcmp!(f32x4_ge, RealOGE, f32x4_type, i32x4_type);
+    emit_simd_fcmp!(f64x2_eq, RealOEQ, f64x2_type, i64x2_type);
+    emit_simd_fcmp!(f64x2_ne, RealUNE, f64x2_type, i64x2_type);
+    emit_simd_fcmp!(f64x2_lt, RealOLT, f64x2_type, i64x2_type);
+    emit_simd_fcmp!(f64x2_gt, RealOGT, f64x2_type, i64x2_type);
+    emit_simd_fcmp!(f64x2_le, RealOLE, f64x2_type, i64x2_type);
+    emit_simd_fcmp!(f64x2_ge, RealOGE, f64x2_type, i64x2_type);
+
+    fn v128_not(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+    ) {
+        let operand = self.pop_vector(ctx.i64x2_type);
+        let ones = const_splat(common::const_int(ctx.i64_type, -1), 2);
+        let res = self.builder.create_xor(operand, ones);
+        self.push_vector(ctx, res);
+    }
+
+    emit_simd_binop!(v128_and, create_and, i64x2_type);
+
+    fn v128_andnot(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+    ) {
+        let right = self.pop_vector(ctx.i64x2_type);
+        let left = self.pop_vector(ctx.i64x2_type);
+        let ones = const_splat(common::const_int(ctx.i64_type, -1), 2);
+        let res = self
+            .builder
+            .create_and(left, self.builder.create_xor(right, ones));
+        self.push_vector(ctx, res);
+    }
+
+    emit_simd_binop!(v128_or, create_or, i64x2_type);
+    emit_simd_binop!(v128_xor, create_xor, i64x2_type);
+
+    // Selects the bits of the first operand where the mask is set, and those of the second
+    // operand elsewhere.
+    fn v128_bitselect(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+    ) {
+        let mask = self.pop_vector(ctx.i64x2_type);
+        let right = self.pop_vector(ctx.i64x2_type);
+        let left = self.pop_vector(ctx.i64x2_type);
+        let ones = const_splat(common::const_int(ctx.i64_type, -1), 2);
+        let res = self.builder.create_or(
+            self.builder.create_and(left, mask),
+            self.builder
+                .create_and(right, self.builder.create_xor(mask, ones)),
+        );
+        self.push_vector(ctx, res);
+    }
+
+    fn v128_any_true(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+    ) {
+        let operand = self.pop();
+        let i128_type = Type::i128(ctx.ctx);
+        let cond = self.builder.create_icmp(
+            IntPredicate::IntNE,
+            self.builder.create_bit_cast(operand, i128_type),
+            common::const_null(i128_type),
+        );
+        self.push(self.builder.create_zext(cond, ctx.i32_type));
+    }
+
+    // The upper lanes of the result are zeroes.
+    fn f32x4_demote_f64x2_zero(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+    ) {
+        let operand = self.pop_vector(ctx.f64x2_type);
+        let res_type = ctx.f32_type.vector(2);
+        let res = self.builder.create_fp_trunc(operand, res_type);
+        let res = self.emit_shuffle(ctx, res, common::const_null(res_type), &[0, 1, 2, 3]);
+        self.push_vector(ctx, res);
+    }
+
+    emit_convert_low!(f64x2_promote_low_f32x4, create_fp_ext, f32x4_type, f64x2_type);
+
+    emit_simd_abs!(i8x16_abs, i8x16_type);
+    emit_simd_neg!(i8x16_neg, i8x16_type);
+    emit_simd_intrinsic_unop!(i8x16_popcnt, "llvm.ctpop.v16i8", i8x16_type);
+    emit_all_true!(i8x16_all_true, i8x16_type);
+    emit_bitmask!(i8x16_bitmask, i8x16_type);
+    emit_narrow!(i8x16_narrow_i16x8_s, i16x8_type, i8x16_type, -128, 127);
+    emit_narrow!(i8x16_narrow_i16x8_u, i16x8_type, i8x16_type, 0, 255);
+    emit_simd_intrinsic_unop!(f32x4_ceil, "llvm.ceil.v4f32", f32x4_type);
+    emit_simd_intrinsic_unop!(f32x4_floor, "llvm.floor.v4f32", f32x4_type);
+    emit_simd_intrinsic_unop!(f32x4_trunc, "llvm.trunc.v4f32", f32x4_type);
+    emit_simd_intrinsic_unop!(f32x4_nearest, "llvm.nearbyint.v4f32", f32x4_type);
+    emit_simd_shift!(i8x16_shl, create_shl, i8x16_type, 8);
+    emit_simd_shift!(i8x16_shr_s, create_ashr, i8x16_type, 8);
+    emit_simd_shift!(i8x16_shr_u, create_lshr, i8x16_type, 8);
+    emit_simd_binop!(i8x16_add, create_add, i8x16_type);
+    emit_simd_intrinsic_binop!(i8x16_add_sat_s, "llvm.sadd.sat.v16i8", i8x16_type);
+    emit_simd_intrinsic_binop!(i8x16_add_sat_u, "llvm.uadd.sat.v16i8", i8x16_type);
+    emit_simd_binop!(i8x16_sub, create_sub, i8x16_type);
+    emit_simd_intrinsic_binop!(i8x16_sub_sat_s, "llvm.ssub.sat.v16i8", i8x16_type);
+    emit_simd_intrinsic_binop!(i8x16_sub_sat_u, "llvm.usub.sat.v16i8", i8x16_type);
+    emit_simd_intrinsic_unop!(f64x2_ceil, "llvm.ceil.v2f64", f64x2_type);
+    emit_simd_intrinsic_unop!(f64x2_floor, "llvm.floor.v2f64", f64x2_type);
+    emit_simd_min_max!(i8x16_min_s, IntSLT, i8x16_type);
+    emit_simd_min_max!(i8x16_min_u, IntULT, i8x16_type);
+    emit_simd_min_max!(i8x16_max_s, IntSGT, i8x16_type);
+    emit_simd_min_max!(i8x16_max_u, IntUGT, i8x16_type);
+    emit_simd_intrinsic_unop!(f64x2_trunc, "llvm.trunc.v2f64", f64x2_type);
+    emit_avgr!(i8x16_avgr_u, i8x16_type, i16_type);
+    emit_extadd_pairwise!(i16x8_extadd_pairwise_i8x16_s, i8x16_type, create_sext, i16x8_type);
+    emit_extadd_pairwise!(i16x8_extadd_pairwise_i8x16_u, i8x16_type, create_zext, i16x8_type);
+    emit_extadd_pairwise!(i32x4_extadd_pairwise_i16x8_s, i16x8_type, create_sext, i32x4_type);
+    emit_extadd_pairwise!(i32x4_extadd_pairwise_i16x8_u, i16x8_type, create_zext, i32x4_type);
+
+    emit_simd_abs!(i16x8_abs, i16x8_type);
+    emit_simd_neg!(i16x8_neg, i16x8_type);
+
+    // Multiplies the lanes as Q15 fixed-point numbers, rounding to nearest. Only the product of
+    // two minimum values overflows, and saturates.
+    fn i16x8_q15mulr_sat_s(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+    ) {
+        let right = self.pop_vector(ctx.i16x8_type);
+        let left = self.pop_vector(ctx.i16x8_type);
+        let wide_type = ctx.i32_type.vector(8);
+        let product = self.builder.create_mul(
+            self.builder.create_sext(left, wide_type),
+            self.builder.create_sext(right, wide_type),
+        );
+        let rounded = self.builder.create_ashr(
+            self.builder.create_add(
+                product,
+                const_splat(common::const_uint(ctx.i32_type, 0x4000), 8),
+            ),
+            const_splat(common::const_uint(ctx.i32_type, 15), 8),
+        );
+        let res = self.builder.create_trunc(
+            self.emit_clamp(rounded, std::i16::MIN as i64, std::i16::MAX as i64),
+            ctx.i16x8_type,
+        );
+        self.push_vector(ctx, res);
+    }
+
+    emit_all_true!(i16x8_all_true, i16x8_type);
+    emit_bitmask!(i16x8_bitmask, i16x8_type);
+    emit_narrow!(i16x8_narrow_i32x4_s, i32x4_type, i16x8_type, -32768, 32767);
+    emit_narrow!(i16x8_narrow_i32x4_u, i32x4_type, i16x8_type, 0, 65535);
+    emit_extend!(i16x8_extend_low_i8x16_s, i8x16_type, false, create_sext, i16x8_type);
+    emit_extend!(i16x8_extend_high_i8x16_s, i8x16_type, true, create_sext, i16x8_type);
+    emit_extend!(i16x8_extend_low_i8x16_u, i8x16_type, false, create_zext, i16x8_type);
+    emit_extend!(i16x8_extend_high_i8x16_u, i8x16_type, true, create_zext, i16x8_type);
+    emit_simd_shift!(i16x8_shl, create_shl, i16x8_type, 16);
+    emit_simd_shift!(i16x8_shr_s, create_ashr, i16x8_type, 16);
+    emit_simd_shift!(i16x8_shr_u, create_lshr, i16x8_type, 16);
+    emit_simd_binop!(i16x8_add, create_add, i16x8_type);
+    emit_simd_intrinsic_binop!(i16x8_add_sat_s, "llvm.sadd.sat.v8i16", i16x8_type);
+    emit_simd_intrinsic_binop!(i16x8_add_sat_u, "llvm.uadd.sat.v8i16", i16x8_type);
+    emit_simd_binop!(i16x8_sub, create_sub, i16x8_type);
+    emit_simd_intrinsic_binop!(i16x8_sub_sat_s, "llvm.ssub.sat.v8i16", i16x8_type);
+    emit_simd_intrinsic_binop!(i16x8_sub_sat_u, "llvm.usub.sat.v8i16", i16x8_type);
+    emit_simd_intrinsic_unop!(f64x2_nearest, "llvm.nearbyint.v2f64", f64x2_type);
+    emit_simd_binop!(i16x8_mul, create_mul, i16x8_type);
+    emit_simd_min_max!(i16x8_min_s, IntSLT, i16x8_type);
+    emit_simd_min_max!(i16x8_min_u, IntULT, i16x8_type);
+    emit_simd_min_max!(i16x8_max_s, IntSGT, i16x8_type);
+    emit_simd_min_max!(i16x8_max_u, IntUGT, i16x8_type);
+    emit_avgr!(i16x8_avgr_u, i16x8_type, i32_type);
+    emit_extmul!(i16x8_extmul_low_i8x16_s, i8x16_type, false, create_sext, i16x8_type);
+    emit_extmul!(i16x8_extmul_high_i8x16_s, i8x16_type, true, create_sext, i16x8_type);
+    emit_extmul!(i16x8_extmul_low_i8x16_u, i8x16_type, false, create_zext, i16x8_type);
+    emit_extmul!(i16x8_extmul_high_i8x16_u, i8x16_type, true, create_zext, i16x8_type);
+
+    emit_simd_abs!(i32x4_abs, i32x4_type);
+    emit_simd_neg!(i32x4_neg, i32x4_type);
+    emit_all_true!(i32x4_all_true, i32x4_type);
+    emit_bitmask!(i32x4_bitmask, i32x4_type);
+    emit_extend!(i32x4_extend_low_i16x8_s, i16x8_type, false, create_sext, i32x4_type);
+    emit_extend!(i32x4_extend_high_i16x8_s, i16x8_type, true, create_sext, i32x4_type);
+    emit_extend!(i32x4_extend_low_i16x8_u, i16x8_type, false, create_zext, i32x4_type);
+    emit_extend!(i32x4_extend_high_i16x8_u, i16x8_type, true, create_zext, i32x4_type);
+    emit_simd_shift!(i32x4_shl, create_shl, i32x4_type, 32);
+    emit_simd_shift!(i32x4_shr_s, create_ashr, i32x4_type, 32);
+    emit_simd_shift!(i32x4_shr_u, create_lshr, i32x4_type, 32);
+    emit_simd_binop!(i32x4_add, create_add, i32x4_type);
+    emit_simd_binop!(i32x4_sub, create_sub, i32x4_type);
+    emit_simd_binop!(i32x4_mul, create_mul, i32x4_type);
+    emit_simd_min_max!(i32x4_min_s, IntSLT, i32x4_type);
+    emit_simd_min_max!(i32x4_min_u, IntULT, i32x4_type);
+    emit_simd_min_max!(i32x4_max_s, IntSGT, i32x4_type);
+    emit_simd_min_max!(i32x4_max_u, IntUGT, i32x4_type);
+
+    // Adds the products of the adjacent pairs of lanes.
+    fn i32x4_dot_i16x8_s(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+    ) {
+        let right = self.pop_vector(ctx.i16x8_type);
+        let left = self.pop_vector(ctx.i16x8_type);
+        let wide_type = ctx.i32_type.vector(8);
+        let product = self.builder.create_mul(
+            self.builder.create_sext(left, wide_type),
+            self.builder.create_sext(right, wide_type),
+        );
+        let res = self.builder.create_add(
+            self.emit_interleaved_lanes(ctx, product, false),
+            self.emit_interleaved_lanes(ctx, product, true),
+        );
+        self.push_vector(ctx, res);
+    }
+
+    emit_extmul!(i32x4_extmul_low_i16x8_s, i16x8_type, false, create_sext, i32x4_type);
+    emit_extmul!(i32x4_extmul_high_i16x8_s, i16x8_type, true, create_sext, i32x4_type);
+    emit_extmul!(i32x4_extmul_low_i16x8_u, i16x8_type, false, create_zext, i32x4_type);
+    emit_extmul!(i32x4_extmul_high_i16x8_u, i16x8_type, true, create_zext, i32x4_type);
+
+    emit_simd_abs!(i64x2_abs, i64x2_type);
+    emit_simd_neg!(i64x2_neg, i64x2_type);
+    emit_all_true!(i64x2_all_true, i64x2_type);
+    emit_bitmask!(i64x2_bitmask, i64x2_type);
+    emit_extend!(i64x2_extend_low_i32x4_s, i32x4_type, false, create_sext, i64x2_type);
+    emit_extend!(i64x2_extend_high_i32x4_s, i32x4_type, true, create_sext, i64x2_type);
+    emit_extend!(i64x2_extend_low_i32x4_u, i32x4_type, false, create_zext, i64x2_type);
+    emit_extend!(i64x2_extend_high_i32x4_u, i32x4_type, true, create_zext, i64x2_type);
+    emit_simd_shift!(i64x2_shl, create_shl, i64x2_type, 64);
+    emit_simd_shift!(i64x2_shr_s, create_ashr, i64x2_type, 64);
+    emit_simd_shift!(i64x2_shr_u, create_lshr, i64x2_type, 64);
+    emit_simd_binop!(i64x2_add, create_add, i64x2_type);
+    emit_simd_binop!(i64x2_sub, create_sub, i64x2_type);
+    emit_simd_binop!(i64x2_mul, create_mul, i64x2_type);
+    emit_extmul!(i64x2_extmul_low_i32x4_s, i32x4_type, false, create_sext, i64x2_type);
+    emit_extmul!(i64x2_extmul_high_i32x4_s, i32x4_type, true, create_sext, i64x2_type);
+    emit_extmul!(i64x2_extmul_low_i32x4_u, i32x4_type, false, create_zext, i64x2_type);
+    emit_extmul!(i64x2_extmul_high_i32x4_u, i32x4_type, true, create_zext, i64x2_type);
+
+    emit_simd_intrinsic_unop!(f32x4_abs, "llvm.fabs.v4f32", f32x4_type);
+    emit_simd_fneg!(f32x4_neg, i32x4_type, 0x8000_0000);
+    emit_simd_intrinsic_unop!(f32x4_sqrt, "llvm.sqrt.v4f32", f32x4_type);
+    emit_simd_binop!(f32x4_add, create_fadd, f32x4_type);
+    emit_simd_binop!(f32x4_sub, create_fsub, f32x4_type);
+    emit_simd_binop!(f32x4_mul, create_fmul, f32x4_type);
+    emit_simd_binop!(f32x4_div, create_fdiv, f32x4_type);
+    emit_simd_fmin_max!(f32x4_min, f32x4_type, i32x4_type, RealOLT, create_or);
+    emit_simd_fmin_max!(f32x4_max, f32x4_type, i32x4_type, RealOGT, create_and);
+    emit_pmin_max!(f32x4_pmin, f32x4_type, false);
+    emit_pmin_max!(f32x4_pmax, f32x4_type, true);
+
+    emit_simd_intrinsic_unop!(f64x2_abs, "llvm.fabs.v2f64", f64x2_type);
+    emit_simd_fneg!(f64x2_neg, i64x2_type, 0x8000_0000_0000_0000);
+    emit_simd_intrinsic_unop!(f64x2_sqrt, "llvm.sqrt.v2f64", f64x2_type);
+    emit_simd_binop!(f64x2_add, create_fadd, f64x2_type);
+    emit_simd_binop!(f64x2_sub, create_fsub, f64x2_type);
+    emit_simd_binop!(f64x2_mul, create_fmul, f64x2_type);
+    emit_simd_binop!(f64x2_div, create_fdiv, f64x2_type);
+    emit_simd_fmin_max!(f64x2_min, f64x2_type, i64x2_type, RealOLT, create_or);
+    emit_simd_fmin_max!(f64x2_max, f64x2_type, i64x2_type, RealOGT, create_and);
+    emit_pmin_max!(f64x2_pmin, f64x2_type, false);
+    emit_pmin_max!(f64x2_pmax, f64x2_type, true);
+
+    emit_trunc_sat!(i32x4_trunc_sat_f32x4_s, f32x4_type, true, -2147483904.0, 2147483648.0);
+    emit_trunc_sat!(i32x4_trunc_sat_f32x4_u, f32x4_type, false, -1.0, 4294967296.0);
+    emit_simd_cast!(f32x4_convert_i32x4_s, create_si_to_fp, i32x4_type, f32x4_type);
+    emit_simd_cast!(f32x4_convert_i32x4_u, create_ui_to_fp, i32x4_type, f32x4_type);
+    emit_trunc_sat_zero!(i32x4_trunc_sat_f64x2_s_zero, true, -2147483649.0, 2147483648.0);
+    emit_trunc_sat_zero!(i32x4_trunc_sat_f64x2_u_zero, false, -1.0, 4294967296.0);
+    emit_convert_low!(f64x2_convert_low_i32x4_s, create_si_to_fp, i32x4_type, f64x2_type);
+    emit_convert_low!(f64x2_convert_low_i32x4_u, create_ui_to_fp, i32x4_type, f64x2_type);
+}
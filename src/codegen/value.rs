@@ -40,6 +40,10 @@ impl<'ll> Value<'ll> {
     }
 
     pub fn set_volatile(&self, volatile: bool) {
-        unsafe { llvm::LLVMSetVolatile(self.0, if volatile { 0 } else { 1 }) }
+        unsafe { llvm::LLVMSetVolatile(self.0, if volatile { 1 } else { 0 }) }
+    }
+
+    pub fn set_ordering(&self, ordering: llvm::AtomicOrdering) {
+        unsafe { llvm::LLVMSetOrdering(self.0, ordering) }
     }
 }
@@ -1,6 +1,26 @@
+use super::value::Value;
 use super::{ContextCodeGen, FunctionCodeGen, ModuleCodeGen};
 use crate::wasm::Module as WASMModule;
 
+impl<'ll> FunctionCodeGen<'ll> {
+    // The address of a mutable global is the value of its symbol, which is the offset of the
+    // global in the runtime data of the context.
+    fn get_mutable_global_pointer(
+        &self,
+        ctx: &ContextCodeGen<'ll>,
+        module: &ModuleCodeGen<'ll>,
+        index: u32,
+    ) -> Value<'ll> {
+        let global_data_offset = self
+            .builder
+            .create_ptr_to_int(module.globals()[index as usize], ctx.iptr_type);
+        self.builder.create_in_bounds_GEP(
+            self.builder.create_load(self.ctx_ptr.unwrap()),
+            &[global_data_offset],
+        )
+    }
+}
+
 pub trait VariableInstrEmit<'ll> {
     declare_variable_instrs!(declear_op, _);
 }
@@ -37,6 +57,19 @@ impl<'ll> VariableInstrEmit<'ll> for FunctionCodeGen<'ll> {
             .create_store(val, self.local_pointers[index as usize]);
     }
 
+    fn tee_local(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        index: u32,
+    ) {
+        let var = self.pop();
+        self.push(var);
+        self.set_local(ctx, wasm_module, module, index);
+        self.push(var);
+    }
+
     fn get_global(
         &mut self,
         ctx: &ContextCodeGen<'ll>,
@@ -47,35 +80,43 @@ impl<'ll> VariableInstrEmit<'ll> for FunctionCodeGen<'ll> {
         let wasm_type = wasm_module.globals().get_type(index as usize);
         let llvm_type = ctx.get_basic_type(*wasm_type.value_type());
 
-        let value = {
-            if wasm_type.is_mutable() {
-                let global_data_offset = self
-                    .builder
-                    .create_ptr_to_int(module.globals()[index as usize], ctx.iptr_type);
-                let global_pointer = self.builder.create_in_bounds_GEP(
-                    self.builder.create_load(self.ctx_ptr.unwrap()),
-                    &[global_data_offset],
-                );
-                self.builder.load_from_untyped_pointer(
-                    global_pointer,
-                    llvm_type,
-                    wasm_type.value_type().get_bytes() as u32,
-                )
-            } else {
-                if wasm_module.globals().is_define(index as usize) {
-                    unimplemented!()
-                } else {
-                    self.builder.load_from_untyped_pointer(
-                        module.globals()[index as usize],
-                        llvm_type,
-                        wasm_type.value_type().get_bytes() as u32,
-                    )
-                }
-            }
+        let value = if wasm_type.is_mutable() {
+            let global_pointer = self.get_mutable_global_pointer(ctx, module, index);
+            self.builder.load_from_untyped_pointer(
+                global_pointer,
+                llvm_type,
+                wasm_type.value_type().get_bytes() as u32,
+            )
+        } else {
+            // Both the imported and the defined immutable globals are read through the address
+            // of their values.
+            self.builder.load_from_untyped_pointer(
+                module.globals()[index as usize],
+                llvm_type,
+                wasm_type.value_type().get_bytes() as u32,
+            )
         };
 
         self.push(value);
+    }
+
+    fn set_global(
+        &mut self,
+        ctx: &ContextCodeGen<'ll>,
+        wasm_module: &WASMModule,
+        module: &ModuleCodeGen<'ll>,
+        index: u32,
+    ) {
+        let wasm_type = wasm_module.globals().get_type(index as usize);
+        let llvm_type = ctx.get_basic_type(*wasm_type.value_type());
 
-        // self.module.get_wasm_module();
+        let var = self.pop();
+        let value = self.builder.create_bit_cast(var, llvm_type);
+        let global_pointer = self.get_mutable_global_pointer(ctx, module, index);
+        self.builder.store_to_untyped_pointer(
+            value,
+            global_pointer,
+            wasm_type.value_type().get_bytes() as u32,
+        );
     }
 }
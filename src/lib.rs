@@ -56,7 +56,7 @@ macro_rules! define_type_wrapper {
 #[macro_use]
 pub mod codegen;
 mod llvm;
-mod stdlib;
+pub mod stdlib;
 pub mod runtime;
 pub mod wasm;
 mod platform;
\ No newline at end of file
@@ -585,7 +585,7 @@ extern "C" {
     pub fn LLVMVectorType(ElementType: &Type, ElementCount: c_uint) -> &Type;
 
     pub fn LLVMGetElementType(Ty: &Type) -> &Type;
-    // pub fn LLVMGetVectorSize(VectorTy: &Type) -> c_uint;
+    pub fn LLVMGetVectorSize(VectorTy: &Type) -> c_uint;
 
     // // Operations on other types
     pub fn LLVMVoidTypeInContext(C: &Context) -> &Type;
@@ -655,7 +655,7 @@ extern "C" {
     // pub fn LLVMConstZExt(ConstantVal: &'a Value, ToType: &'a Type) -> &'a Value;
     pub fn LLVMConstPtrToInt<'a>(ConstantVal: &'a Value, ToType: &'a Type) -> &'a Value;
     // pub fn LLVMConstIntToPtr(ConstantVal: &'a Value, ToType: &'a Type) -> &'a Value;
-    // pub fn LLVMConstBitCast(ConstantVal: &'a Value, ToType: &'a Type) -> &'a Value;
+    pub fn LLVMConstBitCast<'a>(ConstantVal: &'a Value, ToType: &'a Type) -> &'a Value;
     // pub fn LLVMConstPointerCast(ConstantVal: &'a Value, ToType: &'a Type) -> &'a Value;
     // pub fn LLVMConstExtractValue(
     //     AggConstant: &Value,
@@ -698,6 +698,7 @@ extern "C" {
         Name: *const c_char,
         FunctionTy: &'a Type,
     ) -> &'a Value;
+    pub fn LLVMGetNamedFunction<'a>(M: &'a Module, Name: *const c_char) -> Option<&'a Value>;
     // pub fn LLVMRustGetOrInsertFunction(
     //     M: &'a Module,
     //     Name: *const c_char,
@@ -742,6 +743,7 @@ extern "C" {
 
     // Operations on load/store instructions (only)
     pub fn LLVMSetVolatile(MemoryAccessInst: &Value, volatile: Bool);
+    pub fn LLVMSetOrdering(MemoryAccessInst: &Value, Ordering: AtomicOrdering);
 
     // Operations on phi nodes
     pub fn LLVMAddIncoming<'a>(
@@ -767,6 +769,11 @@ extern "C" {
     // // Terminators
     pub fn LLVMBuildRetVoid<'a>(B: &Builder<'a>) -> &'a Value;
     pub fn LLVMBuildRet<'a>(B: &Builder<'a>, V: &'a Value) -> &'a Value;
+    pub fn LLVMBuildAggregateRet<'a>(
+        B: &Builder<'a>,
+        RetVals: *const &'a Value,
+        N: c_uint,
+    ) -> &'a Value;
     pub fn LLVMBuildBr<'a>(B: &Builder<'a>, Dest: &'a BasicBlock) -> &'a Value;
     pub fn LLVMBuildCondBr<'a>(
         B: &Builder<'a>,
@@ -790,13 +797,22 @@ extern "C" {
     //     Bundle: Option<&OperandBundleDef<'a>>,
     //     Name: *const c_char,
     // ) -> &'a Value;
-    // pub fn LLVMBuildLandingPad(
-    //     B: &Builder<'a>,
-    //     Ty: &'a Type,
-    //     PersFn: &'a Value,
-    //     NumClauses: c_uint,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
+    pub fn LLVMBuildInvoke<'a>(
+        B: &Builder<'a>,
+        Fn: &'a Value,
+        Args: *const &'a Value,
+        NumArgs: c_uint,
+        Then: &'a BasicBlock,
+        Catch: &'a BasicBlock,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildLandingPad<'a>(
+        B: &Builder<'a>,
+        Ty: &'a Type,
+        PersFn: &'a Value,
+        NumClauses: c_uint,
+        Name: *const c_char,
+    ) -> &'a Value;
     // pub fn LLVMBuildResume(B: &Builder<'a>, Exn: &'a Value) -> &'a Value;
     pub fn LLVMBuildUnreachable<'a>(B: &Builder<'a>) -> &'a Value;
 
@@ -838,7 +854,7 @@ extern "C" {
     pub fn LLVMAddCase<'a>(Switch: &'a Value, OnVal: &'a Value, Dest: &'a BasicBlock);
 
     // // Add a clause to the landing pad instruction
-    // pub fn LLVMAddClause(LandingPad: &'a Value, ClauseVal: &'a Value);
+    pub fn LLVMAddClause<'a>(LandingPad: &'a Value, ClauseVal: &'a Value);
 
     // // Set the cleanup on a landing pad instruction
     // pub fn LLVMSetCleanup(LandingPad: &Value, Val: Bool);
@@ -850,120 +866,120 @@ extern "C" {
         RHS: &'a Value,
         Name: *const c_char,
     ) -> &'a Value;
-    // pub fn LLVMBuildFAdd(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildSub(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildFSub(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildMul(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildFMul(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildUDiv(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
+    pub fn LLVMBuildFAdd<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildSub<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildFSub<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildMul<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildFMul<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildUDiv<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
     // pub fn LLVMBuildExactUDiv(
     //     B: &Builder<'a>,
     //     LHS: &'a Value,
     //     RHS: &'a Value,
     //     Name: *const c_char,
     // ) -> &'a Value;
-    // pub fn LLVMBuildSDiv(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
+    pub fn LLVMBuildSDiv<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
     // pub fn LLVMBuildExactSDiv(
     //     B: &Builder<'a>,
     //     LHS: &'a Value,
     //     RHS: &'a Value,
     //     Name: *const c_char,
     // ) -> &'a Value;
-    // pub fn LLVMBuildFDiv(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildURem(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildSRem(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
+    pub fn LLVMBuildFDiv<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildURem<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildSRem<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
     // pub fn LLVMBuildFRem(
     //     B: &Builder<'a>,
     //     LHS: &'a Value,
     //     RHS: &'a Value,
     //     Name: *const c_char,
     // ) -> &'a Value;
-    // pub fn LLVMBuildShl(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildLShr(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildAShr(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
+    pub fn LLVMBuildShl<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildLShr<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildAShr<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
     pub fn LLVMBuildAnd<'a>(
         B: &Builder<'a>,
         LHS: &'a Value,
         RHS: &'a Value,
         Name: *const c_char,
     ) -> &'a Value;
-    // pub fn LLVMBuildOr(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildXor(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
+    pub fn LLVMBuildOr<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildXor<'a>(
+        B: &Builder<'a>,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
     // pub fn LLVMBuildNeg(B: &Builder<'a>, V: &'a Value, Name: *const c_char) -> &'a Value;
     // pub fn LLVMBuildFNeg(B: &Builder<'a>, V: &'a Value, Name: *const c_char) -> &'a Value;
     // pub fn LLVMBuildNot(B: &Builder<'a>, V: &'a Value, Name: *const c_char) -> &'a Value;
@@ -1007,60 +1023,60 @@ extern "C" {
     // ) -> &'a Value;
 
     // // Casts
-    // pub fn LLVMBuildTrunc(
-    //     B: &Builder<'a>,
-    //     Val: &'a Value,
-    //     DestTy: &'a Type,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
+    pub fn LLVMBuildTrunc<'a>(
+        B: &Builder<'a>,
+        Val: &'a Value,
+        DestTy: &'a Type,
+        Name: *const c_char,
+    ) -> &'a Value;
     pub fn LLVMBuildZExt<'a>(
         B: &Builder<'a>,
         Val: &'a Value,
         DestTy: &'a Type,
         Name: *const c_char,
     ) -> &'a Value;
-    // pub fn LLVMBuildSExt(
-    //     B: &Builder<'a>,
-    //     Val: &'a Value,
-    //     DestTy: &'a Type,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildFPToUI(
-    //     B: &Builder<'a>,
-    //     Val: &'a Value,
-    //     DestTy: &'a Type,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildFPToSI(
-    //     B: &Builder<'a>,
-    //     Val: &'a Value,
-    //     DestTy: &'a Type,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildUIToFP(
-    //     B: &Builder<'a>,
-    //     Val: &'a Value,
-    //     DestTy: &'a Type,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildSIToFP(
-    //     B: &Builder<'a>,
-    //     Val: &'a Value,
-    //     DestTy: &'a Type,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildFPTrunc(
-    //     B: &Builder<'a>,
-    //     Val: &'a Value,
-    //     DestTy: &'a Type,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildFPExt(
-    //     B: &Builder<'a>,
-    //     Val: &'a Value,
-    //     DestTy: &'a Type,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
+    pub fn LLVMBuildSExt<'a>(
+        B: &Builder<'a>,
+        Val: &'a Value,
+        DestTy: &'a Type,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildFPToUI<'a>(
+        B: &Builder<'a>,
+        Val: &'a Value,
+        DestTy: &'a Type,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildFPToSI<'a>(
+        B: &Builder<'a>,
+        Val: &'a Value,
+        DestTy: &'a Type,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildUIToFP<'a>(
+        B: &Builder<'a>,
+        Val: &'a Value,
+        DestTy: &'a Type,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildSIToFP<'a>(
+        B: &Builder<'a>,
+        Val: &'a Value,
+        DestTy: &'a Type,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildFPTrunc<'a>(
+        B: &Builder<'a>,
+        Val: &'a Value,
+        DestTy: &'a Type,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildFPExt<'a>(
+        B: &Builder<'a>,
+        Val: &'a Value,
+        DestTy: &'a Type,
+        Name: *const c_char,
+    ) -> &'a Value;
     pub fn LLVMBuildPtrToInt<'a>(
         B: &Builder<'a>,
         Val: &'a Value,
@@ -1100,13 +1116,13 @@ extern "C" {
         RHS: &'a Value,
         Name: *const c_char,
     ) -> &'a Value;
-    // pub fn LLVMBuildFCmp(
-    //     B: &Builder<'a>,
-    //     Op: c_uint,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
+    pub fn LLVMBuildFCmp<'a>(
+        B: &Builder<'a>,
+        Op: c_uint,
+        LHS: &'a Value,
+        RHS: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
 
     // // Miscellaneous instructions
     pub fn LLVMBuildPhi<'a>(B: &Builder<'a>, Ty: &'a Type, Name: *const c_char) -> &'a Value;
@@ -1156,32 +1172,32 @@ extern "C" {
     //     Ty: &'a Type,
     //     Name: *const c_char,
     // ) -> &'a Value;
-    // pub fn LLVMBuildExtractElement(
-    //     B: &Builder<'a>,
-    //     VecVal: &'a Value,
-    //     Index: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildInsertElement(
-    //     B: &Builder<'a>,
-    //     VecVal: &'a Value,
-    //     EltVal: &'a Value,
-    //     Index: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildShuffleVector(
-    //     B: &Builder<'a>,
-    //     V1: &'a Value,
-    //     V2: &'a Value,
-    //     Mask: &'a Value,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
-    // pub fn LLVMBuildExtractValue(
-    //     B: &Builder<'a>,
-    //     AggVal: &'a Value,
-    //     Index: c_uint,
-    //     Name: *const c_char,
-    // ) -> &'a Value;
+    pub fn LLVMBuildExtractElement<'a>(
+        B: &Builder<'a>,
+        VecVal: &'a Value,
+        Index: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildInsertElement<'a>(
+        B: &Builder<'a>,
+        VecVal: &'a Value,
+        EltVal: &'a Value,
+        Index: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildShuffleVector<'a>(
+        B: &Builder<'a>,
+        V1: &'a Value,
+        V2: &'a Value,
+        Mask: &'a Value,
+        Name: *const c_char,
+    ) -> &'a Value;
+    pub fn LLVMBuildExtractValue<'a>(
+        B: &Builder<'a>,
+        AggVal: &'a Value,
+        Index: c_uint,
+        Name: *const c_char,
+    ) -> &'a Value;
     // pub fn LLVMBuildInsertValue(
     //     B: &Builder<'a>,
     //     AggVal: &'a Value,
@@ -1231,45 +1247,32 @@ extern "C" {
     //     LHS: &'a Value,
     // ) -> Option<&'a Value>;
 
-    // // Atomic Operations
-    // pub fn LLVMRustBuildAtomicLoad(
-    //     B: &Builder<'a>,
-    //     PointerVal: &'a Value,
-    //     Name: *const c_char,
-    //     Order: AtomicOrdering,
-    // ) -> &'a Value;
-
-    // pub fn LLVMRustBuildAtomicStore(
-    //     B: &Builder<'a>,
-    //     Val: &'a Value,
-    //     Ptr: &'a Value,
-    //     Order: AtomicOrdering,
-    // ) -> &'a Value;
-
-    // pub fn LLVMRustBuildAtomicCmpXchg(
-    //     B: &Builder<'a>,
-    //     LHS: &'a Value,
-    //     CMP: &'a Value,
-    //     RHS: &'a Value,
-    //     Order: AtomicOrdering,
-    //     FailureOrder: AtomicOrdering,
-    //     Weak: Bool,
-    // ) -> &'a Value;
+    // Atomic Operations
+    pub fn LLVMBuildAtomicCmpXchg<'a>(
+        B: &Builder<'a>,
+        Ptr: &'a Value,
+        Cmp: &'a Value,
+        New: &'a Value,
+        SuccessOrdering: AtomicOrdering,
+        FailureOrdering: AtomicOrdering,
+        SingleThread: Bool,
+    ) -> &'a Value;
 
-    // pub fn LLVMBuildAtomicRMW(
-    //     B: &Builder<'a>,
-    //     Op: AtomicRmwBinOp,
-    //     LHS: &'a Value,
-    //     RHS: &'a Value,
-    //     Order: AtomicOrdering,
-    //     SingleThreaded: Bool,
-    // ) -> &'a Value;
+    pub fn LLVMBuildAtomicRMW<'a>(
+        B: &Builder<'a>,
+        Op: AtomicRmwBinOp,
+        Ptr: &'a Value,
+        Val: &'a Value,
+        Ordering: AtomicOrdering,
+        SingleThread: Bool,
+    ) -> &'a Value;
 
-    // pub fn LLVMRustBuildAtomicFence(
-    //     B: &Builder,
-    //     Order: AtomicOrdering,
-    //     Scope: SynchronizationScope,
-    // );
+    pub fn LLVMBuildFence<'a>(
+        B: &Builder<'a>,
+        Ordering: AtomicOrdering,
+        SingleThread: Bool,
+        Name: *const c_char,
+    ) -> &'a Value;
 
     // /// Writes a module to the specified path. Returns 0 on success.
     // pub fn LLVMWriteBitcodeToFile(M: &Module, Path: *const c_char) -> c_int;
@@ -0,0 +1,24 @@
+// The compiled code raises the wasm exceptions with the unwinder of the platform, and its unwind
+// tables are registered with it when the code is loaded.
+#include <unwind.h>
+
+extern void __register_frame(void *begin);
+extern void __deregister_frame(void *begin);
+
+// Returns only if no frame catches `exception`.
+void nrt_raise_exception(struct _Unwind_Exception *exception) {
+    _Unwind_RaiseException(exception);
+}
+
+void nrt_delete_exception(struct _Unwind_Exception *exception) {
+    _Unwind_DeleteException(exception);
+}
+
+// `begin` is the start of an `.eh_frame` section terminated by a zero length entry.
+void nrt_register_frames(void *begin) {
+    __register_frame(begin);
+}
+
+void nrt_deregister_frames(void *begin) {
+    __deregister_frame(begin);
+}
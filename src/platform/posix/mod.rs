@@ -24,6 +24,32 @@ pub fn alloc_pages(num_pages: u32) -> Result<u64, String> {
     }
 }
 
+// Reserves pages at an address which is a multiple of `1 << align_shift`, by reserving enough
+// pages to contain an aligned range and freeing the rest.
+pub fn alloc_aligned_pages(num_pages: u32, align_shift: u64) -> Result<u64, String> {
+    let align_pages = 1u64 << (align_shift - PAGE_SHIFT);
+    let num_unaligned_pages = num_pages as u64 + align_pages;
+    if num_unaligned_pages > std::u32::MAX as u64 {
+        return Err(format!(
+            "reserving {} pages aligned to {} bytes exceeds the address limit",
+            num_pages,
+            1u64 << align_shift
+        ));
+    }
+    let unaligned_addr = alloc_pages(num_unaligned_pages as u32)?;
+    let align = 1u64 << align_shift;
+    let start_addr = (unaligned_addr + align - 1) & !(align - 1);
+    let num_head_pages = (start_addr - unaligned_addr) >> PAGE_SHIFT;
+    let num_tail_pages = align_pages - num_head_pages;
+    if num_head_pages > 0 {
+        free_pages(unaligned_addr, num_head_pages as u32)?;
+    }
+    if num_tail_pages > 0 {
+        free_pages(start_addr + ((num_pages as u64) << PAGE_SHIFT), num_tail_pages as u32)?;
+    }
+    Ok(start_addr)
+}
+
 pub fn commit_pages(start_addr: u64, num_pages: u32) -> Result<(), String> {
     assert!(
         start_addr & (PAGE_SIZE - 1) == 0,
@@ -47,6 +73,39 @@ pub fn commit_pages(start_addr: u64, num_pages: u32) -> Result<(), String> {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PageAccess {
+    ReadOnly,
+    ReadWrite,
+    ReadExecute,
+}
+
+pub fn protect_pages(start_addr: u64, num_pages: u32, access: PageAccess) -> Result<(), String> {
+    let num_bytes = (num_pages as usize) << PAGE_SHIFT;
+    let prot = match access {
+        PageAccess::ReadOnly => libc::PROT_READ,
+        PageAccess::ReadWrite => libc::PROT_READ | libc::PROT_WRITE,
+        PageAccess::ReadExecute => libc::PROT_READ | libc::PROT_EXEC,
+    };
+    match unsafe { libc::mprotect(start_addr as *mut _, num_bytes, prot) } {
+        0 => Ok(()),
+        _ => Err(format!(
+            "mprotect({:x}, {:x}, {}) failed!",
+            start_addr, num_bytes, prot
+        )),
+    }
+}
+
+// Looks up a symbol of the host process, like the functions of the C library which the
+// compiled code may call.
+pub fn lookup_host_symbol(name: &str) -> Option<u64> {
+    let c_name = std::ffi::CString::new(name).ok()?;
+    match unsafe { libc::dlsym(libc::RTLD_DEFAULT, c_name.as_ptr()) } {
+        addr if addr.is_null() => None,
+        addr => Some(addr as u64),
+    }
+}
+
 pub fn free_pages(start_addr: u64, num_pages: u32) -> Result<(), String> {
     let num_bytes = (num_pages as usize) << PAGE_SHIFT;
     match unsafe { libc::munmap(start_addr as *mut _, num_bytes) } {
@@ -58,4 +117,172 @@ pub fn free_pages(start_addr: u64, num_pages: u32) -> Result<(), String> {
 
 pub fn copy_memory(dest_addr: u64, value: &[u8]) {
     unsafe { libc::memmove(dest_addr as *mut _, value.as_ptr() as *const _, value.len())};
-}
\ No newline at end of file
+}
+extern "C" {
+    fn nrt_call_with_trap_handler(
+        func: extern "C" fn(*mut libc::c_void),
+        payload: *mut libc::c_void,
+    ) -> libc::c_int;
+    fn nrt_is_trap_handler_active() -> libc::c_int;
+    fn nrt_unwind_to_trap_handler() -> !;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Signal {
+    AccessViolation,
+    IntegerDivideByZero,
+    IllegalInstruction,
+}
+
+// Decides whether a signal raised at `pc`, accessing `fault_addr` for access violations, is a
+// trap which should unwind to the innermost `call_with_trap_handler`.
+pub type SignalFilter = fn(signal: Signal, pc: u64, fault_addr: u64) -> bool;
+
+const TRAP_SIGNALS: [libc::c_int; 4] = [libc::SIGSEGV, libc::SIGBUS, libc::SIGFPE, libc::SIGILL];
+
+static INSTALL_SIGNAL_HANDLERS: std::sync::Once = std::sync::Once::new();
+static mut SIGNAL_FILTER: Option<SignalFilter> = None;
+static mut PREV_ACTIONS: Option<[libc::sigaction; 4]> = None;
+
+#[cfg(target_arch = "x86_64")]
+unsafe fn get_pc(ucontext: *mut libc::c_void) -> u64 {
+    let ucontext = &*(ucontext as *const libc::ucontext_t);
+    ucontext.uc_mcontext.gregs[libc::REG_RIP as usize] as u64
+}
+
+// The loader only relocates x86_64 objects, and without the faulting pc every fault would be
+// taken for a trap of the compiled code.
+#[cfg(not(target_arch = "x86_64"))]
+compile_error!("the compiled code can only be loaded and run on x86_64");
+
+// Signals which aren't traps are passed to the handlers installed before ours, so that e.g. the
+// stack overflow detection of Rust keeps working.
+unsafe fn chain_signal(signum: libc::c_int, info: *mut libc::siginfo_t, ucontext: *mut libc::c_void) {
+    let prev_action = match (PREV_ACTIONS.as_ref(), TRAP_SIGNALS.iter().position(|s| *s == signum)) {
+        (Some(actions), Some(idx)) => actions[idx],
+        _ => return,
+    };
+    match prev_action.sa_sigaction {
+        libc::SIG_DFL | libc::SIG_IGN => {
+            // Returning re-executes the faulting instruction, which then takes the previous
+            // action.
+            libc::sigaction(signum, &prev_action, std::ptr::null_mut());
+        }
+        handler if prev_action.sa_flags & libc::SA_SIGINFO != 0 => {
+            let handler: extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void) =
+                std::mem::transmute(handler);
+            handler(signum, info, ucontext);
+        }
+        handler => {
+            let handler: extern "C" fn(libc::c_int) = std::mem::transmute(handler);
+            handler(signum);
+        }
+    }
+}
+
+extern "C" fn signal_handler(
+    signum: libc::c_int,
+    info: *mut libc::siginfo_t,
+    ucontext: *mut libc::c_void,
+) {
+    let signal = match signum {
+        libc::SIGSEGV | libc::SIGBUS => Signal::AccessViolation,
+        libc::SIGFPE => Signal::IntegerDivideByZero,
+        _ => Signal::IllegalInstruction,
+    };
+    unsafe {
+        let pc = get_pc(ucontext);
+        let fault_addr = (*info).si_addr() as u64;
+        let is_trap = nrt_is_trap_handler_active() != 0
+            && SIGNAL_FILTER.map_or(false, |filter| filter(signal, pc, fault_addr));
+        if is_trap {
+            nrt_unwind_to_trap_handler();
+        }
+        chain_signal(signum, info, ucontext);
+    }
+}
+
+// The filter is only set by the first call.
+pub fn install_signal_handlers(filter: SignalFilter) -> Result<(), String> {
+    let mut res = Ok(());
+    INSTALL_SIGNAL_HANDLERS.call_once(|| unsafe {
+        SIGNAL_FILTER = Some(filter);
+        let mut prev_actions: [libc::sigaction; 4] = std::mem::zeroed();
+        for (signum, prev_action) in TRAP_SIGNALS.iter().zip(prev_actions.iter_mut()) {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = signal_handler as usize;
+            action.sa_flags = libc::SA_SIGINFO | libc::SA_NODEFER | libc::SA_ONSTACK;
+            libc::sigemptyset(&mut action.sa_mask);
+            if libc::sigaction(*signum, &action, prev_action) != 0 {
+                res = Err(format!("sigaction({}) failed!", signum));
+            }
+        }
+        PREV_ACTIONS = Some(prev_actions);
+    });
+    res
+}
+
+// Calls `f`, and returns false if it's unwound by a trap. Values owned by the frames which are
+// unwound aren't dropped.
+pub fn call_with_trap_handler<F: FnOnce()>(f: F) -> bool {
+    extern "C" fn call<F: FnOnce()>(payload: *mut libc::c_void) {
+        let f = unsafe { &mut *(payload as *mut Option<F>) };
+        (f.take().unwrap())();
+    }
+    let mut f = Some(f);
+    unsafe { nrt_call_with_trap_handler(call::<F>, &mut f as *mut Option<F> as *mut libc::c_void) == 0 }
+}
+
+pub fn unwind_to_trap_handler() -> ! {
+    unsafe { nrt_unwind_to_trap_handler() }
+}
+
+// The header which the unwinder of the platform expects at the start of an exception, see
+// `_Unwind_Exception` in unwind.h.
+#[repr(C, align(16))]
+pub struct UnwindException {
+    pub exception_class: u64,
+    pub cleanup: Option<extern "C" fn(libc::c_int, *mut UnwindException)>,
+    private: [u64; 2],
+}
+
+impl UnwindException {
+    pub fn new(
+        exception_class: u64,
+        cleanup: extern "C" fn(libc::c_int, *mut UnwindException),
+    ) -> Self {
+        UnwindException {
+            exception_class,
+            cleanup: Some(cleanup),
+            private: [0; 2],
+        }
+    }
+}
+
+extern "C" {
+    fn nrt_raise_exception(exception: *mut UnwindException);
+    fn nrt_delete_exception(exception: *mut UnwindException);
+    fn nrt_register_frames(begin: *const u8);
+    fn nrt_deregister_frames(begin: *const u8);
+}
+
+// The function which the compiled code calls to raise an exception, which returns only if no
+// frame catches it.
+pub fn raise_exception_function() -> u64 {
+    nrt_raise_exception as usize as u64
+}
+
+// Frees an exception with its cleanup function.
+pub fn delete_exception(exception: *mut UnwindException) {
+    unsafe { nrt_delete_exception(exception) }
+}
+
+// Registers the unwind tables of loaded code, so that exceptions can be raised through it.
+// `eh_frame_addr` is the start of an `.eh_frame` section terminated by a zero length entry.
+pub fn register_frames(eh_frame_addr: u64) {
+    unsafe { nrt_register_frames(eh_frame_addr as *const u8) }
+}
+
+pub fn deregister_frames(eh_frame_addr: u64) {
+    unsafe { nrt_deregister_frames(eh_frame_addr as *const u8) }
+}
@@ -0,0 +1,34 @@
+// Rust can't call functions which return twice like `sigsetjmp`, so the runtime enters and
+// leaves the compiled code through these helpers.
+#include <setjmp.h>
+#include <stdlib.h>
+
+static __thread sigjmp_buf *nrt_trap_jmp_buf = NULL;
+
+// Calls `func(payload)`, and returns 1 if it's unwound by `nrt_unwind_to_trap_handler`, or 0
+// if it returns normally. The calls can be nested.
+int nrt_call_with_trap_handler(void (*func)(void *), void *payload) {
+    sigjmp_buf buf;
+    sigjmp_buf *prev_jmp_buf = nrt_trap_jmp_buf;
+    if (sigsetjmp(buf, 1) != 0) {
+        nrt_trap_jmp_buf = prev_jmp_buf;
+        return 1;
+    }
+    nrt_trap_jmp_buf = &buf;
+    func(payload);
+    nrt_trap_jmp_buf = prev_jmp_buf;
+    return 0;
+}
+
+int nrt_is_trap_handler_active(void) {
+    return nrt_trap_jmp_buf != NULL;
+}
+
+// Returns to the innermost `nrt_call_with_trap_handler`, which may be called from a signal
+// handler.
+void nrt_unwind_to_trap_handler(void) {
+    if (nrt_trap_jmp_buf == NULL) {
+        abort();
+    }
+    siglongjmp(*nrt_trap_jmp_buf, 1);
+}
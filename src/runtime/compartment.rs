@@ -1,19 +1,259 @@
-use crate::runtime::context::Context;
+use crate::codegen::BoundsCheckMode;
+use crate::platform;
+use crate::runtime::context::ContextRuntimeData;
+use crate::runtime::limiter::ResourceLimiter;
+use crate::runtime::memory::MemoryRuntimeData;
+use crate::runtime::table::TableRuntimeData;
+use crate::wasm::LoadError;
+use crate::wasm::MAX_PAGES as WASM_MAX_PAGES;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
 
+pub const MAX_MEMORIES: usize = 256;
+pub const MAX_TABLES: usize = 256;
+pub const MAX_CONTEXTS: usize = 1024;
+
+// The compiled code derives the compartment address by masking off the lower 32 bits of the
+// context pointer, so the compartment is a 4GiB aligned reservation which contains the contexts.
+const COMPARTMENT_SHIFT: u64 = 32;
+
+// The layout of the start of the compartment reservation. The compiled code finds the runtime
+// data of memories and tables through their offsets in it, see `resolve_symbol`.
+#[repr(C)]
+struct CompartmentRuntimeData {
+    memorys: [MemoryRuntimeData; MAX_MEMORIES],
+    tables: [TableRuntimeData; MAX_TABLES],
+    contexts: [ContextRuntimeData; MAX_CONTEXTS],
+}
+
+#[derive(Clone, Copy)]
+enum SlotKind {
+    Memory,
+    Table,
+    Context,
+}
+
+impl SlotKind {
+    fn max_slots(self) -> usize {
+        match self {
+            SlotKind::Memory => MAX_MEMORIES,
+            SlotKind::Table => MAX_TABLES,
+            SlotKind::Context => MAX_CONTEXTS,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            SlotKind::Memory => "memories",
+            SlotKind::Table => "tables",
+            SlotKind::Context => "contexts",
+        }
+    }
+
+    fn base_offset(self) -> u64 {
+        let memorys_size = std::mem::size_of::<[MemoryRuntimeData; MAX_MEMORIES]>() as u64;
+        let tables_size = std::mem::size_of::<[TableRuntimeData; MAX_TABLES]>() as u64;
+        match self {
+            SlotKind::Memory => 0,
+            SlotKind::Table => memorys_size,
+            SlotKind::Context => memorys_size + tables_size,
+        }
+    }
+}
+
+struct CompartmentInner {
+    base_addr: u64,
+    bounds_check: BoundsCheckMode,
+    max_memory_pages: u32,
+    limiter: Option<Arc<dyn ResourceLimiter>>,
+    // Whether each slot of the memories, tables and contexts is in use.
+    slots: Mutex<[Vec<bool>; 3]>,
+}
+
+impl Drop for CompartmentInner {
+    fn drop(&mut self) {
+        let num_pages = 1u32 << (COMPARTMENT_SHIFT - platform::PAGE_SHIFT);
+        let _ = platform::free_pages(self.base_addr, num_pages);
+    }
+}
+
+// The memories, tables and contexts of a compartment keep it alive.
+#[derive(Clone)]
 pub struct Compartment {
-    contexts: Vec<Context>
+    inner: Arc<CompartmentInner>,
 }
 
 impl Compartment {
-    pub fn new() -> Compartment {
-        Compartment {
-            contexts: Vec::new()
-        }
+    pub fn new() -> Result<Compartment, LoadError> {
+        Compartment::with_bounds_check(BoundsCheckMode::default())
+    }
+
+    // The memories of the compartment are reserved according to how the code running in it
+    // checks the bounds of memory accesses.
+    pub fn with_bounds_check(bounds_check: BoundsCheckMode) -> Result<Compartment, LoadError> {
+        Compartment::with_memory_limit(bounds_check, WASM_MAX_PAGES)
+    }
+
+    // No memory of the compartment can grow beyond `max_memory_pages` pages, whatever the
+    // maximum of its type.
+    pub fn with_memory_limit(
+        bounds_check: BoundsCheckMode,
+        max_memory_pages: u32,
+    ) -> Result<Compartment, LoadError> {
+        Compartment::create(bounds_check, max_memory_pages, None)
+    }
+
+    // The growth of the memories and tables of the compartment, and the creation of instances in
+    // it, are consulted with `limiter`.
+    pub fn with_resource_limiter(
+        bounds_check: BoundsCheckMode,
+        limiter: Arc<dyn ResourceLimiter>,
+    ) -> Result<Compartment, LoadError> {
+        Compartment::create(bounds_check, WASM_MAX_PAGES, Some(limiter))
+    }
+
+    fn create(
+        bounds_check: BoundsCheckMode,
+        max_memory_pages: u32,
+        limiter: Option<Arc<dyn ResourceLimiter>>,
+    ) -> Result<Compartment, LoadError> {
+        let num_pages = 1u32 << (COMPARTMENT_SHIFT - platform::PAGE_SHIFT);
+        let base_addr = platform::alloc_aligned_pages(num_pages, COMPARTMENT_SHIFT)
+            .map_err(LoadError::Instantiate)?;
+        // The compartment owns its pages from now on, so they are released on errors.
+        let inner = CompartmentInner {
+            base_addr,
+            bounds_check,
+            max_memory_pages,
+            limiter,
+            slots: Mutex::new([
+                vec![false; MAX_MEMORIES],
+                vec![false; MAX_TABLES],
+                vec![false; MAX_CONTEXTS],
+            ]),
+        };
+        let runtime_data_size = std::mem::size_of::<CompartmentRuntimeData>() as u64;
+        let num_runtime_data_pages =
+            (runtime_data_size + platform::PAGE_SIZE - 1) >> platform::PAGE_SHIFT;
+        platform::commit_pages(base_addr, num_runtime_data_pages as u32)
+            .map_err(LoadError::Instantiate)?;
+        Ok(Compartment {
+            inner: Arc::new(inner),
+        })
+    }
+
+    #[inline]
+    pub fn bounds_check(&self) -> BoundsCheckMode {
+        self.inner.bounds_check
     }
 
-    // pub fn create_context(&self) -> Result<Context, String> {
-    //     let ctx = Context;
-    //     self.contexts.push(ctx);
-    //     Ok(ctx)
-    // }
+    #[inline]
+    pub fn max_memory_pages(&self) -> u32 {
+        self.inner.max_memory_pages
+    }
+
+    #[inline]
+    pub fn resource_limiter(&self) -> Option<&dyn ResourceLimiter> {
+        self.inner.limiter.as_ref().map(|limiter| &**limiter)
+    }
+
+    #[inline]
+    pub fn base_addr(&self) -> u64 {
+        self.inner.base_addr
+    }
+
+    fn alloc_slot<T>(&self, kind: SlotKind, data: T) -> Result<RuntimeDataSlot<T>, LoadError> {
+        let mut slots = self.inner.slots.lock().unwrap();
+        let used = &mut slots[kind as usize];
+        let index = match used.iter().position(|used| !used) {
+            Some(index) => index,
+            None => {
+                return Err(LoadError::Unsupported(format!(
+                    "more than {} {} in a compartment",
+                    kind.max_slots(),
+                    kind.name()
+                )))
+            }
+        };
+        used[index] = true;
+        let slot: RuntimeDataSlot<T> = RuntimeDataSlot {
+            compartment: self.clone(),
+            kind,
+            index,
+            _marker: PhantomData,
+        };
+        unsafe { std::ptr::write(slot.addr() as *mut T, data) };
+        Ok(slot)
+    }
+
+    pub(super) fn alloc_memory_data(
+        &self,
+        data: MemoryRuntimeData,
+    ) -> Result<RuntimeDataSlot<MemoryRuntimeData>, LoadError> {
+        self.alloc_slot(SlotKind::Memory, data)
+    }
+
+    pub(super) fn alloc_table_data(
+        &self,
+        data: TableRuntimeData,
+    ) -> Result<RuntimeDataSlot<TableRuntimeData>, LoadError> {
+        self.alloc_slot(SlotKind::Table, data)
+    }
+
+    pub(super) fn alloc_context_data(
+        &self,
+        data: ContextRuntimeData,
+    ) -> Result<RuntimeDataSlot<ContextRuntimeData>, LoadError> {
+        self.alloc_slot(SlotKind::Context, data)
+    }
+}
+
+// The runtime data of a memory, table or context in the compartment reservation, which is
+// released on drop.
+pub struct RuntimeDataSlot<T> {
+    compartment: Compartment,
+    kind: SlotKind,
+    index: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T> RuntimeDataSlot<T> {
+    // The offset of the runtime data from the compartment address.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.kind.base_offset() + (self.index * std::mem::size_of::<T>()) as u64
+    }
+
+    #[inline]
+    pub fn addr(&self) -> u64 {
+        self.compartment.base_addr() + self.offset()
+    }
+
+    #[inline]
+    pub fn compartment(&self) -> &Compartment {
+        &self.compartment
+    }
+}
+
+impl<T> Deref for RuntimeDataSlot<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*(self.addr() as *const T) }
+    }
+}
+
+impl<T> DerefMut for RuntimeDataSlot<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *(self.addr() as *mut T) }
+    }
+}
+
+impl<T> Drop for RuntimeDataSlot<T> {
+    fn drop(&mut self) {
+        unsafe { std::ptr::drop_in_place(self.addr() as *mut T) };
+        let mut slots = self.compartment.inner.slots.lock().unwrap();
+        slots[self.kind as usize][self.index] = false;
+    }
 }
@@ -1,9 +1,131 @@
-use crate::runtime::compartment::Compartment;
+use crate::runtime::compartment::{Compartment, RuntimeDataSlot};
+use crate::runtime::data::Segments;
+use crate::runtime::global::{Global, UntaggedValue};
+use crate::runtime::memory::Memory;
+use crate::runtime::table::{FunctionRef, Table};
+use crate::wasm::LoadError;
+use std::sync::Arc;
 
-pub struct Context;
+pub const MAX_MUTABLE_GLOBALS: usize = 256;
+
+// The data of a context which the compiled code reaches through the context pointer. The
+// runtime functions called by the compiled code find the memories, the tables and the segments
+// of the instance through it.
+#[repr(C)]
+pub struct ContextRuntimeData {
+    mutable_globals: [UntaggedValue; MAX_MUTABLE_GLOBALS],
+    memorys: *const Arc<Memory>,
+    num_memorys: usize,
+    tables: *const Arc<Table>,
+    num_tables: usize,
+    function_refs: *const FunctionRef,
+    num_function_refs: usize,
+    segments: *mut Segments,
+}
+
+impl ContextRuntimeData {
+    // The offset of the `idx`th mutable global from the context pointer.
+    pub fn mutable_global_offset(idx: usize) -> Result<usize, LoadError> {
+        if idx >= MAX_MUTABLE_GLOBALS {
+            return Err(LoadError::Unsupported(format!(
+                "more than {} mutable globals",
+                MAX_MUTABLE_GLOBALS
+            )));
+        }
+        Ok(idx * std::mem::size_of::<UntaggedValue>())
+    }
+
+    pub fn memory(&self, idx: usize) -> Option<&Memory> {
+        if idx >= self.num_memorys {
+            return None;
+        }
+        Some(unsafe { &**self.memorys.add(idx) })
+    }
+
+    pub fn table(&self, idx: usize) -> Option<&Table> {
+        if idx >= self.num_tables {
+            return None;
+        }
+        Some(unsafe { &**self.tables.add(idx) })
+    }
+
+    #[inline]
+    pub fn segments(&self) -> &Segments {
+        unsafe { &*self.segments }
+    }
+
+    #[inline]
+    pub fn segments_mut(&mut self) -> &mut Segments {
+        unsafe { &mut *self.segments }
+    }
+
+    // Whether `value` is null or the reference to a function of the instance. The host can't
+    // pass any other value as a `funcref`, since the compiled code calls through it.
+    pub fn is_function_ref(&self, value: u64) -> bool {
+        let start = self.function_refs as u64;
+        let size = std::mem::size_of::<FunctionRef>() as u64;
+        value == 0
+            || (value >= start
+                && value < start + self.num_function_refs as u64 * size
+                && (value - start) % size == 0)
+    }
+}
+
+pub struct Context {
+    runtime_data: RuntimeDataSlot<ContextRuntimeData>,
+}
+
+// The memories and the tables which the runtime data points to are owned by the instance, and
+// are only accessed through shared references, so a context can be moved to another thread with
+// its instance. The instances of a
+// compartment which import a shared memory can then run on several threads at once.
+unsafe impl Send for Context {}
 
 impl Context {
-    // pub fn new(compartment: &Compartment) -> Self {
-    //     let id = compartment.new_id()
-    // }
-}
\ No newline at end of file
+    // Every context has its own copy of the mutable globals, which starts with their initial
+    // values. The memories, the tables, the function references and the segments must outlive
+    // the context, and must not be moved.
+    pub(super) fn new(
+        compartment: &Compartment,
+        globals: &[Global],
+        memorys: &[Arc<Memory>],
+        tables: &[Arc<Table>],
+        function_refs: &[FunctionRef],
+        segments: &mut Segments,
+    ) -> Result<Self, LoadError> {
+        let mut runtime_data = compartment.alloc_context_data(ContextRuntimeData {
+            mutable_globals: [UntaggedValue::zero(); MAX_MUTABLE_GLOBALS],
+            memorys: memorys.as_ptr(),
+            num_memorys: memorys.len(),
+            tables: tables.as_ptr(),
+            num_tables: tables.len(),
+            function_refs: function_refs.as_ptr(),
+            num_function_refs: function_refs.len(),
+            segments,
+        })?;
+        for global in globals {
+            if let Some(offset) = global.mutable_data_offset() {
+                runtime_data.mutable_globals[offset / std::mem::size_of::<UntaggedValue>()] =
+                    *global.initial_value();
+            }
+        }
+        Ok(Context { runtime_data })
+    }
+
+    // The context pointer which is passed to the compiled code, inside the compartment
+    // reservation.
+    #[inline]
+    pub fn runtime_data_addr(&self) -> u64 {
+        self.runtime_data.addr()
+    }
+
+    // The current value of the mutable global at `offset`, see `Global::mutable_data_offset`.
+    pub(super) fn mutable_global(&self, offset: usize) -> UntaggedValue {
+        self.runtime_data.mutable_globals[offset / std::mem::size_of::<UntaggedValue>()]
+    }
+
+    #[inline]
+    pub(super) fn is_function_ref(&self, value: u64) -> bool {
+        self.runtime_data.is_function_ref(value)
+    }
+}
@@ -1,26 +1,260 @@
+use crate::runtime::global::Global;
 use crate::runtime::memory::Memory;
-use std::ops::Index;
-use crate::wasm::Data as WASMData;
+use crate::runtime::table::{FunctionRef, Table};
+use crate::wasm::{DataMode, ElementMode};
 use crate::wasm::Instruction as WASMInstruction;
+use crate::wasm::LoadError;
 use crate::wasm::Module as WASMModule;
+use crate::wasm::Operator as WASMOperator;
 use crate::wasm::Value;
+use std::sync::Arc;
 
-pub fn eval_const_expr(instr: &WASMInstruction, module: &WASMModule) -> Result<Value, String> {
-    match instr {
-        // WASMInstruction::GetGlobal(idx) => {
-        //     if module.globals().is_import(*idx as usize) {
-        //         module.globals().imports[*idx].
-        //     }
-        // }
-        // TODO: Add the the support of get.global instruction
-        WASMInstruction::I32Const(v) => Ok(Value::I32(*v)),
-        _ => Err("Unexpected instruction in constant expression.".to_string()),
+// `globals` are the globals which have been created so far, and only imported globals can be
+// referred to by a constant expression. `ref.func` refers to the function references of the
+// instance, which are filled once the code is loaded.
+pub fn eval_const_expr(
+    op: &WASMOperator,
+    globals: &[Global],
+    function_refs: &[FunctionRef],
+) -> Result<Value, LoadError> {
+    match op {
+        WASMOperator::Instr(instr) => match instr {
+            WASMInstruction::GetGlobal(idx) => match globals.get(*idx as usize) {
+                Some(global) => Ok(global.get_value()),
+                None => Err(LoadError::Malformed(format!(
+                    "unknown global {} in constant expression",
+                    idx
+                ))),
+            },
+            WASMInstruction::I32Const(v) => Ok(Value::I32(*v)),
+            WASMInstruction::I64Const(v) => Ok(Value::I64(*v)),
+            WASMInstruction::F32Const(v) => Ok(Value::F32(f32::from_bits(*v))),
+            WASMInstruction::F64Const(v) => Ok(Value::F64(f64::from_bits(*v))),
+            WASMInstruction::V128Const(v) => Ok(Value::V128(**v)),
+            _ => Err(LoadError::Unsupported(format!(
+                "the instruction {:?} in constant expression",
+                instr
+            ))),
+        },
+        WASMOperator::RefNull(ty) => Value::null_ref(*ty).ok_or_else(|| {
+            LoadError::Malformed(format!("ref.null of non-reference type {}", ty))
+        }),
+        WASMOperator::RefFunc(idx) => match function_refs.get(*idx as usize) {
+            Some(func_ref) => Ok(Value::AnyFunc(func_ref as *const FunctionRef as u64)),
+            None => Err(LoadError::Malformed(format!(
+                "unknown function {} in constant expression",
+                idx
+            ))),
+        },
+        _ => Err(LoadError::Unsupported(format!(
+            "the instruction {:?} in constant expression",
+            op
+        ))),
     }
 }
 
-pub fn fill_data(memory: &mut Memory, data: &WASMData, module: &WASMModule) -> Result<(), String> {
-    match  eval_const_expr(data.offset_instr(), module)? {
-        Value::I32(offset) => memory.copy_into_data(offset as u64, data.value()),
-        _ => Err(format!("the init expr type of data {:?} doesn't match its declaration", data.offset_instr()))
+// The offsets of segments are i32 constant expressions, but they are interpreted as unsigned.
+fn eval_offset(op: &WASMOperator, globals: &[Global], what: &str) -> Result<u32, LoadError> {
+    match eval_const_expr(op, globals, &[])? {
+        Value::I32(offset) => Ok(offset as u32),
+        value => Err(LoadError::Malformed(format!(
+            "the offset of {} must be an i32, but got {:?}",
+            what, value
+        ))),
+    }
+}
+
+// The references are stored in the tables as their untagged bits.
+fn eval_ref(
+    op: &WASMOperator,
+    globals: &[Global],
+    function_refs: &[FunctionRef],
+) -> Result<u64, LoadError> {
+    match eval_const_expr(op, globals, function_refs)? {
+        Value::AnyRef(v) | Value::AnyFunc(v) => Ok(v),
+        value => Err(LoadError::Malformed(format!(
+            "the member of an element segment must be a reference, but got {:?}",
+            value
+        ))),
+    }
+}
+
+// The element and data segments of an instance, which `table.init` and `memory.init` copy from.
+// The active and declared segments are dropped at instantiation, and `elem.drop` and `data.drop`
+// drop the passive ones, which then behave as empty segments.
+#[derive(Default)]
+pub struct Segments {
+    elems: Vec<Box<[u64]>>,
+    datas: Vec<Box<[u8]>>,
+}
+
+fn segment_range<T>(segment: &[T], offset: u32, count: u32) -> Option<&[T]> {
+    segment.get(offset as usize..offset as usize + count as usize)
+}
+
+impl Segments {
+    // The `count` elements of the element segment `idx` from `offset`, or `None` if they are out
+    // of its bounds.
+    pub fn elems(&self, idx: u32, offset: u32, count: u32) -> Option<&[u64]> {
+        self.elems
+            .get(idx as usize)
+            .and_then(|elems| segment_range(elems, offset, count))
+    }
+
+    pub fn data(&self, idx: u32, offset: u32, count: u32) -> Option<&[u8]> {
+        self.datas
+            .get(idx as usize)
+            .and_then(|data| segment_range(data, offset, count))
+    }
+
+    pub fn drop_elem(&mut self, idx: u32) {
+        if let Some(elems) = self.elems.get_mut(idx as usize) {
+            *elems = Box::new([]);
+        }
+    }
+
+    pub fn drop_data(&mut self, idx: u32) {
+        if let Some(data) = self.datas.get_mut(idx as usize) {
+            *data = Box::new([]);
+        }
+    }
+}
+
+fn check_segment_bounds(what: &str, offset: u32, len: usize, size: u64) -> Result<(), LoadError> {
+    if offset as u64 + len as u64 > size {
+        return Err(LoadError::Instantiate(format!(
+            "{} is out of bounds: offset {} + length {} exceeds the size {}",
+            what, offset, len, size
+        )));
+    }
+    Ok(())
+}
+
+// Checks all the active element and data segments against the current sizes of their tables
+// and memories before any of them is copied, so that a failed instantiation doesn't modify the
+// imported memories. The passive segments are kept in `segments`.
+pub fn init_segments(
+    module: &WASMModule,
+    tables: &[Arc<Table>],
+    memorys: &[Arc<Memory>],
+    globals: &[Global],
+    function_refs: &[FunctionRef],
+    segments: &mut Segments,
+) -> Result<(), LoadError> {
+    let eval_members = |members: &[WASMOperator]| {
+        members
+            .iter()
+            .map(|member| eval_ref(member, globals, function_refs))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let mut elem_segments = Vec::with_capacity(module.elements().len());
+    let mut instance_elems = Vec::with_capacity(module.elements().len());
+    for (i, elem) in module.elements().iter().enumerate() {
+        let (table_idx, offset) = match elem.mode() {
+            ElementMode::Active { table_idx, offset } => (*table_idx as usize, offset),
+            ElementMode::Passive => {
+                instance_elems.push(eval_members(elem.members())?.into_boxed_slice());
+                continue;
+            }
+            ElementMode::Declared => {
+                instance_elems.push(Box::new([]) as Box<[u64]>);
+                continue;
+            }
+        };
+        instance_elems.push(Box::new([]));
+        let what = format!("element segment {}", i);
+        let table = tables
+            .get(table_idx)
+            .ok_or_else(|| LoadError::Malformed(format!("unknown table {} in {}", table_idx, what)))?;
+        let offset = eval_offset(offset, globals, &what)?;
+        let elements = eval_members(elem.members())?;
+        check_segment_bounds(&what, offset, elements.len(), table.len() as u64)?;
+        elem_segments.push((table_idx, offset, elements));
+    }
+
+    let mut data_segments = Vec::with_capacity(module.datas().len());
+    let mut instance_datas = Vec::with_capacity(module.datas().len());
+    for (i, data) in module.datas().iter().enumerate() {
+        let (memory_idx, offset) = match data.mode() {
+            DataMode::Active { memory_idx, offset } => (*memory_idx as usize, offset),
+            DataMode::Passive => {
+                instance_datas.push(data.value().to_vec().into_boxed_slice());
+                continue;
+            }
+        };
+        instance_datas.push(Box::new([]) as Box<[u8]>);
+        let what = format!("data segment {}", i);
+        let memory = memorys.get(memory_idx).ok_or_else(|| {
+            LoadError::Malformed(format!("unknown memory {} in {}", memory_idx, what))
+        })?;
+        let offset = eval_offset(offset, globals, &what)?;
+        check_segment_bounds(&what, offset, data.value().len(), memory.byte_length())?;
+        data_segments.push((memory, offset, data.value()));
+    }
+    segments.elems = instance_elems;
+    segments.datas = instance_datas;
+
+    for (table_idx, offset, elements) in elem_segments {
+        tables[table_idx].copy_into_elements(offset, &elements)?;
+    }
+    for (memory, offset, value) in data_segments {
+        memory.copy_into_data(offset as u64, value)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::compartment::Compartment;
+    use crate::runtime::memory::create_memory;
+    use crate::runtime::table::create_table;
+    use crate::wasm::test_util::{module, section};
+    use crate::wasm::types::{MemoryType, TableType};
+    use crate::wasm::ValueType;
+
+    #[test]
+    fn out_of_bounds_segment_leaves_memory_and_tables_untouched() {
+        let bytes = module(&[
+            section(1, &[0x01, 0x60, 0x00, 0x00]),
+            section(3, &[0x01, 0x00]),
+            // (elem (i32.const 0) 0)
+            section(9, &[0x01, 0x00, 0x41, 0x00, 0x0b, 0x01, 0x00]),
+            section(10, &[0x01, 0x02, 0x00, 0x0b]),
+            // (data (i32.const 0) "abc") (data (i32.const 65534) "xyz")
+            section(
+                11,
+                &[
+                    0x02, 0x00, 0x41, 0x00, 0x0b, 0x03, b'a', b'b', b'c', 0x00, 0x41, 0xfe, 0xff,
+                    0x03, 0x0b, 0x03, b'x', b'y', b'z',
+                ],
+            ),
+        ]);
+        let module = WASMModule::parse(&bytes).unwrap();
+        let compartment = Compartment::new().unwrap();
+        // The table and the memory are created here rather than by an instance, so they can be
+        // inspected after the failure.
+        let table = create_table(&compartment, &TableType::new(ValueType::AnyFunc, 1, None));
+        let memory = create_memory(&compartment, &MemoryType::new(1, None, false));
+        let tables = [Arc::new(table.unwrap())];
+        let memorys = [Arc::new(memory.unwrap())];
+        let function_refs = [FunctionRef::null()];
+        let mut segments = Segments::default();
+
+        let res = init_segments(
+            &module,
+            &tables,
+            &memorys,
+            &[],
+            &function_refs,
+            &mut segments,
+        );
+        match res {
+            Err(LoadError::Instantiate(_)) => {}
+            _ => panic!("the data segment 1 is out of bounds"),
+        }
+        assert_eq!(memorys[0].read_bytes(0, 3), Some(vec![0, 0, 0]));
+        assert_eq!(memorys[0].read_bytes(65534, 2), Some(vec![0, 0]));
+        assert_eq!(tables[0].get(0), Some(0));
     }
 }
@@ -0,0 +1,136 @@
+use crate::platform::{self, UnwindException};
+use crate::runtime::context::ContextRuntimeData;
+use crate::runtime::global::UntaggedValue;
+use crate::wasm::ValueType;
+use std::cell::RefCell;
+
+// The class of the exceptions raised by the compiled code, which tells them apart from the
+// foreign exceptions, e.g. the ones of C++, that unwind through it.
+const EXCEPTION_CLASS: u64 = u64::from_be_bytes(*b"NRT\0WASM");
+
+// The runtime type of a tag of an instance. The compiled code identifies it by its address,
+// which it imports as `exceptionTypeId{i}`.
+pub struct ExceptionType {
+    tag_index: u32,
+    params: Vec<ValueType>,
+}
+
+impl ExceptionType {
+    pub fn new(tag_index: u32, params: Vec<ValueType>) -> Self {
+        ExceptionType { tag_index, params }
+    }
+
+    #[inline]
+    pub fn tag_index(&self) -> u32 {
+        self.tag_index
+    }
+
+    #[inline]
+    pub fn id(&self) -> u64 {
+        self as *const ExceptionType as u64
+    }
+}
+
+// The unwinder passes the header to the landing pads, so it must come first.
+#[repr(C)]
+struct Exception {
+    header: UnwindException,
+    ty: *const ExceptionType,
+    args: Box<[UntaggedValue]>,
+}
+
+thread_local! {
+    // The exceptions created or caught on this thread which aren't destroyed yet. The compiled
+    // code destroys an exception at the end of the catch clause which handles it, and the others
+    // are destroyed when the call from the host which created them returns.
+    static LIVE_EXCEPTIONS: RefCell<Vec<*mut Exception>> = RefCell::new(Vec::new());
+}
+
+extern "C" fn cleanup_exception(_reason: libc::c_int, exception: *mut UnwindException) {
+    if !exception.is_null() {
+        unsafe { drop(Box::from_raw(exception as *mut Exception)) };
+    }
+}
+
+// Destroys the exceptions which are created while it's alive.
+pub struct ExceptionScope {
+    num_live_exceptions: usize,
+}
+
+impl ExceptionScope {
+    pub fn enter() -> Self {
+        ExceptionScope {
+            num_live_exceptions: LIVE_EXCEPTIONS.with(|live| live.borrow().len()),
+        }
+    }
+}
+
+impl Drop for ExceptionScope {
+    fn drop(&mut self) {
+        let exceptions = LIVE_EXCEPTIONS.with(|live| {
+            let mut live = live.borrow_mut();
+            let len = std::cmp::min(self.num_live_exceptions, live.len());
+            live.split_off(len)
+        });
+        for exception in exceptions {
+            platform::delete_exception(exception as *mut UnwindException);
+        }
+    }
+}
+
+pub(super) extern "C" fn create_exception(
+    _ctx: *mut ContextRuntimeData,
+    type_id: i64,
+) -> i64 {
+    let ty = unsafe { &*(type_id as *const ExceptionType) };
+    let exception = Box::into_raw(Box::new(Exception {
+        header: UnwindException::new(EXCEPTION_CLASS, cleanup_exception),
+        ty,
+        args: vec![UntaggedValue::zero(); ty.params.len()].into_boxed_slice(),
+    }));
+    LIVE_EXCEPTIONS.with(|live| live.borrow_mut().push(exception));
+    exception as i64
+}
+
+pub(super) extern "C" fn exception_args(_ctx: *mut ContextRuntimeData, exception: i64) -> i64 {
+    let exception = unsafe { &mut *(exception as *mut Exception) };
+    exception.args.as_mut_ptr() as i64
+}
+
+// Called by the landing pads. The type id of a foreign exception is 0, so only `catch_all`
+// handles it, and it's destroyed like the others from then on.
+pub(super) extern "C" fn get_exception_type_id(
+    _ctx: *mut ContextRuntimeData,
+    exception: i64,
+) -> i64 {
+    let exception = exception as *mut Exception;
+    if unsafe { (*exception).header.exception_class } == EXCEPTION_CLASS {
+        return unsafe { (*exception).ty as i64 };
+    }
+    LIVE_EXCEPTIONS.with(|live| {
+        let mut live = live.borrow_mut();
+        if !live.contains(&exception) {
+            live.push(exception);
+        }
+    });
+    0
+}
+
+// An exception can be destroyed more than once when a catch clause handles an exception which
+// is rethrown from an enclosing catch clause, so only the live ones are destroyed.
+pub(super) extern "C" fn destroy_exception(_ctx: *mut ContextRuntimeData, exception: i64) {
+    let exception = exception as *mut Exception;
+    let is_live = LIVE_EXCEPTIONS.with(|live| {
+        let mut live = live.borrow_mut();
+        match live.iter().rposition(|e| *e == exception) {
+            Some(pos) => {
+                live.remove(pos);
+                true
+            }
+            None => false,
+        }
+    });
+    if is_live {
+        platform::delete_exception(exception as *mut UnwindException);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::runtime::context::Context;
+use crate::runtime::exception::ExceptionScope;
+use crate::runtime::global::UntaggedValue;
+use crate::runtime::trap::{catch_traps, Trap, TrapKind};
+use crate::wasm::{FunctionType, Value, ValueType};
+use std::marker::PhantomData;
+
+// The invoke thunk of a function takes the context pointer and an array of untyped values, which
+// holds the arguments on entry and the results on return.
+type InvokeThunk = extern "C" fn(u64, *mut UntaggedValue);
+
+// An exported function of an instance.
+pub struct Function<'a> {
+    ty: &'a FunctionType,
+    invoke_thunk: u64,
+    context: &'a Context,
+}
+
+impl<'a> Function<'a> {
+    pub(super) fn new(ty: &'a FunctionType, invoke_thunk: u64, context: &'a Context) -> Self {
+        Function {
+            ty,
+            invoke_thunk,
+            context,
+        }
+    }
+
+    #[inline]
+    pub fn get_type(&self) -> &FunctionType {
+        self.ty
+    }
+
+    // The number of values which the invoke thunk takes, which is enough for both the arguments
+    // and the results.
+    fn num_values(&self) -> usize {
+        self.ty.params().len().max(self.ty.results().len()).max(1)
+    }
+
+    // `values` must have room for both the arguments and the results. They are owned by the
+    // caller, since a trap unwinds the frames from `catch_traps` without dropping them.
+    fn invoke(&self, values: &mut [UntaggedValue]) -> Result<(), Trap> {
+        debug_assert!(values.len() >= self.num_values());
+        let invoke_thunk: InvokeThunk = unsafe { std::mem::transmute(self.invoke_thunk as usize) };
+        let ctx = self.context.runtime_data_addr();
+        let values = values.as_mut_ptr();
+        let _exception_scope = ExceptionScope::enter();
+        catch_traps(|| invoke_thunk(ctx, values))
+    }
+
+    pub fn call(&self, args: &[Value]) -> Result<Vec<Value>, Trap> {
+        if args.len() != self.ty.params().len()
+            || args
+                .iter()
+                .zip(self.ty.params())
+                .any(|(arg, ty)| arg.value_type() != *ty)
+        {
+            return Err(Trap::new(TrapKind::SignatureMismatch));
+        }
+        // The compiled code calls through a `funcref`, so only those of the instance can be
+        // passed, while an `anyref` may be any value of the host.
+        if args.iter().any(|arg| match arg {
+            Value::AnyFunc(func_ref) => !self.context.is_function_ref(*func_ref),
+            _ => false,
+        }) {
+            return Err(Trap::new(TrapKind::SignatureMismatch));
+        }
+        let mut values = args
+            .iter()
+            .map(|arg| UntaggedValue::from(*arg))
+            .collect::<Vec<_>>();
+        values.resize(self.num_values(), UntaggedValue::zero());
+        self.invoke(&mut values)?;
+        Ok(self
+            .ty
+            .results()
+            .iter()
+            .zip(values.iter())
+            .map(|(ty, v)| v.get(*ty))
+            .collect())
+    }
+
+    // Checks the type of the function once, so that it can be called with native values.
+    pub fn typed<P: WasmParams, R: WasmResults>(&self) -> Result<TypedFunction<'a, P, R>, Trap> {
+        if P::value_types() != self.ty.params() || R::value_types() != self.ty.results() {
+            return Err(Trap::new(TrapKind::SignatureMismatch));
+        }
+        Ok(TypedFunction {
+            func: Function::new(self.ty, self.invoke_thunk, self.context),
+            _marker: PhantomData,
+        })
+    }
+}
+
+pub struct TypedFunction<'a, P, R> {
+    func: Function<'a>,
+    _marker: PhantomData<fn(P) -> R>,
+}
+
+impl<'a, P: WasmParams, R: WasmResults> TypedFunction<'a, P, R> {
+    pub fn call(&self, params: P) -> Result<R, Trap> {
+        let mut values = params.into_values();
+        values.resize(self.func.num_values(), UntaggedValue::zero());
+        self.func.invoke(&mut values)?;
+        Ok(R::from_values(&values))
+    }
+}
+
+// The native types of wasm values.
+pub trait WasmTy: Copy {
+    fn value_type() -> ValueType;
+    fn into_value(self) -> Value;
+    fn from_untagged(value: &UntaggedValue) -> Self;
+}
+
+macro_rules! impl_wasm_ty {
+    ($ty:ty, $value_type:ident) => {
+        impl WasmTy for $ty {
+            fn value_type() -> ValueType {
+                ValueType::$value_type
+            }
+
+            fn into_value(self) -> Value {
+                Value::$value_type(self)
+            }
+
+            fn from_untagged(value: &UntaggedValue) -> Self {
+                match value.get(ValueType::$value_type) {
+                    Value::$value_type(v) => v,
+                    _ => unreachable!(),
+                }
+            }
+        }
+    };
+}
+
+impl_wasm_ty!(i32, I32);
+impl_wasm_ty!(i64, I64);
+impl_wasm_ty!(f32, F32);
+impl_wasm_ty!(f64, F64);
+impl_wasm_ty!([u8; 16], V128);
+
+pub trait WasmParams {
+    fn value_types() -> Vec<ValueType>;
+    fn into_values(self) -> Vec<UntaggedValue>;
+}
+
+macro_rules! impl_wasm_params {
+    ($($param:ident),*) => {
+        impl<$($param: WasmTy),*> WasmParams for ($($param,)*) {
+            fn value_types() -> Vec<ValueType> {
+                vec![$($param::value_type()),*]
+            }
+
+            #[allow(non_snake_case)]
+            fn into_values(self) -> Vec<UntaggedValue> {
+                let ($($param,)*) = self;
+                vec![$(UntaggedValue::from($param.into_value())),*]
+            }
+        }
+    };
+}
+
+impl_wasm_params!();
+impl_wasm_params!(A);
+impl_wasm_params!(A, B);
+impl_wasm_params!(A, B, C);
+impl_wasm_params!(A, B, C, D);
+impl_wasm_params!(A, B, C, D, E);
+impl_wasm_params!(A, B, C, D, E, F);
+impl_wasm_params!(A, B, C, D, E, F, G);
+impl_wasm_params!(A, B, C, D, E, F, G, H);
+
+// A single result is returned as is, and multiple results as a tuple.
+pub trait WasmResults {
+    fn value_types() -> Vec<ValueType>;
+    fn from_values(values: &[UntaggedValue]) -> Self;
+}
+
+impl<T: WasmTy> WasmResults for T {
+    fn value_types() -> Vec<ValueType> {
+        vec![T::value_type()]
+    }
+
+    fn from_values(values: &[UntaggedValue]) -> Self {
+        T::from_untagged(&values[0])
+    }
+}
+
+macro_rules! impl_wasm_results {
+    ($($res:ident),*) => {
+        impl<$($res: WasmTy),*> WasmResults for ($($res,)*) {
+            fn value_types() -> Vec<ValueType> {
+                vec![$($res::value_type()),*]
+            }
+
+            #[allow(unused_variables, unused_mut, unused_assignments)]
+            fn from_values(values: &[UntaggedValue]) -> Self {
+                let mut values = values.iter();
+                ($($res::from_untagged(values.next().unwrap()),)*)
+            }
+        }
+    };
+}
+
+impl_wasm_results!();
+impl_wasm_results!(A, B);
+impl_wasm_results!(A, B, C);
+impl_wasm_results!(A, B, C, D);
+impl_wasm_results!(A, B, C, D, E);
+impl_wasm_results!(A, B, C, D, E, F);
+impl_wasm_results!(A, B, C, D, E, F, G);
+impl_wasm_results!(A, B, C, D, E, F, G, H);
@@ -0,0 +1,106 @@
+use crate::wasm::types::{GlobalType, ValueType};
+use crate::wasm::{LoadError, Value};
+
+// The storage of a value of any type, laid out as the compiled code loads it.
+#[repr(C, align(16))]
+#[derive(Clone, Copy)]
+pub struct UntaggedValue([u8; 16]);
+
+impl UntaggedValue {
+    pub fn zero() -> Self {
+        UntaggedValue([0; 16])
+    }
+
+    pub fn get(&self, ty: ValueType) -> Value {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&self.0[..8]);
+        let bits = u64::from_le_bytes(bytes);
+        match ty {
+            ValueType::I32 => Value::I32(bits as u32 as i32),
+            ValueType::I64 => Value::I64(bits as i64),
+            ValueType::F32 => Value::F32(f32::from_bits(bits as u32)),
+            ValueType::F64 => Value::F64(f64::from_bits(bits)),
+            ValueType::V128 => Value::V128(self.0),
+            ValueType::AnyRef => Value::AnyRef(bits),
+            ValueType::AnyFunc => Value::AnyFunc(bits),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl From<Value> for UntaggedValue {
+    fn from(v: Value) -> Self {
+        let mut res = UntaggedValue::zero();
+        match v {
+            Value::I32(v) => res.0[..4].copy_from_slice(&v.to_le_bytes()),
+            Value::U32(v) => res.0[..4].copy_from_slice(&v.to_le_bytes()),
+            Value::I64(v) => res.0[..8].copy_from_slice(&v.to_le_bytes()),
+            Value::U64(v) => res.0[..8].copy_from_slice(&v.to_le_bytes()),
+            Value::F32(v) => res.0[..4].copy_from_slice(&v.to_bits().to_le_bytes()),
+            Value::F64(v) => res.0[..8].copy_from_slice(&v.to_bits().to_le_bytes()),
+            Value::V128(v) => res.0 = v,
+            Value::AnyRef(v) | Value::AnyFunc(v) => res.0[..8].copy_from_slice(&v.to_le_bytes()),
+        }
+        res
+    }
+}
+
+pub struct Global {
+    ty: GlobalType,
+    // The compiled code reads an immutable global through the address of its value, while the
+    // value of a mutable global is copied into the runtime data of each context at
+    // `mutable_data_offset`.
+    value: Box<UntaggedValue>,
+    mutable_data_offset: Option<usize>,
+}
+
+impl Global {
+    #[inline]
+    pub fn get_type(&self) -> &GlobalType {
+        &self.ty
+    }
+
+    // The value of an immutable global. The compiled code only changes the copies of a mutable
+    // global in the contexts, see `Instance::global_value`, so this is its initial value.
+    #[inline]
+    pub fn get_value(&self) -> Value {
+        self.value.get(*self.ty.value_type())
+    }
+
+    #[inline]
+    pub fn initial_value(&self) -> &UntaggedValue {
+        &self.value
+    }
+
+    #[inline]
+    pub fn mutable_data_offset(&self) -> Option<usize> {
+        self.mutable_data_offset
+    }
+
+    // The value the compiled code gets from the address of the `global{i}` symbol.
+    pub fn symbol_value(&self) -> u64 {
+        match self.mutable_data_offset {
+            Some(offset) => offset as u64,
+            None => &*self.value as *const UntaggedValue as u64,
+        }
+    }
+}
+
+pub fn create_global(
+    ty: &GlobalType,
+    value: Value,
+    mutable_data_offset: Option<usize>,
+) -> Result<Global, LoadError> {
+    if value.value_type() != *ty.value_type() {
+        return Err(LoadError::Malformed(format!(
+            "the initial value {:?} doesn't match the global type {:?}",
+            value,
+            ty.value_type()
+        )));
+    }
+    Ok(Global {
+        ty: ty.clone(),
+        value: Box::new(UntaggedValue::from(value)),
+        mutable_data_offset,
+    })
+}
@@ -0,0 +1,163 @@
+use crate::runtime::context::Context;
+use crate::runtime::data::Segments;
+use crate::runtime::exception::ExceptionType;
+use crate::runtime::function::Function;
+use crate::runtime::limiter::InstanceReservation;
+use crate::runtime::global::Global;
+use crate::runtime::memory::Memory;
+use crate::runtime::object::LoadedObject;
+use crate::runtime::resolver::HostFunction;
+use crate::runtime::table::{FunctionRef, Table};
+use crate::runtime::trap::CodeRegistration;
+use crate::wasm::{Export, FunctionType, Value};
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub struct Instance {
+    memorys: Vec<Arc<Memory>>,
+    tables: Vec<Arc<Table>>,
+    globals: Vec<Global>,
+    // The compiled code and the tables refer to the functions by the addresses of their
+    // references.
+    function_refs: Box<[FunctionRef]>,
+    // The segments which `table.init` and `memory.init` copy from.
+    segments: Box<Segments>,
+    host_functions: Vec<Arc<HostFunction>>,
+    // The compiled code compares the exceptions it catches with their addresses.
+    exception_types: Vec<Box<ExceptionType>>,
+    context: Context,
+    // Unregistered before the code is freed.
+    code_registration: CodeRegistration,
+    code: LoadedObject,
+    function_types: Vec<FunctionType>,
+    invoke_thunks: HashMap<u32, u64>,
+    exports: IndexMap<String, Export>,
+    // Released after everything else of the instance.
+    reservation: InstanceReservation,
+}
+
+// The tables which outlive the instance must not refer to its functions, so calling them traps
+// like calling a null element.
+impl Drop for Instance {
+    fn drop(&mut self) {
+        let size = std::mem::size_of::<FunctionRef>() as u64;
+        let start = self.function_refs.as_ptr() as u64;
+        let end = start + self.function_refs.len() as u64 * size;
+        for table in self.tables.iter().filter(|t| Arc::strong_count(t) > 1) {
+            table.clear_elements(|elem| elem >= start && elem < end);
+        }
+    }
+}
+
+impl Instance {
+    pub(super) fn new(
+        reservation: InstanceReservation,
+        memorys: Vec<Arc<Memory>>,
+        tables: Vec<Arc<Table>>,
+        globals: Vec<Global>,
+        function_refs: Box<[FunctionRef]>,
+        segments: Box<Segments>,
+        host_functions: Vec<Arc<HostFunction>>,
+        exception_types: Vec<Box<ExceptionType>>,
+        context: Context,
+        code_registration: CodeRegistration,
+        code: LoadedObject,
+        function_types: Vec<FunctionType>,
+        invoke_thunks: HashMap<u32, u64>,
+        exports: IndexMap<String, Export>,
+    ) -> Self {
+        Self {
+            memorys,
+            tables,
+            globals,
+            function_refs,
+            segments,
+            host_functions,
+            exception_types,
+            context,
+            code_registration,
+            code,
+            function_types,
+            invoke_thunks,
+            exports,
+            reservation,
+        }
+    }
+
+    #[inline]
+    pub fn memorys(&self) -> &[Arc<Memory>] {
+        &self.memorys
+    }
+
+    #[inline]
+    pub fn tables(&self) -> &[Arc<Table>] {
+        &self.tables
+    }
+
+    #[inline]
+    pub fn globals(&self) -> &[Global] {
+        &self.globals
+    }
+
+    // The current value of the `idx`th global, which the compiled code may have set if it's
+    // mutable.
+    pub fn global_value(&self, idx: u32) -> Option<Value> {
+        let global = self.globals.get(idx as usize)?;
+        match global.mutable_data_offset() {
+            Some(offset) => Some(
+                self.context
+                    .mutable_global(offset)
+                    .get(*global.get_type().value_type()),
+            ),
+            None => Some(global.get_value()),
+        }
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        match self.exports.get(name) {
+            Some(Export::Global(idx)) => self.global_value(*idx),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    pub fn get_func(&self, name: &str) -> Option<Function> {
+        match self.exports.get(name) {
+            Some(Export::Function(idx)) => self.get_func_by_index(*idx),
+            _ => None,
+        }
+    }
+
+    // The memory can be imported by other instances of the same compartment, see
+    // `HostModule::memory`.
+    pub fn get_memory(&self, name: &str) -> Option<Arc<Memory>> {
+        match self.exports.get(name) {
+            Some(Export::Memory(idx)) => self.memorys.get(*idx as usize).cloned(),
+            _ => None,
+        }
+    }
+
+    // The table can be imported by other instances of the same compartment, see
+    // `HostModule::table`.
+    pub fn get_table(&self, name: &str) -> Option<Arc<Table>> {
+        match self.exports.get(name) {
+            Some(Export::Table(idx)) => self.tables.get(*idx as usize).cloned(),
+            _ => None,
+        }
+    }
+
+    // Only the exported functions and the start function can be called from the host.
+    pub(super) fn get_func_by_index(&self, idx: u32) -> Option<Function> {
+        let invoke_thunk = self.invoke_thunks.get(&idx)?;
+        Some(Function::new(
+            &self.function_types[idx as usize],
+            *invoke_thunk,
+            &self.context,
+        ))
+    }
+}
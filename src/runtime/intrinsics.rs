@@ -0,0 +1,353 @@
+use crate::platform;
+use crate::runtime::context::ContextRuntimeData;
+use crate::runtime::exception::{
+    create_exception, destroy_exception, exception_args, get_exception_type_id,
+};
+use crate::runtime::global::UntaggedValue;
+use crate::runtime::parking::{park, unpark};
+use crate::runtime::resolver::{HostContext, HostFunction};
+use crate::runtime::trap::{raise_trap, TrapKind};
+use crate::wasm::Value;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+// The traps which the compiled code raises explicitly pass the index of the trapping function.
+macro_rules! define_trap {
+    ($name:ident, $kind:ident) => {
+        extern "C" fn $name(_ctx: *mut ContextRuntimeData, func_index: i32) {
+            raise_trap(TrapKind::$kind, Some(func_index as u32));
+        }
+    };
+}
+
+define_trap!(unreachable_trap, Unreachable);
+define_trap!(divide_by_zero_trap, IntegerDivideByZero);
+define_trap!(integer_overflow_trap, IntegerOverflow);
+define_trap!(invalid_float_operation_trap, InvalidConversionToInteger);
+define_trap!(memory_out_of_bounds_trap, OutOfBoundsMemoryAccess);
+define_trap!(indirect_call_index_out_of_bounds_trap, UndefinedElement);
+define_trap!(uninitialized_element_trap, UninitializedElement);
+define_trap!(indirect_call_signature_mismatch_trap, IndirectCallSignatureMismatch);
+define_trap!(table_out_of_bounds_trap, OutOfBoundsTableAccess);
+define_trap!(uncaught_exception_trap, UncaughtException);
+define_trap!(misaligned_atomic_trap, MisalignedAtomicMemoryAccess);
+
+extern "C" fn current_memory(ctx: *mut ContextRuntimeData, memory_idx: i32) -> i32 {
+    let ctx = unsafe { &mut *ctx };
+    match ctx.memory(memory_idx as usize) {
+        Some(memory) => memory.cur_pages() as i32,
+        None => -1,
+    }
+}
+
+extern "C" fn grow_memory(ctx: *mut ContextRuntimeData, delta_pages: i32, memory_idx: i32) -> i32 {
+    let ctx = unsafe { &mut *ctx };
+    match ctx.memory(memory_idx as usize) {
+        Some(memory) => match memory.grow_pages(delta_pages as u32) {
+            Some(prev_pages) => prev_pages as i32,
+            None => -1,
+        },
+        None => -1,
+    }
+}
+
+// The references are passed as their untagged bits, which are 0 if they are null.
+extern "C" fn table_grow(
+    ctx: *mut ContextRuntimeData,
+    init: u64,
+    delta: i32,
+    table_idx: i32,
+) -> i32 {
+    let ctx = unsafe { &mut *ctx };
+    match ctx.table(table_idx as usize) {
+        Some(table) => match table.grow(delta as u32, init) {
+            Some(prev_elems) => prev_elems as i32,
+            None => -1,
+        },
+        None => -1,
+    }
+}
+
+extern "C" fn table_fill(
+    ctx: *mut ContextRuntimeData,
+    offset: i32,
+    value: u64,
+    count: i32,
+    table_idx: i32,
+) {
+    let ctx = unsafe { &mut *ctx };
+    let filled = ctx
+        .table(table_idx as usize)
+        .and_then(|table| table.fill(offset as u32, value, count as u32));
+    if filled.is_none() {
+        raise_trap(TrapKind::OutOfBoundsTableAccess, None);
+    }
+}
+
+// The instructions of bulk memory check the whole range before copying anything, and trap even
+// if it's empty but starts out of the bounds.
+fn memory_host_addr(ctx: &ContextRuntimeData, memory_idx: i32, offset: i32, count: i32) -> u64 {
+    match ctx
+        .memory(memory_idx as usize)
+        .and_then(|memory| memory.host_addr(offset as u32 as u64, count as u32 as u64))
+    {
+        Some(addr) => addr,
+        None => raise_trap(TrapKind::OutOfBoundsMemoryAccess, None),
+    }
+}
+
+extern "C" fn memory_init(
+    ctx: *mut ContextRuntimeData,
+    dst: i32,
+    src: i32,
+    count: i32,
+    seg_idx: i32,
+    memory_idx: i32,
+) {
+    let ctx = unsafe { &*ctx };
+    let data = match ctx.segments().data(seg_idx as u32, src as u32, count as u32) {
+        Some(data) => data,
+        None => raise_trap(TrapKind::OutOfBoundsMemoryAccess, None),
+    };
+    let addr = memory_host_addr(ctx, memory_idx, dst, count);
+    unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), addr as *mut u8, data.len()) };
+}
+
+extern "C" fn data_drop(ctx: *mut ContextRuntimeData, seg_idx: i32) {
+    let ctx = unsafe { &mut *ctx };
+    ctx.segments_mut().drop_data(seg_idx as u32);
+}
+
+// The ranges may overlap when both are in the same memory.
+extern "C" fn memory_copy(
+    ctx: *mut ContextRuntimeData,
+    dst: i32,
+    src: i32,
+    count: i32,
+    dst_memory_idx: i32,
+    src_memory_idx: i32,
+) {
+    let ctx = unsafe { &*ctx };
+    let src_addr = memory_host_addr(ctx, src_memory_idx, src, count);
+    let dst_addr = memory_host_addr(ctx, dst_memory_idx, dst, count);
+    unsafe { std::ptr::copy(src_addr as *const u8, dst_addr as *mut u8, count as u32 as usize) };
+}
+
+extern "C" fn memory_fill(
+    ctx: *mut ContextRuntimeData,
+    dst: i32,
+    value: i32,
+    count: i32,
+    memory_idx: i32,
+) {
+    let ctx = unsafe { &*ctx };
+    let addr = memory_host_addr(ctx, memory_idx, dst, count);
+    unsafe { std::ptr::write_bytes(addr as *mut u8, value as u8, count as u32 as usize) };
+}
+
+extern "C" fn table_init(
+    ctx: *mut ContextRuntimeData,
+    dst: i32,
+    src: i32,
+    count: i32,
+    seg_idx: i32,
+    table_idx: i32,
+) {
+    let ctx = unsafe { &*ctx };
+    let copied = ctx
+        .segments()
+        .elems(seg_idx as u32, src as u32, count as u32)
+        .and_then(|elems| {
+            ctx.table(table_idx as usize)
+                .and_then(|table| table.write_elements(dst as u32, elems))
+        });
+    if copied.is_none() {
+        raise_trap(TrapKind::OutOfBoundsTableAccess, None);
+    }
+}
+
+extern "C" fn elem_drop(ctx: *mut ContextRuntimeData, seg_idx: i32) {
+    let ctx = unsafe { &mut *ctx };
+    ctx.segments_mut().drop_elem(seg_idx as u32);
+}
+
+extern "C" fn table_copy(
+    ctx: *mut ContextRuntimeData,
+    dst: i32,
+    src: i32,
+    count: i32,
+    dst_table_idx: i32,
+    src_table_idx: i32,
+) {
+    let ctx = unsafe { &*ctx };
+    let copied = match (ctx.table(dst_table_idx as usize), ctx.table(src_table_idx as usize)) {
+        (Some(dst_table), Some(src_table)) => {
+            dst_table.copy_elements(dst as u32, src_table, src as u32, count as u32)
+        }
+        _ => None,
+    };
+    if copied.is_none() {
+        raise_trap(TrapKind::OutOfBoundsTableAccess, None);
+    }
+}
+
+// The compiled code has checked the alignment of `address`, which is the sum of the operand
+// and the offset, but its bounds are only checked in the explicit mode.
+fn atomic_host_addr(
+    ctx: &ContextRuntimeData,
+    memory_idx: i32,
+    address: i64,
+    num_bytes: u64,
+) -> u64 {
+    let memory = match ctx.memory(memory_idx as usize) {
+        Some(memory) => memory,
+        None => raise_trap(TrapKind::OutOfBoundsMemoryAccess, None),
+    };
+    match memory.host_addr(address as u64, num_bytes) {
+        Some(addr) => addr,
+        None => raise_trap(TrapKind::OutOfBoundsMemoryAccess, None),
+    }
+}
+
+// A negative timeout in nanoseconds waits forever.
+fn wait_timeout(timeout: i64) -> Option<Duration> {
+    if timeout < 0 {
+        None
+    } else {
+        Some(Duration::from_nanos(timeout as u64))
+    }
+}
+
+fn check_shared_memory(ctx: &ContextRuntimeData, memory_idx: i32) {
+    if !ctx.memory(memory_idx as usize).map_or(false, |memory| memory.is_shared()) {
+        raise_trap(TrapKind::ExpectedSharedMemory, None);
+    }
+}
+
+extern "C" fn atomic_wait32(
+    ctx: *mut ContextRuntimeData,
+    address: i64,
+    expected: i32,
+    timeout: i64,
+    memory_idx: i32,
+) -> i32 {
+    let ctx = unsafe { &*ctx };
+    let addr = atomic_host_addr(ctx, memory_idx, address, 4);
+    check_shared_memory(ctx, memory_idx);
+    let value = unsafe { &*(addr as *const AtomicU32) };
+    park(addr, || value.load(Ordering::SeqCst) == expected as u32, wait_timeout(timeout)) as i32
+}
+
+extern "C" fn atomic_wait64(
+    ctx: *mut ContextRuntimeData,
+    address: i64,
+    expected: i64,
+    timeout: i64,
+    memory_idx: i32,
+) -> i32 {
+    let ctx = unsafe { &*ctx };
+    let addr = atomic_host_addr(ctx, memory_idx, address, 8);
+    check_shared_memory(ctx, memory_idx);
+    let value = unsafe { &*(addr as *const AtomicU64) };
+    park(addr, || value.load(Ordering::SeqCst) == expected as u64, wait_timeout(timeout)) as i32
+}
+
+// No thread can wait on a memory which isn't shared, so nothing is woken.
+extern "C" fn atomic_notify(
+    ctx: *mut ContextRuntimeData,
+    address: i64,
+    count: i32,
+    memory_idx: i32,
+) -> i32 {
+    let ctx = unsafe { &*ctx };
+    let addr = atomic_host_addr(ctx, memory_idx, address, 4);
+    unpark(addr, count as u32) as i32
+}
+
+// Called by the stub of an imported function, see `ModuleCodeGen::emit_import_stub`.
+extern "C" fn call_host_function(
+    ctx: *mut ContextRuntimeData,
+    func: *const HostFunction,
+    values: *mut UntaggedValue,
+) {
+    // The trap is raised once the arguments and results are dropped, since it unwinds this
+    // frame without running their destructors.
+    if let Err(kind) = try_call_host_function(ctx, func, values) {
+        raise_trap(kind, None);
+    }
+}
+
+fn try_call_host_function(
+    ctx: *mut ContextRuntimeData,
+    func: *const HostFunction,
+    values: *mut UntaggedValue,
+) -> Result<(), TrapKind> {
+    let func = unsafe { &*func };
+    let ty = func.get_type();
+    let args = ty
+        .params()
+        .iter()
+        .enumerate()
+        .map(|(idx, param)| unsafe { (*values.add(idx)).get(*param) })
+        .collect::<Vec<_>>();
+    let mut host_ctx = HostContext::new(unsafe { &mut *ctx });
+    // Unwinding into the compiled code is undefined behavior.
+    let res = panic::catch_unwind(AssertUnwindSafe(|| func.call(&mut host_ctx, &args)))
+        .map_err(|_| TrapKind::HostFunctionPanicked)?;
+    // The compiled code calls through a `funcref`, so only those of the instance are valid.
+    let is_valid = |v: &Value| match v {
+        Value::AnyFunc(func_ref) => unsafe { &*ctx }.is_function_ref(*func_ref),
+        _ => true,
+    };
+    if !res.iter().map(|v| v.value_type()).eq(ty.results().iter().cloned())
+        || !res.iter().all(is_valid)
+    {
+        return Err(TrapKind::SignatureMismatch);
+    }
+    res.into_iter()
+        .enumerate()
+        .for_each(|(idx, v)| unsafe { *values.add(idx) = UntaggedValue::from(v) });
+    Ok(())
+}
+
+// The runtime functions which the compiled code calls by name. They take the context pointer
+// first, like the compiled functions.
+pub fn get_intrinsic(name: &str) -> Option<u64> {
+    let addr = match name {
+        "unreachableTrap" => unreachable_trap as usize,
+        "divideByZeroTrap" => divide_by_zero_trap as usize,
+        "integerOverflowTrap" => integer_overflow_trap as usize,
+        "invalidFloatOperationTrap" => invalid_float_operation_trap as usize,
+        "memoryOutOfBoundsTrap" => memory_out_of_bounds_trap as usize,
+        "indirectCallIndexOutOfBoundsTrap" => indirect_call_index_out_of_bounds_trap as usize,
+        "uninitializedElementTrap" => uninitialized_element_trap as usize,
+        "indirectCallSignatureMismatchTrap" => indirect_call_signature_mismatch_trap as usize,
+        "tableOutOfBoundsTrap" => table_out_of_bounds_trap as usize,
+        "currentMemory" => current_memory as usize,
+        "growMemory" => grow_memory as usize,
+        "tableGrow" => table_grow as usize,
+        "tableFill" => table_fill as usize,
+        "memoryInit" => memory_init as usize,
+        "dataDrop" => data_drop as usize,
+        "memoryCopy" => memory_copy as usize,
+        "memoryFill" => memory_fill as usize,
+        "tableInit" => table_init as usize,
+        "elemDrop" => elem_drop as usize,
+        "tableCopy" => table_copy as usize,
+        "misalignedAtomicTrap" => misaligned_atomic_trap as usize,
+        "atomicWait32" => atomic_wait32 as usize,
+        "atomicWait64" => atomic_wait64 as usize,
+        "atomicNotify" => atomic_notify as usize,
+        "callHostFunction" => call_host_function as usize,
+        "uncaughtExceptionTrap" => uncaught_exception_trap as usize,
+        "createException" => create_exception as usize,
+        "exceptionArgs" => exception_args as usize,
+        "getExceptionTypeId" => get_exception_type_id as usize,
+        "destroyException" => destroy_exception as usize,
+        // Uses the C calling convention, see `FunctionCodeGen::emit_raise_exception`.
+        "raiseException" => platform::raise_exception_function() as usize,
+        _ => return None,
+    };
+    Some(addr as u64)
+}
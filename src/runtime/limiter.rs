@@ -0,0 +1,162 @@
+use crate::runtime::compartment::Compartment;
+use crate::wasm::LoadError;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+
+// Decides whether the memories, tables and instances of a compartment may grow. A denied growth
+// is seen by the guest as a failed `memory.grow`, and by the host as an instantiation error.
+pub trait ResourceLimiter: Send + Sync {
+    // A memory grows from `current` to `desired` bytes, and can't grow beyond `maximum` bytes.
+    fn memory_growing(&self, current: u64, desired: u64, maximum: u64) -> bool;
+
+    // A memory of `bytes` bytes is dropped.
+    fn memory_released(&self, _bytes: u64) {}
+
+    // A table grows from `current` to `desired` elements, and can't grow beyond `maximum`
+    // elements.
+    fn table_growing(&self, current: u32, desired: u32, maximum: u32) -> bool;
+
+    // A table of `elements` elements is dropped.
+    fn table_released(&self, _elements: u32) {}
+
+    fn instance_creating(&self) -> bool {
+        true
+    }
+
+    fn instance_released(&self) {}
+}
+
+// A panicking limiter denies the growth, since the runtime functions which consult it are called
+// by the compiled code, which can't be unwound.
+pub(super) fn ask_limiter<F: FnOnce() -> bool>(f: F) -> bool {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(false)
+}
+
+pub(super) fn notify_limiter<F: FnOnce()>(f: F) {
+    let _ = panic::catch_unwind(AssertUnwindSafe(f));
+}
+
+#[derive(Default)]
+struct ResourceUsage {
+    memory_bytes: u64,
+    table_elements: u64,
+    instances: usize,
+}
+
+// Limits the total size of the memories and tables, and the number of instances, of a
+// compartment.
+#[derive(Default)]
+pub struct CompartmentLimits {
+    max_memory_bytes: Option<u64>,
+    max_table_elements: Option<u64>,
+    max_instances: Option<usize>,
+    usage: Mutex<ResourceUsage>,
+}
+
+impl CompartmentLimits {
+    pub fn new() -> Self {
+        CompartmentLimits::default()
+    }
+
+    pub fn memory_bytes(mut self, max: u64) -> Self {
+        self.max_memory_bytes = Some(max);
+        self
+    }
+
+    pub fn table_elements(mut self, max: u64) -> Self {
+        self.max_table_elements = Some(max);
+        self
+    }
+
+    pub fn instances(mut self, max: usize) -> Self {
+        self.max_instances = Some(max);
+        self
+    }
+
+    #[inline]
+    pub fn memory_bytes_used(&self) -> u64 {
+        self.usage.lock().unwrap().memory_bytes
+    }
+
+    #[inline]
+    pub fn table_elements_used(&self) -> u64 {
+        self.usage.lock().unwrap().table_elements
+    }
+
+    #[inline]
+    pub fn instances_used(&self) -> usize {
+        self.usage.lock().unwrap().instances
+    }
+}
+
+impl ResourceLimiter for CompartmentLimits {
+    fn memory_growing(&self, current: u64, desired: u64, _maximum: u64) -> bool {
+        let mut usage = self.usage.lock().unwrap();
+        let total = usage.memory_bytes + (desired - current);
+        if self.max_memory_bytes.map_or(false, |max| total > max) {
+            return false;
+        }
+        usage.memory_bytes = total;
+        true
+    }
+
+    fn memory_released(&self, bytes: u64) {
+        let mut usage = self.usage.lock().unwrap();
+        usage.memory_bytes -= bytes;
+    }
+
+    fn table_growing(&self, current: u32, desired: u32, _maximum: u32) -> bool {
+        let mut usage = self.usage.lock().unwrap();
+        let total = usage.table_elements + (desired - current) as u64;
+        if self.max_table_elements.map_or(false, |max| total > max) {
+            return false;
+        }
+        usage.table_elements = total;
+        true
+    }
+
+    fn table_released(&self, elements: u32) {
+        let mut usage = self.usage.lock().unwrap();
+        usage.table_elements -= elements as u64;
+    }
+
+    fn instance_creating(&self) -> bool {
+        let mut usage = self.usage.lock().unwrap();
+        if self.max_instances.map_or(false, |max| usage.instances >= max) {
+            return false;
+        }
+        usage.instances += 1;
+        true
+    }
+
+    fn instance_released(&self) {
+        let mut usage = self.usage.lock().unwrap();
+        usage.instances -= 1;
+    }
+}
+
+// Counts an instance against the limiter of its compartment while it's alive.
+pub struct InstanceReservation {
+    compartment: Compartment,
+}
+
+impl Drop for InstanceReservation {
+    fn drop(&mut self) {
+        if let Some(limiter) = self.compartment.resource_limiter() {
+            notify_limiter(|| limiter.instance_released());
+        }
+    }
+}
+
+pub(super) fn reserve_instance(compartment: &Compartment) -> Result<InstanceReservation, LoadError> {
+    if let Some(limiter) = compartment.resource_limiter() {
+        if !ask_limiter(|| limiter.instance_creating()) {
+            return Err(LoadError::Instantiate(
+                "the resource limiter denied creating an instance".to_string(),
+            ));
+        }
+    }
+    Ok(InstanceReservation {
+        compartment: compartment.clone(),
+    })
+}
@@ -1,28 +1,165 @@
-use crate::wasm::types::Type;
+use crate::runtime::compartment::Compartment;
+use crate::runtime::memory::Memory;
+use crate::runtime::resolver::{HostFunction, Resolver};
+use crate::runtime::table::Table;
+use crate::wasm::types::{GlobalType, MemoryType, TableType, Type};
+use crate::wasm::FunctionType;
 use crate::wasm::Import as WASMImport;
+use crate::wasm::LoadError;
 use crate::wasm::Module as WASMModule;
+use crate::wasm::Value;
+use std::fmt::Display;
+use std::sync::Arc;
 
-struct LinkResult {}
+pub struct LinkResult {
+    pub functions: Vec<Arc<HostFunction>>,
+    pub globals: Vec<(GlobalType, Value)>,
+    pub memorys: Vec<Arc<Memory>>,
+    pub tables: Vec<Arc<Table>>,
+}
 
-pub struct Resolver {}
+fn unresolved_import<T: Type + Display>(import: &WASMImport<T>, got: Option<&T>) -> LoadError {
+    let message = format!(
+        "unresolved import {}.{}: expected {}",
+        import.module_name(),
+        import.export_name(),
+        import.get_type()
+    );
+    LoadError::Instantiate(match got {
+        Some(ty) => format!("{}, but got {}", message, ty),
+        None => message,
+    })
+}
 
-fn link_import<T: Type>(import: &WASMImport<T>, resolver: &Resolver) {
-    // if let Some(obj) = resolver.resolve(
-    //     import.module_name(),
-    //     import.export_name(),
-    //     import.get_type(),
-    // ) {
-    //     println!("Resolved");
-    // } else {
+fn link_function<R: Resolver + ?Sized>(
+    import: &WASMImport<FunctionType>,
+    resolver: &R,
+) -> Result<Arc<HostFunction>, LoadError> {
+    match resolver.resolve_function(import.module_name(), import.export_name()) {
+        Some(func) if func.get_type() == import.get_type() => Ok(func),
+        Some(func) => Err(unresolved_import(import, Some(func.get_type()))),
+        None => Err(unresolved_import(import, None)),
+    }
+}
+
+fn link_global<R: Resolver + ?Sized>(
+    import: &WASMImport<GlobalType>,
+    resolver: &R,
+) -> Result<(GlobalType, Value), LoadError> {
+    match resolver.resolve_global(import.module_name(), import.export_name()) {
+        Some((ty, value)) if ty == *import.get_type() => {
+            if ty.is_mutable() {
+                return Err(LoadError::Unsupported("mutable global imports".to_string()));
+            }
+            Ok((ty, value))
+        }
+        Some((ty, _)) => Err(unresolved_import(import, Some(&ty))),
+        None => Err(unresolved_import(import, None)),
+    }
+}
+
+// A memory matches the import if it's at least as large as the minimum size of the import, and
+// can't grow beyond its maximum size.
+fn is_memory_type_match(ty: &MemoryType, import_ty: &MemoryType) -> bool {
+    ty.min_pages() >= import_ty.min_pages()
+        && ty.is_shared() == import_ty.is_shared()
+        && match import_ty.max_pages() {
+            Some(import_max) => ty.max_pages().map_or(false, |max| max <= import_max),
+            None => true,
+        }
+}
+
+fn link_memory<R: Resolver + ?Sized>(
+    import: &WASMImport<MemoryType>,
+    compartment: &Compartment,
+    resolver: &R,
+) -> Result<Arc<Memory>, LoadError> {
+    let memory = match resolver.resolve_memory(import.module_name(), import.export_name()) {
+        Some(memory) => memory,
+        None => return Err(unresolved_import(import, None)),
+    };
+    let ty = memory.get_type();
+    if !is_memory_type_match(&ty, import.get_type()) {
+        return Err(unresolved_import(import, Some(&ty)));
+    }
+    if !memory.is_in_compartment(compartment) {
+        return Err(LoadError::Instantiate(format!(
+            "the memory imported by {}.{} belongs to another compartment",
+            import.module_name(),
+            import.export_name()
+        )));
+    }
+    Ok(memory)
+}
 
-    //     unreachable!();
-    // }
+// A table matches the import like a memory, and must have the same element type.
+fn is_table_type_match(ty: &TableType, import_ty: &TableType) -> bool {
+    ty.elem_type() == import_ty.elem_type()
+        && ty.min_elems() >= import_ty.min_elems()
+        && match import_ty.max_elems() {
+            Some(import_max) => ty.max_elems().map_or(false, |max| max <= import_max),
+            None => true,
+        }
 }
 
-pub fn link_module(wasm_module: &WASMModule, resolver: &Resolver) {
-    wasm_module
+fn link_table<R: Resolver + ?Sized>(
+    import: &WASMImport<TableType>,
+    compartment: &Compartment,
+    resolver: &R,
+) -> Result<Arc<Table>, LoadError> {
+    let table = match resolver.resolve_table(import.module_name(), import.export_name()) {
+        Some(table) => table,
+        None => return Err(unresolved_import(import, None)),
+    };
+    let ty = table.get_type();
+    if !is_table_type_match(&ty, import.get_type()) {
+        return Err(unresolved_import(import, Some(&ty)));
+    }
+    if !table.is_in_compartment(compartment) {
+        return Err(LoadError::Instantiate(format!(
+            "the table imported by {}.{} belongs to another compartment",
+            import.module_name(),
+            import.export_name()
+        )));
+    }
+    Ok(table)
+}
+
+// Resolves the function, global, memory and table imports of a module, in the order of their
+// indices.
+pub fn link_module<R: Resolver + ?Sized>(
+    wasm_module: &WASMModule,
+    compartment: &Compartment,
+    resolver: &R,
+) -> Result<LinkResult, LoadError> {
+    let functions = wasm_module
         .functions()
         .imports()
         .iter()
-        .for_each(|t| link_import(t, resolver));
+        .map(|import| link_function(import, resolver))
+        .collect::<Result<Vec<_>, _>>()?;
+    let globals = wasm_module
+        .globals()
+        .imports()
+        .iter()
+        .map(|import| link_global(import, resolver))
+        .collect::<Result<Vec<_>, _>>()?;
+    let memorys = wasm_module
+        .memorys()
+        .imports()
+        .iter()
+        .map(|import| link_memory(import, compartment, resolver))
+        .collect::<Result<Vec<_>, _>>()?;
+    let tables = wasm_module
+        .tables()
+        .imports()
+        .iter()
+        .map(|import| link_table(import, compartment, resolver))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(LinkResult {
+        functions,
+        globals,
+        memorys,
+        tables,
+    })
 }
@@ -1,60 +1,370 @@
+use crate::codegen::BoundsCheckMode;
 use crate::platform;
-use crate::runtime::compartment::Compartment;
+use crate::runtime::compartment::{Compartment, RuntimeDataSlot};
+use crate::runtime::limiter::{ask_limiter, notify_limiter};
+use crate::runtime::trap::{register_memory, unregister_memory};
 use crate::wasm::types::MemoryType;
+use crate::wasm::LoadError;
+use crate::wasm::MAX_PAGES as WASM_MAX_PAGES;
 use crate::wasm::PAGE_SHIFT as WASM_PAGE_SHIFT;
-use crate::wasm::PAGE_SIZE as WASM_PAGE_SIZE;
+use std::sync::Mutex;
 
-pub struct Memory {
-    start_addr: u64,
+// A 32-bit address plus a 32-bit offset can address up to 8GiB.
+const MAX_ADDRESSABLE_BYTES: u64 = 1 << 33;
+
+// The pages of a memory are committed in host pages, which are smaller than wasm pages.
+const HOST_PAGES_PER_WASM_PAGE_SHIFT: u64 = WASM_PAGE_SHIFT as u64 - platform::PAGE_SHIFT;
+
+// The runtime data of a memory, which the compiled code loads through `memoryOffset{i}`.
+#[repr(C)]
+pub struct MemoryRuntimeData {
+    base: u64,
+    num_bytes: u64,
+}
+
+struct MemoryState {
+    runtime_data: RuntimeDataSlot<MemoryRuntimeData>,
     cur_pages: u32,
-    min_pages: u32,
+    start_addr: u64,
+    num_reserved_pages: u32,
+}
+
+// A memory can be shared by the instances of a compartment which import it, so it's grown
+// through a shared reference. A memory of a shared type can be accessed by instances running on
+// several threads at once.
+pub struct Memory {
+    ty: MemoryType,
+    state: Mutex<MemoryState>,
+    // The maximum of the memory type, capped by the limit of the compartment.
     max_pages: u32,
+    // With explicit bounds checks, only the committed pages of a memory are reserved, and it's
+    // moved to a larger reservation when it grows. The compiled code reloads its base after
+    // anything which can grow it, but a shared memory may be accessed by other threads at any
+    // time, so it's reserved up to its maximum like with guard pages.
+    is_movable: bool,
 }
 
 impl Drop for Memory {
-    fn drop(&mut self) {}
+    fn drop(&mut self) {
+        let state = match self.state.get_mut() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if let Some(limiter) = state.runtime_data.compartment().resource_limiter() {
+            let num_bytes = state.runtime_data.num_bytes;
+            notify_limiter(|| limiter.memory_released(num_bytes));
+        }
+        unregister_memory(state.start_addr);
+        let _ = platform::free_pages(state.start_addr, state.num_reserved_pages);
+    }
 }
 
-//TODO: figure out whether the address space is 32bit or 64bit, which stated in
-//      WebAssembly Specification.
 impl Memory {
-    pub fn grow_pages(&mut self, num_pages: u32) -> Result<u32, String> {
-        let prev_pages = self.cur_pages;
-        if num_pages > self.max_pages || prev_pages + num_pages > self.max_pages {
-            return Err("The number of pages is exceeding address limit".to_string());
+    // Grows the memory by `num_pages` wasm pages, and returns its previous size in pages, or
+    // `None` if it can't grow that much or the resource limiter of the compartment denies it, as
+    // `memory.grow` returns -1.
+    pub fn grow_pages(&self, num_pages: u32) -> Option<u32> {
+        let mut state = self.state.lock().unwrap();
+        let prev_pages = state.cur_pages;
+        if num_pages == 0 {
+            return Some(prev_pages);
         }
-        platform::commit_pages(self.start_addr, num_pages)?;
-        self.cur_pages += prev_pages + num_pages;
-        Ok(prev_pages)
+        let new_pages = prev_pages as u64 + num_pages as u64;
+        if new_pages > self.max_pages as u64 {
+            return None;
+        }
+        if let Some(limiter) = state.runtime_data.compartment().resource_limiter() {
+            let current = (prev_pages as u64) << WASM_PAGE_SHIFT;
+            let desired = new_pages << WASM_PAGE_SHIFT;
+            let maximum = (self.max_pages as u64) << WASM_PAGE_SHIFT;
+            if !ask_limiter(|| limiter.memory_growing(current, desired, maximum)) {
+                return None;
+            }
+        }
+        let committed = if self.is_movable {
+            move_to_reservation(&mut state, new_pages)
+        } else {
+            let commit_addr = state.start_addr + ((prev_pages as u64) << WASM_PAGE_SHIFT);
+            let num_host_pages = (num_pages as u64) << HOST_PAGES_PER_WASM_PAGE_SHIFT;
+            platform::commit_pages(commit_addr, num_host_pages as u32)
+        };
+        if committed.is_err() {
+            if let Some(limiter) = state.runtime_data.compartment().resource_limiter() {
+                let num_bytes = (num_pages as u64) << WASM_PAGE_SHIFT;
+                notify_limiter(|| limiter.memory_released(num_bytes));
+            }
+            return None;
+        }
+        state.cur_pages = new_pages as u32;
+        state.runtime_data.num_bytes = new_pages << WASM_PAGE_SHIFT;
+        Some(prev_pages)
     }
 
-    pub fn drop_all_pages(&mut self) {
-        platform::free_pages(self.start_addr, self.max_pages + 1);
+    #[inline]
+    pub fn cur_pages(&self) -> u32 {
+        self.state.lock().unwrap().cur_pages
     }
 
-    pub fn copy_into_data(&mut self, offset: u64, value: &[u8]) -> Result<(), String> {
+    #[inline]
+    pub fn max_pages(&self) -> u32 {
+        self.max_pages
+    }
+
+    #[inline]
+    pub fn is_shared(&self) -> bool {
+        self.ty.is_shared()
+    }
+
+    #[inline]
+    pub fn byte_length(&self) -> u64 {
+        self.state.lock().unwrap().runtime_data.num_bytes
+    }
+
+    // The type of the memory with its current size, which is what the imports of the memory
+    // are matched against.
+    pub fn get_type(&self) -> MemoryType {
+        MemoryType::new(self.cur_pages(), self.ty.max_pages(), self.ty.is_shared())
+    }
+
+    // The offset of the runtime data from the compartment address.
+    #[inline]
+    pub fn runtime_data_offset(&self) -> u64 {
+        self.state.lock().unwrap().runtime_data.offset()
+    }
+
+    // The compiled code only finds the memories of its own compartment.
+    pub fn is_in_compartment(&self, compartment: &Compartment) -> bool {
+        self.state.lock().unwrap().runtime_data.compartment().base_addr() == compartment.base_addr()
+    }
+
+    // A copy of the bytes in `[offset, offset + len)`, if they are all in bounds. The bytes are
+    // copied since the memory may move when it grows.
+    pub fn read_bytes(&self, offset: u32, len: u32) -> Option<Vec<u8>> {
+        let state = self.state.lock().unwrap();
+        let end = offset as u64 + len as u64;
+        if end > state.runtime_data.num_bytes {
+            return None;
+        }
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                (state.start_addr + offset as u64) as *const u8,
+                len as usize,
+            )
+        };
+        Some(bytes.to_vec())
+    }
+
+    // The host address of the `num_bytes` bytes at `offset`, if they are all in bounds. It's
+    // only valid until the memory grows.
+    pub(super) fn host_addr(&self, offset: u64, num_bytes: u64) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        match offset.checked_add(num_bytes) {
+            Some(end) if end <= state.runtime_data.num_bytes => Some(state.start_addr + offset),
+            _ => None,
+        }
+    }
+
+    // Copies `value` to `offset`, if it's in the bounds of the current size.
+    pub fn copy_into_data(&self, offset: u64, value: &[u8]) -> Result<(), LoadError> {
+        let state = self.state.lock().unwrap();
         let len = value.len() as u64;
-        let max_bytes = (self.max_pages as u64) << WASM_PAGE_SHIFT;
-        if offset > max_bytes || len + offset > max_bytes {
-            return Err("the data's len is too long.".to_string());
+        let num_bytes = state.runtime_data.num_bytes;
+        if offset > num_bytes || len > num_bytes - offset {
+            return Err(LoadError::Instantiate(
+                "the data segment is out of the memory's bounds.".to_string(),
+            ));
         }
-        platform::copy_memory(self.start_addr + offset, value);
+        platform::copy_memory(state.start_addr + offset, value);
         Ok(())
     }
 }
 
-pub fn create_memory(compartment: &Compartment, ty: &MemoryType) -> Result<Memory, String> {
-    let max_pages = ty.max_pages().unwrap_or(ty.min_pages());
-    let start_addr = platform::alloc_pages(max_pages + 1)?;
+// Reserves and commits `new_pages` wasm pages, copies the memory there and releases its previous
+// reservation.
+fn move_to_reservation(state: &mut MemoryState, new_pages: u64) -> Result<(), String> {
+    let num_reserved_bytes = std::cmp::max(new_pages << WASM_PAGE_SHIFT, platform::PAGE_SIZE);
+    let num_reserved_pages = (num_reserved_bytes >> platform::PAGE_SHIFT) as u32;
+    let new_addr = platform::alloc_pages(num_reserved_pages)?;
+    if let Err(e) = platform::commit_pages(new_addr, num_reserved_pages) {
+        let _ = platform::free_pages(new_addr, num_reserved_pages);
+        return Err(e);
+    }
+    let num_bytes = state.runtime_data.num_bytes;
+    if num_bytes > 0 {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(state.start_addr as *const u8, num_bytes as usize)
+        };
+        platform::copy_memory(new_addr, bytes);
+    }
+    unregister_memory(state.start_addr);
+    let _ = platform::free_pages(state.start_addr, state.num_reserved_pages);
+    register_memory(new_addr, new_addr + num_reserved_bytes);
+    state.start_addr = new_addr;
+    state.num_reserved_pages = num_reserved_pages;
+    state.runtime_data.base = new_addr;
+    Ok(())
+}
+
+pub fn create_memory(compartment: &Compartment, ty: &MemoryType) -> Result<Memory, LoadError> {
+    let max_pages = std::cmp::min(
+        ty.max_pages().unwrap_or(WASM_MAX_PAGES),
+        compartment.max_memory_pages(),
+    );
+    if ty.min_pages() > max_pages {
+        return Err(LoadError::Instantiate(format!(
+            "the initial size of {} exceeds the limit of {} pages",
+            ty, max_pages
+        )));
+    }
+    let is_movable = compartment.bounds_check() == BoundsCheckMode::Explicit && !ty.is_shared();
+    let num_reserved_bytes = match compartment.bounds_check() {
+        BoundsCheckMode::GuardPages => MAX_ADDRESSABLE_BYTES,
+        // A movable memory is reserved as it grows to its initial size below.
+        BoundsCheckMode::Explicit if is_movable => platform::PAGE_SIZE,
+        BoundsCheckMode::Explicit => std::cmp::max(
+            (max_pages as u64) << WASM_PAGE_SHIFT,
+            platform::PAGE_SIZE,
+        ),
+    };
+    let num_reserved_pages = (num_reserved_bytes >> platform::PAGE_SHIFT) as u32;
+    let start_addr =
+        platform::alloc_pages(num_reserved_pages).map_err(LoadError::Instantiate)?;
+    // Faults in the reservation are out of bounds accesses.
+    register_memory(start_addr, start_addr + num_reserved_bytes);
 
-    // TODO: the `cur_pages` is temporarily smaller than `min_pages`,
-    // and causes some inconsistency.
-    let mut memory = Memory {
-        start_addr,
-        cur_pages: 0,
-        min_pages: ty.min_pages(),
+    let runtime_data = compartment.alloc_memory_data(MemoryRuntimeData {
+        base: start_addr,
+        num_bytes: 0,
+    });
+    let runtime_data = match runtime_data {
+        Ok(runtime_data) => runtime_data,
+        Err(e) => {
+            unregister_memory(start_addr);
+            let _ = platform::free_pages(start_addr, num_reserved_pages);
+            return Err(e);
+        }
+    };
+    // The memory owns its reservation from now on, so it's released on errors.
+    let memory = Memory {
+        ty: *ty,
+        state: Mutex::new(MemoryState {
+            runtime_data,
+            cur_pages: 0,
+            start_addr,
+            num_reserved_pages,
+        }),
         max_pages,
+        is_movable,
     };
-    memory.grow_pages(ty.min_pages())?;
+    if memory.grow_pages(ty.min_pages()).is_none() {
+        return Err(LoadError::Instantiate(format!(
+            "failed to allocate the initial pages of {}",
+            ty
+        )));
+    }
     Ok(memory)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm::PAGE_SIZE as WASM_PAGE_SIZE;
+
+    fn new_memory(compartment: &Compartment, min: u32, max: Option<u32>) -> Memory {
+        create_memory(compartment, &MemoryType::new(min, max, false)).unwrap()
+    }
+
+    #[test]
+    fn grow_returns_previous_size() {
+        let compartment = Compartment::new().unwrap();
+        let memory = new_memory(&compartment, 1, Some(4));
+        assert_eq!(memory.byte_length(), WASM_PAGE_SIZE);
+        assert_eq!(memory.grow_pages(2), Some(1));
+        assert_eq!(memory.cur_pages(), 3);
+        assert_eq!(memory.byte_length(), 3 * WASM_PAGE_SIZE);
+        assert_eq!(memory.grow_pages(0), Some(3));
+        assert_eq!(memory.cur_pages(), 3);
+    }
+
+    #[test]
+    fn grown_pages_are_accessible() {
+        let compartment = Compartment::with_bounds_check(BoundsCheckMode::Explicit).unwrap();
+        let memory = new_memory(&compartment, 0, Some(2));
+        assert!(memory.read_bytes(0, 1).is_none());
+        assert_eq!(memory.grow_pages(2), Some(0));
+        let last = 2 * WASM_PAGE_SIZE - 4;
+        memory.copy_into_data(last, &[1, 2, 3, 4]).unwrap();
+        assert_eq!(memory.read_bytes(last as u32, 4), Some(vec![1, 2, 3, 4]));
+        assert!(memory.read_bytes(last as u32, 5).is_none());
+    }
+
+    #[test]
+    fn grow_respects_max() {
+        let compartment = Compartment::new().unwrap();
+        let memory = new_memory(&compartment, 1, Some(2));
+        assert_eq!(memory.grow_pages(2), None);
+        assert_eq!(memory.grow_pages(std::u32::MAX), None);
+        assert_eq!(memory.cur_pages(), 1);
+        assert_eq!(memory.byte_length(), WASM_PAGE_SIZE);
+        assert_eq!(memory.grow_pages(1), Some(1));
+        assert_eq!(memory.grow_pages(1), None);
+    }
+
+    #[test]
+    fn grow_without_max_is_limited_to_4gib() {
+        let compartment = Compartment::new().unwrap();
+        let memory = new_memory(&compartment, 0, None);
+        assert_eq!(memory.max_pages(), WASM_MAX_PAGES);
+        assert_eq!(memory.grow_pages(WASM_MAX_PAGES + 1), None);
+        assert_eq!(memory.cur_pages(), 0);
+    }
+
+    #[test]
+    fn grow_respects_embedder_limit() {
+        let compartment = Compartment::with_memory_limit(BoundsCheckMode::default(), 3).unwrap();
+        let memory = new_memory(&compartment, 1, None);
+        assert_eq!(memory.max_pages(), 3);
+        assert_eq!(memory.grow_pages(3), None);
+        assert_eq!(memory.grow_pages(2), Some(1));
+        assert_eq!(memory.grow_pages(1), None);
+        assert!(create_memory(&compartment, &MemoryType::new(4, None, false)).is_err());
+    }
+
+    #[test]
+    fn grow_respects_resource_limiter() {
+        use crate::runtime::limiter::CompartmentLimits;
+        use std::sync::Arc;
+
+        let limits = Arc::new(CompartmentLimits::new().memory_bytes(3 * WASM_PAGE_SIZE));
+        let compartment =
+            Compartment::with_resource_limiter(BoundsCheckMode::default(), limits.clone())
+                .unwrap();
+        let first = new_memory(&compartment, 1, None);
+        let second = new_memory(&compartment, 1, None);
+        assert_eq!(first.grow_pages(2), None);
+        assert_eq!(first.grow_pages(1), Some(1));
+        assert_eq!(second.grow_pages(1), None);
+        assert_eq!(limits.memory_bytes_used(), 3 * WASM_PAGE_SIZE);
+        drop(first);
+        assert_eq!(limits.memory_bytes_used(), WASM_PAGE_SIZE);
+        assert_eq!(second.grow_pages(2), Some(1));
+    }
+
+    #[test]
+    fn drop_releases_reservation() {
+        let compartment = Compartment::new().unwrap();
+        let memory = new_memory(&compartment, 1, Some(1));
+        let start_addr = memory.state.lock().unwrap().start_addr;
+        let mut residency = [0u8; 1];
+        let is_mapped = |addr: u64, residency: &mut [u8; 1]| unsafe {
+            libc::mincore(
+                addr as *mut libc::c_void,
+                platform::PAGE_SIZE as usize,
+                residency.as_mut_ptr() as *mut _,
+            ) == 0
+        };
+        assert!(is_mapped(start_addr, &mut residency));
+        drop(memory);
+        assert!(!is_mapped(start_addr, &mut residency));
+    }
+}
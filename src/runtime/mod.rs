@@ -4,23 +4,279 @@ mod resolver;
 mod context;
 mod memory;
 mod data;
+mod exception;
+mod function;
+mod instance;
+mod intrinsics;
+mod global;
+mod limiter;
+mod object;
+mod parking;
+mod table;
+mod trap;
+mod types;
 
 pub use self::compartment::*;
+pub use self::context::Context;
+pub(crate) use self::context::ContextRuntimeData;
+pub use self::function::{Function, TypedFunction, WasmParams, WasmResults, WasmTy};
+pub use self::instance::Instance;
+pub use self::limiter::{CompartmentLimits, ResourceLimiter};
+pub use self::memory::{create_memory, Memory};
+pub use self::resolver::{HostContext, HostFunction, HostModule, Resolver};
+pub use self::table::{create_table, Table};
+pub use self::trap::{Trap, TrapKind};
+use crate::platform;
 use crate::wasm::Module as WASMModule;
 use crate::wasm::Entry;
-use crate::runtime::memory::create_memory;
-use crate::runtime::data::fill_data;
+use crate::wasm::LoadError;
+use crate::runtime::link::link_module;
+use crate::runtime::limiter::reserve_instance;
+use crate::wasm::types::GlobalType;
+use crate::wasm::Value;
+use crate::runtime::table::FunctionRef;
+use crate::runtime::data::{eval_const_expr, init_segments, Segments};
+use crate::runtime::exception::ExceptionType;
+use crate::runtime::global::{create_global, Global};
+use crate::runtime::object::load_object;
+use crate::runtime::trap::register_code;
+use crate::runtime::types::get_type_id;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 fn i32_remu(left: u32, right: u32) -> u32 {
     left % right
 }
 
-pub fn setup_env(compartment: &Compartment, module: &WASMModule) -> Result<(), String> {
-    assert!(module.memorys_count() == 1);
-    // TODO: currently, we only support one memory in a module.
-    let mut memory = create_memory(compartment, module.memorys()[0].get_type())?;
-    for data in module.datas() {
-        fill_data(&mut memory, data, module)?;
+fn create_globals(
+    module: &WASMModule,
+    imports: Vec<(GlobalType, Value)>,
+    function_refs: &[FunctionRef],
+) -> Result<Vec<Global>, LoadError> {
+    let mut globals = Vec::with_capacity(module.globals().len());
+    for (ty, value) in imports {
+        globals.push(create_global(&ty, value, None)?);
+    }
+    let mut num_mutable_globals = 0;
+    for global in module.globals().defines() {
+        let ty = global.get_type();
+        let mutable_data_offset = if ty.is_mutable() {
+            num_mutable_globals += 1;
+            Some(ContextRuntimeData::mutable_global_offset(num_mutable_globals - 1)?)
+        } else {
+            None
+        };
+        let value = eval_const_expr(global.init_instr(), &globals, function_refs)?;
+        globals.push(create_global(ty, value, mutable_data_offset)?);
+    }
+    Ok(globals)
+}
+
+// The library functions which LLVM may emit calls to when it lowers the compiled code. The
+// object code can't refer to any other symbol of the host process.
+const LIBCALLS: &[&str] = &[
+    "memcpy", "memmove", "memset", "fmod", "fmodf", "ceil", "ceilf", "floor", "floorf", "trunc",
+    "truncf", "nearbyint", "nearbyintf", "rint", "rintf", "sqrt", "sqrtf",
+];
+
+// Resolves the symbols which the compiled code imports, see `ModuleCodeGen::new`.
+fn resolve_symbol(
+    name: &str,
+    module: &WASMModule,
+    host_functions: &[Arc<HostFunction>],
+    memorys: &[Arc<Memory>],
+    tables: &[Arc<Table>],
+    globals: &[Global],
+    function_refs: &[FunctionRef],
+    exception_types: &[Box<ExceptionType>],
+) -> Option<u64> {
+    let index = |prefix: &str| -> Option<usize> {
+        if name.starts_with(prefix) {
+            name[prefix.len()..].parse().ok()
+        } else {
+            None
+        }
     };
-    Ok(())
+    if let Some(idx) = index("typeId") {
+        if idx < module.types_count() {
+            return Some(get_type_id(module.get_func_type(idx as u32)) as u64);
+        }
+    } else if let Some(idx) = index("exceptionTypeId") {
+        return exception_types.get(idx).map(|ty| ty.id());
+    } else if let Some(idx) = index("tableOffset") {
+        return tables
+            .get(idx)
+            .map(|t| t.runtime_data_offset());
+    } else if let Some(idx) = index("memoryOffset") {
+        return memorys
+            .get(idx)
+            .map(|m| m.runtime_data_offset());
+    } else if let Some(idx) = index("functionRef") {
+        return function_refs
+            .get(idx)
+            .map(|f| f as *const FunctionRef as u64);
+    } else if let Some(idx) = index("global") {
+        return globals.get(idx).map(Global::symbol_value);
+    } else if let Some(idx) = index("hostFunction") {
+        return host_functions
+            .get(idx)
+            .map(|f| &**f as *const HostFunction as u64);
+    }
+    intrinsics::get_intrinsic(name).or_else(|| {
+        if LIBCALLS.contains(&name) {
+            platform::lookup_host_symbol(name)
+        } else {
+            None
+        }
+    })
+}
+
+// Instantiates `module` with `object_code`, the object file which `codegen::compile_module`
+// compiled from it, resolving its imports with `resolver`.
+pub fn setup_env<R: Resolver + ?Sized>(
+    compartment: &Compartment,
+    module: &WASMModule,
+    object_code: &[u8],
+    resolver: &R,
+) -> Result<Instance, LoadError> {
+    let reservation = reserve_instance(compartment)?;
+    let imports = link_module(module, compartment, resolver)?;
+    let host_functions = imports.functions;
+    // The references to the functions are filled once the code is loaded, but their addresses
+    // may be referred to by the globals and the code before.
+    let mut function_refs =
+        vec![FunctionRef::null(); module.functions().len()].into_boxed_slice();
+    let globals = create_globals(module, imports.globals, &function_refs)?;
+
+    // The imported tables come first in the index space, like the memories.
+    let mut tables = imports.tables;
+    for table in module.tables().defines() {
+        tables.push(Arc::new(create_table(compartment, table.get_type())?));
+    }
+
+    // The imported memories come first in the index space.
+    let mut memorys = imports.memorys;
+    for memory in module.memorys().defines() {
+        memorys.push(Arc::new(create_memory(compartment, memory.get_type())?));
+    }
+
+    // The exception types are boxed, since the compiled code refers to them by address.
+    let exception_types = module
+        .tags()
+        .iter()
+        .enumerate()
+        .map(|(i, tag)| Box::new(ExceptionType::new(i as u32, tag.get_type().params().to_vec())))
+        .collect::<Vec<_>>();
+
+    // The passive segments are kept once the active ones are copied.
+    let mut segments = Box::new(Segments::default());
+    let context = Context::new(
+        compartment,
+        &globals,
+        &memorys,
+        &tables,
+        &function_refs,
+        &mut segments,
+    )?;
+    let code = load_object(object_code, |name| {
+        resolve_symbol(
+            name,
+            module,
+            &host_functions,
+            &memorys,
+            &tables,
+            &globals,
+            &function_refs,
+            &exception_types,
+        )
+    })?;
+
+    let functions = (0..module.functions().len())
+        .map(|i| {
+            let name = if module.functions().is_import(i) {
+                format!("functionImport{}", i)
+            } else {
+                format!("functionDef{}", i)
+            };
+            code.get_symbol(&name).ok_or_else(|| {
+                LoadError::Instantiate(format!("the compiled code doesn't define function {}", i))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    for (i, func) in functions.iter().enumerate() {
+        function_refs[i] = FunctionRef {
+            type_id: get_type_id(module.functions().get_type(i)),
+            func: *func as usize,
+            context: context.runtime_data_addr() as usize,
+        };
+    }
+    let invoke_thunks = (0..module.functions().len() as u32)
+        .filter_map(|i| {
+            code.get_symbol(&format!("invokeThunk{}", i))
+                .map(|thunk| (i, thunk))
+        })
+        .collect::<HashMap<_, _>>();
+    let function_ranges = (0..module.functions().len() as u32)
+        .filter_map(|i| {
+            let name = if module.functions().is_import(i as usize) {
+                format!("functionImport{}", i)
+            } else {
+                format!("functionDef{}", i)
+            };
+            code.get_symbol_range(&name).map(|(start, end)| (start, end, i))
+        })
+        .collect();
+    let code_registration = register_code(code.base_addr(), code.end_addr(), function_ranges);
+
+    init_segments(
+        module,
+        &tables,
+        &memorys,
+        &globals,
+        &function_refs,
+        &mut segments,
+    )?;
+
+    let function_types = (0..module.functions().len())
+        .map(|i| module.functions().get_type(i).clone())
+        .collect();
+    let instance = Instance::new(
+        reservation,
+        memorys,
+        tables,
+        globals,
+        function_refs,
+        segments,
+        host_functions,
+        exception_types,
+        context,
+        code_registration,
+        code,
+        function_types,
+        invoke_thunks,
+        module.exports().clone(),
+    );
+    if let Some(start) = module.start_function() {
+        run_start_function(&instance, module, start)?;
+    }
+    Ok(instance)
+}
+
+fn run_start_function(instance: &Instance, module: &WASMModule, index: u32) -> Result<(), LoadError> {
+    let ty = module.functions().get_type(index as usize);
+    if !ty.params().is_empty() || !ty.results().is_empty() {
+        return Err(LoadError::Malformed(format!(
+            "the start function {} must have type [] -> [], but got {:?}",
+            index, ty
+        )));
+    }
+    match instance.get_func_by_index(index) {
+        Some(func) => func.call(&[]).map(|_| ()).map_err(|trap| {
+            LoadError::Instantiate(format!("the start function {} failed with {}", index, trap))
+        }),
+        None => Err(LoadError::Instantiate(format!(
+            "the compiled code doesn't define the start function {}",
+            index
+        ))),
+    }
 }
@@ -0,0 +1,392 @@
+use crate::platform::{self, PageAccess};
+use crate::wasm::LoadError;
+use std::collections::HashMap;
+
+// Only the parts of ELF64 which are used by the relocatable object files that the code
+// generator emits for x86-64 are supported.
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const ET_REL: u16 = 1;
+const EM_X86_64: u16 = 62;
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+
+const SHF_WRITE: u64 = 0x1;
+const SHF_ALLOC: u64 = 0x2;
+const SHF_EXECINSTR: u64 = 0x4;
+
+const SHN_UNDEF: u16 = 0;
+const SHN_ABS: u16 = 0xfff1;
+const SHN_COMMON: u16 = 0xfff2;
+
+const STT_SECTION: u8 = 3;
+
+const R_X86_64_NONE: u32 = 0;
+const R_X86_64_64: u32 = 1;
+const R_X86_64_PC32: u32 = 2;
+const R_X86_64_PLT32: u32 = 4;
+const R_X86_64_32: u32 = 10;
+const R_X86_64_32S: u32 = 11;
+const R_X86_64_PC64: u32 = 24;
+
+const SECTION_HEADER_SIZE: usize = 64;
+const SYMBOL_SIZE: usize = 24;
+const RELA_SIZE: usize = 24;
+
+// The unwind tables of the compiled code, which are registered with the unwinder of the
+// platform so that exceptions can be raised through it. The unwinder expects them to end with a
+// zero length entry, which the object file doesn't contain.
+const EH_FRAME_SECTION: &str = ".eh_frame";
+const EH_FRAME_TERMINATOR_SIZE: u64 = 4;
+
+fn truncated() -> LoadError {
+    LoadError::Malformed("the object file is truncated".to_string())
+}
+
+fn read_bytes(bytes: &[u8], offset: usize, len: usize) -> Result<&[u8], LoadError> {
+    match offset.checked_add(len) {
+        Some(end) if end <= bytes.len() => Ok(&bytes[offset..end]),
+        _ => Err(truncated()),
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, LoadError> {
+    let mut buf = [0u8; 2];
+    buf.copy_from_slice(read_bytes(bytes, offset, 2)?);
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, LoadError> {
+    let mut buf = [0u8; 4];
+    buf.copy_from_slice(read_bytes(bytes, offset, 4)?);
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, LoadError> {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(read_bytes(bytes, offset, 8)?);
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_str(bytes: &[u8], offset: usize) -> Result<&str, LoadError> {
+    let tail = read_bytes(bytes, offset, 0).map(|_| &bytes[offset..])?;
+    let len = tail.iter().position(|b| *b == 0).ok_or_else(truncated)?;
+    std::str::from_utf8(&tail[..len])
+        .map_err(|_| LoadError::Malformed("invalid symbol name in the object file".to_string()))
+}
+
+fn align_up(v: u64, align: u64) -> u64 {
+    (v + align - 1) & !(align - 1)
+}
+
+struct SectionHeader {
+    name: u32,
+    ty: u32,
+    flags: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+    info: u32,
+    addralign: u64,
+}
+
+impl SectionHeader {
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self, LoadError> {
+        Ok(SectionHeader {
+            name: read_u32(bytes, offset)?,
+            ty: read_u32(bytes, offset + 4)?,
+            flags: read_u64(bytes, offset + 8)?,
+            offset: read_u64(bytes, offset + 24)?,
+            size: read_u64(bytes, offset + 32)?,
+            link: read_u32(bytes, offset + 40)?,
+            info: read_u32(bytes, offset + 44)?,
+            addralign: read_u64(bytes, offset + 48)?,
+        })
+    }
+
+    #[inline]
+    fn is_alloc(&self) -> bool {
+        self.flags & SHF_ALLOC != 0
+    }
+
+    // The allocated sections are grouped by their access, and every group is mapped to its own
+    // pages.
+    fn access(&self) -> PageAccess {
+        if self.flags & SHF_EXECINSTR != 0 {
+            PageAccess::ReadExecute
+        } else if self.flags & SHF_WRITE != 0 {
+            PageAccess::ReadWrite
+        } else {
+            PageAccess::ReadOnly
+        }
+    }
+
+    fn data<'a>(&self, bytes: &'a [u8]) -> Result<&'a [u8], LoadError> {
+        read_bytes(bytes, self.offset as usize, self.size as usize)
+    }
+}
+
+// An object file which is mapped into memory, with its relocations applied.
+pub struct LoadedObject {
+    base_addr: u64,
+    num_pages: u32,
+    // The addresses and sizes of the symbols.
+    symbols: HashMap<String, (u64, u64)>,
+    eh_frame_addr: Option<u64>,
+}
+
+impl Drop for LoadedObject {
+    fn drop(&mut self) {
+        if let Some(eh_frame_addr) = self.eh_frame_addr {
+            platform::deregister_frames(eh_frame_addr);
+        }
+        let _ = platform::free_pages(self.base_addr, self.num_pages);
+    }
+}
+
+impl LoadedObject {
+    // The address of a symbol defined by the object.
+    pub fn get_symbol(&self, name: &str) -> Option<u64> {
+        self.symbols.get(name).map(|(addr, _)| *addr)
+    }
+
+    // The `[start, end)` range of a symbol defined by the object.
+    pub fn get_symbol_range(&self, name: &str) -> Option<(u64, u64)> {
+        self.symbols.get(name).map(|(addr, size)| (*addr, *addr + *size))
+    }
+
+    #[inline]
+    pub fn base_addr(&self) -> u64 {
+        self.base_addr
+    }
+
+    #[inline]
+    pub fn end_addr(&self) -> u64 {
+        self.base_addr + ((self.num_pages as u64) << platform::PAGE_SHIFT)
+    }
+}
+
+// Maps the allocated sections of a relocatable object into memory, resolving its undefined
+// symbols with `resolve`.
+pub fn load_object<F>(bytes: &[u8], resolve: F) -> Result<LoadedObject, LoadError>
+where
+    F: Fn(&str) -> Option<u64>,
+{
+    if read_bytes(bytes, 0, 4)? != ELF_MAGIC
+        || read_bytes(bytes, 4, 1)?[0] != ELFCLASS64
+        || read_bytes(bytes, 5, 1)?[0] != ELFDATA2LSB
+        || read_u16(bytes, 16)? != ET_REL
+    {
+        return Err(LoadError::Malformed(
+            "the object file isn't a 64-bit little-endian relocatable ELF".to_string(),
+        ));
+    }
+    let machine = read_u16(bytes, 18)?;
+    if machine != EM_X86_64 {
+        return Err(LoadError::Unsupported(format!(
+            "loading object files for the machine {}",
+            machine
+        )));
+    }
+
+    let sh_offset = read_u64(bytes, 40)? as usize;
+    let sh_num = read_u16(bytes, 60)? as usize;
+    let sections = (0..sh_num)
+        .map(|i| SectionHeader::parse(bytes, sh_offset + i * SECTION_HEADER_SIZE))
+        .collect::<Result<Vec<_>, _>>()?;
+    let shstrtab = sections
+        .get(read_u16(bytes, 62)? as usize)
+        .ok_or_else(truncated)?
+        .data(bytes)?;
+    let mut eh_frame_index = None;
+    for (i, section) in sections.iter().enumerate() {
+        if section.is_alloc() && read_str(shstrtab, section.name as usize)? == EH_FRAME_SECTION {
+            eh_frame_index = Some(i);
+        }
+    }
+
+    // Lay out the allocated sections.
+    let mut section_offsets = vec![None; sections.len()];
+    let mut groups = Vec::new();
+    let mut image_size = 0;
+    for access in &[
+        PageAccess::ReadExecute,
+        PageAccess::ReadOnly,
+        PageAccess::ReadWrite,
+    ] {
+        let group_start = image_size;
+        for (i, section) in sections.iter().enumerate() {
+            if !section.is_alloc() || section.access() != *access {
+                continue;
+            }
+            let offset = align_up(image_size, std::cmp::max(section.addralign, 1));
+            section_offsets[i] = Some(offset);
+            image_size = offset + section.size;
+            if eh_frame_index == Some(i) {
+                // The pages are zeroed, so the terminator only needs to be reserved.
+                image_size += EH_FRAME_TERMINATOR_SIZE;
+            }
+        }
+        image_size = align_up(image_size, platform::PAGE_SIZE);
+        if image_size > group_start {
+            groups.push((group_start, image_size, *access));
+        }
+    }
+
+    let num_pages = std::cmp::max(image_size >> platform::PAGE_SHIFT, 1) as u32;
+    let base_addr = platform::alloc_pages(num_pages).map_err(LoadError::Instantiate)?;
+    // The object owns its pages from now on, so they are released on errors.
+    let mut object = LoadedObject {
+        base_addr,
+        num_pages,
+        symbols: HashMap::new(),
+        eh_frame_addr: None,
+    };
+    platform::commit_pages(base_addr, num_pages).map_err(LoadError::Instantiate)?;
+    for (section, offset) in sections.iter().zip(section_offsets.iter()) {
+        if let Some(offset) = offset {
+            if section.ty != SHT_NOBITS {
+                platform::copy_memory(base_addr + offset, section.data(bytes)?);
+            }
+        }
+    }
+    let section_addrs = section_offsets
+        .iter()
+        .map(|offset| offset.map(|offset| base_addr + offset))
+        .collect::<Vec<_>>();
+
+    // Resolve the symbols of the symbol table, which is the only one in a relocatable object.
+    let mut symbol_addrs = Vec::new();
+    if let Some(section) = sections.iter().find(|s| s.ty == SHT_SYMTAB) {
+        let symtab = section.data(bytes)?;
+        let strtab = sections
+            .get(section.link as usize)
+            .ok_or_else(truncated)?
+            .data(bytes)?;
+        for i in 0..symtab.len() / SYMBOL_SIZE {
+            let sym = i * SYMBOL_SIZE;
+            let name = read_str(strtab, read_u32(symtab, sym)? as usize)?;
+            let info = symtab[sym + 4];
+            let shndx = read_u16(symtab, sym + 6)?;
+            let value = read_u64(symtab, sym + 8)?;
+            let size = read_u64(symtab, sym + 16)?;
+            let addr = match shndx {
+                SHN_UNDEF if name.is_empty() => Some(0),
+                SHN_UNDEF => match resolve(name) {
+                    Some(addr) => Some(addr),
+                    None => {
+                        return Err(LoadError::Instantiate(format!(
+                            "unresolved symbol {} in the compiled code",
+                            name
+                        )))
+                    }
+                },
+                SHN_ABS => Some(value),
+                SHN_COMMON => {
+                    return Err(LoadError::Unsupported(format!(
+                        "the common symbol {} in the compiled code",
+                        name
+                    )))
+                }
+                _ => section_addrs
+                    .get(shndx as usize)
+                    .cloned()
+                    .unwrap_or(None)
+                    .map(|addr| addr + value),
+            };
+            if let Some(addr) = addr {
+                if shndx != SHN_UNDEF && !name.is_empty() && info & 0xf != STT_SECTION {
+                    object.symbols.insert(name.to_string(), (addr, size));
+                }
+            }
+            symbol_addrs.push(addr);
+        }
+    }
+
+    // Apply the relocations of the allocated sections.
+    for section in sections.iter().filter(|s| s.ty == SHT_RELA) {
+        let target_addr = match section_addrs.get(section.info as usize) {
+            Some(Some(addr)) => *addr,
+            _ => continue,
+        };
+        let relas = section.data(bytes)?;
+        for i in 0..relas.len() / RELA_SIZE {
+            let rela = i * RELA_SIZE;
+            let p = target_addr + read_u64(relas, rela)?;
+            let info = read_u64(relas, rela + 8)?;
+            let a = read_u64(relas, rela + 16)? as i64;
+            let ty = (info & 0xffff_ffff) as u32;
+            let s = match symbol_addrs.get((info >> 32) as usize) {
+                Some(Some(addr)) => *addr,
+                _ => {
+                    return Err(LoadError::Malformed(format!(
+                        "relocation against an unknown symbol {}",
+                        info >> 32
+                    )))
+                }
+            };
+            apply_relocation(ty, p, s, a)?;
+        }
+    }
+
+    for (start, end, access) in groups {
+        platform::protect_pages(
+            base_addr + start,
+            ((end - start) >> platform::PAGE_SHIFT) as u32,
+            access,
+        )
+        .map_err(LoadError::Instantiate)?;
+    }
+    if let Some(Some(eh_frame_addr)) = eh_frame_index.map(|i| section_addrs[i]) {
+        platform::register_frames(eh_frame_addr);
+        object.eh_frame_addr = Some(eh_frame_addr);
+    }
+    Ok(object)
+}
+
+fn apply_relocation(ty: u32, p: u64, s: u64, a: i64) -> Result<(), LoadError> {
+    let out_of_range = || {
+        LoadError::Instantiate(format!(
+            "the relocation {} at {:x} against {:x} is out of range",
+            ty, p, s
+        ))
+    };
+    let value = s.wrapping_add(a as u64);
+    unsafe {
+        match ty {
+            R_X86_64_NONE => {}
+            R_X86_64_64 => std::ptr::write_unaligned(p as *mut u64, value),
+            R_X86_64_PC64 => std::ptr::write_unaligned(p as *mut u64, value.wrapping_sub(p)),
+            R_X86_64_PC32 | R_X86_64_PLT32 => {
+                let rel = value.wrapping_sub(p) as i64;
+                if rel < std::i32::MIN as i64 || rel > std::i32::MAX as i64 {
+                    return Err(out_of_range());
+                }
+                std::ptr::write_unaligned(p as *mut i32, rel as i32);
+            }
+            R_X86_64_32 => {
+                if value > std::u32::MAX as u64 {
+                    return Err(out_of_range());
+                }
+                std::ptr::write_unaligned(p as *mut u32, value as u32);
+            }
+            R_X86_64_32S => {
+                let value = value as i64;
+                if value < std::i32::MIN as i64 || value > std::i32::MAX as i64 {
+                    return Err(out_of_range());
+                }
+                std::ptr::write_unaligned(p as *mut i32, value as i32);
+            }
+            _ => {
+                return Err(LoadError::Unsupported(format!(
+                    "the relocation type {} in the compiled code",
+                    ty
+                )))
+            }
+        }
+    }
+    Ok(())
+}
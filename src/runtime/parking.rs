@@ -0,0 +1,101 @@
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+// The results of `memory.atomic.wait32` and `memory.atomic.wait64`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WaitResult {
+    Woken = 0,
+    NotEqual = 1,
+    TimedOut = 2,
+}
+
+struct Waiter {
+    notified: Mutex<bool>,
+    condvar: Condvar,
+}
+
+// The threads parked on each address, in the order they were parked. The addresses are host
+// addresses, so the instances sharing a memory park on the same queues.
+lazy_static! {
+    static ref PARKED: Mutex<HashMap<u64, Vec<Arc<Waiter>>>> = Mutex::new(HashMap::new());
+}
+
+// Parks the current thread on `addr` until it's unparked or `timeout` passes. `is_expected` is
+// called with the queues locked, so an `unpark` after the value changes can't be missed.
+pub fn park<F: FnOnce() -> bool>(
+    addr: u64,
+    is_expected: F,
+    timeout: Option<Duration>,
+) -> WaitResult {
+    let waiter = {
+        let mut parked = PARKED.lock().unwrap();
+        if !is_expected() {
+            return WaitResult::NotEqual;
+        }
+        let waiter = Arc::new(Waiter {
+            notified: Mutex::new(false),
+            condvar: Condvar::new(),
+        });
+        parked.entry(addr).or_insert_with(Vec::new).push(waiter.clone());
+        waiter
+    };
+
+    // A timeout too large to represent is no timeout.
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    let mut notified = waiter.notified.lock().unwrap();
+    while !*notified {
+        match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                notified = waiter.condvar.wait_timeout(notified, deadline - now).unwrap().0;
+            }
+            None => notified = waiter.condvar.wait(notified).unwrap(),
+        }
+    }
+    if *notified {
+        return WaitResult::Woken;
+    }
+    drop(notified);
+
+    // The waiter may have been unparked after the deadline passed, in which case it's no longer
+    // in the queue.
+    let mut parked = PARKED.lock().unwrap();
+    let queue = match parked.get_mut(&addr) {
+        Some(queue) => queue,
+        None => return WaitResult::Woken,
+    };
+    match queue.iter().position(|w| Arc::ptr_eq(w, &waiter)) {
+        Some(idx) => {
+            queue.remove(idx);
+            if queue.is_empty() {
+                parked.remove(&addr);
+            }
+            WaitResult::TimedOut
+        }
+        None => WaitResult::Woken,
+    }
+}
+
+// Unparks up to `count` threads parked on `addr`, the earliest first, and returns how many
+// were unparked.
+pub fn unpark(addr: u64, count: u32) -> u32 {
+    let mut parked = PARKED.lock().unwrap();
+    let queue = match parked.get_mut(&addr) {
+        Some(queue) => queue,
+        None => return 0,
+    };
+    let num_unparked = std::cmp::min(count as usize, queue.len());
+    for waiter in queue.drain(..num_unparked) {
+        *waiter.notified.lock().unwrap() = true;
+        waiter.condvar.notify_one();
+    }
+    if queue.is_empty() {
+        parked.remove(&addr);
+    }
+    num_unparked as u32
+}
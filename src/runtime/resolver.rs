@@ -0,0 +1,185 @@
+use crate::runtime::context::ContextRuntimeData;
+use crate::runtime::memory::Memory;
+use crate::runtime::table::Table;
+use crate::wasm::types::GlobalType;
+use crate::wasm::{FunctionType, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// The state of the calling instance which is visible to a host function.
+pub struct HostContext<'a> {
+    runtime_data: &'a mut ContextRuntimeData,
+}
+
+impl<'a> HostContext<'a> {
+    pub(super) fn new(runtime_data: &'a mut ContextRuntimeData) -> Self {
+        HostContext { runtime_data }
+    }
+
+    pub fn memory(&self, idx: usize) -> Option<&Memory> {
+        self.runtime_data.memory(idx)
+    }
+}
+
+type HostFunctionBody = dyn Fn(&mut HostContext, &[Value]) -> Vec<Value> + Send + Sync;
+
+// A function of the host which can be imported by modules. It's called with arguments of the
+// types of its parameters, and must return values of its result types.
+pub struct HostFunction {
+    ty: FunctionType,
+    body: Box<HostFunctionBody>,
+}
+
+impl HostFunction {
+    pub fn new<F>(ty: FunctionType, body: F) -> Self
+    where
+        F: Fn(&mut HostContext, &[Value]) -> Vec<Value> + Send + Sync + 'static,
+    {
+        HostFunction {
+            ty,
+            body: Box::new(body),
+        }
+    }
+
+    #[inline]
+    pub fn get_type(&self) -> &FunctionType {
+        &self.ty
+    }
+
+    pub(super) fn call(&self, ctx: &mut HostContext, args: &[Value]) -> Vec<Value> {
+        (self.body)(ctx, args)
+    }
+}
+
+// Resolves the imports of a module by their module and field names.
+pub trait Resolver {
+    fn resolve_function(&self, module_name: &str, export_name: &str) -> Option<Arc<HostFunction>>;
+
+    fn resolve_global(&self, module_name: &str, export_name: &str) -> Option<(GlobalType, Value)> {
+        None
+    }
+
+    // Memories can be shared with the instances of the compartment which they are created in.
+    fn resolve_memory(&self, module_name: &str, export_name: &str) -> Option<Arc<Memory>> {
+        None
+    }
+
+    // Tables can be shared with the instances of the compartment which they are created in.
+    fn resolve_table(&self, module_name: &str, export_name: &str) -> Option<Arc<Table>> {
+        None
+    }
+}
+
+// Tries the resolvers in order.
+impl<R: Resolver> Resolver for [R] {
+    fn resolve_function(&self, module_name: &str, export_name: &str) -> Option<Arc<HostFunction>> {
+        self.iter()
+            .filter_map(|r| r.resolve_function(module_name, export_name))
+            .next()
+    }
+
+    fn resolve_global(&self, module_name: &str, export_name: &str) -> Option<(GlobalType, Value)> {
+        self.iter()
+            .filter_map(|r| r.resolve_global(module_name, export_name))
+            .next()
+    }
+
+    fn resolve_memory(&self, module_name: &str, export_name: &str) -> Option<Arc<Memory>> {
+        self.iter()
+            .filter_map(|r| r.resolve_memory(module_name, export_name))
+            .next()
+    }
+
+    fn resolve_table(&self, module_name: &str, export_name: &str) -> Option<Arc<Table>> {
+        self.iter()
+            .filter_map(|r| r.resolve_table(module_name, export_name))
+            .next()
+    }
+}
+
+// A module of host functions, globals, memories and tables, which resolves the imports from its
+// name.
+pub struct HostModule {
+    name: String,
+    functions: HashMap<String, Arc<HostFunction>>,
+    globals: HashMap<String, (GlobalType, Value)>,
+    memorys: HashMap<String, Arc<Memory>>,
+    tables: HashMap<String, Arc<Table>>,
+}
+
+impl HostModule {
+    pub fn new(name: &str) -> Self {
+        HostModule {
+            name: name.to_string(),
+            functions: HashMap::new(),
+            globals: HashMap::new(),
+            memorys: HashMap::new(),
+            tables: HashMap::new(),
+        }
+    }
+
+    pub fn function<F>(mut self, name: &str, ty: FunctionType, body: F) -> Self
+    where
+        F: Fn(&mut HostContext, &[Value]) -> Vec<Value> + Send + Sync + 'static,
+    {
+        self.functions
+            .insert(name.to_string(), Arc::new(HostFunction::new(ty, body)));
+        self
+    }
+
+    // Only immutable globals can be imported for now.
+    pub fn global(mut self, name: &str, value: Value) -> Self {
+        self.globals.insert(
+            name.to_string(),
+            (GlobalType::new(value.value_type(), false), value),
+        );
+        self
+    }
+
+    // Exports a memory of the host, or one exported by another instance.
+    pub fn memory(mut self, name: &str, memory: Arc<Memory>) -> Self {
+        self.memorys.insert(name.to_string(), memory);
+        self
+    }
+
+    // Exports a table of the host, or one exported by another instance.
+    pub fn table(mut self, name: &str, table: Arc<Table>) -> Self {
+        self.tables.insert(name.to_string(), table);
+        self
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Resolver for HostModule {
+    fn resolve_function(&self, module_name: &str, export_name: &str) -> Option<Arc<HostFunction>> {
+        if module_name != self.name {
+            return None;
+        }
+        self.functions.get(export_name).cloned()
+    }
+
+    fn resolve_global(&self, module_name: &str, export_name: &str) -> Option<(GlobalType, Value)> {
+        if module_name != self.name {
+            return None;
+        }
+        self.globals.get(export_name).cloned()
+    }
+
+    fn resolve_memory(&self, module_name: &str, export_name: &str) -> Option<Arc<Memory>> {
+        if module_name != self.name {
+            return None;
+        }
+        self.memorys.get(export_name).cloned()
+    }
+
+    fn resolve_table(&self, module_name: &str, export_name: &str) -> Option<Arc<Table>> {
+        if module_name != self.name {
+            return None;
+        }
+        self.tables.get(export_name).cloned()
+    }
+}
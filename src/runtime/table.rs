@@ -0,0 +1,384 @@
+use crate::platform;
+use crate::runtime::compartment::{Compartment, RuntimeDataSlot};
+use crate::runtime::limiter::{ask_limiter, notify_limiter};
+use crate::wasm::types::{TableType, ValueType};
+use crate::wasm::LoadError;
+use std::sync::Mutex;
+
+// No table can grow beyond this many elements, whatever the maximum of its type, so that the
+// reservation of a table without a maximum stays small.
+pub const MAX_TABLE_ELEMS: u32 = 10_000_000;
+
+const ELEM_SIZE: u64 = std::mem::size_of::<u64>() as u64;
+
+// What a `funcref` points to, which the runtime allocates for every function of an instance.
+// The layout is shared with the code emitted for `call_indirect`, which calls the function with
+// `context`, the context pointer of its instance, since the table may be shared with the other
+// instances of the compartment.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct FunctionRef {
+    pub type_id: usize,
+    pub func: usize,
+    pub context: usize,
+}
+
+impl FunctionRef {
+    pub fn null() -> Self {
+        FunctionRef {
+            type_id: 0,
+            func: 0,
+            context: 0,
+        }
+    }
+}
+
+// The runtime data of a table, which the compiled code loads through `tableOffset{i}`.
+#[repr(C)]
+pub struct TableRuntimeData {
+    base: u64,
+    num_elements: u64,
+}
+
+// The elements are committed in a reservation for the maximum size of the table, which never
+// moves, since the compiled code of the instances sharing the table on other threads reads them
+// without holding the lock.
+struct TableState {
+    runtime_data: RuntimeDataSlot<TableRuntimeData>,
+    start_addr: u64,
+    num_elements: u32,
+    num_committed_pages: u32,
+    num_reserved_pages: u32,
+}
+
+impl TableState {
+    fn elements(&self) -> &[u64] {
+        unsafe {
+            std::slice::from_raw_parts(self.start_addr as *const u64, self.num_elements as usize)
+        }
+    }
+
+    fn elements_mut(&mut self) -> &mut [u64] {
+        unsafe {
+            std::slice::from_raw_parts_mut(self.start_addr as *mut u64, self.num_elements as usize)
+        }
+    }
+}
+
+// The elements of a table are references, which are 0 if they are null. A `funcref` is the
+// address of a `FunctionRef`. Like a memory, a table can be imported by the instances of its
+// compartment, so it's modified through a shared reference.
+pub struct Table {
+    ty: TableType,
+    state: Mutex<TableState>,
+    // The maximum of the table type, capped by `MAX_TABLE_ELEMS`.
+    max_elems: u32,
+}
+
+impl Drop for Table {
+    fn drop(&mut self) {
+        let state = match self.state.get_mut() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if let Some(limiter) = state.runtime_data.compartment().resource_limiter() {
+            let num_elements = state.num_elements;
+            notify_limiter(|| limiter.table_released(num_elements));
+        }
+        let _ = platform::free_pages(state.start_addr, state.num_reserved_pages);
+    }
+}
+
+impl Table {
+    // Grows the table by `num_elems` elements initialized to `init`, and returns its previous
+    // size, or `None` if it can't grow that much or the resource limiter of the compartment
+    // denies it.
+    pub fn grow(&self, num_elems: u32, init: u64) -> Option<u32> {
+        let mut state = self.state.lock().unwrap();
+        let prev_elems = state.num_elements;
+        if num_elems == 0 {
+            return Some(prev_elems);
+        }
+        let new_elems = prev_elems as u64 + num_elems as u64;
+        if new_elems > self.max_elems as u64 {
+            return None;
+        }
+        if let Some(limiter) = state.runtime_data.compartment().resource_limiter() {
+            let max_elems = self.max_elems;
+            if !ask_limiter(|| limiter.table_growing(prev_elems, new_elems as u32, max_elems)) {
+                return None;
+            }
+        }
+        let num_pages = num_pages_for(new_elems);
+        if num_pages > state.num_committed_pages {
+            let commit_addr =
+                state.start_addr + ((state.num_committed_pages as u64) << platform::PAGE_SHIFT);
+            let num_commit_pages = num_pages - state.num_committed_pages;
+            if platform::commit_pages(commit_addr, num_commit_pages).is_err() {
+                if let Some(limiter) = state.runtime_data.compartment().resource_limiter() {
+                    notify_limiter(|| limiter.table_released(num_elems));
+                }
+                return None;
+            }
+            state.num_committed_pages = num_pages;
+        }
+        state.num_elements = new_elems as u32;
+        state.elements_mut()[prev_elems as usize..]
+            .iter_mut()
+            .for_each(|elem| *elem = init);
+        state.runtime_data.num_elements = new_elems;
+        Some(prev_elems)
+    }
+
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.state.lock().unwrap().num_elements
+    }
+
+    #[inline]
+    pub fn elem_type(&self) -> ValueType {
+        self.ty.elem_type()
+    }
+
+    // The type of the table with its current size, which is what the imports of the table are
+    // matched against.
+    pub fn get_type(&self) -> TableType {
+        TableType::new(self.ty.elem_type(), self.len(), self.ty.max_elems())
+    }
+
+    // The offset of the runtime data from the compartment address.
+    #[inline]
+    pub fn runtime_data_offset(&self) -> u64 {
+        self.state.lock().unwrap().runtime_data.offset()
+    }
+
+    // The compiled code only finds the tables of its own compartment.
+    pub fn is_in_compartment(&self, compartment: &Compartment) -> bool {
+        let state = self.state.lock().unwrap();
+        state.runtime_data.compartment().base_addr() == compartment.base_addr()
+    }
+
+    pub fn get(&self, idx: u32) -> Option<u64> {
+        self.state
+            .lock()
+            .unwrap()
+            .elements()
+            .get(idx as usize)
+            .cloned()
+    }
+
+    pub fn set(&self, idx: u32, value: u64) -> Option<()> {
+        let mut state = self.state.lock().unwrap();
+        state
+            .elements_mut()
+            .get_mut(idx as usize)
+            .map(|elem| *elem = value)
+    }
+
+    // Sets `count` elements from `offset` to `value`, or returns `None` without modifying the
+    // table if they are out of its bounds.
+    pub fn fill(&self, offset: u32, value: u64, count: u32) -> Option<()> {
+        let mut state = self.state.lock().unwrap();
+        let end = offset as usize + count as usize;
+        if end > state.num_elements as usize {
+            return None;
+        }
+        state.elements_mut()[offset as usize..end]
+            .iter_mut()
+            .for_each(|elem| *elem = value);
+        Some(())
+    }
+
+    // Copies `elems` to `offset`, or returns `None` without modifying the table if they are out
+    // of its bounds.
+    pub fn write_elements(&self, offset: u32, elems: &[u64]) -> Option<()> {
+        let mut state = self.state.lock().unwrap();
+        let end = offset as usize + elems.len();
+        state
+            .elements_mut()
+            .get_mut(offset as usize..end)
+            .map(|dst| dst.copy_from_slice(elems))
+    }
+
+    // Copies the `count` elements from `src` of `src_table`, which may be this table, to `dst`,
+    // or returns `None` without modifying the table if either range is out of bounds.
+    pub fn copy_elements(&self, dst: u32, src_table: &Table, src: u32, count: u32) -> Option<()> {
+        let (dst, src, count) = (dst as usize, src as usize, count as usize);
+        if std::ptr::eq(self, src_table) {
+            let mut state = self.state.lock().unwrap();
+            let num_elements = state.num_elements as usize;
+            if dst + count > num_elements || src + count > num_elements {
+                return None;
+            }
+            state.elements_mut().copy_within(src..src + count, dst);
+            return Some(());
+        }
+        // The tables are always locked in the same order, since another instance sharing them
+        // may copy the other way.
+        let (mut dst_state, src_state) = if (self as *const Table) < (src_table as *const Table) {
+            let dst_state = self.state.lock().unwrap();
+            (dst_state, src_table.state.lock().unwrap())
+        } else {
+            let src_state = src_table.state.lock().unwrap();
+            (self.state.lock().unwrap(), src_state)
+        };
+        let elems = src_state.elements().get(src..src + count)?;
+        dst_state
+            .elements_mut()
+            .get_mut(dst..dst + count)
+            .map(|dst| dst.copy_from_slice(elems))
+    }
+
+    pub fn copy_into_elements(&self, offset: u32, elems: &[u64]) -> Result<(), LoadError> {
+        self.write_elements(offset, elems).ok_or_else(|| {
+            LoadError::Instantiate("the element segment is out of the table's bounds.".to_string())
+        })
+    }
+
+    // Sets the elements which satisfy `pred` to null.
+    pub(super) fn clear_elements<F: Fn(u64) -> bool>(&self, pred: F) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .elements_mut()
+            .iter_mut()
+            .filter(|elem| pred(**elem))
+            .for_each(|elem| *elem = 0);
+    }
+}
+
+// The number of host pages which hold `num_elems` elements.
+fn num_pages_for(num_elems: u64) -> u32 {
+    ((num_elems * ELEM_SIZE + platform::PAGE_SIZE - 1) >> platform::PAGE_SHIFT) as u32
+}
+
+pub fn create_table(compartment: &Compartment, ty: &TableType) -> Result<Table, LoadError> {
+    if ty.min_elems() > ty.max_elems().unwrap_or(std::u32::MAX) {
+        return Err(LoadError::Malformed(
+            "the initial size of table is larger than its maximum.".to_string(),
+        ));
+    }
+    let max_elems = std::cmp::min(ty.max_elems().unwrap_or(MAX_TABLE_ELEMS), MAX_TABLE_ELEMS);
+    let num_reserved_pages = std::cmp::max(num_pages_for(max_elems as u64), 1);
+    let start_addr = platform::alloc_pages(num_reserved_pages).map_err(LoadError::Instantiate)?;
+    let runtime_data = match compartment.alloc_table_data(TableRuntimeData {
+        base: start_addr,
+        num_elements: 0,
+    }) {
+        Ok(runtime_data) => runtime_data,
+        Err(e) => {
+            let _ = platform::free_pages(start_addr, num_reserved_pages);
+            return Err(e);
+        }
+    };
+    let table = Table {
+        ty: *ty,
+        state: Mutex::new(TableState {
+            runtime_data,
+            start_addr,
+            num_elements: 0,
+            num_committed_pages: 0,
+            num_reserved_pages,
+        }),
+        max_elems,
+    };
+    if table.grow(ty.min_elems(), 0).is_none() {
+        return Err(LoadError::Instantiate(format!(
+            "failed to allocate the initial {} elements of the table",
+            ty.min_elems()
+        )));
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_table(compartment: &Compartment, min: u32, max: Option<u32>) -> Table {
+        create_table(compartment, &TableType::new(ValueType::AnyFunc, min, max)).unwrap()
+    }
+
+    #[test]
+    fn grow_denied_by_resource_limiter_fails() {
+        use crate::codegen::BoundsCheckMode;
+        use crate::runtime::limiter::ResourceLimiter;
+        use std::sync::Arc;
+
+        struct DenyTables;
+
+        impl ResourceLimiter for DenyTables {
+            fn memory_growing(&self, _current: u64, _desired: u64, _maximum: u64) -> bool {
+                true
+            }
+
+            fn table_growing(&self, _current: u32, _desired: u32, _maximum: u32) -> bool {
+                false
+            }
+        }
+
+        let compartment =
+            Compartment::with_resource_limiter(BoundsCheckMode::default(), Arc::new(DenyTables))
+                .unwrap();
+        let table = new_table(&compartment, 0, Some(4));
+        assert_eq!(table.grow(1, 0), None);
+        assert_eq!(table.len(), 0);
+        // Growing by nothing doesn't ask the limiter.
+        assert_eq!(table.grow(0, 0), Some(0));
+        assert!(create_table(&compartment, &TableType::new(ValueType::AnyFunc, 1, None)).is_err());
+    }
+
+    #[test]
+    fn grow_beyond_element_cap_fails() {
+        let compartment = Compartment::new().unwrap();
+        let table = new_table(&compartment, 1, None);
+        assert_eq!(table.grow(0x7fff_ffff, 0), None);
+        assert_eq!(table.grow(MAX_TABLE_ELEMS, 0), None);
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.grow(MAX_TABLE_ELEMS - 1, 7), Some(1));
+        assert_eq!(table.get(MAX_TABLE_ELEMS - 1), Some(7));
+    }
+
+    // The compiled code of other threads reads the elements without the lock, so they must not
+    // move when the table grows.
+    #[test]
+    fn grow_keeps_elements_in_place() {
+        let compartment = Compartment::new().unwrap();
+        let table = new_table(&compartment, 1, None);
+        table.set(0, 3).unwrap();
+        let base = table.state.lock().unwrap().runtime_data.base;
+        assert_eq!(table.grow(4096, 5), Some(1));
+        let state = table.state.lock().unwrap();
+        assert_eq!(state.runtime_data.base, base);
+        assert_eq!(state.runtime_data.num_elements, 4097);
+        assert_eq!(state.elements()[0], 3);
+        assert_eq!(state.elements()[4096], 5);
+    }
+
+    #[test]
+    fn copy_elements_within_table_may_overlap() {
+        let compartment = Compartment::new().unwrap();
+        let table = new_table(&compartment, 4, None);
+        table.write_elements(0, &[1, 2, 3, 4]).unwrap();
+        assert_eq!(table.copy_elements(1, &table, 0, 3), Some(()));
+        assert_eq!(
+            (0..4).map(|i| table.get(i).unwrap()).collect::<Vec<_>>(),
+            [1, 1, 2, 3]
+        );
+        assert_eq!(table.copy_elements(0, &table, 2, 3), None);
+        assert_eq!(table.get(0), Some(1));
+    }
+
+    #[test]
+    fn copy_elements_between_tables_checks_both_bounds() {
+        let compartment = Compartment::new().unwrap();
+        let src = new_table(&compartment, 2, None);
+        let dst = new_table(&compartment, 3, None);
+        src.write_elements(0, &[5, 6]).unwrap();
+        assert_eq!(dst.copy_elements(2, &src, 0, 2), None);
+        assert_eq!(dst.copy_elements(0, &src, 1, 2), None);
+        assert_eq!(dst.get(0), Some(0));
+        assert_eq!(dst.copy_elements(1, &src, 0, 2), Some(()));
+        assert_eq!(dst.get(1), Some(5));
+        assert_eq!(dst.get(2), Some(6));
+    }
+}
@@ -0,0 +1,215 @@
+use crate::platform::{self, Signal};
+use lazy_static::lazy_static;
+use std::cell::Cell;
+use std::fmt;
+use std::sync::RwLock;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrapKind {
+    // The host called a function with arguments or results which don't match its type, or a host
+    // function returned results which don't match its type.
+    SignatureMismatch,
+    Unreachable,
+    IntegerDivideByZero,
+    IntegerOverflow,
+    InvalidConversionToInteger,
+    OutOfBoundsMemoryAccess,
+    UndefinedElement,
+    // `call_indirect` of a null element.
+    UninitializedElement,
+    IndirectCallSignatureMismatch,
+    // `table.get`, `table.set`, `table.fill`, `table.copy` or `table.init` out of the bounds of
+    // the table or the segment.
+    OutOfBoundsTableAccess,
+    // An atomic access to an address which isn't a multiple of its size.
+    MisalignedAtomicMemoryAccess,
+    // `memory.atomic.wait32` or `memory.atomic.wait64` on a memory which isn't shared.
+    ExpectedSharedMemory,
+    // An exception which no catch clause handled reached the host.
+    UncaughtException,
+    // A host function called by the compiled code panicked.
+    HostFunctionPanicked,
+    // Any other fault in the compiled code, e.g. a stack overflow.
+    AccessViolation,
+}
+
+impl fmt::Display for TrapKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            TrapKind::SignatureMismatch => "signature mismatch",
+            TrapKind::Unreachable => "unreachable executed",
+            TrapKind::IntegerDivideByZero => "integer divide by zero",
+            TrapKind::IntegerOverflow => "integer overflow",
+            TrapKind::InvalidConversionToInteger => "invalid conversion to integer",
+            TrapKind::OutOfBoundsMemoryAccess => "out of bounds memory access",
+            TrapKind::UndefinedElement => "undefined element",
+            TrapKind::UninitializedElement => "uninitialized element",
+            TrapKind::IndirectCallSignatureMismatch => "indirect call signature mismatch",
+            TrapKind::OutOfBoundsTableAccess => "out of bounds table access",
+            TrapKind::MisalignedAtomicMemoryAccess => "unaligned atomic",
+            TrapKind::ExpectedSharedMemory => "expected shared memory",
+            TrapKind::UncaughtException => "uncaught exception",
+            TrapKind::HostFunctionPanicked => "host function panicked",
+            TrapKind::AccessViolation => "access violation",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+// The reason why a call into an instance didn't return normally.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Trap {
+    kind: TrapKind,
+    func_index: Option<u32>,
+}
+
+impl Trap {
+    pub fn new(kind: TrapKind) -> Self {
+        Trap {
+            kind,
+            func_index: None,
+        }
+    }
+
+    #[inline]
+    pub fn kind(&self) -> TrapKind {
+        self.kind
+    }
+
+    // The index of the wasm function which trapped, if the trap was raised by compiled code.
+    #[inline]
+    pub fn func_index(&self) -> Option<u32> {
+        self.func_index
+    }
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "trap: {}", self.kind)?;
+        if let Some(idx) = self.func_index {
+            write!(f, " in function {}", idx)?;
+        }
+        Ok(())
+    }
+}
+
+struct CodeRange {
+    start: u64,
+    end: u64,
+    // The `[start, end)` ranges of the wasm functions in the code, and their indices.
+    functions: Vec<(u64, u64, u32)>,
+}
+
+lazy_static! {
+    static ref CODE_RANGES: RwLock<Vec<CodeRange>> = RwLock::new(Vec::new());
+    static ref MEMORY_RANGES: RwLock<Vec<(u64, u64)>> = RwLock::new(Vec::new());
+}
+
+thread_local! {
+    static PENDING_TRAP: Cell<Option<Trap>> = Cell::new(None);
+}
+
+// Keeps the code of an instance registered, so that faults in it are recognized as traps.
+pub struct CodeRegistration {
+    start: u64,
+}
+
+impl Drop for CodeRegistration {
+    fn drop(&mut self) {
+        let mut ranges = CODE_RANGES.write().unwrap();
+        ranges.retain(|range| range.start != self.start);
+    }
+}
+
+pub fn register_code(start: u64, end: u64, functions: Vec<(u64, u64, u32)>) -> CodeRegistration {
+    let mut ranges = CODE_RANGES.write().unwrap();
+    ranges.push(CodeRange {
+        start,
+        end,
+        functions,
+    });
+    CodeRegistration { start }
+}
+
+// The reserved address range of a linear memory, including its guard pages.
+pub fn register_memory(start: u64, end: u64) {
+    MEMORY_RANGES.write().unwrap().push((start, end));
+}
+
+pub fn unregister_memory(start: u64) {
+    MEMORY_RANGES.write().unwrap().retain(|range| range.0 != start);
+}
+
+// The registries can't be blocked on in a signal handler, but a writer never holds them for
+// long, and never while running compiled code.
+fn try_read<T, R, F: FnOnce(&T) -> R>(lock: &RwLock<T>, f: F) -> Option<R> {
+    for _ in 0..1000 {
+        if let Ok(value) = lock.try_read() {
+            return Some(f(&value));
+        }
+        std::thread::yield_now();
+    }
+    None
+}
+
+// Returns the index of the function containing `pc`, or `Some(None)` if `pc` is in compiled code
+// but not in any wasm function, e.g. in an invoke thunk.
+fn lookup_code(pc: u64) -> Option<Option<u32>> {
+    try_read(&CODE_RANGES, |ranges| {
+        ranges
+            .iter()
+            .find(|range| range.start <= pc && pc < range.end)
+            .map(|range| {
+                range
+                    .functions
+                    .iter()
+                    .find(|(start, end, _)| *start <= pc && pc < *end)
+                    .map(|(_, _, idx)| *idx)
+            })
+    })
+    .unwrap_or(None)
+}
+
+fn is_memory_addr(addr: u64) -> bool {
+    try_read(&MEMORY_RANGES, |ranges| {
+        ranges.iter().any(|(start, end)| *start <= addr && addr < *end)
+    })
+    .unwrap_or(false)
+}
+
+// Only faults in the compiled code are traps, faults in the runtime are left to the previous
+// signal handlers.
+fn filter_signal(signal: Signal, pc: u64, fault_addr: u64) -> bool {
+    let func_index = match lookup_code(pc) {
+        Some(func_index) => func_index,
+        None => return false,
+    };
+    let kind = match signal {
+        Signal::AccessViolation if is_memory_addr(fault_addr) => TrapKind::OutOfBoundsMemoryAccess,
+        Signal::AccessViolation => TrapKind::AccessViolation,
+        Signal::IntegerDivideByZero => TrapKind::IntegerDivideByZero,
+        Signal::IllegalInstruction => TrapKind::Unreachable,
+    };
+    PENDING_TRAP.with(|trap| trap.set(Some(Trap { kind, func_index })));
+    true
+}
+
+// Unwinds to the innermost `catch_traps`, which returns the trap. The frames which are skipped
+// aren't dropped, so it must be called from a runtime function which is called by compiled code,
+// once the values which it owns and the locks which it holds are released.
+pub fn raise_trap(kind: TrapKind, func_index: Option<u32>) -> ! {
+    PENDING_TRAP.with(|trap| trap.set(Some(Trap { kind, func_index })));
+    platform::unwind_to_trap_handler()
+}
+
+// Calls `f`, which calls into compiled code, and catches the traps raised in it.
+pub fn catch_traps<F: FnOnce()>(f: F) -> Result<(), Trap> {
+    if let Err(message) = platform::install_signal_handlers(filter_signal) {
+        panic!("failed to install the signal handlers: {}", message);
+    }
+    if platform::call_with_trap_handler(f) {
+        return Ok(());
+    }
+    let trap = PENDING_TRAP.with(|trap| trap.take());
+    Err(trap.unwrap_or_else(|| Trap::new(TrapKind::AccessViolation)))
+}
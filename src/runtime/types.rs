@@ -0,0 +1,22 @@
+use crate::wasm::FunctionType;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref FUNCTION_TYPES: Mutex<Vec<FunctionType>> = Mutex::new(Vec::new());
+}
+
+// Type ids are shared by all compartments, so that an indirect call through a table element
+// written by another module still compares signatures correctly. Zero is never handed out,
+// it marks an uninitialized table element.
+pub fn get_type_id(ty: &FunctionType) -> usize {
+    let mut types = FUNCTION_TYPES.lock().unwrap();
+    let idx = match types.iter().position(|t| t == ty) {
+        Some(idx) => idx,
+        None => {
+            types.push(ty.clone());
+            types.len() - 1
+        }
+    };
+    idx + 1
+}
@@ -2,3 +2,51 @@
 // but it's only designed for very early development of runtime.
 // When the 'real' standard library is stable, this module will be moved out of 
 // the source tree
+
+use crate::runtime::{HostContext, HostModule};
+use crate::wasm::{FunctionType, Value, ValueType};
+
+// The modules built against this library keep the stream of the standard output at address 0
+// of their memory, which is zero-initialized. Only the stream 0, the standard output, and the
+// stream 1, the standard error, can be written; any other stream writes nothing.
+const STDOUT_PTR: i32 = 0;
+
+fn fwrite(ctx: &mut HostContext, args: &[Value]) -> Vec<Value> {
+    let (ptr, size, nmemb, stream) = match args {
+        [Value::I32(ptr), Value::I32(size), Value::I32(nmemb), Value::I32(stream)] => {
+            (*ptr as u32, *size as u32, *nmemb as u32, *stream)
+        }
+        _ => unreachable!(),
+    };
+    let fd = match stream {
+        0 => libc::STDOUT_FILENO,
+        1 => libc::STDERR_FILENO,
+        _ => return vec![Value::I32(0)],
+    };
+    let num_bytes = match size.checked_mul(nmemb) {
+        Some(num_bytes) if size != 0 => num_bytes,
+        _ => return vec![Value::I32(0)],
+    };
+    let bytes = match ctx.memory(0).and_then(|m| m.read_bytes(ptr, num_bytes)) {
+        Some(bytes) => bytes,
+        None => return vec![Value::I32(0)],
+    };
+    let written = unsafe { libc::write(fd, bytes.as_ptr() as *const _, bytes.len()) };
+    if written < 0 {
+        return vec![Value::I32(0)];
+    }
+    vec![Value::I32((written as u32 / size) as i32)]
+}
+
+pub fn host_module() -> HostModule {
+    HostModule::new("std")
+        .function(
+            "fwrite",
+            FunctionType::new(
+                vec![ValueType::I32, ValueType::I32, ValueType::I32, ValueType::I32],
+                vec![ValueType::I32],
+            ),
+            fwrite,
+        )
+        .global("stdout", Value::I32(STDOUT_PTR))
+}
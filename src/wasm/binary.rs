@@ -0,0 +1,1063 @@
+use super::instruction;
+use super::simd::{self, SimdOperator};
+use super::{
+    BlockType, Export, FunctionType, GlobalType, Instruction, LoadError, MemoryType, TableType,
+    ValueType,
+};
+use std::io::Cursor;
+
+const MAGIC: [u8; 4] = [0x00, 0x61, 0x73, 0x6d];
+const VERSION: [u8; 4] = [0x01, 0x00, 0x00, 0x00];
+
+const CUSTOM_SECTION_ID: u8 = 0;
+const TYPE_SECTION_ID: u8 = 1;
+const IMPORT_SECTION_ID: u8 = 2;
+const FUNCTION_SECTION_ID: u8 = 3;
+const TABLE_SECTION_ID: u8 = 4;
+const MEMORY_SECTION_ID: u8 = 5;
+const GLOBAL_SECTION_ID: u8 = 6;
+const EXPORT_SECTION_ID: u8 = 7;
+const START_SECTION_ID: u8 = 8;
+const ELEMENT_SECTION_ID: u8 = 9;
+const CODE_SECTION_ID: u8 = 10;
+const DATA_SECTION_ID: u8 = 11;
+const DATA_COUNT_SECTION_ID: u8 = 12;
+const TAG_SECTION_ID: u8 = 13;
+
+const TRY: u8 = 0x06;
+const CATCH: u8 = 0x07;
+const THROW: u8 = 0x08;
+const RETHROW: u8 = 0x09;
+const DELEGATE: u8 = 0x18;
+const CATCH_ALL: u8 = 0x19;
+
+const BLOCK: u8 = 0x02;
+const LOOP: u8 = 0x03;
+const IF: u8 = 0x04;
+const END: u8 = 0x0b;
+const CALL_INDIRECT: u8 = 0x11;
+const SELECT_TYPED: u8 = 0x1c;
+const TABLE_GET: u8 = 0x25;
+const TABLE_SET: u8 = 0x26;
+const REF_NULL: u8 = 0xd0;
+const REF_IS_NULL: u8 = 0xd1;
+const REF_FUNC: u8 = 0xd2;
+const BULK_PREFIX: u8 = 0xfc;
+const MEMORY_INIT: u32 = 0x08;
+const DATA_DROP: u32 = 0x09;
+const MEMORY_COPY: u32 = 0x0a;
+const MEMORY_FILL: u32 = 0x0b;
+const TABLE_INIT: u32 = 0x0c;
+const ELEM_DROP: u32 = 0x0d;
+const TABLE_COPY: u32 = 0x0e;
+const TABLE_GROW: u32 = 0x0f;
+const TABLE_SIZE: u32 = 0x10;
+const TABLE_FILL: u32 = 0x11;
+const SIMD_PREFIX: u8 = 0xfd;
+const ATOMIC_PREFIX: u8 = 0xfe;
+// The other atomic instructions are followed by a memory argument, but `atomic.fence` is
+// followed by a single reserved byte.
+const ATOMIC_FENCE: u8 = 0x03;
+
+const EMPTY_BLOCK_TYPE: u8 = 0x40;
+const FUNC_TYPE: u8 = 0x60;
+const I32: u8 = 0x7f;
+const I64: u8 = 0x7e;
+const F32: u8 = 0x7d;
+const F64: u8 = 0x7c;
+const V128: u8 = 0x7b;
+const FUNC_REF: u8 = 0x70;
+const EXTERN_REF: u8 = 0x6f;
+// The only element kind of the element segments of function indices.
+const ELEM_KIND_FUNC_REF: u8 = 0x00;
+
+// A function body can't declare more locals than this, like in other engines.
+const MAX_LOCALS: u64 = 50000;
+
+// The instructions of a function body. The instructions of the MVP and of the threads proposal
+// are decoded by `instruction::read_instruction`, and the others by `read_operator`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operator {
+    Instr(Instruction),
+    Block(BlockType),
+    Loop(BlockType),
+    If(BlockType),
+    Try(BlockType),
+    Catch(u32),
+    CatchAll,
+    Throw(u32),
+    Rethrow(u32),
+    Delegate(u32),
+    Simd(SimdOperator),
+    AtomicFence,
+    // The type index and the table index.
+    CallIndirect(u32, u32),
+    RefNull(ValueType),
+    RefIsNull,
+    RefFunc(u32),
+    TableGet(u32),
+    TableSet(u32),
+    TableSize(u32),
+    TableGrow(u32),
+    TableFill(u32),
+    SelectTyped(Vec<ValueType>),
+    // The data segment index and the memory index.
+    MemoryInit(u32, u32),
+    DataDrop(u32),
+    // The destination and the source memory indices.
+    MemoryCopy(u32, u32),
+    MemoryFill(u32),
+    // The element segment index and the table index.
+    TableInit(u32, u32),
+    ElemDrop(u32),
+    // The destination and the source table indices.
+    TableCopy(u32, u32),
+}
+
+pub(super) struct FuncBody {
+    pub locals: Vec<ValueType>,
+    pub code: Vec<Operator>,
+    // The offsets of the operators in the module, followed by the offset of the end of the body.
+    pub offsets: Vec<usize>,
+}
+
+// The payloads of the known sections of a module. The custom sections are skipped.
+#[derive(Default)]
+pub(super) struct Sections<'a> {
+    // The offset of the code section payload in the module.
+    pub code_offset: usize,
+    pub types: Option<&'a [u8]>,
+    pub imports: Option<&'a [u8]>,
+    pub functions: Option<&'a [u8]>,
+    pub tables: Option<&'a [u8]>,
+    pub memorys: Option<&'a [u8]>,
+    pub tags: Option<&'a [u8]>,
+    pub globals: Option<&'a [u8]>,
+    pub exports: Option<&'a [u8]>,
+    pub start: Option<&'a [u8]>,
+    pub elements: Option<&'a [u8]>,
+    pub data_count: Option<&'a [u8]>,
+    pub code: Option<&'a [u8]>,
+    pub datas: Option<&'a [u8]>,
+}
+
+pub(super) enum ImportDesc {
+    Function(u32),
+    Table(TableType),
+    Memory(MemoryType),
+    Global(GlobalType),
+}
+
+pub(super) struct ImportEntry {
+    pub module: String,
+    pub field: String,
+    pub desc: ImportDesc,
+}
+
+// Where the members of an element segment go. An active segment is copied into a table at
+// instantiation, a passive one is only copied by `table.init`, and a declared one only declares
+// the functions which `ref.func` may refer to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ElementMode {
+    Active { table_idx: u32, offset: Operator },
+    Passive,
+    Declared,
+}
+
+pub(super) struct ElementEntry {
+    pub mode: ElementMode,
+    pub elem_type: ValueType,
+    // The constant expressions of the members, which are `ref.func` for the segments of
+    // function indices.
+    pub members: Vec<Operator>,
+}
+
+// An active data segment is copied into a memory at instantiation, and a passive one is only
+// copied by `memory.init`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataMode {
+    Active { memory_idx: u32, offset: Operator },
+    Passive,
+}
+
+pub(super) struct DataEntry {
+    pub mode: DataMode,
+    pub value: Vec<u8>,
+}
+
+fn malformed(msg: &str) -> LoadError {
+    LoadError::Malformed(msg.to_string())
+}
+
+pub(super) struct Reader<'a> {
+    cursor: Cursor<&'a [u8]>,
+}
+
+impl<'a> Reader<'a> {
+    pub(super) fn new(bytes: &'a [u8]) -> Self {
+        Reader {
+            cursor: Cursor::new(bytes),
+        }
+    }
+
+    #[inline]
+    pub(super) fn position(&self) -> usize {
+        self.cursor.position() as usize
+    }
+
+    #[inline]
+    pub(super) fn is_empty(&self) -> bool {
+        self.position() >= self.cursor.get_ref().len()
+    }
+
+    pub(super) fn peek_u8(&self) -> Result<u8, LoadError> {
+        self.cursor
+            .get_ref()
+            .get(self.position())
+            .cloned()
+            .ok_or_else(|| malformed("unexpected end of the module"))
+    }
+
+    // Returns the byte `n` bytes after the current one, without reading anything.
+    fn peek_nth_u8(&self, n: usize) -> Option<u8> {
+        self.cursor.get_ref().get(self.position() + n).cloned()
+    }
+
+    pub(super) fn read_u8(&mut self) -> Result<u8, LoadError> {
+        let byte = self.peek_u8()?;
+        self.cursor.set_position(self.cursor.position() + 1);
+        Ok(byte)
+    }
+
+    pub(super) fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], LoadError> {
+        let start = self.position();
+        let bytes = *self.cursor.get_ref();
+        match start.checked_add(len) {
+            Some(end) if end <= bytes.len() => {
+                self.cursor.set_position(end as u64);
+                Ok(&bytes[start..end])
+            }
+            _ => Err(malformed("unexpected end of the module")),
+        }
+    }
+
+    // Reads a LEB128 integer of `bits` bits. It can't have more bytes than needed for the bits,
+    // and the unused bits of its last byte must be 0, or the sign bit if it's signed.
+    fn read_var_int(&mut self, bits: u32, signed: bool) -> Result<u64, LoadError> {
+        let mut value: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift > bits {
+                    let num_used_bits = 7 - (shift - bits);
+                    let unused_bits = (byte & 0x7f) >> num_used_bits;
+                    let sign_bit = (byte >> (num_used_bits - 1)) & 1;
+                    let expected = if signed && sign_bit != 0 {
+                        0x7f >> num_used_bits
+                    } else {
+                        0
+                    };
+                    if unused_bits != expected {
+                        return Err(malformed("integer too large"));
+                    }
+                }
+                if signed && shift < 64 && byte & 0x40 != 0 {
+                    value |= !0 << shift;
+                }
+                return Ok(value);
+            }
+            if shift >= bits {
+                return Err(malformed("integer representation too long"));
+            }
+        }
+    }
+
+    pub(super) fn read_var_u32(&mut self) -> Result<u32, LoadError> {
+        Ok(self.read_var_int(32, false)? as u32)
+    }
+
+    pub(super) fn read_var_i32(&mut self) -> Result<i32, LoadError> {
+        Ok(self.read_var_int(32, true)? as i32)
+    }
+
+    pub(super) fn read_var_i64(&mut self) -> Result<i64, LoadError> {
+        Ok(self.read_var_int(64, true)? as i64)
+    }
+
+    // Skips a LEB128 integer and returns its bytes.
+    fn read_leb(&mut self) -> Result<&'a [u8], LoadError> {
+        let start = self.position();
+        while self.read_u8()? & 0x80 != 0 {}
+        Ok(&self.cursor.get_ref()[start..self.position()])
+    }
+
+    pub(super) fn read_value_type(&mut self) -> Result<ValueType, LoadError> {
+        match self.peek_u8()? {
+            FUNC_REF | EXTERN_REF => self.read_ref_type(),
+            _ => match self.read_u8()? {
+                I32 => Ok(ValueType::I32),
+                I64 => Ok(ValueType::I64),
+                F32 => Ok(ValueType::F32),
+                F64 => Ok(ValueType::F64),
+                V128 => Ok(ValueType::V128),
+                _ => Err(malformed("malformed value type")),
+            },
+        }
+    }
+
+    fn read_ref_type(&mut self) -> Result<ValueType, LoadError> {
+        match self.read_u8()? {
+            FUNC_REF => Ok(ValueType::AnyFunc),
+            EXTERN_REF => Ok(ValueType::AnyRef),
+            _ => Err(malformed("malformed reference type")),
+        }
+    }
+
+    fn read_value_types(&mut self) -> Result<Vec<ValueType>, LoadError> {
+        let count = self.read_var_u32()?;
+        let mut types = Vec::new();
+        for _ in 0..count {
+            types.push(self.read_value_type()?);
+        }
+        Ok(types)
+    }
+
+    fn read_name(&mut self) -> Result<String, LoadError> {
+        let len = self.read_var_u32()?;
+        let bytes = self.read_bytes(len as usize)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| malformed("malformed UTF-8 encoding"))
+    }
+
+    // Returns the minimum, the maximum and whether the limits are shared.
+    fn read_limits(&mut self) -> Result<(u32, Option<u32>, bool), LoadError> {
+        let flags = self.read_u8()?;
+        if flags > 3 {
+            return Err(malformed("integer too large"));
+        }
+        let min = self.read_var_u32()?;
+        let max = if flags & 1 != 0 {
+            Some(self.read_var_u32()?)
+        } else {
+            None
+        };
+        Ok((min, max, flags & 2 != 0))
+    }
+
+    fn read_table_type(&mut self) -> Result<TableType, LoadError> {
+        let elem_type = self.read_ref_type()?;
+        match self.read_limits()? {
+            (_, _, true) => Err(malformed("a table can't be shared")),
+            (min, max, false) => Ok(TableType::new(elem_type, min, max)),
+        }
+    }
+
+    fn read_global_type(&mut self) -> Result<GlobalType, LoadError> {
+        let ty = self.read_value_type()?;
+        match self.read_u8()? {
+            0 => Ok(GlobalType::new(ty, false)),
+            1 => Ok(GlobalType::new(ty, true)),
+            _ => Err(malformed("malformed mutability")),
+        }
+    }
+
+    // A block type is either a value type, which is encoded as a negative single byte, or the
+    // index of a function type, which is encoded as a non-negative 33-bit signed integer.
+    fn read_block_type(&mut self) -> Result<BlockType, LoadError> {
+        let byte = self.peek_u8()?;
+        if byte == EMPTY_BLOCK_TYPE {
+            self.read_u8()?;
+            return Ok(BlockType::NoResult);
+        }
+        if byte & 0xc0 == 0x40 {
+            return Ok(BlockType::Value(self.read_value_type()?));
+        }
+        let bytes = self.read_leb()?;
+        if bytes.len() > 5 {
+            return Err(malformed("integer representation too long"));
+        }
+        let mut index: i64 = 0;
+        for (i, byte) in bytes.iter().enumerate() {
+            index |= ((byte & 0x7f) as i64) << (i * 7);
+        }
+        // Sign extends the 33 bits.
+        let shift = 64 - std::cmp::min(bytes.len() * 7, 33);
+        let index = (index << shift) >> shift;
+        if index < 0 || index > std::u32::MAX as i64 {
+            return Err(malformed("invalid block type"));
+        }
+        Ok(BlockType::TypeIndex(index as u32))
+    }
+}
+
+// The position of a section in a module. The sections are ordered by their id, except the tag
+// section, which comes before the global section, and the data count section, which comes before
+// the code section.
+fn section_order(id: u8) -> Option<u8> {
+    let order = match id {
+        TYPE_SECTION_ID => 1,
+        IMPORT_SECTION_ID => 2,
+        FUNCTION_SECTION_ID => 3,
+        TABLE_SECTION_ID => 4,
+        MEMORY_SECTION_ID => 5,
+        TAG_SECTION_ID => 6,
+        GLOBAL_SECTION_ID => 7,
+        EXPORT_SECTION_ID => 8,
+        START_SECTION_ID => 9,
+        ELEMENT_SECTION_ID => 10,
+        DATA_COUNT_SECTION_ID => 11,
+        CODE_SECTION_ID => 12,
+        DATA_SECTION_ID => 13,
+        _ => return None,
+    };
+    Some(order)
+}
+
+pub(super) fn split_sections(bytes: &[u8]) -> Result<Sections<'_>, LoadError> {
+    let mut reader = Reader::new(bytes);
+    if reader.read_bytes(4)? != MAGIC || reader.read_bytes(4)? != VERSION {
+        return Err(malformed("the module doesn't start with the wasm magic and version"));
+    }
+    let mut sections = Sections::default();
+    let mut last_order = 0;
+    while !reader.is_empty() {
+        let id = reader.read_u8()?;
+        let size = reader.read_var_u32()?;
+        let offset = reader.position();
+        let payload = reader.read_bytes(size as usize)?;
+        if id == CUSTOM_SECTION_ID {
+            // Only the name of a custom section is checked.
+            Reader::new(payload).read_name()?;
+            continue;
+        }
+        let order = match section_order(id) {
+            Some(order) => order,
+            None => return Err(malformed(&format!("malformed section id {}", id))),
+        };
+        if order == last_order {
+            return Err(malformed(&format!("duplicated section {}", id)));
+        }
+        if order < last_order {
+            return Err(malformed(&format!("unexpected section {}", id)));
+        }
+        last_order = order;
+        if id == CODE_SECTION_ID {
+            sections.code_offset = offset;
+        }
+        let section = match id {
+            TYPE_SECTION_ID => &mut sections.types,
+            IMPORT_SECTION_ID => &mut sections.imports,
+            FUNCTION_SECTION_ID => &mut sections.functions,
+            TABLE_SECTION_ID => &mut sections.tables,
+            MEMORY_SECTION_ID => &mut sections.memorys,
+            TAG_SECTION_ID => &mut sections.tags,
+            GLOBAL_SECTION_ID => &mut sections.globals,
+            EXPORT_SECTION_ID => &mut sections.exports,
+            START_SECTION_ID => &mut sections.start,
+            ELEMENT_SECTION_ID => &mut sections.elements,
+            DATA_COUNT_SECTION_ID => &mut sections.data_count,
+            CODE_SECTION_ID => &mut sections.code,
+            _ => &mut sections.datas,
+        };
+        *section = Some(payload);
+    }
+    Ok(sections)
+}
+
+pub(super) fn parse_type_section(payload: &[u8]) -> Result<Vec<FunctionType>, LoadError> {
+    let mut reader = Reader::new(payload);
+    let count = reader.read_var_u32()?;
+    let mut types = Vec::new();
+    for _ in 0..count {
+        if reader.read_u8()? != FUNC_TYPE {
+            return Err(malformed("the form of a type must be 0x60"));
+        }
+        let params = reader.read_value_types()?;
+        let results = reader.read_value_types()?;
+        types.push(FunctionType::new(params, results));
+    }
+    if !reader.is_empty() {
+        return Err(malformed("unexpected data at the end of the type section"));
+    }
+    Ok(types)
+}
+
+pub(super) fn parse_import_section(payload: &[u8]) -> Result<Vec<ImportEntry>, LoadError> {
+    let mut reader = Reader::new(payload);
+    let count = reader.read_var_u32()?;
+    let mut imports = Vec::new();
+    for _ in 0..count {
+        let module = reader.read_name()?;
+        let field = reader.read_name()?;
+        let desc = match reader.read_u8()? {
+            0x00 => ImportDesc::Function(reader.read_var_u32()?),
+            0x01 => ImportDesc::Table(reader.read_table_type()?),
+            0x02 => {
+                let (min, max, shared) = reader.read_limits()?;
+                ImportDesc::Memory(MemoryType::new(min, max, shared))
+            }
+            0x03 => ImportDesc::Global(reader.read_global_type()?),
+            0x04 => return Err(LoadError::Unsupported("tag imports".to_string())),
+            _ => return Err(malformed("malformed import kind")),
+        };
+        imports.push(ImportEntry { module, field, desc });
+    }
+    if !reader.is_empty() {
+        return Err(malformed("unexpected data at the end of the import section"));
+    }
+    Ok(imports)
+}
+
+// Returns the type indices of the functions defined by the module.
+pub(super) fn parse_function_section(payload: &[u8]) -> Result<Vec<u32>, LoadError> {
+    let mut reader = Reader::new(payload);
+    let count = reader.read_var_u32()?;
+    let mut funcs = Vec::new();
+    for _ in 0..count {
+        funcs.push(reader.read_var_u32()?);
+    }
+    if !reader.is_empty() {
+        return Err(malformed("unexpected data at the end of the function section"));
+    }
+    Ok(funcs)
+}
+
+pub(super) fn parse_table_section(payload: &[u8]) -> Result<Vec<TableType>, LoadError> {
+    let mut reader = Reader::new(payload);
+    let count = reader.read_var_u32()?;
+    let mut tables = Vec::new();
+    for _ in 0..count {
+        tables.push(reader.read_table_type()?);
+    }
+    if !reader.is_empty() {
+        return Err(malformed("unexpected data at the end of the table section"));
+    }
+    Ok(tables)
+}
+
+pub(super) fn parse_memory_section(payload: &[u8]) -> Result<Vec<MemoryType>, LoadError> {
+    let mut reader = Reader::new(payload);
+    let count = reader.read_var_u32()?;
+    let mut memorys = Vec::new();
+    for _ in 0..count {
+        let (min, max, shared) = reader.read_limits()?;
+        memorys.push(MemoryType::new(min, max, shared));
+    }
+    if !reader.is_empty() {
+        return Err(malformed("unexpected data at the end of the memory section"));
+    }
+    Ok(memorys)
+}
+
+pub(super) fn parse_global_section(
+    payload: &[u8],
+) -> Result<Vec<(GlobalType, Operator)>, LoadError> {
+    let mut reader = Reader::new(payload);
+    let count = reader.read_var_u32()?;
+    let mut globals = Vec::new();
+    for _ in 0..count {
+        let ty = reader.read_global_type()?;
+        globals.push((ty, read_const_expr(&mut reader)?));
+    }
+    if !reader.is_empty() {
+        return Err(malformed("unexpected data at the end of the global section"));
+    }
+    Ok(globals)
+}
+
+pub(super) fn parse_export_section(payload: &[u8]) -> Result<Vec<(String, Export)>, LoadError> {
+    let mut reader = Reader::new(payload);
+    let count = reader.read_var_u32()?;
+    let mut exports = Vec::new();
+    for _ in 0..count {
+        let name = reader.read_name()?;
+        let export = match reader.read_u8()? {
+            0x00 => Export::Function(reader.read_var_u32()?),
+            0x01 => Export::Table(reader.read_var_u32()?),
+            0x02 => Export::Memory(reader.read_var_u32()?),
+            0x03 => Export::Global(reader.read_var_u32()?),
+            0x04 => return Err(LoadError::Unsupported("tag exports".to_string())),
+            _ => return Err(malformed("malformed export kind")),
+        };
+        exports.push((name, export));
+    }
+    if !reader.is_empty() {
+        return Err(malformed("unexpected data at the end of the export section"));
+    }
+    Ok(exports)
+}
+
+pub(super) fn parse_start_section(payload: &[u8]) -> Result<u32, LoadError> {
+    let mut reader = Reader::new(payload);
+    let func_idx = reader.read_var_u32()?;
+    if !reader.is_empty() {
+        return Err(malformed("unexpected data at the end of the start section"));
+    }
+    Ok(func_idx)
+}
+
+// The flags of an element segment tell whether it's passive or declared (bit 0), whether an
+// active one has a table index, or a passive or declared one has an element type (bit 1), and
+// whether its members are constant expressions rather than function indices (bit 2).
+pub(super) fn parse_element_section(payload: &[u8]) -> Result<Vec<ElementEntry>, LoadError> {
+    let mut reader = Reader::new(payload);
+    let count = reader.read_var_u32()?;
+    let mut elements = Vec::new();
+    for _ in 0..count {
+        let flags = reader.read_var_u32()?;
+        if flags > 7 {
+            return Err(malformed("malformed elements segment kind"));
+        }
+        let mode = if flags & 1 == 0 {
+            let table_idx = if flags & 2 != 0 {
+                reader.read_var_u32()?
+            } else {
+                0
+            };
+            let offset = read_const_expr(&mut reader)?;
+            ElementMode::Active { table_idx, offset }
+        } else if flags & 2 == 0 {
+            ElementMode::Passive
+        } else {
+            ElementMode::Declared
+        };
+        // The element type is implicit in the active segments without a table index.
+        let has_elem_type = flags & 3 != 0;
+        let is_expr = flags & 4 != 0;
+        let elem_type = if !has_elem_type {
+            ValueType::AnyFunc
+        } else if is_expr {
+            reader.read_ref_type()?
+        } else if reader.read_u8()? == ELEM_KIND_FUNC_REF {
+            ValueType::AnyFunc
+        } else {
+            return Err(malformed("malformed element kind"));
+        };
+        let num_members = reader.read_var_u32()?;
+        let mut members = Vec::new();
+        for _ in 0..num_members {
+            members.push(if is_expr {
+                read_const_expr(&mut reader)?
+            } else {
+                Operator::RefFunc(reader.read_var_u32()?)
+            });
+        }
+        elements.push(ElementEntry {
+            mode,
+            elem_type,
+            members,
+        });
+    }
+    if !reader.is_empty() {
+        return Err(malformed("unexpected data at the end of the element section"));
+    }
+    Ok(elements)
+}
+
+pub(super) fn parse_data_count_section(payload: &[u8]) -> Result<u32, LoadError> {
+    let mut reader = Reader::new(payload);
+    let count = reader.read_var_u32()?;
+    if !reader.is_empty() {
+        return Err(malformed("unexpected data at the end of the data count section"));
+    }
+    Ok(count)
+}
+
+// The flags of a data segment tell whether it's passive (bit 0), or whether an active one has a
+// memory index (bit 1).
+pub(super) fn parse_data_section(payload: &[u8]) -> Result<Vec<DataEntry>, LoadError> {
+    let mut reader = Reader::new(payload);
+    let count = reader.read_var_u32()?;
+    let mut datas = Vec::new();
+    for _ in 0..count {
+        let mode = match reader.read_var_u32()? {
+            0 => DataMode::Active {
+                memory_idx: 0,
+                offset: read_const_expr(&mut reader)?,
+            },
+            1 => DataMode::Passive,
+            2 => {
+                let memory_idx = reader.read_var_u32()?;
+                DataMode::Active {
+                    memory_idx,
+                    offset: read_const_expr(&mut reader)?,
+                }
+            }
+            _ => return Err(malformed("malformed data segment kind")),
+        };
+        let len = reader.read_var_u32()?;
+        let value = reader.read_bytes(len as usize)?.to_vec();
+        datas.push(DataEntry { mode, value });
+    }
+    if !reader.is_empty() {
+        return Err(malformed("unexpected data at the end of the data section"));
+    }
+    Ok(datas)
+}
+
+// A constant expression consists of a single instruction followed by `end`.
+fn read_const_expr(reader: &mut Reader) -> Result<Operator, LoadError> {
+    match read_operator(reader)? {
+        Operator::Instr(Instruction::End) => {
+            Err(malformed("constant expression must be terminated by end"))
+        }
+        op => match read_operator(reader)? {
+            Operator::Instr(Instruction::End) => Ok(op),
+            _ => Err(LoadError::Unsupported(
+                "constant expressions with more than one instruction".to_string(),
+            )),
+        },
+    }
+}
+
+// Returns the type indices of the tags. Only the exception attribute is defined.
+pub(super) fn parse_tag_section(payload: &[u8]) -> Result<Vec<u32>, LoadError> {
+    let mut reader = Reader::new(payload);
+    let count = reader.read_var_u32()?;
+    let mut tags = Vec::new();
+    for _ in 0..count {
+        if reader.read_u8()? != 0 {
+            return Err(malformed("the attribute of a tag must be 0"));
+        }
+        tags.push(reader.read_var_u32()?);
+    }
+    if !reader.is_empty() {
+        return Err(malformed("unexpected data at the end of the tag section"));
+    }
+    Ok(tags)
+}
+
+// `offset` is the offset of the payload in the module, which the offsets of the operators are
+// relative to.
+pub(super) fn parse_code_section(
+    payload: &[u8],
+    offset: usize,
+) -> Result<Vec<FuncBody>, LoadError> {
+    let mut reader = Reader::new(payload);
+    let count = reader.read_var_u32()?;
+    let mut bodys = Vec::new();
+    for _ in 0..count {
+        let size = reader.read_var_u32()?;
+        let body_offset = offset + reader.position();
+        bodys.push(parse_func_body(
+            reader.read_bytes(size as usize)?,
+            body_offset,
+        )?);
+    }
+    if !reader.is_empty() {
+        return Err(malformed("unexpected data at the end of the code section"));
+    }
+    Ok(bodys)
+}
+
+fn parse_func_body(bytes: &[u8], offset: usize) -> Result<FuncBody, LoadError> {
+    let mut reader = Reader::new(bytes);
+    let num_entries = reader.read_var_u32()?;
+    let mut locals = Vec::new();
+    for _ in 0..num_entries {
+        let count = reader.read_var_u32()?;
+        if locals.len() as u64 + count as u64 > MAX_LOCALS {
+            return Err(LoadError::Unsupported(format!(
+                "more than {} locals in a function",
+                MAX_LOCALS
+            )));
+        }
+        let ty = reader.read_value_type()?;
+        locals.extend((0..count).map(|_| ty));
+    }
+
+    let mut code = Vec::new();
+    let mut offsets = Vec::new();
+    while !reader.is_empty() {
+        offsets.push(offset + reader.position());
+        code.push(read_operator(&mut reader)?);
+    }
+    offsets.push(offset + reader.position());
+    Ok(FuncBody {
+        locals,
+        code,
+        offsets,
+    })
+}
+
+fn read_operator(reader: &mut Reader) -> Result<Operator, LoadError> {
+    let op = match reader.peek_u8()? {
+        BLOCK => {
+            reader.read_u8()?;
+            Operator::Block(reader.read_block_type()?)
+        }
+        LOOP => {
+            reader.read_u8()?;
+            Operator::Loop(reader.read_block_type()?)
+        }
+        IF => {
+            reader.read_u8()?;
+            Operator::If(reader.read_block_type()?)
+        }
+        TRY => {
+            reader.read_u8()?;
+            Operator::Try(reader.read_block_type()?)
+        }
+        CATCH => {
+            reader.read_u8()?;
+            Operator::Catch(reader.read_var_u32()?)
+        }
+        CATCH_ALL => {
+            reader.read_u8()?;
+            Operator::CatchAll
+        }
+        THROW => {
+            reader.read_u8()?;
+            Operator::Throw(reader.read_var_u32()?)
+        }
+        RETHROW => {
+            reader.read_u8()?;
+            Operator::Rethrow(reader.read_var_u32()?)
+        }
+        DELEGATE => {
+            reader.read_u8()?;
+            Operator::Delegate(reader.read_var_u32()?)
+        }
+        SIMD_PREFIX => {
+            reader.read_u8()?;
+            simd::read_simd_operator(reader)?
+        }
+        ATOMIC_PREFIX if reader.peek_nth_u8(1) == Some(ATOMIC_FENCE) => {
+            reader.read_bytes(2)?;
+            if reader.read_u8()? != 0 {
+                return Err(malformed("the reserved byte of atomic.fence must be 0"));
+            }
+            Operator::AtomicFence
+        }
+        CALL_INDIRECT => {
+            reader.read_u8()?;
+            let ty_idx = reader.read_var_u32()?;
+            Operator::CallIndirect(ty_idx, reader.read_var_u32()?)
+        }
+        SELECT_TYPED => {
+            reader.read_u8()?;
+            Operator::SelectTyped(reader.read_value_types()?)
+        }
+        TABLE_GET => {
+            reader.read_u8()?;
+            Operator::TableGet(reader.read_var_u32()?)
+        }
+        TABLE_SET => {
+            reader.read_u8()?;
+            Operator::TableSet(reader.read_var_u32()?)
+        }
+        REF_NULL => {
+            reader.read_u8()?;
+            Operator::RefNull(reader.read_ref_type()?)
+        }
+        REF_IS_NULL => {
+            reader.read_u8()?;
+            Operator::RefIsNull
+        }
+        REF_FUNC => {
+            reader.read_u8()?;
+            Operator::RefFunc(reader.read_var_u32()?)
+        }
+        BULK_PREFIX => read_bulk_operator(reader)?,
+        _ => Operator::Instr(instruction::read_instruction(reader)?),
+    };
+    Ok(op)
+}
+
+fn read_bulk_operator(reader: &mut Reader) -> Result<Operator, LoadError> {
+    reader.read_u8()?;
+    let opcode = reader.read_var_u32()?;
+    let op = match opcode {
+        MEMORY_INIT => {
+            let seg = reader.read_var_u32()?;
+            Operator::MemoryInit(seg, reader.read_var_u32()?)
+        }
+        DATA_DROP => Operator::DataDrop(reader.read_var_u32()?),
+        MEMORY_COPY => {
+            let dst = reader.read_var_u32()?;
+            Operator::MemoryCopy(dst, reader.read_var_u32()?)
+        }
+        MEMORY_FILL => Operator::MemoryFill(reader.read_var_u32()?),
+        TABLE_INIT => {
+            let seg = reader.read_var_u32()?;
+            Operator::TableInit(seg, reader.read_var_u32()?)
+        }
+        ELEM_DROP => Operator::ElemDrop(reader.read_var_u32()?),
+        TABLE_COPY => {
+            let dst = reader.read_var_u32()?;
+            Operator::TableCopy(dst, reader.read_var_u32()?)
+        }
+        TABLE_GROW => Operator::TableGrow(reader.read_var_u32()?),
+        TABLE_SIZE => Operator::TableSize(reader.read_var_u32()?),
+        TABLE_FILL => Operator::TableFill(reader.read_var_u32()?),
+        _ => return Err(malformed(&format!("unknown bulk opcode 0x{:x}", opcode))),
+    };
+    Ok(op)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm::test_util::{module, section};
+
+    #[test]
+    fn leb128_must_fit_in_32_bits() {
+        let max = [0xff, 0xff, 0xff, 0xff, 0x0f];
+        assert_eq!(Reader::new(&max).read_var_u32().unwrap(), std::u32::MAX);
+        // The unused bits of the last byte must be 0.
+        let overflow = [0xff, 0xff, 0xff, 0xff, 0x1f];
+        assert!(Reader::new(&overflow).read_var_u32().is_err());
+        let too_long = [0x80, 0x80, 0x80, 0x80, 0x80, 0x00];
+        assert!(Reader::new(&too_long).read_var_u32().is_err());
+        let block_type = [0x80, 0x80, 0x80, 0x80, 0x80, 0x00];
+        assert!(Reader::new(&block_type).read_block_type().is_err());
+    }
+
+    #[test]
+    fn truncated_leb128_is_rejected() {
+        assert!(Reader::new(&[0x80, 0x80]).read_var_u32().is_err());
+        assert!(Reader::new(&[]).read_var_u32().is_err());
+        assert!(Reader::new(&[0x80]).read_block_type().is_err());
+        // The count of the types is truncated.
+        let truncated = module(&[section(TYPE_SECTION_ID, &[0x80])]);
+        assert!(crate::wasm::Module::parse(&truncated).is_err());
+    }
+
+    #[test]
+    fn signed_leb128_is_sign_extended() {
+        assert_eq!(Reader::new(&[0x7f]).read_var_i32().unwrap(), -1);
+        let min = [0x80, 0x80, 0x80, 0x80, 0x78];
+        assert_eq!(Reader::new(&min).read_var_i32().unwrap(), std::i32::MIN);
+        // The unused bits of the last byte must be the sign bit.
+        assert!(Reader::new(&[0x80, 0x80, 0x80, 0x80, 0x70]).read_var_i32().is_err());
+        let min = [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7f];
+        assert_eq!(Reader::new(&min).read_var_i64().unwrap(), std::i64::MIN);
+        let too_long = [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00];
+        assert!(Reader::new(&too_long).read_var_i64().is_err());
+    }
+
+    #[test]
+    fn sections_must_be_in_order() {
+        let types = section(TYPE_SECTION_ID, &[0x00]);
+        let funcs = section(FUNCTION_SECTION_ID, &[0x00]);
+        let custom = section(CUSTOM_SECTION_ID, &[0x04, b'n', b'a', b'm', b'e', 0xff]);
+        let bytes = module(&[types.clone(), custom, funcs.clone()]);
+        assert!(split_sections(&bytes).unwrap().functions.is_some());
+        assert!(split_sections(&module(&[funcs, types.clone()])).is_err());
+        assert!(split_sections(&module(&[types.clone(), types])).is_err());
+        // The data count section comes before the code section.
+        let data_count = section(DATA_COUNT_SECTION_ID, &[0x00]);
+        let code = section(CODE_SECTION_ID, &[0x00]);
+        assert!(split_sections(&module(&[data_count.clone(), code.clone()])).is_ok());
+        assert!(split_sections(&module(&[code, data_count])).is_err());
+        // The name of a custom section must be UTF-8.
+        let custom = section(CUSTOM_SECTION_ID, &[0x01, 0xff]);
+        assert!(split_sections(&module(&[custom])).is_err());
+    }
+
+    #[test]
+    fn atomic_instructions_have_a_memory_argument() {
+        let code = [
+            // No locals.
+            &[0x00][..],
+            // i32.atomic.rmw.add align=2 offset=8, atomic.fence
+            &[ATOMIC_PREFIX, 0x1e, 0x02, 0x08, ATOMIC_PREFIX, ATOMIC_FENCE, 0x00],
+            &[END],
+        ]
+        .concat();
+        let arg = crate::wasm::MemArg {
+            align: 2,
+            offset: 8,
+            memory: 0,
+        };
+        assert_eq!(
+            parse_func_body(&code, 0).unwrap().code,
+            vec![
+                Operator::Instr(Instruction::I32AtomicRmwAdd(arg)),
+                Operator::AtomicFence,
+                Operator::Instr(Instruction::End),
+            ]
+        );
+    }
+
+    #[test]
+    fn memory_argument_may_have_a_memory_index() {
+        let code = [
+            // No locals.
+            &[0x00][..],
+            // i32.store align=2 offset=4 memory=1, memory.size 1
+            &[0x36, 0x42, 0x01, 0x04, 0x3f, 0x01],
+            &[END],
+        ]
+        .concat();
+        let arg = crate::wasm::MemArg {
+            align: 2,
+            offset: 4,
+            memory: 1,
+        };
+        assert_eq!(
+            parse_func_body(&code, 0).unwrap().code,
+            vec![
+                Operator::Instr(Instruction::I32Store(arg)),
+                Operator::Instr(Instruction::CurrentMemory(1)),
+                Operator::Instr(Instruction::End),
+            ]
+        );
+    }
+
+    #[test]
+    fn section_size_must_match_its_contents() {
+        // The size of the section is larger than the rest of the module.
+        let mut bytes = module(&[section(TYPE_SECTION_ID, &[0x01, FUNC_TYPE, 0x00, 0x00])]);
+        bytes[9] += 1;
+        assert!(split_sections(&bytes).is_err());
+        // The section has bytes after its entries.
+        let trailing = [0x01, FUNC_TYPE, 0x00, 0x00, 0x00];
+        assert!(parse_type_section(&trailing).is_err());
+        assert!(parse_tag_section(&[0x01, 0x00, 0x00, 0x00]).is_err());
+        // The entries need more bytes than the section has.
+        assert!(parse_type_section(&[0x02, FUNC_TYPE, 0x00, 0x00]).is_err());
+        // The size of a function body is smaller than its code.
+        let body = [0x01, 0x02, 0x00, 0x01, 0x0b];
+        assert!(parse_code_section(&body, 0).is_err());
+    }
+
+    #[test]
+    fn exception_handling_operators_are_decoded() {
+        let code = [
+            // No locals.
+            &[0x00][..],
+            // try (throw 0) catch 0 (rethrow 0) catch_all end
+            &[TRY, EMPTY_BLOCK_TYPE, THROW, 0x00],
+            &[CATCH, 0x00, RETHROW, 0x00],
+            &[CATCH_ALL, END],
+            // try (result i32) delegate 0
+            &[TRY, 0x7f, DELEGATE, 0x00],
+            // The end of the body.
+            &[END],
+        ]
+        .concat();
+        let body = parse_func_body(&code, 0).unwrap();
+        assert_eq!(
+            body.code,
+            vec![
+                Operator::Try(BlockType::NoResult),
+                Operator::Throw(0),
+                Operator::Catch(0),
+                Operator::Rethrow(0),
+                Operator::CatchAll,
+                Operator::Instr(Instruction::End),
+                Operator::Try(BlockType::Value(ValueType::I32)),
+                Operator::Delegate(0),
+                Operator::Instr(Instruction::End),
+            ]
+        );
+        assert_eq!(
+            parse_tag_section(&[0x02, 0x00, 0x01, 0x00, 0x03]).unwrap(),
+            vec![1, 3]
+        );
+        assert!(parse_tag_section(&[0x01, 0x01, 0x00]).is_err());
+    }
+}
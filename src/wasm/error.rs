@@ -0,0 +1,22 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum LoadError {
+    // The module uses a feature which isn't supported by the runtime yet.
+    Unsupported(String),
+    // The binary can't be decoded as a WebAssembly module, or its sections are inconsistent with
+    // each other.
+    Malformed(String),
+    // The module can't be instantiated, e.g. a segment is out of bounds.
+    Instantiate(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Unsupported(message) => write!(f, "unsupported feature: {}", message),
+            LoadError::Malformed(message) => write!(f, "malformed module: {}", message),
+            LoadError::Instantiate(message) => write!(f, "failed to instantiate module: {}", message),
+        }
+    }
+}
@@ -0,0 +1,633 @@
+use super::binary::Reader;
+use super::LoadError;
+
+// The instructions of the MVP and of the threads proposal, which the other instructions are
+// decoded around by `binary::read_operator`. The names of the instructions are the ones of the
+// MVP drafts, which the code generator uses too.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Instruction {
+    Unreachable,
+    Nop,
+    Else,
+    End,
+    Br(u32),
+    BrIf(u32),
+    BrTable(Box<BrTableData>),
+    Return,
+    Call(u32),
+    Drop,
+    Select,
+
+    GetLocal(u32),
+    SetLocal(u32),
+    TeeLocal(u32),
+    GetGlobal(u32),
+    SetGlobal(u32),
+
+    I32Load(MemArg),
+    I64Load(MemArg),
+    F32Load(MemArg),
+    F64Load(MemArg),
+    I32Load8S(MemArg),
+    I32Load8U(MemArg),
+    I32Load16S(MemArg),
+    I32Load16U(MemArg),
+    I64Load8S(MemArg),
+    I64Load8U(MemArg),
+    I64Load16S(MemArg),
+    I64Load16U(MemArg),
+    I64Load32S(MemArg),
+    I64Load32U(MemArg),
+    I32Store(MemArg),
+    I64Store(MemArg),
+    F32Store(MemArg),
+    F64Store(MemArg),
+    I32Store8(MemArg),
+    I32Store16(MemArg),
+    I64Store8(MemArg),
+    I64Store16(MemArg),
+    I64Store32(MemArg),
+    // The memory index.
+    CurrentMemory(u32),
+    GrowMemory(u32),
+
+    I32Const(i32),
+    I64Const(i64),
+    // The bits of the float, so that the NaN payloads are kept.
+    F32Const(u32),
+    F64Const(u64),
+    // Decoded by `simd::read_simd_operator`, but a constant expression like the other constants.
+    V128Const(Box<[u8; 16]>),
+
+    I32Eqz,
+    I32Eq,
+    I32Ne,
+    I32LtS,
+    I32LtU,
+    I32GtS,
+    I32GtU,
+    I32LeS,
+    I32LeU,
+    I32GeS,
+    I32GeU,
+
+    I64Eqz,
+    I64Eq,
+    I64Ne,
+    I64LtS,
+    I64LtU,
+    I64GtS,
+    I64GtU,
+    I64LeS,
+    I64LeU,
+    I64GeS,
+    I64GeU,
+
+    F32Eq,
+    F32Ne,
+    F32Lt,
+    F32Gt,
+    F32Le,
+    F32Ge,
+
+    F64Eq,
+    F64Ne,
+    F64Lt,
+    F64Gt,
+    F64Le,
+    F64Ge,
+
+    I32Clz,
+    I32Ctz,
+    I32Popcnt,
+    I32Add,
+    I32Sub,
+    I32Mul,
+    I32DivS,
+    I32DivU,
+    I32RemS,
+    I32RemU,
+    I32And,
+    I32Or,
+    I32Xor,
+    I32Shl,
+    I32ShrS,
+    I32ShrU,
+    I32Rotl,
+    I32Rotr,
+
+    I64Clz,
+    I64Ctz,
+    I64Popcnt,
+    I64Add,
+    I64Sub,
+    I64Mul,
+    I64DivS,
+    I64DivU,
+    I64RemS,
+    I64RemU,
+    I64And,
+    I64Or,
+    I64Xor,
+    I64Shl,
+    I64ShrS,
+    I64ShrU,
+    I64Rotl,
+    I64Rotr,
+
+    F32Abs,
+    F32Neg,
+    F32Ceil,
+    F32Floor,
+    F32Trunc,
+    F32Nearest,
+    F32Sqrt,
+    F32Add,
+    F32Sub,
+    F32Mul,
+    F32Div,
+    F32Min,
+    F32Max,
+    F32Copysign,
+
+    F64Abs,
+    F64Neg,
+    F64Ceil,
+    F64Floor,
+    F64Trunc,
+    F64Nearest,
+    F64Sqrt,
+    F64Add,
+    F64Sub,
+    F64Mul,
+    F64Div,
+    F64Min,
+    F64Max,
+    F64Copysign,
+
+    I32WrapI64,
+    I32TruncSF32,
+    I32TruncUF32,
+    I32TruncSF64,
+    I32TruncUF64,
+    I64ExtendSI32,
+    I64ExtendUI32,
+    I64TruncSF32,
+    I64TruncUF32,
+    I64TruncSF64,
+    I64TruncUF64,
+    F32ConvertSI32,
+    F32ConvertUI32,
+    F32ConvertSI64,
+    F32ConvertUI64,
+    F32DemoteF64,
+    F64ConvertSI32,
+    F64ConvertUI32,
+    F64ConvertSI64,
+    F64ConvertUI64,
+    F64PromoteF32,
+    I32ReinterpretF32,
+    I64ReinterpretF64,
+    F32ReinterpretI32,
+    F64ReinterpretI64,
+
+    I32Extend8S,
+    I32Extend16S,
+    I64Extend8S,
+    I64Extend16S,
+    I64Extend32S,
+
+    AtomicWake(MemArg),
+    I32AtomicWait(MemArg),
+    I64AtomicWait(MemArg),
+
+    I32AtomicLoad(MemArg),
+    I64AtomicLoad(MemArg),
+    I32AtomicLoad8u(MemArg),
+    I32AtomicLoad16u(MemArg),
+    I64AtomicLoad8u(MemArg),
+    I64AtomicLoad16u(MemArg),
+    I64AtomicLoad32u(MemArg),
+    I32AtomicStore(MemArg),
+    I64AtomicStore(MemArg),
+    I32AtomicStore8u(MemArg),
+    I32AtomicStore16u(MemArg),
+    I64AtomicStore8u(MemArg),
+    I64AtomicStore16u(MemArg),
+    I64AtomicStore32u(MemArg),
+
+    I32AtomicRmwAdd(MemArg),
+    I64AtomicRmwAdd(MemArg),
+    I32AtomicRmwAdd8u(MemArg),
+    I32AtomicRmwAdd16u(MemArg),
+    I64AtomicRmwAdd8u(MemArg),
+    I64AtomicRmwAdd16u(MemArg),
+    I64AtomicRmwAdd32u(MemArg),
+
+    I32AtomicRmwSub(MemArg),
+    I64AtomicRmwSub(MemArg),
+    I32AtomicRmwSub8u(MemArg),
+    I32AtomicRmwSub16u(MemArg),
+    I64AtomicRmwSub8u(MemArg),
+    I64AtomicRmwSub16u(MemArg),
+    I64AtomicRmwSub32u(MemArg),
+
+    I32AtomicRmwAnd(MemArg),
+    I64AtomicRmwAnd(MemArg),
+    I32AtomicRmwAnd8u(MemArg),
+    I32AtomicRmwAnd16u(MemArg),
+    I64AtomicRmwAnd8u(MemArg),
+    I64AtomicRmwAnd16u(MemArg),
+    I64AtomicRmwAnd32u(MemArg),
+
+    I32AtomicRmwOr(MemArg),
+    I64AtomicRmwOr(MemArg),
+    I32AtomicRmwOr8u(MemArg),
+    I32AtomicRmwOr16u(MemArg),
+    I64AtomicRmwOr8u(MemArg),
+    I64AtomicRmwOr16u(MemArg),
+    I64AtomicRmwOr32u(MemArg),
+
+    I32AtomicRmwXor(MemArg),
+    I64AtomicRmwXor(MemArg),
+    I32AtomicRmwXor8u(MemArg),
+    I32AtomicRmwXor16u(MemArg),
+    I64AtomicRmwXor8u(MemArg),
+    I64AtomicRmwXor16u(MemArg),
+    I64AtomicRmwXor32u(MemArg),
+
+    I32AtomicRmwXchg(MemArg),
+    I64AtomicRmwXchg(MemArg),
+    I32AtomicRmwXchg8u(MemArg),
+    I32AtomicRmwXchg16u(MemArg),
+    I64AtomicRmwXchg8u(MemArg),
+    I64AtomicRmwXchg16u(MemArg),
+    I64AtomicRmwXchg32u(MemArg),
+
+    I32AtomicRmwCmpxchg(MemArg),
+    I64AtomicRmwCmpxchg(MemArg),
+    I32AtomicRmwCmpxchg8u(MemArg),
+    I32AtomicRmwCmpxchg16u(MemArg),
+    I64AtomicRmwCmpxchg8u(MemArg),
+    I64AtomicRmwCmpxchg16u(MemArg),
+    I64AtomicRmwCmpxchg32u(MemArg),
+}
+
+// The labels of `br_table`, and the label taken when the operand is out of their range.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrTableData {
+    pub table: Box<[u32]>,
+    pub default: u32,
+}
+
+// The alignment exponent, the offset and the memory index of a memory access.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MemArg {
+    pub align: u32,
+    pub offset: u32,
+    pub memory: u32,
+}
+
+// The bit of the alignment which tells that the memory index follows it, see
+// https://github.com/WebAssembly/multi-memory. Otherwise the memory is 0.
+const MEMORY_INDEX_FLAG: u32 = 1 << 6;
+
+pub(super) fn read_mem_arg(reader: &mut Reader) -> Result<MemArg, LoadError> {
+    let mut align = reader.read_var_u32()?;
+    let mut memory = 0;
+    if align & MEMORY_INDEX_FLAG != 0 && align < MEMORY_INDEX_FLAG << 1 {
+        align &= !MEMORY_INDEX_FLAG;
+        memory = reader.read_var_u32()?;
+    }
+    let offset = reader.read_var_u32()?;
+    Ok(MemArg {
+        align,
+        offset,
+        memory,
+    })
+}
+
+fn read_br_table(reader: &mut Reader) -> Result<Box<BrTableData>, LoadError> {
+    let count = reader.read_var_u32()?;
+    let mut table = Vec::new();
+    for _ in 0..count {
+        table.push(reader.read_var_u32()?);
+    }
+    Ok(Box::new(BrTableData {
+        table: table.into_boxed_slice(),
+        default: reader.read_var_u32()?,
+    }))
+}
+
+fn read_f32_bits(reader: &mut Reader) -> Result<u32, LoadError> {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(reader.read_bytes(4)?);
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f64_bits(reader: &mut Reader) -> Result<u64, LoadError> {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(reader.read_bytes(8)?);
+    Ok(u64::from_le_bytes(bytes))
+}
+
+// Decodes the instruction after the `0xfe` prefix. `atomic.fence` has no memory argument and is
+// decoded by `binary::read_operator`.
+fn read_atomic_instruction(reader: &mut Reader) -> Result<Instruction, LoadError> {
+    use self::Instruction::*;
+
+    let opcode = reader.read_var_u32()?;
+    let arg = read_mem_arg(reader)?;
+    let instr = match opcode {
+        0x00 => AtomicWake(arg),
+        0x01 => I32AtomicWait(arg),
+        0x02 => I64AtomicWait(arg),
+
+        0x10 => I32AtomicLoad(arg),
+        0x11 => I64AtomicLoad(arg),
+        0x12 => I32AtomicLoad8u(arg),
+        0x13 => I32AtomicLoad16u(arg),
+        0x14 => I64AtomicLoad8u(arg),
+        0x15 => I64AtomicLoad16u(arg),
+        0x16 => I64AtomicLoad32u(arg),
+        0x17 => I32AtomicStore(arg),
+        0x18 => I64AtomicStore(arg),
+        0x19 => I32AtomicStore8u(arg),
+        0x1a => I32AtomicStore16u(arg),
+        0x1b => I64AtomicStore8u(arg),
+        0x1c => I64AtomicStore16u(arg),
+        0x1d => I64AtomicStore32u(arg),
+
+        0x1e => I32AtomicRmwAdd(arg),
+        0x1f => I64AtomicRmwAdd(arg),
+        0x20 => I32AtomicRmwAdd8u(arg),
+        0x21 => I32AtomicRmwAdd16u(arg),
+        0x22 => I64AtomicRmwAdd8u(arg),
+        0x23 => I64AtomicRmwAdd16u(arg),
+        0x24 => I64AtomicRmwAdd32u(arg),
+
+        0x25 => I32AtomicRmwSub(arg),
+        0x26 => I64AtomicRmwSub(arg),
+        0x27 => I32AtomicRmwSub8u(arg),
+        0x28 => I32AtomicRmwSub16u(arg),
+        0x29 => I64AtomicRmwSub8u(arg),
+        0x2a => I64AtomicRmwSub16u(arg),
+        0x2b => I64AtomicRmwSub32u(arg),
+
+        0x2c => I32AtomicRmwAnd(arg),
+        0x2d => I64AtomicRmwAnd(arg),
+        0x2e => I32AtomicRmwAnd8u(arg),
+        0x2f => I32AtomicRmwAnd16u(arg),
+        0x30 => I64AtomicRmwAnd8u(arg),
+        0x31 => I64AtomicRmwAnd16u(arg),
+        0x32 => I64AtomicRmwAnd32u(arg),
+
+        0x33 => I32AtomicRmwOr(arg),
+        0x34 => I64AtomicRmwOr(arg),
+        0x35 => I32AtomicRmwOr8u(arg),
+        0x36 => I32AtomicRmwOr16u(arg),
+        0x37 => I64AtomicRmwOr8u(arg),
+        0x38 => I64AtomicRmwOr16u(arg),
+        0x39 => I64AtomicRmwOr32u(arg),
+
+        0x3a => I32AtomicRmwXor(arg),
+        0x3b => I64AtomicRmwXor(arg),
+        0x3c => I32AtomicRmwXor8u(arg),
+        0x3d => I32AtomicRmwXor16u(arg),
+        0x3e => I64AtomicRmwXor8u(arg),
+        0x3f => I64AtomicRmwXor16u(arg),
+        0x40 => I64AtomicRmwXor32u(arg),
+
+        0x41 => I32AtomicRmwXchg(arg),
+        0x42 => I64AtomicRmwXchg(arg),
+        0x43 => I32AtomicRmwXchg8u(arg),
+        0x44 => I32AtomicRmwXchg16u(arg),
+        0x45 => I64AtomicRmwXchg8u(arg),
+        0x46 => I64AtomicRmwXchg16u(arg),
+        0x47 => I64AtomicRmwXchg32u(arg),
+
+        0x48 => I32AtomicRmwCmpxchg(arg),
+        0x49 => I64AtomicRmwCmpxchg(arg),
+        0x4a => I32AtomicRmwCmpxchg8u(arg),
+        0x4b => I32AtomicRmwCmpxchg16u(arg),
+        0x4c => I64AtomicRmwCmpxchg8u(arg),
+        0x4d => I64AtomicRmwCmpxchg16u(arg),
+        0x4e => I64AtomicRmwCmpxchg32u(arg),
+        _ => {
+            return Err(LoadError::Malformed(format!(
+                "unknown atomic opcode 0x{:x}",
+                opcode
+            )))
+        }
+    };
+    Ok(instr)
+}
+
+pub(super) fn read_instruction(reader: &mut Reader) -> Result<Instruction, LoadError> {
+    use self::Instruction::*;
+
+    macro_rules! mem {
+        ($op:ident) => {
+            $op(read_mem_arg(reader)?)
+        };
+    }
+
+    let opcode = reader.read_u8()?;
+    let instr = match opcode {
+        0x00 => Unreachable,
+        0x01 => Nop,
+        0x05 => Else,
+        0x0b => End,
+        0x0c => Br(reader.read_var_u32()?),
+        0x0d => BrIf(reader.read_var_u32()?),
+        0x0e => BrTable(read_br_table(reader)?),
+        0x0f => Return,
+        0x10 => Call(reader.read_var_u32()?),
+        0x1a => Drop,
+        0x1b => Select,
+
+        0x20 => GetLocal(reader.read_var_u32()?),
+        0x21 => SetLocal(reader.read_var_u32()?),
+        0x22 => TeeLocal(reader.read_var_u32()?),
+        0x23 => GetGlobal(reader.read_var_u32()?),
+        0x24 => SetGlobal(reader.read_var_u32()?),
+
+        0x28 => mem!(I32Load),
+        0x29 => mem!(I64Load),
+        0x2a => mem!(F32Load),
+        0x2b => mem!(F64Load),
+        0x2c => mem!(I32Load8S),
+        0x2d => mem!(I32Load8U),
+        0x2e => mem!(I32Load16S),
+        0x2f => mem!(I32Load16U),
+        0x30 => mem!(I64Load8S),
+        0x31 => mem!(I64Load8U),
+        0x32 => mem!(I64Load16S),
+        0x33 => mem!(I64Load16U),
+        0x34 => mem!(I64Load32S),
+        0x35 => mem!(I64Load32U),
+        0x36 => mem!(I32Store),
+        0x37 => mem!(I64Store),
+        0x38 => mem!(F32Store),
+        0x39 => mem!(F64Store),
+        0x3a => mem!(I32Store8),
+        0x3b => mem!(I32Store16),
+        0x3c => mem!(I64Store8),
+        0x3d => mem!(I64Store16),
+        0x3e => mem!(I64Store32),
+        0x3f => CurrentMemory(reader.read_var_u32()?),
+        0x40 => GrowMemory(reader.read_var_u32()?),
+
+        0x41 => I32Const(reader.read_var_i32()?),
+        0x42 => I64Const(reader.read_var_i64()?),
+        0x43 => F32Const(read_f32_bits(reader)?),
+        0x44 => F64Const(read_f64_bits(reader)?),
+
+        0x45 => I32Eqz,
+        0x46 => I32Eq,
+        0x47 => I32Ne,
+        0x48 => I32LtS,
+        0x49 => I32LtU,
+        0x4a => I32GtS,
+        0x4b => I32GtU,
+        0x4c => I32LeS,
+        0x4d => I32LeU,
+        0x4e => I32GeS,
+        0x4f => I32GeU,
+
+        0x50 => I64Eqz,
+        0x51 => I64Eq,
+        0x52 => I64Ne,
+        0x53 => I64LtS,
+        0x54 => I64LtU,
+        0x55 => I64GtS,
+        0x56 => I64GtU,
+        0x57 => I64LeS,
+        0x58 => I64LeU,
+        0x59 => I64GeS,
+        0x5a => I64GeU,
+
+        0x5b => F32Eq,
+        0x5c => F32Ne,
+        0x5d => F32Lt,
+        0x5e => F32Gt,
+        0x5f => F32Le,
+        0x60 => F32Ge,
+
+        0x61 => F64Eq,
+        0x62 => F64Ne,
+        0x63 => F64Lt,
+        0x64 => F64Gt,
+        0x65 => F64Le,
+        0x66 => F64Ge,
+
+        0x67 => I32Clz,
+        0x68 => I32Ctz,
+        0x69 => I32Popcnt,
+        0x6a => I32Add,
+        0x6b => I32Sub,
+        0x6c => I32Mul,
+        0x6d => I32DivS,
+        0x6e => I32DivU,
+        0x6f => I32RemS,
+        0x70 => I32RemU,
+        0x71 => I32And,
+        0x72 => I32Or,
+        0x73 => I32Xor,
+        0x74 => I32Shl,
+        0x75 => I32ShrS,
+        0x76 => I32ShrU,
+        0x77 => I32Rotl,
+        0x78 => I32Rotr,
+
+        0x79 => I64Clz,
+        0x7a => I64Ctz,
+        0x7b => I64Popcnt,
+        0x7c => I64Add,
+        0x7d => I64Sub,
+        0x7e => I64Mul,
+        0x7f => I64DivS,
+        0x80 => I64DivU,
+        0x81 => I64RemS,
+        0x82 => I64RemU,
+        0x83 => I64And,
+        0x84 => I64Or,
+        0x85 => I64Xor,
+        0x86 => I64Shl,
+        0x87 => I64ShrS,
+        0x88 => I64ShrU,
+        0x89 => I64Rotl,
+        0x8a => I64Rotr,
+
+        0x8b => F32Abs,
+        0x8c => F32Neg,
+        0x8d => F32Ceil,
+        0x8e => F32Floor,
+        0x8f => F32Trunc,
+        0x90 => F32Nearest,
+        0x91 => F32Sqrt,
+        0x92 => F32Add,
+        0x93 => F32Sub,
+        0x94 => F32Mul,
+        0x95 => F32Div,
+        0x96 => F32Min,
+        0x97 => F32Max,
+        0x98 => F32Copysign,
+
+        0x99 => F64Abs,
+        0x9a => F64Neg,
+        0x9b => F64Ceil,
+        0x9c => F64Floor,
+        0x9d => F64Trunc,
+        0x9e => F64Nearest,
+        0x9f => F64Sqrt,
+        0xa0 => F64Add,
+        0xa1 => F64Sub,
+        0xa2 => F64Mul,
+        0xa3 => F64Div,
+        0xa4 => F64Min,
+        0xa5 => F64Max,
+        0xa6 => F64Copysign,
+
+        0xa7 => I32WrapI64,
+        0xa8 => I32TruncSF32,
+        0xa9 => I32TruncUF32,
+        0xaa => I32TruncSF64,
+        0xab => I32TruncUF64,
+        0xac => I64ExtendSI32,
+        0xad => I64ExtendUI32,
+        0xae => I64TruncSF32,
+        0xaf => I64TruncUF32,
+        0xb0 => I64TruncSF64,
+        0xb1 => I64TruncUF64,
+        0xb2 => F32ConvertSI32,
+        0xb3 => F32ConvertUI32,
+        0xb4 => F32ConvertSI64,
+        0xb5 => F32ConvertUI64,
+        0xb6 => F32DemoteF64,
+        0xb7 => F64ConvertSI32,
+        0xb8 => F64ConvertUI32,
+        0xb9 => F64ConvertSI64,
+        0xba => F64ConvertUI64,
+        0xbb => F64PromoteF32,
+        0xbc => I32ReinterpretF32,
+        0xbd => I64ReinterpretF64,
+        0xbe => F32ReinterpretI32,
+        0xbf => F64ReinterpretI64,
+
+        0xc0 => I32Extend8S,
+        0xc1 => I32Extend16S,
+        0xc2 => I64Extend8S,
+        0xc3 => I64Extend16S,
+        0xc4 => I64Extend32S,
+
+        0xfe => read_atomic_instruction(reader)?,
+        _ => {
+            return Err(LoadError::Malformed(format!(
+                "unknown opcode 0x{:x}",
+                opcode
+            )))
+        }
+    };
+    Ok(instr)
+}
@@ -1,19 +1,29 @@
+mod binary;
 pub mod call_conv;
 mod defines;
+mod error;
 mod imports;
+mod instruction;
+mod simd;
+#[cfg(test)]
+pub(crate) mod test_util;
 pub mod types;
+mod validate;
 
+pub use self::binary::{DataMode, ElementMode, Operator};
+pub use self::error::LoadError;
+pub use self::instruction::{BrTableData, Instruction, MemArg};
+pub use self::simd::SimdOperator;
 pub use self::types::*;
+pub use self::validate::{validate, ValidationError};
 use self::types::{GlobalType, Type};
-pub use parity_wasm::elements::BlockType;
-pub use parity_wasm::elements::BrTableData;
-pub use parity_wasm::elements::InitExpr;
-pub use parity_wasm::elements::Instruction;
-pub use parity_wasm::elements::Instructions;
+use indexmap::IndexMap;
 use std::ops::Index;
 
 pub const PAGE_SHIFT: u8 = 16;
 pub const PAGE_SIZE: u64 = 1 << PAGE_SHIFT;
+// The maximum number of pages of a 32-bit linear memory.
+pub const MAX_PAGES: u32 = 65536;
 
 pub trait Entry<T: Type> {
     fn get_type(&self) -> &T;
@@ -21,18 +31,50 @@ pub trait Entry<T: Type> {
 
 pub trait Def<T: Type>: Entry<T> {}
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Value {
     I32(i32),
     U32(u32),
     U64(u64),
     I64(i64),
+    F32(f32),
+    F64(f64),
+    V128([u8; 16]),
+    // A reference is 0 if it's null. An `anyref` is opaque to the module, and the host may pass
+    // any other value as one, but a `funcref` must refer to a function of the instance.
+    AnyRef(u64),
+    AnyFunc(u64),
+}
+
+impl Value {
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Value::I32(_) | Value::U32(_) => ValueType::I32,
+            Value::I64(_) | Value::U64(_) => ValueType::I64,
+            Value::F32(_) => ValueType::F32,
+            Value::F64(_) => ValueType::F64,
+            Value::V128(_) => ValueType::V128,
+            Value::AnyRef(_) => ValueType::AnyRef,
+            Value::AnyFunc(_) => ValueType::AnyFunc,
+        }
+    }
+
+    // The null reference of a reference type.
+    pub fn null_ref(ty: ValueType) -> Option<Value> {
+        match ty {
+            ValueType::AnyRef => Some(Value::AnyRef(0)),
+            ValueType::AnyFunc => Some(Value::AnyFunc(0)),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Function {
     ty: FunctionType,
     locals: Vec<ValueType>,
-    code: Instructions,
+    code: Vec<Operator>,
+    offsets: Vec<usize>,
 }
 
 impl Entry<FunctionType> for Function {
@@ -43,29 +85,32 @@ impl Entry<FunctionType> for Function {
 impl Def<FunctionType> for Function {}
 
 impl Function {
-    pub fn new(
-        func_types: &Vec<FunctionType>,
-        func_def: parity_wasm::elements::Func,
-        func_body: parity_wasm::elements::FuncBody,
-    ) -> Self {
-        Self {
-            ty: func_types[func_def.type_ref() as usize].clone(),
-            locals: func_body
-                .locals()
-                .iter()
-                .map(|t| ValueType::from(t.value_type()))
-                .collect(),
-            code: func_body.code().clone(),
-        }
+    fn new(
+        func_types: &[FunctionType],
+        type_idx: u32,
+        func_body: binary::FuncBody,
+    ) -> Result<Self, LoadError> {
+        Ok(Self {
+            ty: get_func_type(func_types, type_idx)?.clone(),
+            locals: func_body.locals,
+            code: func_body.code,
+            offsets: func_body.offsets,
+        })
     }
 
-    pub fn instructions(&self) -> &[Instruction] {
-        self.code.elements()
+    pub fn operators(&self) -> &[Operator] {
+        &self.code
     }
 
     pub fn locals(&self) -> &[ValueType] {
         &self.locals
     }
+
+    // The offset of an operator in the module. The index of the end of the operators is the
+    // offset of the end of the body.
+    pub fn operator_offset(&self, index: usize) -> usize {
+        self.offsets[index]
+    }
 }
 
 #[derive(Debug)]
@@ -170,34 +215,59 @@ impl Entry<MemoryType> for Memory {
 
 impl Def<MemoryType> for Memory {}
 
-pub struct Table;
+// An exception tag. The params of its type are the values carried by its exceptions, and the
+// type has no results.
+pub struct Tag {
+    ty: FunctionType,
+}
+
+impl Entry<FunctionType> for Tag {
+    fn get_type(&self) -> &FunctionType {
+        &self.ty
+    }
+}
+
+pub struct Table {
+    ty: TableType,
+}
+
+impl Entry<TableType> for Table {
+    fn get_type(&self) -> &TableType {
+        &self.ty
+    }
+}
+
+impl Def<TableType> for Table {}
 
 #[derive(Debug)]
-pub struct Global(GlobalType);
+pub struct Global {
+    ty: GlobalType,
+    init_instr: Operator,
+}
 
-impl From<parity_wasm::elements::GlobalEntry> for Global {
-    fn from(v: parity_wasm::elements::GlobalEntry) -> Global {
-        Global(GlobalType::from(*v.global_type()))
+impl Global {
+    #[inline]
+    pub fn init_instr(&self) -> &Operator {
+        &self.init_instr
     }
 }
 
 impl Entry<GlobalType> for Global {
     fn get_type(&self) -> &GlobalType {
-        &self.0
+        &self.ty
     }
 }
 impl Def<GlobalType> for Global {}
 
 pub struct Data {
-    idx: u32,
-    offset_instr: Instruction,
+    mode: DataMode,
     value: Vec<u8>,
 }
 
 impl Data {
     #[inline]
-    pub fn offset_instr(&self) -> &Instruction {
-        &self.offset_instr
+    pub fn mode(&self) -> &DataMode {
+        &self.mode
     }
 
     #[inline]
@@ -206,150 +276,252 @@ impl Data {
     }
 }
 
-impl From<parity_wasm::elements::DataSegment> for Data {
-    fn from(data: parity_wasm::elements::DataSegment) -> Self {
+impl From<binary::DataEntry> for Data {
+    fn from(data: binary::DataEntry) -> Self {
         Data {
-            idx: data.index(),
-            offset_instr: data
-                .offset()
-                .clone()
-                .unwrap()
-                .code()
-                .last()
-                .unwrap()
-                .clone(), // TODO: deal with None!
-            value: data.value().to_vec(),
+            mode: data.mode,
+            value: data.value,
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Export {
+    Function(u32),
+    Table(u32),
+    Memory(u32),
+    Global(u32),
+}
+
+pub struct Element {
+    mode: ElementMode,
+    elem_type: ValueType,
+    members: Vec<Operator>,
+}
+
+impl Element {
+    #[inline]
+    pub fn mode(&self) -> &ElementMode {
+        &self.mode
+    }
+
+    #[inline]
+    pub fn elem_type(&self) -> ValueType {
+        self.elem_type
+    }
+
+    // The constant expressions of the members, which are `ref.null` or `ref.func`.
+    #[inline]
+    pub fn members(&self) -> &[Operator] {
+        &self.members
+    }
+}
+
+impl From<binary::ElementEntry> for Element {
+    fn from(elem: binary::ElementEntry) -> Self {
+        Element {
+            mode: elem.mode,
+            elem_type: elem.elem_type,
+            members: elem.members,
+        }
+    }
+}
+
+fn get_func_type(func_types: &[FunctionType], index: u32) -> Result<&FunctionType, LoadError> {
+    func_types
+        .get(index as usize)
+        .ok_or_else(|| LoadError::Malformed(format!("unknown type {}", index)))
+}
+
+// The decoded sections of a module, except the type section, see `split_sections`.
+struct ParsedSections {
+    imports: Vec<binary::ImportEntry>,
+    funcs: Vec<u32>,
+    tables: Vec<TableType>,
+    memorys: Vec<MemoryType>,
+    globals: Vec<(GlobalType, Operator)>,
+    exports: Vec<(String, Export)>,
+    start: Option<u32>,
+    elements: Vec<binary::ElementEntry>,
+    data_count: Option<u32>,
+    func_bodys: Vec<binary::FuncBody>,
+    datas: Vec<binary::DataEntry>,
+    tags: Vec<u32>,
+}
+
 pub struct Module {
     types: Vec<FunctionType>,
     memorys: CombinedDeclear<Memory, MemoryType>,
+    tables: CombinedDeclear<Table, TableType>,
     functions: CombinedDeclear<Function, FunctionType>,
     globals: CombinedDeclear<Global, GlobalType>,
+    tags: Vec<Tag>,
     datas: Vec<Data>,
+    // Whether the module has a data count section, which `memory.init` and `data.drop`
+    // require.
+    has_data_count: bool,
+    elements: Vec<Element>,
+    exports: IndexMap<String, Export>,
+    start: Option<u32>,
 }
 
-impl From<parity_wasm::elements::Module> for Module {
-    fn from(module: parity_wasm::elements::Module) -> Self {
-        let func_types = match module.type_section() {
-            None => Vec::new(),
-            Some(section) => section
-                .types()
-                .iter()
-                .map(|t| match t {
-                    parity_wasm::elements::Type::Function(ty) => FunctionType::from(ty.clone()),
-                })
-                .collect(),
-        };
-
-        let memorys = match module.memory_section() {
+impl Module {
+    pub fn parse(bytes: &[u8]) -> Result<Module, LoadError> {
+        let sections = binary::split_sections(bytes)?;
+        let func_types = match sections.types {
             None => Vec::new(),
-            Some(section) => section
-                .entries()
-                .iter()
-                .map(|t| Memory {
-                    ty: MemoryType::from(*t),
-                })
-                .collect(),
+            Some(payload) => binary::parse_type_section(payload)?,
         };
-
-        let memory_imports = match module.import_section() {
+        let func_bodys = match sections.code {
             None => Vec::new(),
-            Some(section) => section
-                .entries()
-                .iter()
-                .filter_map(|t| {
-                    if let parity_wasm::elements::External::Memory(ty) = t.external() {
-                        Some(Import::new(MemoryType::from(*ty), t.module(), t.field()))
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
+            Some(payload) => binary::parse_code_section(payload, sections.code_offset)?,
         };
-
-        let global_imports = match module.import_section() {
+        let tags = match sections.tags {
             None => Vec::new(),
-            Some(section) => section
-                .entries()
-                .iter()
-                .filter_map(|t| {
-                    if let parity_wasm::elements::External::Global(global_ty) = t.external() {
-                        Some(Import::new(
-                            GlobalType::from(*global_ty),
-                            t.module(),
-                            t.field(),
-                        ))
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
+            Some(payload) => binary::parse_tag_section(payload)?,
         };
-
-        let global_defs = match module.global_section() {
-            None => vec![],
-            Some(section) => section
-                .entries()
-                .iter()
-                .map(|t| Global::from(t.clone()))
-                .collect(),
+        let parsed = ParsedSections {
+            imports: match sections.imports {
+                None => Vec::new(),
+                Some(payload) => binary::parse_import_section(payload)?,
+            },
+            funcs: match sections.functions {
+                None => Vec::new(),
+                Some(payload) => binary::parse_function_section(payload)?,
+            },
+            tables: match sections.tables {
+                None => Vec::new(),
+                Some(payload) => binary::parse_table_section(payload)?,
+            },
+            memorys: match sections.memorys {
+                None => Vec::new(),
+                Some(payload) => binary::parse_memory_section(payload)?,
+            },
+            globals: match sections.globals {
+                None => Vec::new(),
+                Some(payload) => binary::parse_global_section(payload)?,
+            },
+            exports: match sections.exports {
+                None => Vec::new(),
+                Some(payload) => binary::parse_export_section(payload)?,
+            },
+            start: match sections.start {
+                None => None,
+                Some(payload) => Some(binary::parse_start_section(payload)?),
+            },
+            elements: match sections.elements {
+                None => Vec::new(),
+                Some(payload) => binary::parse_element_section(payload)?,
+            },
+            data_count: match sections.data_count {
+                None => None,
+                Some(payload) => Some(binary::parse_data_count_section(payload)?),
+            },
+            func_bodys,
+            datas: match sections.datas {
+                None => Vec::new(),
+                Some(payload) => binary::parse_data_section(payload)?,
+            },
+            tags,
         };
+        Self::new(func_types, parsed)
+    }
 
-        let func_defs = match module.function_section() {
-            None => &[],
-            Some(section) => section.entries(),
-        };
+    fn new(func_types: Vec<FunctionType>, parsed: ParsedSections) -> Result<Self, LoadError> {
+        let ParsedSections {
+            imports,
+            funcs: func_defs,
+            tables,
+            memorys,
+            globals,
+            exports,
+            start,
+            elements,
+            data_count,
+            func_bodys,
+            datas,
+            tags: tag_types,
+        } = parsed;
+        let mut func_imports = Vec::new();
+        let mut table_imports = Vec::new();
+        let mut memory_imports = Vec::new();
+        let mut global_imports = Vec::new();
+        for import in imports {
+            let (module, field) = (import.module, import.field);
+            match import.desc {
+                binary::ImportDesc::Function(index) => func_imports.push(Import::new(
+                    get_func_type(&func_types, index)?.clone(),
+                    module,
+                    field,
+                )),
+                binary::ImportDesc::Table(ty) => table_imports.push(Import::new(ty, module, field)),
+                binary::ImportDesc::Memory(ty) => {
+                    memory_imports.push(Import::new(ty, module, field))
+                }
+                binary::ImportDesc::Global(ty) => {
+                    global_imports.push(Import::new(ty, module, field))
+                }
+            }
+        }
 
-        let func_imports = match module.import_section() {
-            None => vec![],
-            Some(section) => section
-                .entries()
-                .iter()
-                .filter_map(|t| {
-                    if let parity_wasm::elements::External::Function(index) = t.external() {
-                        Some(Import::new(
-                            func_types[*index as usize].clone(),
-                            t.module(),
-                            t.field(),
-                        ))
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
-        };
+        let memorys = memorys.into_iter().map(|ty| Memory { ty }).collect();
 
-        let func_bodys = match module.code_section() {
-            None => &[],
-            Some(section) => section.bodies(),
-        };
+        let tables = tables.into_iter().map(|ty| Table { ty }).collect();
+        let global_defs = globals
+            .into_iter()
+            .map(|(ty, init_instr)| Global { ty, init_instr })
+            .collect();
 
-        assert!(func_bodys.len() == func_defs.len());
+        if func_bodys.len() != func_defs.len() {
+            return Err(LoadError::Malformed(format!(
+                "the function section declares {} functions, but the code section has {} bodies",
+                func_defs.len(),
+                func_bodys.len()
+            )));
+        }
 
         let functions = func_defs
-            .iter()
-            .zip(func_bodys.iter())
-            .map(|(def, body)| Function::new(&func_types, *def, body.clone()))
-            .collect();
+            .into_iter()
+            .zip(func_bodys.into_iter())
+            .map(|(type_idx, body)| Function::new(&func_types, type_idx, body))
+            .collect::<Result<_, _>>()?;
+
+        let tags = tag_types
+            .into_iter()
+            .map(|idx| {
+                get_func_type(&func_types, idx).map(|ty| Tag { ty: ty.clone() })
+            })
+            .collect::<Result<_, _>>()?;
+
+        if let Some(count) = data_count {
+            if count as usize != datas.len() {
+                return Err(LoadError::Malformed(
+                    "data count and data section have inconsistent lengths".to_string(),
+                ));
+            }
+        }
+        let datas = datas.into_iter().map(Data::from).collect();
 
-        let datas = match module.data_section() {
-            None => Vec::new(),
-            Some(sections) => sections
-                .entries()
-                .iter()
-                .map(|data| Data::from(data.clone()))
-                .collect(),
-        };
+        let elements = elements.into_iter().map(Element::from).collect();
 
-        Self {
+        let mut export_map = IndexMap::new();
+        for (name, export) in exports {
+            if export_map.contains_key(&name) {
+                return Err(LoadError::Malformed(format!("duplicate export name {}", name)));
+            }
+            export_map.insert(name, export);
+        }
+
+        Ok(Self {
             types: func_types,
             memorys: CombinedDeclear {
                 defines: memorys,
                 imports: memory_imports,
             },
+            tables: CombinedDeclear {
+                defines: tables,
+                imports: table_imports,
+            },
             functions: CombinedDeclear {
                 defines: functions,
                 imports: func_imports,
@@ -358,8 +530,13 @@ impl From<parity_wasm::elements::Module> for Module {
                 defines: global_defs,
                 imports: global_imports,
             },
+            tags,
             datas,
-        }
+            has_data_count: data_count.is_some(),
+            elements,
+            exports: export_map,
+            start,
+        })
     }
 }
 
@@ -374,6 +551,15 @@ impl Module {
         self.types.len()
     }
 
+    // Returns the params and the results of a block as a function type.
+    pub fn get_block_type(&self, ty: BlockType) -> FunctionType {
+        match ty {
+            BlockType::NoResult => FunctionType::default(),
+            BlockType::Value(ty) => FunctionType::new(vec![], vec![ty]),
+            BlockType::TypeIndex(index) => self.get_func_type(index).clone(),
+        }
+    }
+
     #[inline]
     pub fn functions(&self) -> &CombinedDeclear<Function, FunctionType> {
         &self.functions
@@ -389,8 +575,8 @@ impl Module {
     }
 
     #[inline]
-    pub fn memorys(&self) -> &[Memory] {
-        &self.memorys.defines
+    pub fn memorys(&self) -> &CombinedDeclear<Memory, MemoryType> {
+        &self.memorys
     }
 
     #[inline]
@@ -398,8 +584,49 @@ impl Module {
         self.memorys.len()
     }
 
+    #[inline]
+    pub fn tables(&self) -> &CombinedDeclear<Table, TableType> {
+        &self.tables
+    }
+
+    #[inline]
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
     #[inline]
     pub fn datas(&self) -> &[Data] {
-        &self.datas()
+        &self.datas
+    }
+
+    #[inline]
+    pub fn has_data_count(&self) -> bool {
+        self.has_data_count
+    }
+
+    #[inline]
+    pub fn elements(&self) -> &[Element] {
+        &self.elements
+    }
+
+    #[inline]
+    pub fn exports(&self) -> &IndexMap<String, Export> {
+        &self.exports
+    }
+
+    pub fn get_export(&self, name: &str) -> Option<Export> {
+        self.exports.get(name).cloned()
+    }
+
+    #[inline]
+    pub fn start_function(&self) -> Option<u32> {
+        self.start
+    }
+
+    pub fn get_function_export(&self, name: &str) -> Option<u32> {
+        match self.get_export(name) {
+            Some(Export::Function(idx)) => Some(idx),
+            _ => None,
+        }
     }
 }
@@ -0,0 +1,522 @@
+use super::binary::{Operator, Reader};
+use super::instruction::read_mem_arg;
+use super::{Instruction, LoadError, MemArg};
+
+// The instructions of the fixed-width SIMD proposal, which follow the `0xfd` prefix.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SimdOperator {
+    V128Load(MemArg),
+    V128Load8x8S(MemArg),
+    V128Load8x8U(MemArg),
+    V128Load16x4S(MemArg),
+    V128Load16x4U(MemArg),
+    V128Load32x2S(MemArg),
+    V128Load32x2U(MemArg),
+    V128Load8Splat(MemArg),
+    V128Load16Splat(MemArg),
+    V128Load32Splat(MemArg),
+    V128Load64Splat(MemArg),
+    V128Store(MemArg),
+    I8x16Shuffle(Box<[u8; 16]>),
+    I8x16Swizzle,
+    I8x16Splat,
+    I16x8Splat,
+    I32x4Splat,
+    I64x2Splat,
+    F32x4Splat,
+    F64x2Splat,
+    I8x16ExtractLaneS(u8),
+    I8x16ExtractLaneU(u8),
+    I8x16ReplaceLane(u8),
+    I16x8ExtractLaneS(u8),
+    I16x8ExtractLaneU(u8),
+    I16x8ReplaceLane(u8),
+    I32x4ExtractLane(u8),
+    I32x4ReplaceLane(u8),
+    I64x2ExtractLane(u8),
+    I64x2ReplaceLane(u8),
+    F32x4ExtractLane(u8),
+    F32x4ReplaceLane(u8),
+    F64x2ExtractLane(u8),
+    F64x2ReplaceLane(u8),
+    I8x16Eq,
+    I8x16Ne,
+    I8x16LtS,
+    I8x16LtU,
+    I8x16GtS,
+    I8x16GtU,
+    I8x16LeS,
+    I8x16LeU,
+    I8x16GeS,
+    I8x16GeU,
+    I16x8Eq,
+    I16x8Ne,
+    I16x8LtS,
+    I16x8LtU,
+    I16x8GtS,
+    I16x8GtU,
+    I16x8LeS,
+    I16x8LeU,
+    I16x8GeS,
+    I16x8GeU,
+    I32x4Eq,
+    I32x4Ne,
+    I32x4LtS,
+    I32x4LtU,
+    I32x4GtS,
+    I32x4GtU,
+    I32x4LeS,
+    I32x4LeU,
+    I32x4GeS,
+    I32x4GeU,
+    F32x4Eq,
+    F32x4Ne,
+    F32x4Lt,
+    F32x4Gt,
+    F32x4Le,
+    F32x4Ge,
+    F64x2Eq,
+    F64x2Ne,
+    F64x2Lt,
+    F64x2Gt,
+    F64x2Le,
+    F64x2Ge,
+    V128Not,
+    V128And,
+    V128AndNot,
+    V128Or,
+    V128Xor,
+    V128Bitselect,
+    V128AnyTrue,
+    V128Load8Lane(MemArg, u8),
+    V128Load16Lane(MemArg, u8),
+    V128Load32Lane(MemArg, u8),
+    V128Load64Lane(MemArg, u8),
+    V128Store8Lane(MemArg, u8),
+    V128Store16Lane(MemArg, u8),
+    V128Store32Lane(MemArg, u8),
+    V128Store64Lane(MemArg, u8),
+    V128Load32Zero(MemArg),
+    V128Load64Zero(MemArg),
+    F32x4DemoteF64x2Zero,
+    F64x2PromoteLowF32x4,
+    I8x16Abs,
+    I8x16Neg,
+    I8x16Popcnt,
+    I8x16AllTrue,
+    I8x16Bitmask,
+    I8x16NarrowI16x8S,
+    I8x16NarrowI16x8U,
+    F32x4Ceil,
+    F32x4Floor,
+    F32x4Trunc,
+    F32x4Nearest,
+    I8x16Shl,
+    I8x16ShrS,
+    I8x16ShrU,
+    I8x16Add,
+    I8x16AddSatS,
+    I8x16AddSatU,
+    I8x16Sub,
+    I8x16SubSatS,
+    I8x16SubSatU,
+    F64x2Ceil,
+    F64x2Floor,
+    I8x16MinS,
+    I8x16MinU,
+    I8x16MaxS,
+    I8x16MaxU,
+    F64x2Trunc,
+    I8x16AvgrU,
+    I16x8ExtaddPairwiseI8x16S,
+    I16x8ExtaddPairwiseI8x16U,
+    I32x4ExtaddPairwiseI16x8S,
+    I32x4ExtaddPairwiseI16x8U,
+    I16x8Abs,
+    I16x8Neg,
+    I16x8Q15MulrSatS,
+    I16x8AllTrue,
+    I16x8Bitmask,
+    I16x8NarrowI32x4S,
+    I16x8NarrowI32x4U,
+    I16x8ExtendLowI8x16S,
+    I16x8ExtendHighI8x16S,
+    I16x8ExtendLowI8x16U,
+    I16x8ExtendHighI8x16U,
+    I16x8Shl,
+    I16x8ShrS,
+    I16x8ShrU,
+    I16x8Add,
+    I16x8AddSatS,
+    I16x8AddSatU,
+    I16x8Sub,
+    I16x8SubSatS,
+    I16x8SubSatU,
+    F64x2Nearest,
+    I16x8Mul,
+    I16x8MinS,
+    I16x8MinU,
+    I16x8MaxS,
+    I16x8MaxU,
+    I16x8AvgrU,
+    I16x8ExtmulLowI8x16S,
+    I16x8ExtmulHighI8x16S,
+    I16x8ExtmulLowI8x16U,
+    I16x8ExtmulHighI8x16U,
+    I32x4Abs,
+    I32x4Neg,
+    I32x4AllTrue,
+    I32x4Bitmask,
+    I32x4ExtendLowI16x8S,
+    I32x4ExtendHighI16x8S,
+    I32x4ExtendLowI16x8U,
+    I32x4ExtendHighI16x8U,
+    I32x4Shl,
+    I32x4ShrS,
+    I32x4ShrU,
+    I32x4Add,
+    I32x4Sub,
+    I32x4Mul,
+    I32x4MinS,
+    I32x4MinU,
+    I32x4MaxS,
+    I32x4MaxU,
+    I32x4DotI16x8S,
+    I32x4ExtmulLowI16x8S,
+    I32x4ExtmulHighI16x8S,
+    I32x4ExtmulLowI16x8U,
+    I32x4ExtmulHighI16x8U,
+    I64x2Abs,
+    I64x2Neg,
+    I64x2AllTrue,
+    I64x2Bitmask,
+    I64x2ExtendLowI32x4S,
+    I64x2ExtendHighI32x4S,
+    I64x2ExtendLowI32x4U,
+    I64x2ExtendHighI32x4U,
+    I64x2Shl,
+    I64x2ShrS,
+    I64x2ShrU,
+    I64x2Add,
+    I64x2Sub,
+    I64x2Mul,
+    I64x2Eq,
+    I64x2Ne,
+    I64x2LtS,
+    I64x2GtS,
+    I64x2LeS,
+    I64x2GeS,
+    I64x2ExtmulLowI32x4S,
+    I64x2ExtmulHighI32x4S,
+    I64x2ExtmulLowI32x4U,
+    I64x2ExtmulHighI32x4U,
+    F32x4Abs,
+    F32x4Neg,
+    F32x4Sqrt,
+    F32x4Add,
+    F32x4Sub,
+    F32x4Mul,
+    F32x4Div,
+    F32x4Min,
+    F32x4Max,
+    F32x4Pmin,
+    F32x4Pmax,
+    F64x2Abs,
+    F64x2Neg,
+    F64x2Sqrt,
+    F64x2Add,
+    F64x2Sub,
+    F64x2Mul,
+    F64x2Div,
+    F64x2Min,
+    F64x2Max,
+    F64x2Pmin,
+    F64x2Pmax,
+    I32x4TruncSatF32x4S,
+    I32x4TruncSatF32x4U,
+    F32x4ConvertI32x4S,
+    F32x4ConvertI32x4U,
+    I32x4TruncSatF64x2SZero,
+    I32x4TruncSatF64x2UZero,
+    F64x2ConvertLowI32x4S,
+    F64x2ConvertLowI32x4U,
+}
+
+fn read_bytes16(reader: &mut Reader) -> Result<Box<[u8; 16]>, LoadError> {
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(reader.read_bytes(16)?);
+    Ok(Box::new(bytes))
+}
+
+// Decodes the instruction after the `0xfd` prefix. `v128.const` is decoded as an `Instruction`,
+// like the other constants which the constant expressions use.
+pub(super) fn read_simd_operator(reader: &mut Reader) -> Result<Operator, LoadError> {
+    use self::SimdOperator::*;
+
+    macro_rules! mem {
+        ($op:ident) => {
+            $op(read_mem_arg(reader)?)
+        };
+    }
+    macro_rules! mem_lane {
+        ($op:ident) => {{
+            let arg = read_mem_arg(reader)?;
+            $op(arg, reader.read_u8()?)
+        }};
+    }
+    macro_rules! lane {
+        ($op:ident) => {
+            $op(reader.read_u8()?)
+        };
+    }
+
+    let opcode = reader.read_var_u32()?;
+    let op = match opcode {
+        0x00 => mem!(V128Load),
+        0x01 => mem!(V128Load8x8S),
+        0x02 => mem!(V128Load8x8U),
+        0x03 => mem!(V128Load16x4S),
+        0x04 => mem!(V128Load16x4U),
+        0x05 => mem!(V128Load32x2S),
+        0x06 => mem!(V128Load32x2U),
+        0x07 => mem!(V128Load8Splat),
+        0x08 => mem!(V128Load16Splat),
+        0x09 => mem!(V128Load32Splat),
+        0x0a => mem!(V128Load64Splat),
+        0x0b => mem!(V128Store),
+        0x0c => {
+            let bytes = read_bytes16(reader)?;
+            return Ok(Operator::Instr(Instruction::V128Const(bytes)));
+        }
+        0x0d => I8x16Shuffle(read_bytes16(reader)?),
+        0x0e => I8x16Swizzle,
+        0x0f => I8x16Splat,
+        0x10 => I16x8Splat,
+        0x11 => I32x4Splat,
+        0x12 => I64x2Splat,
+        0x13 => F32x4Splat,
+        0x14 => F64x2Splat,
+        0x15 => lane!(I8x16ExtractLaneS),
+        0x16 => lane!(I8x16ExtractLaneU),
+        0x17 => lane!(I8x16ReplaceLane),
+        0x18 => lane!(I16x8ExtractLaneS),
+        0x19 => lane!(I16x8ExtractLaneU),
+        0x1a => lane!(I16x8ReplaceLane),
+        0x1b => lane!(I32x4ExtractLane),
+        0x1c => lane!(I32x4ReplaceLane),
+        0x1d => lane!(I64x2ExtractLane),
+        0x1e => lane!(I64x2ReplaceLane),
+        0x1f => lane!(F32x4ExtractLane),
+        0x20 => lane!(F32x4ReplaceLane),
+        0x21 => lane!(F64x2ExtractLane),
+        0x22 => lane!(F64x2ReplaceLane),
+        0x23 => I8x16Eq,
+        0x24 => I8x16Ne,
+        0x25 => I8x16LtS,
+        0x26 => I8x16LtU,
+        0x27 => I8x16GtS,
+        0x28 => I8x16GtU,
+        0x29 => I8x16LeS,
+        0x2a => I8x16LeU,
+        0x2b => I8x16GeS,
+        0x2c => I8x16GeU,
+        0x2d => I16x8Eq,
+        0x2e => I16x8Ne,
+        0x2f => I16x8LtS,
+        0x30 => I16x8LtU,
+        0x31 => I16x8GtS,
+        0x32 => I16x8GtU,
+        0x33 => I16x8LeS,
+        0x34 => I16x8LeU,
+        0x35 => I16x8GeS,
+        0x36 => I16x8GeU,
+        0x37 => I32x4Eq,
+        0x38 => I32x4Ne,
+        0x39 => I32x4LtS,
+        0x3a => I32x4LtU,
+        0x3b => I32x4GtS,
+        0x3c => I32x4GtU,
+        0x3d => I32x4LeS,
+        0x3e => I32x4LeU,
+        0x3f => I32x4GeS,
+        0x40 => I32x4GeU,
+        0x41 => F32x4Eq,
+        0x42 => F32x4Ne,
+        0x43 => F32x4Lt,
+        0x44 => F32x4Gt,
+        0x45 => F32x4Le,
+        0x46 => F32x4Ge,
+        0x47 => F64x2Eq,
+        0x48 => F64x2Ne,
+        0x49 => F64x2Lt,
+        0x4a => F64x2Gt,
+        0x4b => F64x2Le,
+        0x4c => F64x2Ge,
+        0x4d => V128Not,
+        0x4e => V128And,
+        0x4f => V128AndNot,
+        0x50 => V128Or,
+        0x51 => V128Xor,
+        0x52 => V128Bitselect,
+        0x53 => V128AnyTrue,
+        0x54 => mem_lane!(V128Load8Lane),
+        0x55 => mem_lane!(V128Load16Lane),
+        0x56 => mem_lane!(V128Load32Lane),
+        0x57 => mem_lane!(V128Load64Lane),
+        0x58 => mem_lane!(V128Store8Lane),
+        0x59 => mem_lane!(V128Store16Lane),
+        0x5a => mem_lane!(V128Store32Lane),
+        0x5b => mem_lane!(V128Store64Lane),
+        0x5c => mem!(V128Load32Zero),
+        0x5d => mem!(V128Load64Zero),
+        0x5e => F32x4DemoteF64x2Zero,
+        0x5f => F64x2PromoteLowF32x4,
+        0x60 => I8x16Abs,
+        0x61 => I8x16Neg,
+        0x62 => I8x16Popcnt,
+        0x63 => I8x16AllTrue,
+        0x64 => I8x16Bitmask,
+        0x65 => I8x16NarrowI16x8S,
+        0x66 => I8x16NarrowI16x8U,
+        0x67 => F32x4Ceil,
+        0x68 => F32x4Floor,
+        0x69 => F32x4Trunc,
+        0x6a => F32x4Nearest,
+        0x6b => I8x16Shl,
+        0x6c => I8x16ShrS,
+        0x6d => I8x16ShrU,
+        0x6e => I8x16Add,
+        0x6f => I8x16AddSatS,
+        0x70 => I8x16AddSatU,
+        0x71 => I8x16Sub,
+        0x72 => I8x16SubSatS,
+        0x73 => I8x16SubSatU,
+        0x74 => F64x2Ceil,
+        0x75 => F64x2Floor,
+        0x76 => I8x16MinS,
+        0x77 => I8x16MinU,
+        0x78 => I8x16MaxS,
+        0x79 => I8x16MaxU,
+        0x7a => F64x2Trunc,
+        0x7b => I8x16AvgrU,
+        0x7c => I16x8ExtaddPairwiseI8x16S,
+        0x7d => I16x8ExtaddPairwiseI8x16U,
+        0x7e => I32x4ExtaddPairwiseI16x8S,
+        0x7f => I32x4ExtaddPairwiseI16x8U,
+        0x80 => I16x8Abs,
+        0x81 => I16x8Neg,
+        0x82 => I16x8Q15MulrSatS,
+        0x83 => I16x8AllTrue,
+        0x84 => I16x8Bitmask,
+        0x85 => I16x8NarrowI32x4S,
+        0x86 => I16x8NarrowI32x4U,
+        0x87 => I16x8ExtendLowI8x16S,
+        0x88 => I16x8ExtendHighI8x16S,
+        0x89 => I16x8ExtendLowI8x16U,
+        0x8a => I16x8ExtendHighI8x16U,
+        0x8b => I16x8Shl,
+        0x8c => I16x8ShrS,
+        0x8d => I16x8ShrU,
+        0x8e => I16x8Add,
+        0x8f => I16x8AddSatS,
+        0x90 => I16x8AddSatU,
+        0x91 => I16x8Sub,
+        0x92 => I16x8SubSatS,
+        0x93 => I16x8SubSatU,
+        0x94 => F64x2Nearest,
+        0x95 => I16x8Mul,
+        0x96 => I16x8MinS,
+        0x97 => I16x8MinU,
+        0x98 => I16x8MaxS,
+        0x99 => I16x8MaxU,
+        0x9b => I16x8AvgrU,
+        0x9c => I16x8ExtmulLowI8x16S,
+        0x9d => I16x8ExtmulHighI8x16S,
+        0x9e => I16x8ExtmulLowI8x16U,
+        0x9f => I16x8ExtmulHighI8x16U,
+        0xa0 => I32x4Abs,
+        0xa1 => I32x4Neg,
+        0xa3 => I32x4AllTrue,
+        0xa4 => I32x4Bitmask,
+        0xa7 => I32x4ExtendLowI16x8S,
+        0xa8 => I32x4ExtendHighI16x8S,
+        0xa9 => I32x4ExtendLowI16x8U,
+        0xaa => I32x4ExtendHighI16x8U,
+        0xab => I32x4Shl,
+        0xac => I32x4ShrS,
+        0xad => I32x4ShrU,
+        0xae => I32x4Add,
+        0xb1 => I32x4Sub,
+        0xb5 => I32x4Mul,
+        0xb6 => I32x4MinS,
+        0xb7 => I32x4MinU,
+        0xb8 => I32x4MaxS,
+        0xb9 => I32x4MaxU,
+        0xba => I32x4DotI16x8S,
+        0xbc => I32x4ExtmulLowI16x8S,
+        0xbd => I32x4ExtmulHighI16x8S,
+        0xbe => I32x4ExtmulLowI16x8U,
+        0xbf => I32x4ExtmulHighI16x8U,
+        0xc0 => I64x2Abs,
+        0xc1 => I64x2Neg,
+        0xc3 => I64x2AllTrue,
+        0xc4 => I64x2Bitmask,
+        0xc7 => I64x2ExtendLowI32x4S,
+        0xc8 => I64x2ExtendHighI32x4S,
+        0xc9 => I64x2ExtendLowI32x4U,
+        0xca => I64x2ExtendHighI32x4U,
+        0xcb => I64x2Shl,
+        0xcc => I64x2ShrS,
+        0xcd => I64x2ShrU,
+        0xce => I64x2Add,
+        0xd1 => I64x2Sub,
+        0xd5 => I64x2Mul,
+        0xd6 => I64x2Eq,
+        0xd7 => I64x2Ne,
+        0xd8 => I64x2LtS,
+        0xd9 => I64x2GtS,
+        0xda => I64x2LeS,
+        0xdb => I64x2GeS,
+        0xdc => I64x2ExtmulLowI32x4S,
+        0xdd => I64x2ExtmulHighI32x4S,
+        0xde => I64x2ExtmulLowI32x4U,
+        0xdf => I64x2ExtmulHighI32x4U,
+        0xe0 => F32x4Abs,
+        0xe1 => F32x4Neg,
+        0xe3 => F32x4Sqrt,
+        0xe4 => F32x4Add,
+        0xe5 => F32x4Sub,
+        0xe6 => F32x4Mul,
+        0xe7 => F32x4Div,
+        0xe8 => F32x4Min,
+        0xe9 => F32x4Max,
+        0xea => F32x4Pmin,
+        0xeb => F32x4Pmax,
+        0xec => F64x2Abs,
+        0xed => F64x2Neg,
+        0xef => F64x2Sqrt,
+        0xf0 => F64x2Add,
+        0xf1 => F64x2Sub,
+        0xf2 => F64x2Mul,
+        0xf3 => F64x2Div,
+        0xf4 => F64x2Min,
+        0xf5 => F64x2Max,
+        0xf6 => F64x2Pmin,
+        0xf7 => F64x2Pmax,
+        0xf8 => I32x4TruncSatF32x4S,
+        0xf9 => I32x4TruncSatF32x4U,
+        0xfa => F32x4ConvertI32x4S,
+        0xfb => F32x4ConvertI32x4U,
+        0xfc => I32x4TruncSatF64x2SZero,
+        0xfd => I32x4TruncSatF64x2UZero,
+        0xfe => F64x2ConvertLowI32x4S,
+        0xff => F64x2ConvertLowI32x4U,
+        _ => {
+            return Err(LoadError::Malformed(format!(
+                "unknown SIMD opcode 0x{:x}",
+                opcode
+            )))
+        }
+    };
+    Ok(Operator::Simd(op))
+}
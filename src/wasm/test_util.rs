@@ -0,0 +1,37 @@
+// Builders of the binary format for the unit tests.
+
+const HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+// The unsigned LEB128 encoding of `v`.
+pub(crate) fn leb(mut v: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+// A vector of entries, which is their count followed by the entries.
+pub(crate) fn entries(items: &[&[u8]]) -> Vec<u8> {
+    let mut bytes = leb(items.len() as u32);
+    items.iter().for_each(|item| bytes.extend(*item));
+    bytes
+}
+
+pub(crate) fn section(id: u8, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![id];
+    bytes.extend(leb(payload.len() as u32));
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+pub(crate) fn module(sections: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = HEADER.to_vec();
+    sections.iter().for_each(|s| bytes.extend(s));
+    bytes
+}
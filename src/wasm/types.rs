@@ -1,9 +1,9 @@
-use super::BlockType;
 use std::convert::From;
+use std::fmt;
 
 pub trait Type {}
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ValueType {
     None = 0,
     Any = 1,
@@ -39,25 +39,31 @@ impl ValueType {
     }
 }
 
-impl From<parity_wasm::elements::ValueType> for ValueType {
-    fn from(ty: parity_wasm::elements::ValueType) -> Self {
-        match ty {
-            parity_wasm::elements::ValueType::F32 => ValueType::F32,
-            parity_wasm::elements::ValueType::F64 => ValueType::F64,
-            parity_wasm::elements::ValueType::I32 => ValueType::I32,
-            parity_wasm::elements::ValueType::I64 => ValueType::I64,
-            parity_wasm::elements::ValueType::V128 => ValueType::V128,
-        }
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ValueType::None => "none",
+            ValueType::Any => "any",
+            ValueType::I32 => "i32",
+            ValueType::I64 => "i64",
+            ValueType::F32 => "f32",
+            ValueType::F64 => "f64",
+            ValueType::V128 => "v128",
+            ValueType::AnyRef => "anyref",
+            ValueType::AnyFunc => "funcref",
+            ValueType::NullRef => "nullref",
+        };
+        write!(f, "{}", name)
     }
 }
 
-impl From<BlockType> for ValueType {
-    fn from(ty: BlockType) -> Self {
-        match ty {
-            BlockType::Value(v) => ValueType::from(v),
-            BlockType::NoResult => ValueType::None,
-        }
-    }
+// The type of a block, loop, if or try. A type index refers to a function type in the type
+// section, whose params are the block params.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlockType {
+    NoResult,
+    Value(ValueType),
+    TypeIndex(u32),
 }
 
 pub struct I32(pub i32);
@@ -109,13 +115,13 @@ impl Type for V128 {}
 
 impl From<u32> for F32 {
     fn from(v: u32) -> F32 {
-        F32(v as f32)
+        F32(f32::from_bits(v))
     }
 }
 
 impl From<u64> for F64 {
     fn from(v: u64) -> F64 {
-        F64(v as f64)
+        F64(f64::from_bits(v))
     }
 }
 
@@ -135,7 +141,7 @@ impl From<Box<[u8; 16]>> for V128 {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct GlobalType {
     mutable: bool,
     ty: ValueType,
@@ -143,16 +149,21 @@ pub struct GlobalType {
 
 impl Type for GlobalType {}
 
-impl From<parity_wasm::elements::GlobalType> for GlobalType {
-    fn from(v: parity_wasm::elements::GlobalType) -> GlobalType {
-        GlobalType {
-            mutable: v.is_mutable(),
-            ty: ValueType::from(v.content_type()),
+impl fmt::Display for GlobalType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.mutable {
+            write!(f, "global mut {}", self.ty)
+        } else {
+            write!(f, "global {}", self.ty)
         }
     }
 }
 
 impl GlobalType {
+    pub fn new(ty: ValueType, mutable: bool) -> Self {
+        GlobalType { mutable, ty }
+    }
+
     pub fn value_type(&self) -> &ValueType {
         &self.ty
     }
@@ -162,45 +173,87 @@ impl GlobalType {
     }
 }
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Hash)]
 pub struct FunctionType {
-    res: Option<ValueType>,
     params: Vec<ValueType>,
+    results: Vec<ValueType>,
 }
 
 impl Type for FunctionType {}
 
-impl From<parity_wasm::elements::FunctionType> for FunctionType {
-    fn from(func_type: parity_wasm::elements::FunctionType) -> Self {
-        let res = if let Some(res_type) = func_type.return_type() {
-            Some(ValueType::from(res_type))
-        } else {
-            None
+// Formats the type like `(i32 i32) -> i32`, or `(i32) -> (i32 i64)` with multiple results.
+impl fmt::Display for FunctionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |types: &[ValueType]| {
+            types
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
         };
-        let params = func_type
-            .params()
-            .iter()
-            .map(|t| ValueType::from(*t))
-            .collect();
-
-        Self { res, params }
+        match self.results.as_slice() {
+            [res] => write!(f, "({}) -> {}", join(&self.params), res),
+            results => write!(f, "({}) -> ({})", join(&self.params), join(results)),
+        }
     }
 }
 
 impl FunctionType {
-    pub fn new(params: Vec<ValueType>, res: Option<ValueType>) -> Self {
-        Self { res, params }
-    }
-    pub fn res(&self) -> Option<ValueType> {
-        self.res
+    pub fn new(params: Vec<ValueType>, results: Vec<ValueType>) -> Self {
+        Self { params, results }
     }
 
     pub fn params(&self) -> &[ValueType] {
         &self.params
     }
+
+    pub fn results(&self) -> &[ValueType] {
+        &self.results
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TableType {
+    elem_type: ValueType,
+    min: u32,
+    max: Option<u32>,
+}
+
+impl Type for TableType {}
+
+impl fmt::Display for TableType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "table {}", self.min)?;
+        if let Some(max) = self.max {
+            write!(f, " {}", max)?;
+        }
+        write!(f, " {}", self.elem_type)
+    }
 }
 
-#[derive(Clone, Copy)]
+impl TableType {
+    pub fn new(elem_type: ValueType, min: u32, max: Option<u32>) -> Self {
+        Self {
+            elem_type,
+            min,
+            max,
+        }
+    }
+
+    pub fn elem_type(&self) -> ValueType {
+        self.elem_type
+    }
+
+    pub fn min_elems(&self) -> u32 {
+        self.min
+    }
+
+    pub fn max_elems(&self) -> Option<u32> {
+        self.max
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct MemoryType {
     min: u32,
     max: Option<u32>,
@@ -209,16 +262,24 @@ pub struct MemoryType {
 
 impl Type for MemoryType {}
 
-impl From<parity_wasm::elements::MemoryType> for MemoryType {
-    fn from(mem_type: parity_wasm::elements::MemoryType) -> Self {
-        let min = mem_type.limits().initial();
-        let max = mem_type.limits().maximum();
-        let shared = mem_type.limits().shared();
-        Self { min, max, shared }
+impl fmt::Display for MemoryType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "memory {}", self.min)?;
+        if let Some(max) = self.max {
+            write!(f, " {}", max)?;
+        }
+        if self.shared {
+            write!(f, " shared")?;
+        }
+        Ok(())
     }
 }
 
 impl MemoryType {
+    pub fn new(min: u32, max: Option<u32>, shared: bool) -> Self {
+        Self { min, max, shared }
+    }
+
     pub fn min_pages(&self) -> u32 { self.min }
 
     pub fn max_pages(&self) -> Option<u32> { self.max }
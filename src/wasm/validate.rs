@@ -0,0 +1,1263 @@
+use super::{
+    BlockType, DataMode, ElementMode, Entry, Export, Function, FunctionType, Instruction, Module,
+    MemArg, Operator, SimdOperator, ValueType, MAX_PAGES,
+};
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    Module(String),
+    Function {
+        index: u32,
+        // The offset of the invalid operator in the module.
+        offset: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::Module(message) => write!(f, "invalid module: {}", message),
+            ValidationError::Function {
+                index,
+                offset,
+                message,
+            } => write!(
+                f,
+                "invalid function {} at offset 0x{:x}: {}",
+                index, offset, message
+            ),
+        }
+    }
+}
+
+pub fn validate(module: &Module) -> Result<(), ValidationError> {
+    validate_module(module).map_err(ValidationError::Module)?;
+
+    let declared_funcs = declared_functions(module);
+    let num_imports = module.functions().imports().len();
+    for (i, func) in module.function_defs().iter().enumerate() {
+        FunctionValidator::new(module, &declared_funcs, func)
+            .validate(func)
+            .map_err(|(offset, message)| ValidationError::Function {
+                index: (num_imports + i) as u32,
+                offset,
+                message,
+            })?;
+    }
+    Ok(())
+}
+
+fn validate_limits(min: u32, max: Option<u32>, bound: u32, what: &str) -> Result<(), String> {
+    if min > bound || max.map_or(false, |max| max > bound) {
+        return Err(format!("the size of {} must be at most {}", what, bound));
+    }
+    if max.map_or(false, |max| min > max) {
+        return Err(format!(
+            "the initial size of {} is larger than its maximum",
+            what
+        ));
+    }
+    Ok(())
+}
+
+fn is_ref_type(ty: ValueType) -> bool {
+    ty == ValueType::AnyRef || ty == ValueType::AnyFunc
+}
+
+fn validate_const_expr(module: &Module, op: &Operator, expected: ValueType) -> Result<(), String> {
+    let ty = match op {
+        Operator::Instr(Instruction::I32Const(_)) => ValueType::I32,
+        Operator::Instr(Instruction::I64Const(_)) => ValueType::I64,
+        Operator::Instr(Instruction::F32Const(_)) => ValueType::F32,
+        Operator::Instr(Instruction::F64Const(_)) => ValueType::F64,
+        Operator::Instr(Instruction::V128Const(_)) => ValueType::V128,
+        Operator::Instr(Instruction::GetGlobal(idx)) => {
+            // Only imported globals are initialized before the constant expressions of a
+            // module are evaluated.
+            let global = match module.globals().imports().get(*idx as usize) {
+                Some(global) => global.get_type(),
+                None => return Err(format!("unknown imported global {}", idx)),
+            };
+            if global.is_mutable() {
+                return Err(format!(
+                    "the global {} referred by a constant expression must be immutable",
+                    idx
+                ));
+            }
+            *global.value_type()
+        }
+        Operator::RefNull(ty) => *ty,
+        Operator::RefFunc(idx) => {
+            if *idx as usize >= module.functions().len() {
+                return Err(format!("unknown function {}", idx));
+            }
+            ValueType::AnyFunc
+        }
+        _ => {
+            return Err(format!(
+                "constant expression required, but got {:?}",
+                op
+            ))
+        }
+    };
+    if ty != expected {
+        return Err(format!(
+            "type mismatch in constant expression: expected {:?}, but got {:?}",
+            expected, ty
+        ));
+    }
+    Ok(())
+}
+
+// The functions which `ref.func` may refer to in the code, which are those referred to outside
+// of the functions.
+fn declared_functions(module: &Module) -> HashSet<u32> {
+    let mut funcs = HashSet::new();
+    let mut declare = |op: &Operator| {
+        if let Operator::RefFunc(idx) = op {
+            funcs.insert(*idx);
+        }
+    };
+    module.elements().iter().flat_map(|e| e.members()).for_each(&mut declare);
+    module
+        .globals()
+        .defines()
+        .iter()
+        .for_each(|g| declare(g.init_instr()));
+    for export in module.exports().values() {
+        if let Export::Function(idx) = export {
+            funcs.insert(*idx);
+        }
+    }
+    funcs
+}
+
+fn validate_module(module: &Module) -> Result<(), String> {
+    if cfg!(not(feature = "multi-memory")) && module.memorys_count() > 1 {
+        return Err("multiple memories require the multi-memory feature".to_string());
+    }
+
+    for ty in module
+        .memorys()
+        .imports()
+        .iter()
+        .map(|t| t.get_type())
+        .chain(module.memorys().defines().iter().map(|t| t.get_type()))
+    {
+        validate_limits(ty.min_pages(), ty.max_pages(), MAX_PAGES, "memory")?;
+        if ty.is_shared() && ty.max_pages().is_none() {
+            return Err("shared memory must have a maximum".to_string());
+        }
+    }
+
+    for ty in module
+        .tables()
+        .imports()
+        .iter()
+        .map(|t| t.get_type())
+        .chain(module.tables().defines().iter().map(|t| t.get_type()))
+    {
+        validate_limits(ty.min_elems(), ty.max_elems(), std::u32::MAX, "table")?;
+    }
+
+    for (i, tag) in module.tags().iter().enumerate() {
+        if !tag.get_type().results().is_empty() {
+            return Err(format!("the type of tag {} must not have results", i));
+        }
+    }
+
+    for global in module.globals().defines() {
+        validate_const_expr(module, global.init_instr(), *global.get_type().value_type())?;
+    }
+
+    for data in module.datas() {
+        if let DataMode::Active { memory_idx, offset } = data.mode() {
+            if *memory_idx as usize >= module.memorys_count() {
+                return Err(format!("unknown memory {} in data segment", memory_idx));
+            }
+            validate_const_expr(module, offset, ValueType::I32)?;
+        }
+    }
+
+    for elem in module.elements() {
+        if let ElementMode::Active { table_idx, offset } = elem.mode() {
+            if *table_idx as usize >= module.tables().len() {
+                return Err(format!("unknown table {} in element segment", table_idx));
+            }
+            let table_type = module.tables().get_type(*table_idx as usize).elem_type();
+            if table_type != elem.elem_type() {
+                return Err(format!(
+                    "type mismatch: element segment of {} for table {} of {}",
+                    elem.elem_type(),
+                    table_idx,
+                    table_type
+                ));
+            }
+            validate_const_expr(module, offset, ValueType::I32)?;
+        }
+        for member in elem.members() {
+            validate_const_expr(module, member, elem.elem_type())?;
+        }
+    }
+
+    if let Some(start) = module.start_function() {
+        if start as usize >= module.functions().len() {
+            return Err(format!("unknown start function {}", start));
+        }
+        let ty = module.functions().get_type(start as usize);
+        if !ty.params().is_empty() || !ty.results().is_empty() {
+            return Err(format!(
+                "the start function {} must have type [] -> [], but got {:?}",
+                start, ty
+            ));
+        }
+    }
+
+    for (name, export) in module.exports() {
+        let (idx, len) = match export {
+            Export::Function(idx) => (*idx, module.functions().len()),
+            Export::Table(idx) => (*idx, module.tables().len()),
+            Export::Memory(idx) => (*idx, module.memorys_count()),
+            Export::Global(idx) => (*idx, module.globals().len()),
+        };
+        if idx as usize >= len {
+            return Err(format!("the export {} refers to unknown {:?}", name, export));
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FrameKind {
+    Function,
+    Block,
+    Loop,
+    If,
+    Else,
+    Try,
+    Catch,
+    CatchAll,
+}
+
+struct ControlFrame {
+    kind: FrameKind,
+    params: Vec<ValueType>,
+    results: Vec<ValueType>,
+    height: usize,
+    unreachable: bool,
+}
+
+impl ControlFrame {
+    fn label_types(&self) -> &[ValueType] {
+        match self.kind {
+            FrameKind::Loop => &self.params,
+            _ => &self.results,
+        }
+    }
+}
+
+// Type-checks a function body with the algorithm described in the appendix of the
+// WebAssembly specification. `ValueType::Any` stands for an operand of unknown type, which
+// only appears on the stack of unreachable code.
+struct FunctionValidator<'a> {
+    module: &'a Module,
+    declared_funcs: &'a HashSet<u32>,
+    locals: Vec<ValueType>,
+    results: Vec<ValueType>,
+    operands: Vec<ValueType>,
+    frames: Vec<ControlFrame>,
+}
+
+impl<'a> FunctionValidator<'a> {
+    fn new(module: &'a Module, declared_funcs: &'a HashSet<u32>, func: &Function) -> Self {
+        let mut locals = func.get_type().params().to_vec();
+        locals.extend_from_slice(func.locals());
+        Self {
+            module,
+            declared_funcs,
+            locals,
+            results: func.get_type().results().to_vec(),
+            operands: Vec::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    fn validate(mut self, func: &Function) -> Result<(), (usize, String)> {
+        let results = self.results.clone();
+        self.push_ctrl(FrameKind::Function, vec![], results);
+
+        for (i, op) in func.operators().iter().enumerate() {
+            let offset = func.operator_offset(i);
+            if self.frames.is_empty() {
+                return Err((offset, "unexpected instruction after the end of function".to_string()));
+            }
+            self.validate_operator(op).map_err(|e| (offset, e))?;
+        }
+
+        if !self.frames.is_empty() {
+            return Err((
+                func.operator_offset(func.operators().len()),
+                "the function body is not terminated by end".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, ty: ValueType) {
+        self.operands.push(ty);
+    }
+
+    fn pop(&mut self) -> Result<ValueType, String> {
+        let frame = self.frames.last().unwrap();
+        if self.operands.len() == frame.height {
+            if frame.unreachable {
+                return Ok(ValueType::Any);
+            }
+            return Err("type mismatch: the operand stack is empty".to_string());
+        }
+        Ok(self.operands.pop().unwrap())
+    }
+
+    fn pop_expect(&mut self, expected: ValueType) -> Result<ValueType, String> {
+        let actual = self.pop()?;
+        if actual == ValueType::Any {
+            return Ok(expected);
+        }
+        if expected != ValueType::Any && actual != expected {
+            return Err(format!(
+                "type mismatch: expected {:?}, but got {:?}",
+                expected, actual
+            ));
+        }
+        Ok(actual)
+    }
+
+    fn pop_values(&mut self, types: &[ValueType]) -> Result<(), String> {
+        for ty in types.iter().rev() {
+            self.pop_expect(*ty)?;
+        }
+        Ok(())
+    }
+
+    // Enters a block, whose params are pushed to the stack.
+    fn push_ctrl(&mut self, kind: FrameKind, params: Vec<ValueType>, results: Vec<ValueType>) {
+        let height = self.operands.len();
+        self.operands.extend_from_slice(&params);
+        self.frames.push(ControlFrame {
+            kind,
+            params,
+            results,
+            height,
+            unreachable: false,
+        });
+    }
+
+    fn pop_ctrl(&mut self) -> Result<ControlFrame, String> {
+        let results = self.frames.last().unwrap().results.clone();
+        self.pop_values(&results)?;
+        if self.operands.len() != self.frames.last().unwrap().height {
+            return Err("type mismatch: values remain on the stack at the end of block".to_string());
+        }
+        Ok(self.frames.pop().unwrap())
+    }
+
+    fn set_unreachable(&mut self) {
+        let frame = self.frames.last_mut().unwrap();
+        self.operands.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    fn label_types(&self, depth: u32) -> Result<Vec<ValueType>, String> {
+        if depth as usize >= self.frames.len() {
+            return Err(format!("unknown label {}", depth));
+        }
+        Ok(self.frames[self.frames.len() - 1 - depth as usize]
+            .label_types()
+            .to_vec())
+    }
+
+    fn unop(&mut self, ty: ValueType) -> Result<(), String> {
+        self.cvtop(ty, ty)
+    }
+
+    fn binop(&mut self, ty: ValueType) -> Result<(), String> {
+        self.pop_expect(ty)?;
+        self.pop_expect(ty)?;
+        self.push(ty);
+        Ok(())
+    }
+
+    fn testop(&mut self, ty: ValueType) -> Result<(), String> {
+        self.cvtop(ty, ValueType::I32)
+    }
+
+    fn relop(&mut self, ty: ValueType) -> Result<(), String> {
+        self.pop_expect(ty)?;
+        self.pop_expect(ty)?;
+        self.push(ValueType::I32);
+        Ok(())
+    }
+
+    fn cvtop(&mut self, from: ValueType, to: ValueType) -> Result<(), String> {
+        self.pop_expect(from)?;
+        self.push(to);
+        Ok(())
+    }
+
+    fn check_memory(&self, idx: u32) -> Result<(), String> {
+        if idx as usize >= self.module.memorys_count() {
+            return Err(format!("unknown memory {}", idx));
+        }
+        Ok(())
+    }
+
+    // Returns the element type of the table.
+    fn check_table(&self, idx: u32) -> Result<ValueType, String> {
+        if idx as usize >= self.module.tables().len() {
+            return Err(format!("unknown table {}", idx));
+        }
+        Ok(self.module.tables().get_type(idx as usize).elem_type())
+    }
+
+    // The indices of the data segments are only known before the code section if the module
+    // has a data count section.
+    fn check_data(&self, seg: u32) -> Result<(), String> {
+        if !self.module.has_data_count() {
+            return Err("data count section required".to_string());
+        }
+        if seg as usize >= self.module.datas().len() {
+            return Err(format!("unknown data segment {}", seg));
+        }
+        Ok(())
+    }
+
+    // Returns the element type of the segment.
+    fn check_elem(&self, seg: u32) -> Result<ValueType, String> {
+        match self.module.elements().get(seg as usize) {
+            Some(elem) => Ok(elem.elem_type()),
+            None => Err(format!("unknown element segment {}", seg)),
+        }
+    }
+
+    // The alignment of `arg` is an exponent, which must not be larger than the natural alignment
+    // of the access.
+    fn check_align(&self, arg: &MemArg, natural: u32) -> Result<(), String> {
+        self.check_memory(arg.memory)?;
+        if arg.align > natural {
+            return Err(format!(
+                "alignment 2^{} must not be larger than natural alignment 2^{}",
+                arg.align, natural
+            ));
+        }
+        Ok(())
+    }
+
+    // Atomic accesses must be naturally aligned.
+    fn check_atomic_align(&self, arg: &MemArg, natural: u32) -> Result<(), String> {
+        self.check_memory(arg.memory)?;
+        if arg.align != natural {
+            return Err(format!(
+                "alignment 2^{} of atomic access must be equal to natural alignment 2^{}",
+                arg.align, natural
+            ));
+        }
+        Ok(())
+    }
+
+    fn load(&mut self, ty: ValueType, arg: &MemArg, natural: u32) -> Result<(), String> {
+        self.check_align(arg, natural)?;
+        self.cvtop(ValueType::I32, ty)
+    }
+
+    fn store(&mut self, ty: ValueType, arg: &MemArg, natural: u32) -> Result<(), String> {
+        self.check_align(arg, natural)?;
+        self.pop_expect(ty)?;
+        self.pop_expect(ValueType::I32)?;
+        Ok(())
+    }
+
+    fn atomic_load(&mut self, ty: ValueType, arg: &MemArg, natural: u32) -> Result<(), String> {
+        self.check_atomic_align(arg, natural)?;
+        self.cvtop(ValueType::I32, ty)
+    }
+
+    fn atomic_store(&mut self, ty: ValueType, arg: &MemArg, natural: u32) -> Result<(), String> {
+        self.check_atomic_align(arg, natural)?;
+        self.pop_expect(ty)?;
+        self.pop_expect(ValueType::I32)?;
+        Ok(())
+    }
+
+    fn atomic_rmw(&mut self, ty: ValueType, arg: &MemArg, natural: u32) -> Result<(), String> {
+        self.check_atomic_align(arg, natural)?;
+        self.pop_expect(ty)?;
+        self.pop_expect(ValueType::I32)?;
+        self.push(ty);
+        Ok(())
+    }
+
+    fn atomic_cmpxchg(&mut self, ty: ValueType, arg: &MemArg, natural: u32) -> Result<(), String> {
+        self.check_atomic_align(arg, natural)?;
+        self.pop_expect(ty)?;
+        self.pop_expect(ty)?;
+        self.pop_expect(ValueType::I32)?;
+        self.push(ty);
+        Ok(())
+    }
+
+    fn extract_lane(&mut self, lane: u8, num_lanes: u8, ty: ValueType) -> Result<(), String> {
+        if lane >= num_lanes {
+            return Err(format!("lane index {} out of range", lane));
+        }
+        self.cvtop(ValueType::V128, ty)
+    }
+
+    fn replace_lane(&mut self, lane: u8, num_lanes: u8, ty: ValueType) -> Result<(), String> {
+        if lane >= num_lanes {
+            return Err(format!("lane index {} out of range", lane));
+        }
+        self.pop_expect(ty)?;
+        self.pop_expect(ValueType::V128)?;
+        self.push(ValueType::V128);
+        Ok(())
+    }
+
+    fn load_lane(
+        &mut self,
+        arg: &MemArg,
+        natural: u32,
+        lane: u8,
+        num_lanes: u8,
+    ) -> Result<(), String> {
+        self.check_align(arg, natural)?;
+        if lane >= num_lanes {
+            return Err(format!("lane index {} out of range", lane));
+        }
+        self.pop_expect(ValueType::V128)?;
+        self.cvtop(ValueType::I32, ValueType::V128)
+    }
+
+    fn store_lane(
+        &mut self,
+        arg: &MemArg,
+        natural: u32,
+        lane: u8,
+        num_lanes: u8,
+    ) -> Result<(), String> {
+        self.check_align(arg, natural)?;
+        if lane >= num_lanes {
+            return Err(format!("lane index {} out of range", lane));
+        }
+        self.pop_expect(ValueType::V128)?;
+        self.pop_expect(ValueType::I32)?;
+        Ok(())
+    }
+
+    fn vector_shift(&mut self) -> Result<(), String> {
+        self.pop_expect(ValueType::I32)?;
+        self.pop_expect(ValueType::V128)?;
+        self.push(ValueType::V128);
+        Ok(())
+    }
+
+    fn bulk_op(&mut self) -> Result<(), String> {
+        self.pop_expect(ValueType::I32)?;
+        self.pop_expect(ValueType::I32)?;
+        self.pop_expect(ValueType::I32)?;
+        Ok(())
+    }
+
+    // Pops the params of a block and returns its params and results.
+    fn block_type(&mut self, ty: BlockType) -> Result<(Vec<ValueType>, Vec<ValueType>), String> {
+        if let BlockType::TypeIndex(idx) = ty {
+            if idx as usize >= self.module.types_count() {
+                return Err(format!("unknown type {}", idx));
+            }
+        }
+        let ty = self.module.get_block_type(ty);
+        self.pop_values(ty.params())?;
+        Ok((ty.params().to_vec(), ty.results().to_vec()))
+    }
+
+    fn tag_params(&self, idx: u32) -> Result<Vec<ValueType>, String> {
+        match self.module.tags().get(idx as usize) {
+            Some(tag) => Ok(tag.get_type().params().to_vec()),
+            None => Err(format!("unknown tag {}", idx)),
+        }
+    }
+
+    fn validate_operator(&mut self, op: &Operator) -> Result<(), String> {
+        match op {
+            Operator::Instr(instr) => return self.validate_instr(instr),
+            Operator::Simd(op) => return self.validate_simd(op),
+            Operator::AtomicFence => {}
+            Operator::CallIndirect(ty_idx, table_idx) => {
+                if self.check_table(*table_idx)? != ValueType::AnyFunc {
+                    return Err(format!("type mismatch: table {} isn't a funcref table", table_idx));
+                }
+                if *ty_idx as usize >= self.module.types_count() {
+                    return Err(format!("unknown type {}", ty_idx));
+                }
+                self.pop_expect(ValueType::I32)?;
+                let ty = self.module.get_func_type(*ty_idx).clone();
+                self.call(&ty)?;
+            }
+            Operator::SelectTyped(types) => {
+                if types.len() != 1 {
+                    return Err("invalid result arity of typed select".to_string());
+                }
+                self.pop_expect(ValueType::I32)?;
+                self.pop_expect(types[0])?;
+                self.pop_expect(types[0])?;
+                self.push(types[0]);
+            }
+            Operator::RefNull(ty) => self.push(*ty),
+            Operator::RefIsNull => {
+                let ty = self.pop()?;
+                if ty != ValueType::Any && !is_ref_type(ty) {
+                    return Err(format!("type mismatch: expected a reference, but got {:?}", ty));
+                }
+                self.push(ValueType::I32);
+            }
+            Operator::RefFunc(idx) => {
+                if *idx as usize >= self.module.functions().len() {
+                    return Err(format!("unknown function {}", idx));
+                }
+                if !self.declared_funcs.contains(idx) {
+                    return Err(format!("undeclared function reference {}", idx));
+                }
+                self.push(ValueType::AnyFunc);
+            }
+            Operator::TableGet(idx) => {
+                let ty = self.check_table(*idx)?;
+                self.cvtop(ValueType::I32, ty)?;
+            }
+            Operator::TableSet(idx) => {
+                let ty = self.check_table(*idx)?;
+                self.pop_expect(ty)?;
+                self.pop_expect(ValueType::I32)?;
+            }
+            Operator::TableSize(idx) => {
+                self.check_table(*idx)?;
+                self.push(ValueType::I32);
+            }
+            Operator::TableGrow(idx) => {
+                let ty = self.check_table(*idx)?;
+                self.pop_expect(ValueType::I32)?;
+                self.pop_expect(ty)?;
+                self.push(ValueType::I32);
+            }
+            Operator::TableFill(idx) => {
+                let ty = self.check_table(*idx)?;
+                self.pop_expect(ValueType::I32)?;
+                self.pop_expect(ty)?;
+                self.pop_expect(ValueType::I32)?;
+            }
+            Operator::MemoryInit(seg, idx) => {
+                self.check_memory(*idx)?;
+                self.check_data(*seg)?;
+                self.bulk_op()?
+            }
+            Operator::DataDrop(seg) => self.check_data(*seg)?,
+            Operator::MemoryCopy(dst, src) => {
+                self.check_memory(*dst)?;
+                self.check_memory(*src)?;
+                self.bulk_op()?
+            }
+            Operator::MemoryFill(idx) => {
+                self.check_memory(*idx)?;
+                self.bulk_op()?
+            }
+            Operator::TableInit(seg, idx) => {
+                let ty = self.check_table(*idx)?;
+                let elem_type = self.check_elem(*seg)?;
+                if elem_type != ty {
+                    return Err(format!(
+                        "type mismatch: element segment {} of {} for table {} of {}",
+                        seg, elem_type, idx, ty
+                    ));
+                }
+                self.bulk_op()?
+            }
+            Operator::ElemDrop(seg) => {
+                self.check_elem(*seg)?;
+            }
+            Operator::TableCopy(dst, src) => {
+                let dst_type = self.check_table(*dst)?;
+                let src_type = self.check_table(*src)?;
+                if dst_type != src_type {
+                    return Err(format!(
+                        "type mismatch: table {} of {} copied to table {} of {}",
+                        src, src_type, dst, dst_type
+                    ));
+                }
+                self.bulk_op()?
+            }
+            Operator::Block(ty) => {
+                let (params, results) = self.block_type(*ty)?;
+                self.push_ctrl(FrameKind::Block, params, results);
+            }
+            Operator::Loop(ty) => {
+                let (params, results) = self.block_type(*ty)?;
+                self.push_ctrl(FrameKind::Loop, params, results);
+            }
+            Operator::If(ty) => {
+                self.pop_expect(ValueType::I32)?;
+                let (params, results) = self.block_type(*ty)?;
+                self.push_ctrl(FrameKind::If, params, results);
+            }
+            Operator::Try(ty) => {
+                let (params, results) = self.block_type(*ty)?;
+                self.push_ctrl(FrameKind::Try, params, results);
+            }
+            // The params of the tag are the params of the catch.
+            Operator::Catch(idx) => {
+                let params = self.tag_params(*idx)?;
+                let frame = self.pop_handler_ctrl("catch")?;
+                self.push_ctrl(FrameKind::Catch, params, frame.results);
+            }
+            Operator::CatchAll => {
+                let frame = self.pop_handler_ctrl("catch_all")?;
+                self.push_ctrl(FrameKind::CatchAll, vec![], frame.results);
+            }
+            Operator::Throw(idx) => {
+                let params = self.tag_params(*idx)?;
+                self.pop_values(&params)?;
+                self.set_unreachable();
+            }
+            Operator::Rethrow(depth) => {
+                if *depth as usize >= self.frames.len() {
+                    return Err(format!("unknown label {}", depth));
+                }
+                match self.frames[self.frames.len() - 1 - *depth as usize].kind {
+                    FrameKind::Catch | FrameKind::CatchAll => {}
+                    _ => return Err(format!("the label {} of rethrow isn't a catch", depth)),
+                }
+                self.set_unreachable();
+            }
+            Operator::Delegate(depth) => {
+                if self.frames.last().unwrap().kind != FrameKind::Try {
+                    return Err("delegate doesn't match a try".to_string());
+                }
+                let frame = self.pop_ctrl()?;
+                // The label is relative to the blocks enclosing the try.
+                if *depth as usize >= self.frames.len() {
+                    return Err(format!("unknown label {}", depth));
+                }
+                frame.results.iter().for_each(|ty| self.push(*ty));
+            }
+        }
+        Ok(())
+    }
+
+    // Ends the body of a try or one of its catches, which a catch can follow.
+    fn pop_handler_ctrl(&mut self, name: &str) -> Result<ControlFrame, String> {
+        match self.frames.last().unwrap().kind {
+            FrameKind::Try | FrameKind::Catch => self.pop_ctrl(),
+            FrameKind::CatchAll => Err(format!("{} follows catch_all", name)),
+            _ => Err(format!("{} doesn't match a try", name)),
+        }
+    }
+
+    fn validate_instr(&mut self, instr: &Instruction) -> Result<(), String> {
+        use self::ValueType::*;
+        use crate::wasm::Instruction::*;
+
+        match instr {
+            Unreachable => self.set_unreachable(),
+            Nop => {}
+            Else => {
+                if self.frames.last().unwrap().kind != FrameKind::If {
+                    return Err("else doesn't match an if".to_string());
+                }
+                let frame = self.pop_ctrl()?;
+                self.push_ctrl(FrameKind::Else, frame.params, frame.results);
+            }
+            End => {
+                let frame = self.pop_ctrl()?;
+                // The params of an if without else are the results of the missing else.
+                if frame.kind == FrameKind::If && frame.params != frame.results {
+                    return Err(
+                        "type mismatch: the params and results of if without else differ"
+                            .to_string(),
+                    );
+                }
+                if frame.kind != FrameKind::Function {
+                    frame.results.iter().for_each(|ty| self.push(*ty));
+                }
+            }
+            Br(depth) => {
+                let types = self.label_types(*depth)?;
+                self.pop_values(&types)?;
+                self.set_unreachable();
+            }
+            BrIf(depth) => {
+                self.pop_expect(I32)?;
+                let types = self.label_types(*depth)?;
+                self.pop_values(&types)?;
+                types.iter().for_each(|ty| self.push(*ty));
+            }
+            BrTable(data) => {
+                self.pop_expect(I32)?;
+                let types = self.label_types(data.default)?;
+                for depth in data.table.iter() {
+                    if self.label_types(*depth)? != types {
+                        return Err(format!(
+                            "type mismatch: the label {} of br_table has a different arity",
+                            depth
+                        ));
+                    }
+                }
+                self.pop_values(&types)?;
+                self.set_unreachable();
+            }
+            Return => {
+                let results = self.results.clone();
+                self.pop_values(&results)?;
+                self.set_unreachable();
+            }
+            Call(idx) => {
+                if *idx as usize >= self.module.functions().len() {
+                    return Err(format!("unknown function {}", idx));
+                }
+                let ty = self.module.functions().get_type(*idx as usize).clone();
+                self.call(&ty)?;
+            }
+            Drop => {
+                self.pop()?;
+            }
+            // The operands of the untyped select can't be references.
+            Select => {
+                self.pop_expect(I32)?;
+                let ty = self.pop()?;
+                let ty = self.pop_expect(ty)?;
+                if is_ref_type(ty) {
+                    return Err("type mismatch: select of references requires a type".to_string());
+                }
+                self.push(ty);
+            }
+
+            GetLocal(idx) => {
+                let ty = self.local_type(*idx)?;
+                self.push(ty);
+            }
+            SetLocal(idx) => {
+                let ty = self.local_type(*idx)?;
+                self.pop_expect(ty)?;
+            }
+            TeeLocal(idx) => {
+                let ty = self.local_type(*idx)?;
+                self.cvtop(ty, ty)?;
+            }
+            GetGlobal(idx) => {
+                let ty = self.global_type(*idx)?;
+                self.push(ty);
+            }
+            SetGlobal(idx) => {
+                let ty = self.global_type(*idx)?;
+                if !self
+                    .module
+                    .globals()
+                    .get_type(*idx as usize)
+                    .is_mutable()
+                {
+                    return Err(format!("the global {} is immutable", idx));
+                }
+                self.pop_expect(ty)?;
+            }
+
+            I32Load(arg) => self.load(I32, arg, 2)?,
+            I64Load(arg) => self.load(I64, arg, 3)?,
+            F32Load(arg) => self.load(F32, arg, 2)?,
+            F64Load(arg) => self.load(F64, arg, 3)?,
+            I32Load8S(arg) | I32Load8U(arg) => self.load(I32, arg, 0)?,
+            I32Load16S(arg) | I32Load16U(arg) => self.load(I32, arg, 1)?,
+            I64Load8S(arg) | I64Load8U(arg) => self.load(I64, arg, 0)?,
+            I64Load16S(arg) | I64Load16U(arg) => self.load(I64, arg, 1)?,
+            I64Load32S(arg) | I64Load32U(arg) => self.load(I64, arg, 2)?,
+            I32Store(arg) => self.store(I32, arg, 2)?,
+            I64Store(arg) => self.store(I64, arg, 3)?,
+            F32Store(arg) => self.store(F32, arg, 2)?,
+            F64Store(arg) => self.store(F64, arg, 3)?,
+            I32Store8(arg) => self.store(I32, arg, 0)?,
+            I32Store16(arg) => self.store(I32, arg, 1)?,
+            I64Store8(arg) => self.store(I64, arg, 0)?,
+            I64Store16(arg) => self.store(I64, arg, 1)?,
+            I64Store32(arg) => self.store(I64, arg, 2)?,
+            CurrentMemory(idx) => {
+                self.check_memory(*idx)?;
+                self.push(I32);
+            }
+            GrowMemory(idx) => {
+                self.check_memory(*idx)?;
+                self.cvtop(I32, I32)?;
+            }
+
+            I32Const(_) => self.push(I32),
+            I64Const(_) => self.push(I64),
+            F32Const(_) => self.push(F32),
+            F64Const(_) => self.push(F64),
+
+            I32Eqz => self.testop(I32)?,
+            I64Eqz => self.testop(I64)?,
+            I32Eq | I32Ne | I32LtS | I32LtU | I32GtS | I32GtU | I32LeS | I32LeU | I32GeS
+            | I32GeU => self.relop(I32)?,
+            I64Eq | I64Ne | I64LtS | I64LtU | I64GtS | I64GtU | I64LeS | I64LeU | I64GeS
+            | I64GeU => self.relop(I64)?,
+            F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge => self.relop(F32)?,
+            F64Eq | F64Ne | F64Lt | F64Gt | F64Le | F64Ge => self.relop(F64)?,
+
+            I32Clz | I32Ctz | I32Popcnt | I32Extend8S | I32Extend16S => self.unop(I32)?,
+            I64Clz | I64Ctz | I64Popcnt | I64Extend8S | I64Extend16S | I64Extend32S => {
+                self.unop(I64)?
+            }
+            F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest | F32Sqrt => {
+                self.unop(F32)?
+            }
+            F64Abs | F64Neg | F64Ceil | F64Floor | F64Trunc | F64Nearest | F64Sqrt => {
+                self.unop(F64)?
+            }
+            I32Add | I32Sub | I32Mul | I32DivS | I32DivU | I32RemS | I32RemU | I32And | I32Or
+            | I32Xor | I32Shl | I32ShrS | I32ShrU | I32Rotl | I32Rotr => self.binop(I32)?,
+            I64Add | I64Sub | I64Mul | I64DivS | I64DivU | I64RemS | I64RemU | I64And | I64Or
+            | I64Xor | I64Shl | I64ShrS | I64ShrU | I64Rotl | I64Rotr => self.binop(I64)?,
+            F32Add | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32Copysign => {
+                self.binop(F32)?
+            }
+            F64Add | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64Copysign => {
+                self.binop(F64)?
+            }
+
+            I32WrapI64 => self.cvtop(I64, I32)?,
+            I32TruncSF32 | I32TruncUF32 | I32ReinterpretF32 => self.cvtop(F32, I32)?,
+            I32TruncSF64 | I32TruncUF64 => self.cvtop(F64, I32)?,
+            I64ExtendSI32 | I64ExtendUI32 => self.cvtop(I32, I64)?,
+            I64TruncSF32 | I64TruncUF32 => self.cvtop(F32, I64)?,
+            I64TruncSF64 | I64TruncUF64 | I64ReinterpretF64 => self.cvtop(F64, I64)?,
+            F32ConvertSI32 | F32ConvertUI32 | F32ReinterpretI32 => self.cvtop(I32, F32)?,
+            F32ConvertSI64 | F32ConvertUI64 => self.cvtop(I64, F32)?,
+            F32DemoteF64 => self.cvtop(F64, F32)?,
+            F64ConvertSI32 | F64ConvertUI32 => self.cvtop(I32, F64)?,
+            F64ConvertSI64 | F64ConvertUI64 | F64ReinterpretI64 => self.cvtop(I64, F64)?,
+            F64PromoteF32 => self.cvtop(F32, F64)?,
+
+            AtomicWake(arg) => {
+                self.check_atomic_align(arg, 2)?;
+                self.pop_expect(I32)?;
+                self.cvtop(I32, I32)?;
+            }
+            I32AtomicWait(arg) => {
+                self.check_atomic_align(arg, 2)?;
+                self.pop_expect(I64)?;
+                self.pop_expect(I32)?;
+                self.cvtop(I32, I32)?;
+            }
+            I64AtomicWait(arg) => {
+                self.check_atomic_align(arg, 3)?;
+                self.pop_expect(I64)?;
+                self.pop_expect(I64)?;
+                self.cvtop(I32, I32)?;
+            }
+            I32AtomicLoad(arg) => self.atomic_load(I32, arg, 2)?,
+            I64AtomicLoad(arg) => self.atomic_load(I64, arg, 3)?,
+            I32AtomicLoad8u(arg) => self.atomic_load(I32, arg, 0)?,
+            I32AtomicLoad16u(arg) => self.atomic_load(I32, arg, 1)?,
+            I64AtomicLoad8u(arg) => self.atomic_load(I64, arg, 0)?,
+            I64AtomicLoad16u(arg) => self.atomic_load(I64, arg, 1)?,
+            I64AtomicLoad32u(arg) => self.atomic_load(I64, arg, 2)?,
+            I32AtomicStore(arg) => self.atomic_store(I32, arg, 2)?,
+            I64AtomicStore(arg) => self.atomic_store(I64, arg, 3)?,
+            I32AtomicStore8u(arg) => self.atomic_store(I32, arg, 0)?,
+            I32AtomicStore16u(arg) => self.atomic_store(I32, arg, 1)?,
+            I64AtomicStore8u(arg) => self.atomic_store(I64, arg, 0)?,
+            I64AtomicStore16u(arg) => self.atomic_store(I64, arg, 1)?,
+            I64AtomicStore32u(arg) => self.atomic_store(I64, arg, 2)?,
+            I32AtomicRmwAdd(arg) | I32AtomicRmwSub(arg) | I32AtomicRmwAnd(arg)
+            | I32AtomicRmwOr(arg) | I32AtomicRmwXor(arg) | I32AtomicRmwXchg(arg) => {
+                self.atomic_rmw(I32, arg, 2)?
+            }
+            I64AtomicRmwAdd(arg) | I64AtomicRmwSub(arg) | I64AtomicRmwAnd(arg)
+            | I64AtomicRmwOr(arg) | I64AtomicRmwXor(arg) | I64AtomicRmwXchg(arg) => {
+                self.atomic_rmw(I64, arg, 3)?
+            }
+            I32AtomicRmwAdd8u(arg) | I32AtomicRmwSub8u(arg) | I32AtomicRmwAnd8u(arg)
+            | I32AtomicRmwOr8u(arg) | I32AtomicRmwXor8u(arg) | I32AtomicRmwXchg8u(arg) => {
+                self.atomic_rmw(I32, arg, 0)?
+            }
+            I32AtomicRmwAdd16u(arg) | I32AtomicRmwSub16u(arg) | I32AtomicRmwAnd16u(arg)
+            | I32AtomicRmwOr16u(arg) | I32AtomicRmwXor16u(arg) | I32AtomicRmwXchg16u(arg) => {
+                self.atomic_rmw(I32, arg, 1)?
+            }
+            I64AtomicRmwAdd8u(arg) | I64AtomicRmwSub8u(arg) | I64AtomicRmwAnd8u(arg)
+            | I64AtomicRmwOr8u(arg) | I64AtomicRmwXor8u(arg) | I64AtomicRmwXchg8u(arg) => {
+                self.atomic_rmw(I64, arg, 0)?
+            }
+            I64AtomicRmwAdd16u(arg) | I64AtomicRmwSub16u(arg) | I64AtomicRmwAnd16u(arg)
+            | I64AtomicRmwOr16u(arg) | I64AtomicRmwXor16u(arg) | I64AtomicRmwXchg16u(arg) => {
+                self.atomic_rmw(I64, arg, 1)?
+            }
+            I64AtomicRmwAdd32u(arg) | I64AtomicRmwSub32u(arg) | I64AtomicRmwAnd32u(arg)
+            | I64AtomicRmwOr32u(arg) | I64AtomicRmwXor32u(arg) | I64AtomicRmwXchg32u(arg) => {
+                self.atomic_rmw(I64, arg, 2)?
+            }
+            I32AtomicRmwCmpxchg(arg) => self.atomic_cmpxchg(I32, arg, 2)?,
+            I64AtomicRmwCmpxchg(arg) => self.atomic_cmpxchg(I64, arg, 3)?,
+            I32AtomicRmwCmpxchg8u(arg) => self.atomic_cmpxchg(I32, arg, 0)?,
+            I32AtomicRmwCmpxchg16u(arg) => self.atomic_cmpxchg(I32, arg, 1)?,
+            I64AtomicRmwCmpxchg8u(arg) => self.atomic_cmpxchg(I64, arg, 0)?,
+            I64AtomicRmwCmpxchg16u(arg) => self.atomic_cmpxchg(I64, arg, 1)?,
+            I64AtomicRmwCmpxchg32u(arg) => self.atomic_cmpxchg(I64, arg, 2)?,
+
+            V128Const(_) => self.push(V128),
+        };
+        Ok(())
+    }
+
+    fn validate_simd(&mut self, op: &SimdOperator) -> Result<(), String> {
+        use self::SimdOperator::*;
+        use self::ValueType::{F32, F64, I32, I64, V128};
+
+        match op {
+            V128Load(arg) => self.load(V128, arg, 4)?,
+            V128Load8x8S(arg) | V128Load8x8U(arg) | V128Load16x4S(arg)
+            | V128Load16x4U(arg) | V128Load32x2S(arg) | V128Load32x2U(arg)
+            | V128Load64Splat(arg) | V128Load64Zero(arg) => self.load(V128, arg, 3)?,
+            V128Load8Splat(arg) => self.load(V128, arg, 0)?,
+            V128Load16Splat(arg) => self.load(V128, arg, 1)?,
+            V128Load32Splat(arg) | V128Load32Zero(arg) => self.load(V128, arg, 2)?,
+            V128Store(arg) => self.store(V128, arg, 4)?,
+            V128Load8Lane(arg, lane) => self.load_lane(arg, 0, *lane, 16)?,
+            V128Load16Lane(arg, lane) => self.load_lane(arg, 1, *lane, 8)?,
+            V128Load32Lane(arg, lane) => self.load_lane(arg, 2, *lane, 4)?,
+            V128Load64Lane(arg, lane) => self.load_lane(arg, 3, *lane, 2)?,
+            V128Store8Lane(arg, lane) => self.store_lane(arg, 0, *lane, 16)?,
+            V128Store16Lane(arg, lane) => self.store_lane(arg, 1, *lane, 8)?,
+            V128Store32Lane(arg, lane) => self.store_lane(arg, 2, *lane, 4)?,
+            V128Store64Lane(arg, lane) => self.store_lane(arg, 3, *lane, 2)?,
+
+            I8x16Shuffle(lanes) => {
+                if let Some(lane) = lanes.iter().find(|lane| **lane >= 32) {
+                    return Err(format!("lane index {} out of range", lane));
+                }
+                self.binop(V128)?
+            }
+            I8x16Splat | I16x8Splat | I32x4Splat => self.cvtop(I32, V128)?,
+            I64x2Splat => self.cvtop(I64, V128)?,
+            F32x4Splat => self.cvtop(F32, V128)?,
+            F64x2Splat => self.cvtop(F64, V128)?,
+            I8x16ExtractLaneS(lane) | I8x16ExtractLaneU(lane) => {
+                self.extract_lane(*lane, 16, I32)?
+            }
+            I16x8ExtractLaneS(lane) | I16x8ExtractLaneU(lane) => {
+                self.extract_lane(*lane, 8, I32)?
+            }
+            I32x4ExtractLane(lane) => self.extract_lane(*lane, 4, I32)?,
+            I64x2ExtractLane(lane) => self.extract_lane(*lane, 2, I64)?,
+            F32x4ExtractLane(lane) => self.extract_lane(*lane, 4, F32)?,
+            F64x2ExtractLane(lane) => self.extract_lane(*lane, 2, F64)?,
+            I8x16ReplaceLane(lane) => self.replace_lane(*lane, 16, I32)?,
+            I16x8ReplaceLane(lane) => self.replace_lane(*lane, 8, I32)?,
+            I32x4ReplaceLane(lane) => self.replace_lane(*lane, 4, I32)?,
+            I64x2ReplaceLane(lane) => self.replace_lane(*lane, 2, I64)?,
+            F32x4ReplaceLane(lane) => self.replace_lane(*lane, 4, F32)?,
+            F64x2ReplaceLane(lane) => self.replace_lane(*lane, 2, F64)?,
+
+            I8x16Shl | I8x16ShrS | I8x16ShrU | I16x8Shl | I16x8ShrS | I16x8ShrU | I32x4Shl
+            | I32x4ShrS | I32x4ShrU | I64x2Shl | I64x2ShrS | I64x2ShrU => self.vector_shift()?,
+            V128AnyTrue | I8x16AllTrue | I16x8AllTrue | I32x4AllTrue | I64x2AllTrue
+            | I8x16Bitmask | I16x8Bitmask | I32x4Bitmask | I64x2Bitmask => self.testop(V128)?,
+            V128Bitselect => {
+                self.pop_expect(V128)?;
+                self.binop(V128)?
+            }
+
+            V128Not | I8x16Abs | I8x16Neg | I8x16Popcnt | I16x8Abs | I16x8Neg | I32x4Abs
+            | I32x4Neg | I64x2Abs | I64x2Neg | F32x4Abs | F32x4Neg | F32x4Sqrt | F32x4Ceil
+            | F32x4Floor | F32x4Trunc | F32x4Nearest | F64x2Abs | F64x2Neg | F64x2Sqrt
+            | F64x2Ceil | F64x2Floor | F64x2Trunc | F64x2Nearest | I16x8ExtaddPairwiseI8x16S
+            | I16x8ExtaddPairwiseI8x16U | I32x4ExtaddPairwiseI16x8S | I32x4ExtaddPairwiseI16x8U
+            | I16x8ExtendLowI8x16S | I16x8ExtendHighI8x16S | I16x8ExtendLowI8x16U
+            | I16x8ExtendHighI8x16U | I32x4ExtendLowI16x8S | I32x4ExtendHighI16x8S
+            | I32x4ExtendLowI16x8U | I32x4ExtendHighI16x8U | I64x2ExtendLowI32x4S
+            | I64x2ExtendHighI32x4S | I64x2ExtendLowI32x4U | I64x2ExtendHighI32x4U
+            | I32x4TruncSatF32x4S | I32x4TruncSatF32x4U | F32x4ConvertI32x4S
+            | F32x4ConvertI32x4U | I32x4TruncSatF64x2SZero | I32x4TruncSatF64x2UZero
+            | F64x2ConvertLowI32x4S | F64x2ConvertLowI32x4U | F32x4DemoteF64x2Zero
+            | F64x2PromoteLowF32x4 => self.unop(V128)?,
+
+            // The remaining instructions are binary operators on vectors.
+            _ => self.binop(V128)?,
+        };
+        Ok(())
+    }
+
+    fn call(&mut self, ty: &FunctionType) -> Result<(), String> {
+        self.pop_values(ty.params())?;
+        ty.results().iter().for_each(|ty| self.push(*ty));
+        Ok(())
+    }
+
+    fn local_type(&self, idx: u32) -> Result<ValueType, String> {
+        match self.locals.get(idx as usize) {
+            Some(ty) => Ok(*ty),
+            None => Err(format!("unknown local {}", idx)),
+        }
+    }
+
+    fn global_type(&self, idx: u32) -> Result<ValueType, String> {
+        if idx as usize >= self.module.globals().len() {
+            return Err(format!("unknown global {}", idx));
+        }
+        Ok(*self.module.globals().get_type(idx as usize).value_type())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm::test_util::{entries, leb, module, section};
+
+    // A module with a single function of type `[] -> results`, whose body is `code` followed by
+    // `end`, and with the sections in `extra`, which must be between the function and the code
+    // sections.
+    fn function_module(results: &[u8], code: &[u8], extra: &[Vec<u8>]) -> Vec<u8> {
+        let mut func_type = vec![0x60, 0x00];
+        func_type.extend(leb(results.len() as u32));
+        func_type.extend(results);
+        let mut body = vec![0x00];
+        body.extend(code);
+        body.push(0x0b);
+        let mut entry = leb(body.len() as u32);
+        entry.extend(body);
+
+        let mut sections = vec![
+            section(1, &entries(&[&func_type])),
+            section(3, &entries(&[&[0x00]])),
+        ];
+        sections.extend_from_slice(extra);
+        sections.push(section(10, &entries(&[&entry])));
+        module(&sections)
+    }
+
+    fn validate_function(results: &[u8], code: &[u8], extra: &[Vec<u8>]) -> Result<(), String> {
+        let bytes = function_module(results, code, extra);
+        let module = Module::parse(&bytes).map_err(|e| format!("{:?}", e))?;
+        match validate(&module) {
+            Ok(()) => Ok(()),
+            Err(ValidationError::Function { message, .. }) => Err(message),
+            Err(e) => panic!("the function isn't validated: {}", e),
+        }
+    }
+
+    fn memory_section() -> Vec<u8> {
+        section(5, &entries(&[&[0x00, 0x01]]))
+    }
+
+    #[test]
+    fn error_is_reported_at_the_offset_of_the_operator() {
+        // (i32.add (i32.const 0) (i64.const 0)), where `i32.add` is followed by `end` only.
+        let bytes = function_module(&[0x7f], &[0x41, 0x00, 0x42, 0x00, 0x6a], &[]);
+        let module = Module::parse(&bytes).unwrap();
+        match validate(&module) {
+            Err(ValidationError::Function { index, offset, .. }) => {
+                assert_eq!(index, 0);
+                assert_eq!(offset, bytes.len() - 2);
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+        // A body without `end` is reported at the end of the body.
+        let bytes = function_module(&[], &[0x02, 0x40], &[]);
+        let module = Module::parse(&bytes).unwrap();
+        match validate(&module) {
+            Err(ValidationError::Function { offset, .. }) => assert_eq!(offset, bytes.len()),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn select_operands_must_have_the_same_type() {
+        let i32_select = [0x41, 0x01, 0x41, 0x02, 0x41, 0x00, 0x1b];
+        assert_eq!(validate_function(&[0x7f], &i32_select, &[]), Ok(()));
+        let mixed_select = [0x41, 0x01, 0x42, 0x02, 0x41, 0x00, 0x1b];
+        assert!(validate_function(&[0x7f], &mixed_select, &[]).is_err());
+        // The condition must be an i32.
+        let i64_condition = [0x41, 0x01, 0x41, 0x02, 0x42, 0x00, 0x1b];
+        assert!(validate_function(&[0x7f], &i64_condition, &[]).is_err());
+    }
+
+    #[test]
+    fn alignment_must_not_exceed_natural_alignment() {
+        let memory = [memory_section()];
+        // i32.load with an alignment of 4 and 8 bytes.
+        let aligned = [0x41, 0x00, 0x28, 0x02, 0x00, 0x1a];
+        assert_eq!(validate_function(&[], &aligned, &memory), Ok(()));
+        let overaligned = [0x41, 0x00, 0x28, 0x03, 0x00, 0x1a];
+        assert!(validate_function(&[], &overaligned, &memory).is_err());
+        // i64.store8 with an alignment of 2 bytes.
+        let overaligned_store = [0x41, 0x00, 0x42, 0x00, 0x3c, 0x01, 0x00];
+        assert!(validate_function(&[], &overaligned_store, &memory).is_err());
+    }
+
+    #[test]
+    fn br_table_targets_must_have_the_same_arity() {
+        // block (result i32) (block (br_table 0 1 (i32.const 0) (i32.const 0))) (i32.const 1)
+        let code = [
+            0x02, 0x7f, 0x02, 0x40, 0x41, 0x00, 0x41, 0x00, 0x0e, 0x01, 0x00, 0x01, 0x0b, 0x41,
+            0x01, 0x0b,
+        ];
+        assert!(validate_function(&[0x7f], &code, &[]).is_err());
+        // Both targets are the outer block.
+        let code = [
+            0x02, 0x7f, 0x02, 0x40, 0x41, 0x00, 0x41, 0x00, 0x0e, 0x01, 0x01, 0x01, 0x0b, 0x41,
+            0x01, 0x0b,
+        ];
+        assert_eq!(validate_function(&[0x7f], &code, &[]), Ok(()));
+    }
+
+    #[test]
+    fn if_without_else_must_not_have_results() {
+        let without_else = [0x41, 0x01, 0x04, 0x7f, 0x41, 0x02, 0x0b];
+        assert!(validate_function(&[0x7f], &without_else, &[]).is_err());
+        let with_else = [0x41, 0x01, 0x04, 0x7f, 0x41, 0x02, 0x05, 0x41, 0x03, 0x0b];
+        assert_eq!(validate_function(&[0x7f], &with_else, &[]), Ok(()));
+    }
+
+    #[test]
+    fn stack_is_polymorphic_after_unreachable() {
+        // unreachable i32.add
+        assert_eq!(validate_function(&[0x7f], &[0x00, 0x6a], &[]), Ok(()));
+        // unreachable br 0 i32.add
+        assert_eq!(
+            validate_function(&[0x7f], &[0x00, 0x0c, 0x00, 0x6a], &[]),
+            Ok(())
+        );
+        // The values pushed after it are still typed: unreachable (i64.const 0) i32.add
+        assert!(validate_function(&[0x7f], &[0x00, 0x42, 0x00, 0x6a], &[]).is_err());
+        // The stack can't be popped beyond the block: block (unreachable) end i32.add
+        assert!(validate_function(&[0x7f], &[0x02, 0x40, 0x00, 0x0b, 0x6a], &[]).is_err());
+    }
+
+    #[test]
+    fn global_and_memory_indices_must_be_defined() {
+        assert!(validate_function(&[0x7f], &[0x23, 0x00], &[]).is_err());
+        // A mutable i32 global initialized to 0.
+        let globals = [section(6, &entries(&[&[0x7f, 0x01, 0x41, 0x00, 0x0b]]))];
+        assert_eq!(validate_function(&[0x7f], &[0x23, 0x00], &globals), Ok(()));
+        assert!(validate_function(&[0x7f], &[0x23, 0x01], &globals).is_err());
+
+        let load = [0x41, 0x00, 0x28, 0x02, 0x00];
+        assert!(validate_function(&[0x7f], &load, &[]).is_err());
+        assert!(validate_function(&[0x7f], &[0x3f, 0x00], &[]).is_err());
+        assert_eq!(
+            validate_function(&[0x7f], &[0x3f, 0x00], &[memory_section()]),
+            Ok(())
+        );
+        // i32.load from memory 1, which has the flag in its alignment.
+        let load_from_memory_1 = [0x41, 0x00, 0x28, 0x42, 0x01, 0x00];
+        assert!(validate_function(&[0x7f], &load_from_memory_1, &[memory_section()]).is_err());
+    }
+}
@@ -0,0 +1,47 @@
+// The address space limit applies to the whole process, so this is the only test of its binary.
+mod common;
+
+use common::*;
+use nrt::codegen::CompileOptions;
+use nrt::runtime::{setup_env, HostModule};
+use nrt::wasm::{Module, PAGE_SIZE as WASM_PAGE_SIZE};
+
+fn address_space_size() -> u64 {
+    let statm = std::fs::read_to_string("/proc/self/statm").unwrap();
+    let num_pages: u64 = statm.split_whitespace().next().unwrap().parse().unwrap();
+    num_pages * unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64
+}
+
+// With explicit bounds checks, a memory without a maximum only reserves its current size, so it
+// can be instantiated and grown far below the 4GiB it may grow to.
+#[test]
+fn memory_without_max_fits_small_address_space() {
+    let bytes = ModuleBuilder::new()
+        .memory(1, None)
+        .export("memory", EXPORT_MEMORY, 0)
+        .data(0, b"nrt")
+        .build();
+    let module = Module::parse(&bytes).unwrap();
+    let options = CompileOptions {
+        bounds_check: nrt::codegen::BoundsCheckMode::Explicit,
+    };
+    let object_code = nrt::codegen::compile_module(&module, &options);
+    let compartment = explicit_compartment();
+
+    let limit = address_space_size() + (256 << 20);
+    let rlimit = libc::rlimit {
+        rlim_cur: limit,
+        rlim_max: libc::RLIM_INFINITY,
+    };
+    assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_AS, &rlimit) }, 0);
+
+    let instance = setup_env(&compartment, &module, &object_code, &HostModule::new("env")).unwrap();
+    let memory = instance.get_memory("memory").unwrap();
+    assert_eq!(memory.max_pages(), nrt::wasm::MAX_PAGES);
+    assert_eq!(memory.grow_pages(15), Some(1));
+    assert_eq!(memory.read_bytes(0, 3), Some(b"nrt".to_vec()));
+    assert_eq!(memory.byte_length(), 16 * WASM_PAGE_SIZE);
+    // Growing beyond the limit fails instead of aborting.
+    assert_eq!(memory.grow_pages(8192), None);
+    assert_eq!(memory.cur_pages(), 16);
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use common::*;
+use nrt::runtime::{HostModule, Instance, TrapKind};
+use nrt::wasm::Value;
+
+fn instantiate_module(bytes: &[u8]) -> Instance {
+    let compartment = explicit_compartment();
+    instantiate(&compartment, bytes, &HostModule::new("env")).unwrap()
+}
+
+fn call(instance: &Instance, name: &str, args: &[i32]) -> Result<Vec<Value>, TrapKind> {
+    let args = args.iter().map(|arg| Value::I32(*arg)).collect::<Vec<_>>();
+    instance
+        .get_func(name)
+        .unwrap()
+        .call(&args)
+        .map_err(|trap| trap.kind())
+}
+
+fn read_memory(instance: &Instance, offset: u32, len: u32) -> Vec<u8> {
+    let memory = instance.get_memory("memory").unwrap();
+    memory.read_bytes(offset, len).unwrap()
+}
+
+// The operands of the bulk instructions are the params of the exported functions, so that one
+// module covers every case.
+fn memory_copy_module() -> Vec<u8> {
+    ModuleBuilder::new()
+        .func_type(&[I32, I32, I32], &[])
+        .memory(1, None)
+        // copy: (memory.copy (local.get 0) (local.get 1) (local.get 2))
+        .function(
+            0,
+            &[],
+            &[0x20, 0x00, 0x20, 0x01, 0x20, 0x02, 0xfc, 0x0a, 0x00, 0x00],
+        )
+        .export("copy", EXPORT_FUNCTION, 0)
+        .export("memory", EXPORT_MEMORY, 0)
+        .data(0, &[1, 2, 3, 4, 5, 6, 7, 8])
+        .build()
+}
+
+#[test]
+fn memory_copy_handles_overlapping_ranges() {
+    let forward = instantiate_module(&memory_copy_module());
+    assert_eq!(call(&forward, "copy", &[2, 0, 6]), Ok(vec![]));
+    assert_eq!(read_memory(&forward, 0, 8), vec![1, 2, 1, 2, 3, 4, 5, 6]);
+
+    let backward = instantiate_module(&memory_copy_module());
+    assert_eq!(call(&backward, "copy", &[0, 2, 6]), Ok(vec![]));
+    assert_eq!(read_memory(&backward, 0, 8), vec![3, 4, 5, 6, 7, 8, 7, 8]);
+}
+
+// The whole range is checked before anything is copied.
+#[test]
+fn memory_copy_out_of_bounds_copies_nothing() {
+    let instance = instantiate_module(&memory_copy_module());
+    assert_eq!(
+        call(&instance, "copy", &[65535, 0, 2]),
+        Err(TrapKind::OutOfBoundsMemoryAccess)
+    );
+    assert_eq!(read_memory(&instance, 65535, 1), vec![0]);
+}
+
+#[test]
+fn memory_init_after_data_drop_traps() {
+    let bytes = ModuleBuilder::new()
+        .func_type(&[I32, I32, I32], &[])
+        .func_type(&[], &[])
+        .memory(1, None)
+        // init: (memory.init 0 (local.get 0) (local.get 1) (local.get 2))
+        .function(
+            0,
+            &[],
+            &[0x20, 0x00, 0x20, 0x01, 0x20, 0x02, 0xfc, 0x08, 0x00, 0x00],
+        )
+        // drop: (data.drop 0)
+        .function(1, &[], &[0xfc, 0x09, 0x00])
+        .export("init", EXPORT_FUNCTION, 0)
+        .export("drop", EXPORT_FUNCTION, 1)
+        .export("memory", EXPORT_MEMORY, 0)
+        .passive_data(&[0xaa, 0xbb, 0xcc])
+        .build();
+    let instance = instantiate_module(&bytes);
+    // A passive segment isn't copied at instantiation.
+    assert_eq!(read_memory(&instance, 0, 3), vec![0, 0, 0]);
+    assert_eq!(call(&instance, "init", &[4, 1, 2]), Ok(vec![]));
+    assert_eq!(read_memory(&instance, 4, 2), vec![0xbb, 0xcc]);
+
+    assert_eq!(call(&instance, "drop", &[]), Ok(vec![]));
+    // A dropped segment is empty.
+    assert_eq!(
+        call(&instance, "init", &[4, 1, 2]),
+        Err(TrapKind::OutOfBoundsMemoryAccess)
+    );
+    assert_eq!(call(&instance, "init", &[0, 0, 0]), Ok(vec![]));
+    assert_eq!(call(&instance, "drop", &[]), Ok(vec![]));
+}
+
+// Functions 0 and 1 return 10 and 20, and are the members of the passive segment. `call` calls
+// an element of the table of 4 elements.
+fn table_module() -> Vec<u8> {
+    ModuleBuilder::new()
+        .func_type(&[], &[I32])
+        .func_type(&[I32, I32, I32], &[])
+        .func_type(&[], &[])
+        .func_type(&[I32], &[I32])
+        .table(4, None)
+        .function(0, &[], &[0x41, 0x0a])
+        .function(0, &[], &[0x41, 0x14])
+        // init: (table.init 0 (local.get 0) (local.get 1) (local.get 2))
+        .function(
+            1,
+            &[],
+            &[0x20, 0x00, 0x20, 0x01, 0x20, 0x02, 0xfc, 0x0c, 0x00, 0x00],
+        )
+        // copy: (table.copy (local.get 0) (local.get 1) (local.get 2))
+        .function(
+            1,
+            &[],
+            &[0x20, 0x00, 0x20, 0x01, 0x20, 0x02, 0xfc, 0x0e, 0x00, 0x00],
+        )
+        // drop: (elem.drop 0)
+        .function(2, &[], &[0xfc, 0x0d, 0x00])
+        // call: (call_indirect (type 0) (local.get 0))
+        .function(3, &[], &[0x20, 0x00, 0x11, 0x00, 0x00])
+        .export("init", EXPORT_FUNCTION, 2)
+        .export("copy", EXPORT_FUNCTION, 3)
+        .export("drop", EXPORT_FUNCTION, 4)
+        .export("call", EXPORT_FUNCTION, 5)
+        .passive_element(&[0, 1])
+        .build()
+}
+
+#[test]
+fn table_init_and_table_copy_write_elements() {
+    let instance = instantiate_module(&table_module());
+    assert_eq!(
+        call(&instance, "call", &[1]),
+        Err(TrapKind::UninitializedElement)
+    );
+    assert_eq!(call(&instance, "init", &[1, 0, 2]), Ok(vec![]));
+    assert_eq!(call(&instance, "call", &[1]), Ok(vec![Value::I32(10)]));
+    assert_eq!(call(&instance, "call", &[2]), Ok(vec![Value::I32(20)]));
+
+    // The ranges overlap, and the null elements are copied too.
+    assert_eq!(call(&instance, "copy", &[0, 1, 3]), Ok(vec![]));
+    assert_eq!(call(&instance, "call", &[0]), Ok(vec![Value::I32(10)]));
+    assert_eq!(call(&instance, "call", &[1]), Ok(vec![Value::I32(20)]));
+    assert_eq!(
+        call(&instance, "call", &[2]),
+        Err(TrapKind::UninitializedElement)
+    );
+}
+
+#[test]
+fn table_init_and_table_copy_out_of_bounds_trap() {
+    let instance = instantiate_module(&table_module());
+    assert_eq!(
+        call(&instance, "copy", &[2, 3, 2]),
+        Err(TrapKind::OutOfBoundsTableAccess)
+    );
+    assert_eq!(
+        call(&instance, "init", &[3, 0, 2]),
+        Err(TrapKind::OutOfBoundsTableAccess)
+    );
+    // Nothing was written by the failed `table.init`.
+    assert_eq!(
+        call(&instance, "call", &[3]),
+        Err(TrapKind::UninitializedElement)
+    );
+
+    assert_eq!(call(&instance, "drop", &[]), Ok(vec![]));
+    assert_eq!(
+        call(&instance, "init", &[0, 0, 1]),
+        Err(TrapKind::OutOfBoundsTableAccess)
+    );
+    assert_eq!(call(&instance, "init", &[0, 0, 0]), Ok(vec![]));
+}
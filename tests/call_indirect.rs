@@ -0,0 +1,60 @@
+mod common;
+
+use common::*;
+use nrt::runtime::{HostModule, TrapKind};
+use nrt::wasm::Value;
+
+// Elements 0 to 2 of the table of 4 elements are functions returning 10, 20 and an i64, and
+// `call` calls an element with the type [] -> [i32].
+fn call_indirect_module() -> Vec<u8> {
+    ModuleBuilder::new()
+        .func_type(&[], &[I32])
+        .func_type(&[I32], &[I32])
+        .func_type(&[], &[I64])
+        .table(4, None)
+        .function(0, &[], &[0x41, 0x0a])
+        .function(0, &[], &[0x41, 0x14])
+        .function(2, &[], &[0x42, 0x01])
+        // call: (call_indirect (type 0) (local.get 0))
+        .function(1, &[], &[0x20, 0x00, 0x11, 0x00, 0x00])
+        .export("call", EXPORT_FUNCTION, 3)
+        .element(0, &[0, 1, 2])
+        .build()
+}
+
+fn call(idx: i32) -> Result<Vec<Value>, TrapKind> {
+    let compartment = explicit_compartment();
+    let instance = instantiate(
+        &compartment,
+        &call_indirect_module(),
+        &HostModule::new("env"),
+    )
+    .unwrap();
+    instance
+        .get_func("call")
+        .unwrap()
+        .call(&[Value::I32(idx)])
+        .map_err(|trap| trap.kind())
+}
+
+#[test]
+fn call_indirect_calls_element() {
+    assert_eq!(call(0), Ok(vec![Value::I32(10)]));
+    assert_eq!(call(1), Ok(vec![Value::I32(20)]));
+}
+
+#[test]
+fn call_indirect_of_another_type_traps() {
+    assert_eq!(call(2), Err(TrapKind::IndirectCallSignatureMismatch));
+}
+
+#[test]
+fn call_indirect_of_null_element_traps() {
+    assert_eq!(call(3), Err(TrapKind::UninitializedElement));
+}
+
+#[test]
+fn call_indirect_out_of_bounds_traps() {
+    assert_eq!(call(4), Err(TrapKind::UndefinedElement));
+    assert_eq!(call(-1), Err(TrapKind::UndefinedElement));
+}
@@ -0,0 +1,274 @@
+// Assembles the binary format of the modules used by the tests, and instantiates them.
+#![allow(dead_code)]
+
+use nrt::codegen::{BoundsCheckMode, CompileOptions};
+use nrt::runtime::{setup_env, Compartment, Instance, Resolver};
+use nrt::wasm::{LoadError, Module};
+
+pub const I32: u8 = 0x7f;
+pub const I64: u8 = 0x7e;
+pub const V128: u8 = 0x7b;
+pub const FUNC_REF: u8 = 0x70;
+
+pub const EXPORT_FUNCTION: u8 = 0x00;
+pub const EXPORT_TABLE: u8 = 0x01;
+pub const EXPORT_MEMORY: u8 = 0x02;
+pub const EXPORT_GLOBAL: u8 = 0x03;
+
+pub fn leb_u32(mut v: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+pub fn leb_i32(mut v: i32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if (v == 0 && byte & 0x40 == 0) || (v == -1 && byte & 0x40 != 0) {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+pub fn vec(items: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = leb_u32(items.len() as u32);
+    items.iter().for_each(|item| bytes.extend(item));
+    bytes
+}
+
+pub fn name(s: &str) -> Vec<u8> {
+    let mut bytes = leb_u32(s.len() as u32);
+    bytes.extend(s.as_bytes());
+    bytes
+}
+
+pub fn limits(min: u32, max: Option<u32>) -> Vec<u8> {
+    match max {
+        None => [vec![0x00], leb_u32(min)].concat(),
+        Some(max) => [vec![0x01], leb_u32(min), leb_u32(max)].concat(),
+    }
+}
+
+// The limits of a memory, which are flagged with 0x02 if it's shared.
+pub fn memory_limits(min: u32, max: Option<u32>, shared: bool) -> Vec<u8> {
+    let mut bytes = limits(min, max);
+    bytes[0] |= (shared as u8) << 1;
+    bytes
+}
+
+#[derive(Default)]
+pub struct ModuleBuilder {
+    types: Vec<Vec<u8>>,
+    imports: Vec<Vec<u8>>,
+    functions: Vec<Vec<u8>>,
+    tables: Vec<Vec<u8>>,
+    memorys: Vec<Vec<u8>>,
+    tags: Vec<Vec<u8>>,
+    globals: Vec<Vec<u8>>,
+    exports: Vec<Vec<u8>>,
+    start: Option<u32>,
+    elements: Vec<Vec<u8>>,
+    code: Vec<Vec<u8>>,
+    datas: Vec<Vec<u8>>,
+}
+
+impl ModuleBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn func_type(mut self, params: &[u8], results: &[u8]) -> Self {
+        let params = params.iter().map(|ty| vec![*ty]).collect::<Vec<_>>();
+        let results = results.iter().map(|ty| vec![*ty]).collect::<Vec<_>>();
+        self.types
+            .push([vec![0x60], vec(&params), vec(&results)].concat());
+        self
+    }
+
+    pub fn import_function(mut self, module: &str, field: &str, ty: u32) -> Self {
+        self.imports
+            .push([name(module), name(field), vec![0x00], leb_u32(ty)].concat());
+        self
+    }
+
+    pub fn import_table(mut self, module: &str, field: &str, min: u32, max: Option<u32>) -> Self {
+        self.imports.push(
+            [
+                name(module),
+                name(field),
+                vec![0x01, FUNC_REF],
+                limits(min, max),
+            ]
+            .concat(),
+        );
+        self
+    }
+
+    pub fn import_memory(
+        mut self,
+        module: &str,
+        field: &str,
+        min: u32,
+        max: Option<u32>,
+        shared: bool,
+    ) -> Self {
+        self.imports.push(
+            [
+                name(module),
+                name(field),
+                vec![0x02],
+                memory_limits(min, max, shared),
+            ]
+            .concat(),
+        );
+        self
+    }
+
+    // `code` is the body without the final `end`, and `locals` are single local entries.
+    pub fn function(mut self, ty: u32, locals: &[u8], code: &[u8]) -> Self {
+        self.functions.push(leb_u32(ty));
+        let locals = locals.iter().map(|ty| vec![1, *ty]).collect::<Vec<_>>();
+        let body = [vec(&locals), code.to_vec(), vec![0x0b]].concat();
+        self.code.push([leb_u32(body.len() as u32), body].concat());
+        self
+    }
+
+    pub fn table(mut self, min: u32, max: Option<u32>) -> Self {
+        self.tables
+            .push([vec![FUNC_REF], limits(min, max)].concat());
+        self
+    }
+
+    pub fn memory(mut self, min: u32, max: Option<u32>) -> Self {
+        self.memorys.push(limits(min, max));
+        self
+    }
+
+    pub fn shared_memory(mut self, min: u32, max: Option<u32>) -> Self {
+        self.memorys.push(memory_limits(min, max, true));
+        self
+    }
+
+    // A tag of exceptions whose payload are the params of type `ty`.
+    pub fn tag(mut self, ty: u32) -> Self {
+        self.tags.push([vec![0x00], leb_u32(ty)].concat());
+        self
+    }
+
+    // `init` is the constant expression without the final `end`.
+    pub fn global(mut self, ty: u8, mutable: bool, init: &[u8]) -> Self {
+        self.globals
+            .push([vec![ty, mutable as u8], init.to_vec(), vec![0x0b]].concat());
+        self
+    }
+
+    pub fn export(mut self, field: &str, kind: u8, idx: u32) -> Self {
+        self.exports
+            .push([name(field), vec![kind], leb_u32(idx)].concat());
+        self
+    }
+
+    // The function called at instantiation.
+    pub fn start(mut self, func_idx: u32) -> Self {
+        self.start = Some(func_idx);
+        self
+    }
+
+    // An active segment of function indices for table 0 at `offset`.
+    pub fn element(mut self, offset: i32, funcs: &[u32]) -> Self {
+        let funcs = funcs.iter().map(|idx| leb_u32(*idx)).collect::<Vec<_>>();
+        self.elements
+            .push([vec![0x00, 0x41], leb_i32(offset), vec![0x0b], vec(&funcs)].concat());
+        self
+    }
+
+    // A passive segment of function indices, which only `table.init` copies.
+    pub fn passive_element(mut self, funcs: &[u32]) -> Self {
+        let funcs = funcs.iter().map(|idx| leb_u32(*idx)).collect::<Vec<_>>();
+        self.elements.push([vec![0x01, 0x00], vec(&funcs)].concat());
+        self
+    }
+
+    // An active segment for memory 0 at `offset`.
+    pub fn data(mut self, offset: i32, bytes: &[u8]) -> Self {
+        self.datas.push(
+            [
+                vec![0x00, 0x41],
+                leb_i32(offset),
+                vec![0x0b],
+                leb_u32(bytes.len() as u32),
+                bytes.to_vec(),
+            ]
+            .concat(),
+        );
+        self
+    }
+
+    // A passive segment, which only `memory.init` copies.
+    pub fn passive_data(mut self, bytes: &[u8]) -> Self {
+        self.datas
+            .push([vec![0x01], leb_u32(bytes.len() as u32), bytes.to_vec()].concat());
+        self
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut bytes = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+        let entries = |items: &Vec<Vec<u8>>| Some(vec(items)).filter(|_| !items.is_empty());
+        // The data count section, which `memory.init` and `data.drop` require.
+        let data_count = Some(leb_u32(self.datas.len() as u32)).filter(|_| !self.datas.is_empty());
+        let sections = [
+            (1, entries(&self.types)),
+            (2, entries(&self.imports)),
+            (3, entries(&self.functions)),
+            (4, entries(&self.tables)),
+            (5, entries(&self.memorys)),
+            (13, entries(&self.tags)),
+            (6, entries(&self.globals)),
+            (7, entries(&self.exports)),
+            (8, self.start.map(leb_u32)),
+            (9, entries(&self.elements)),
+            (12, data_count),
+            (10, entries(&self.code)),
+            (11, entries(&self.datas)),
+        ];
+        for (id, payload) in sections.iter() {
+            let payload = match payload {
+                Some(payload) => payload,
+                None => continue,
+            };
+            bytes.push(*id);
+            bytes.extend(leb_u32(payload.len() as u32));
+            bytes.extend(payload);
+        }
+        bytes
+    }
+}
+
+pub fn instantiate<R: Resolver + ?Sized>(
+    compartment: &Compartment,
+    bytes: &[u8],
+    resolver: &R,
+) -> Result<Instance, LoadError> {
+    let module = Module::parse(bytes)?;
+    nrt::wasm::validate(&module).expect("the test module is invalid");
+    let options = CompileOptions {
+        bounds_check: compartment.bounds_check(),
+    };
+    let object_code = nrt::codegen::compile_module(&module, &options);
+    setup_env(compartment, &module, &object_code, resolver)
+}
+
+pub fn explicit_compartment() -> Compartment {
+    Compartment::with_bounds_check(BoundsCheckMode::Explicit).unwrap()
+}
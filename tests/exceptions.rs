@@ -0,0 +1,124 @@
+mod common;
+
+use common::*;
+use nrt::runtime::{HostModule, TrapKind};
+use nrt::wasm::Value;
+
+// Function 0 throws its param with tag 0, and the others call it in a `try`. Tags 0 and 1 both
+// have an i32 payload.
+fn exception_module() -> Vec<u8> {
+    ModuleBuilder::new()
+        .func_type(&[I32], &[])
+        .func_type(&[], &[I32])
+        .func_type(&[I32], &[I32])
+        .tag(0)
+        .tag(0)
+        // throw: (throw 0 (local.get 0))
+        .function(0, &[], &[0x20, 0x00, 0x08, 0x00])
+        // catch: (try (result i32) (do (call $throw (local.get 0)) (i32.const 0))
+        //             (catch 0 (i32.add (i32.const 1))))
+        .function(
+            2,
+            &[],
+            &[
+                0x06, 0x7f, 0x20, 0x00, 0x10, 0x00, 0x41, 0x00, //
+                0x07, 0x00, 0x41, 0x01, 0x6a, 0x0b,
+            ],
+        )
+        // catch_all: (try (result i32) (do (call $throw (i32.const 7)) (i32.const 0))
+        //                 (catch_all (i32.const 42)))
+        .function(
+            1,
+            &[],
+            &[
+                0x06, 0x7f, 0x41, 0x07, 0x10, 0x00, 0x41, 0x00, //
+                0x19, 0x41, 0x2a, 0x0b,
+            ],
+        )
+        // rethrow: (try (result i32)
+        //            (do (try (do (call $throw (local.get 0))) (catch 0 (drop) (rethrow 0)))
+        //                (i32.const 0))
+        //            (catch 0 (i32.mul (i32.const 2))))
+        .function(
+            2,
+            &[],
+            &[
+                0x06, 0x7f, //
+                0x06, 0x40, 0x20, 0x00, 0x10, 0x00, 0x07, 0x00, 0x1a, 0x09, 0x00, 0x0b, //
+                0x41, 0x00, 0x07, 0x00, 0x41, 0x02, 0x6c, 0x0b,
+            ],
+        )
+        // delegate: (try (result i32)
+        //             (do (try (do (call $throw (local.get 0))) (delegate 0)) (i32.const 0))
+        //             (catch 0 (i32.add (i32.const 3))))
+        .function(
+            2,
+            &[],
+            &[
+                0x06, 0x7f, //
+                0x06, 0x40, 0x20, 0x00, 0x10, 0x00, 0x18, 0x00, //
+                0x41, 0x00, 0x07, 0x00, 0x41, 0x03, 0x6a, 0x0b,
+            ],
+        )
+        // other_tag: (try (result i32) (do (call $throw (local.get 0)) (i32.const 0))
+        //                 (catch 1))
+        .function(
+            2,
+            &[],
+            &[
+                0x06, 0x7f, 0x20, 0x00, 0x10, 0x00, 0x41, 0x00, //
+                0x07, 0x01, 0x0b,
+            ],
+        )
+        .export("throw", EXPORT_FUNCTION, 0)
+        .export("catch", EXPORT_FUNCTION, 1)
+        .export("catch_all", EXPORT_FUNCTION, 2)
+        .export("rethrow", EXPORT_FUNCTION, 3)
+        .export("delegate", EXPORT_FUNCTION, 4)
+        .export("other_tag", EXPORT_FUNCTION, 5)
+        .build()
+}
+
+fn call(name: &str, args: &[Value]) -> Result<Vec<Value>, TrapKind> {
+    let compartment = explicit_compartment();
+    let instance = instantiate(&compartment, &exception_module(), &HostModule::new("env")).unwrap();
+    instance
+        .get_func(name)
+        .unwrap()
+        .call(args)
+        .map_err(|trap| trap.kind())
+}
+
+#[test]
+fn catch_receives_payload_of_exception() {
+    assert_eq!(call("catch", &[Value::I32(5)]), Ok(vec![Value::I32(6)]));
+}
+
+#[test]
+fn catch_all_catches_any_exception() {
+    assert_eq!(call("catch_all", &[]), Ok(vec![Value::I32(42)]));
+}
+
+#[test]
+fn rethrown_exception_is_caught_by_outer_try() {
+    assert_eq!(call("rethrow", &[Value::I32(5)]), Ok(vec![Value::I32(10)]));
+}
+
+#[test]
+fn delegate_passes_exception_to_outer_try() {
+    assert_eq!(call("delegate", &[Value::I32(5)]), Ok(vec![Value::I32(8)]));
+}
+
+// An exception which isn't caught, or is only matched against the catch clauses of other tags,
+// traps once it reaches the host.
+#[test]
+fn uncaught_exception_traps() {
+    assert_eq!(
+        call("throw", &[Value::I32(5)]),
+        Err(TrapKind::UncaughtException)
+    );
+    assert_eq!(
+        call("other_tag", &[Value::I32(5)]),
+        Err(TrapKind::UncaughtException)
+    );
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use common::*;
+use nrt::runtime::HostModule;
+use nrt::wasm::Value;
+
+// The compiled code sets the copy of a mutable global in the context of the instance, which is
+// what the host must read.
+#[test]
+fn host_reads_global_set_by_wasm() {
+    let bytes = ModuleBuilder::new()
+        .func_type(&[I32], &[])
+        .global(I32, true, &[0x41, 0x01])
+        .global(I64, false, &[0x42, 0x07])
+        // set: (global.set 0 (local.get 0))
+        .function(0, &[], &[0x20, 0x00, 0x24, 0x00])
+        .export("set", EXPORT_FUNCTION, 0)
+        .export("counter", EXPORT_GLOBAL, 0)
+        .export("seven", EXPORT_GLOBAL, 1)
+        .build();
+    let compartment = explicit_compartment();
+    let instance = instantiate(&compartment, &bytes, &HostModule::new("env")).unwrap();
+    assert_eq!(instance.get_global("counter"), Some(Value::I32(1)));
+    let set = instance.get_func("set").unwrap();
+    set.call(&[Value::I32(42)]).unwrap();
+    assert_eq!(instance.get_global("counter"), Some(Value::I32(42)));
+    assert_eq!(instance.global_value(0), Some(Value::I32(42)));
+    assert_eq!(instance.get_global("seven"), Some(Value::I64(7)));
+    assert_eq!(instance.get_global("set"), None);
+}
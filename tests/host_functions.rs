@@ -0,0 +1,49 @@
+mod common;
+
+use common::*;
+use nrt::runtime::{HostModule, TrapKind};
+use nrt::wasm::{FunctionType, Value, ValueType};
+
+// A module which imports `env.f: [] -> [i32]` and exports a function returning its result.
+fn caller_module() -> Vec<u8> {
+    ModuleBuilder::new()
+        .func_type(&[], &[I32])
+        .import_function("env", "f", 0)
+        .function(0, &[], &[0x10, 0x00])
+        .export("main", EXPORT_FUNCTION, 1)
+        .build()
+}
+
+fn call_main(host: HostModule) -> Result<Vec<Value>, nrt::runtime::Trap> {
+    let compartment = explicit_compartment();
+    let instance = instantiate(&compartment, &caller_module(), &host).unwrap();
+    instance.get_func("main").unwrap().call(&[])
+}
+
+fn i32_result() -> FunctionType {
+    FunctionType::new(vec![], vec![ValueType::I32])
+}
+
+#[test]
+fn host_function_result_is_returned() {
+    let host = HostModule::new("env").function("f", i32_result(), |_, _| vec![Value::I32(7)]);
+    assert_eq!(call_main(host).unwrap(), vec![Value::I32(7)]);
+}
+
+#[test]
+fn panicking_host_function_traps() {
+    let host = HostModule::new("env").function("f", i32_result(), |_, _| panic!("host failure"));
+    let trap = call_main(host).unwrap_err();
+    assert_eq!(trap.kind(), TrapKind::HostFunctionPanicked);
+}
+
+#[test]
+fn host_function_with_wrong_results_traps() {
+    let host = HostModule::new("env").function("f", i32_result(), |_, _| vec![Value::I64(7)]);
+    let trap = call_main(host).unwrap_err();
+    assert_eq!(trap.kind(), TrapKind::SignatureMismatch);
+
+    let host = HostModule::new("env").function("f", i32_result(), |_, _| vec![]);
+    let trap = call_main(host).unwrap_err();
+    assert_eq!(trap.kind(), TrapKind::SignatureMismatch);
+}
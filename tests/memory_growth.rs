@@ -0,0 +1,35 @@
+mod common;
+
+use common::*;
+use nrt::runtime::HostModule;
+use nrt::wasm::Value;
+
+// The callee grows the memory, so the caller must not keep using the size, or the base, which
+// it loaded before the call.
+#[test]
+fn caller_accesses_page_grown_by_callee() {
+    let bytes = ModuleBuilder::new()
+        .func_type(&[], &[])
+        .func_type(&[], &[I32])
+        .memory(1, None)
+        // grow: (drop (memory.grow (i32.const 1)))
+        .function(0, &[], &[0x41, 0x01, 0x40, 0x00, 0x1a])
+        // main: (call $grow) (i32.store (i32.const 65536) (i32.const 42))
+        //       (i32.load (i32.const 65536))
+        .function(
+            1,
+            &[],
+            &[
+                0x10, 0x00, //
+                0x41, 0x80, 0x80, 0x04, 0x41, 0x2a, 0x36, 0x02, 0x00, //
+                0x41, 0x80, 0x80, 0x04, 0x28, 0x02, 0x00,
+            ],
+        )
+        .export("main", EXPORT_FUNCTION, 1)
+        .build();
+    let compartment = explicit_compartment();
+    let instance = instantiate(&compartment, &bytes, &HostModule::new("env")).unwrap();
+    let main = instance.get_func("main").unwrap();
+    assert_eq!(main.call(&[]).unwrap(), vec![Value::I32(42)]);
+    assert_eq!(instance.memorys()[0].cur_pages(), 2);
+}
@@ -0,0 +1,39 @@
+#![cfg(feature = "multi-memory")]
+
+mod common;
+
+use common::*;
+use nrt::runtime::HostModule;
+use nrt::wasm::Value;
+
+// The memory arguments of the accesses to memory 1 set the flag 0x40 in their alignment, which
+// is followed by the memory index.
+#[test]
+fn store_to_second_memory_is_visible_to_host() {
+    let bytes = ModuleBuilder::new()
+        .func_type(&[], &[I32])
+        .memory(1, None)
+        .memory(1, None)
+        // main: (i32.store memory=1 (i32.const 8) (i32.const 42))
+        //       (i32.load memory=1 (i32.const 8))
+        .function(
+            0,
+            &[],
+            &[
+                0x41, 0x08, 0x41, 0x2a, 0x36, 0x42, 0x01, 0x00, //
+                0x41, 0x08, 0x28, 0x42, 0x01, 0x00,
+            ],
+        )
+        .export("main", EXPORT_FUNCTION, 0)
+        .export("mem0", EXPORT_MEMORY, 0)
+        .export("mem1", EXPORT_MEMORY, 1)
+        .build();
+    let compartment = explicit_compartment();
+    let instance = instantiate(&compartment, &bytes, &HostModule::new("env")).unwrap();
+    let main = instance.get_func("main").unwrap();
+    assert_eq!(main.call(&[]).unwrap(), vec![Value::I32(42)]);
+    let mem1 = instance.get_memory("mem1").unwrap();
+    assert_eq!(mem1.read_bytes(8, 4), Some(vec![42, 0, 0, 0]));
+    let mem0 = instance.get_memory("mem0").unwrap();
+    assert_eq!(mem0.read_bytes(8, 4), Some(vec![0, 0, 0, 0]));
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use common::*;
+use nrt::runtime::HostModule;
+use nrt::wasm::Value;
+
+// Type 0 is [] -> [i32 i32], type 1 is [i32 i32] -> [i32 i32], type 2 is [] -> [i32] and type 3
+// is [i32] -> [i32]. The blocks refer to types 1 and 3 by their index.
+fn multi_value_module() -> Vec<u8> {
+    ModuleBuilder::new()
+        .func_type(&[], &[I32, I32])
+        .func_type(&[I32, I32], &[I32, I32])
+        .func_type(&[], &[I32])
+        .func_type(&[I32], &[I32])
+        // swap: (i32.const 1) (i32.const 2)
+        //       (block (type 1) (local.set 0) (local.set 1) (local.get 0) (local.get 1))
+        .function(
+            0,
+            &[I32, I32],
+            &[
+                0x41, 0x01, 0x41, 0x02, //
+                0x02, 0x01, 0x21, 0x00, 0x21, 0x01, 0x20, 0x00, 0x20, 0x01, 0x0b,
+            ],
+        )
+        // sub: (i32.sub (call $swap))
+        .function(2, &[], &[0x10, 0x00, 0x6b])
+        // sum: (i32.const 0)
+        //      (loop (type 3) (i32.add (local.get 0))
+        //            (br_if 0 (local.tee 0 (i32.sub (local.get 0) (i32.const 1)))))
+        .function(
+            3,
+            &[],
+            &[
+                0x41, 0x00, //
+                0x03, 0x03, 0x20, 0x00, 0x6a, //
+                0x20, 0x00, 0x41, 0x01, 0x6b, 0x22, 0x00, 0x0d, 0x00, 0x0b,
+            ],
+        )
+        // step: (i32.const 10) (if (type 3) (local.get 0)
+        //                         (then (i32.add (i32.const 1))) (else (i32.sub (i32.const 1))))
+        .function(
+            3,
+            &[],
+            &[
+                0x41, 0x0a, 0x20, 0x00, //
+                0x04, 0x03, 0x41, 0x01, 0x6a, //
+                0x05, 0x41, 0x01, 0x6b, 0x0b,
+            ],
+        )
+        .export("swap", EXPORT_FUNCTION, 0)
+        .export("sub", EXPORT_FUNCTION, 1)
+        .export("sum", EXPORT_FUNCTION, 2)
+        .export("step", EXPORT_FUNCTION, 3)
+        .build()
+}
+
+fn call(name: &str, args: &[Value]) -> Vec<Value> {
+    let compartment = explicit_compartment();
+    let instance =
+        instantiate(&compartment, &multi_value_module(), &HostModule::new("env")).unwrap();
+    instance.get_func(name).unwrap().call(args).unwrap()
+}
+
+#[test]
+fn block_takes_params_and_returns_several_results() {
+    assert_eq!(call("swap", &[]), vec![Value::I32(2), Value::I32(1)]);
+}
+
+#[test]
+fn call_returns_several_results() {
+    assert_eq!(call("sub", &[]), vec![Value::I32(1)]);
+}
+
+// The branch to the loop passes the accumulated sum as its param.
+#[test]
+fn branch_to_loop_passes_its_params() {
+    assert_eq!(call("sum", &[Value::I32(4)]), vec![Value::I32(10)]);
+}
+
+#[test]
+fn if_takes_params() {
+    assert_eq!(call("step", &[Value::I32(1)]), vec![Value::I32(11)]);
+    assert_eq!(call("step", &[Value::I32(0)]), vec![Value::I32(9)]);
+}
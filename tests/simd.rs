@@ -0,0 +1,210 @@
+mod common;
+
+use common::*;
+use nrt::runtime::HostModule;
+use nrt::wasm::Value;
+
+fn simd(op: u32) -> Vec<u8> {
+    [vec![0xfd], leb_u32(op)].concat()
+}
+
+fn v128_const(bytes: [u8; 16]) -> Vec<u8> {
+    [simd(0x0c), bytes.to_vec()].concat()
+}
+
+fn i8x16(lanes: [i8; 16]) -> [u8; 16] {
+    let mut bytes = [0; 16];
+    lanes
+        .iter()
+        .enumerate()
+        .for_each(|(i, v)| bytes[i] = *v as u8);
+    bytes
+}
+
+fn i16x8(lanes: [i16; 8]) -> [u8; 16] {
+    let mut bytes = [0; 16];
+    for (i, v) in lanes.iter().enumerate() {
+        bytes[i * 2..i * 2 + 2].copy_from_slice(&v.to_le_bytes());
+    }
+    bytes
+}
+
+fn i32x4(lanes: [i32; 4]) -> [u8; 16] {
+    let mut bytes = [0; 16];
+    for (i, v) in lanes.iter().enumerate() {
+        bytes[i * 4..i * 4 + 4].copy_from_slice(&v.to_le_bytes());
+    }
+    bytes
+}
+
+fn i64x2(lanes: [i64; 2]) -> [u8; 16] {
+    let mut bytes = [0; 16];
+    for (i, v) in lanes.iter().enumerate() {
+        bytes[i * 8..i * 8 + 8].copy_from_slice(&v.to_le_bytes());
+    }
+    bytes
+}
+
+fn f32x4(lanes: [f32; 4]) -> [u8; 16] {
+    i32x4([
+        lanes[0].to_bits() as i32,
+        lanes[1].to_bits() as i32,
+        lanes[2].to_bits() as i32,
+        lanes[3].to_bits() as i32,
+    ])
+}
+
+fn f64x2(lanes: [f64; 2]) -> [u8; 16] {
+    i64x2([lanes[0].to_bits() as i64, lanes[1].to_bits() as i64])
+}
+
+// Runs `code`, which leaves a v128 on the stack, in a function of its own.
+fn eval(code: &[u8]) -> [u8; 16] {
+    let bytes = ModuleBuilder::new()
+        .func_type(&[], &[V128])
+        .function(0, &[], code)
+        .export("main", EXPORT_FUNCTION, 0)
+        .build();
+    let compartment = explicit_compartment();
+    let instance = instantiate(&compartment, &bytes, &HostModule::new("env")).unwrap();
+    match instance.get_func("main").unwrap().call(&[]).unwrap()[..] {
+        [Value::V128(res)] => res,
+        ref res => panic!("unexpected results {:?}", res),
+    }
+}
+
+fn unop(op: u32, operand: [u8; 16]) -> [u8; 16] {
+    eval(&[v128_const(operand), simd(op)].concat())
+}
+
+fn binop(op: u32, left: [u8; 16], right: [u8; 16]) -> [u8; 16] {
+    eval(&[v128_const(left), v128_const(right), simd(op)].concat())
+}
+
+fn shift(op: u32, operand: [u8; 16], count: i32) -> [u8; 16] {
+    eval(&[v128_const(operand), vec![0x41], leb_i32(count), simd(op)].concat())
+}
+
+#[test]
+fn trunc_sat_saturates_out_of_range_lanes() {
+    // i32x4.trunc_sat_f32x4_s
+    assert_eq!(
+        unop(0xf8, f32x4([f32::NAN, -1e10, 1e10, -2.5])),
+        i32x4([0, i32::MIN, i32::MAX, -2])
+    );
+    // i32x4.trunc_sat_f32x4_u
+    assert_eq!(
+        unop(0xf9, f32x4([f32::NAN, -1.0, 5e9, 3.7])),
+        i32x4([0, 0, u32::MAX as i32, 3])
+    );
+    // i32x4.trunc_sat_f64x2_s_zero
+    assert_eq!(
+        unop(0xfc, f64x2([-1e20, 1e20])),
+        i32x4([i32::MIN, i32::MAX, 0, 0])
+    );
+    // i32x4.trunc_sat_f64x2_u_zero
+    assert_eq!(
+        unop(0xfd, f64x2([-0.5, 4294967295.5])),
+        i32x4([0, u32::MAX as i32, 0, 0])
+    );
+}
+
+#[test]
+fn q15mulr_rounds_and_saturates() {
+    // i16x8.q15mulr_sat_s
+    assert_eq!(
+        binop(
+            0x82,
+            i16x8([-32768, 16384, -32768, 3, -1, 100, 32767, 0]),
+            i16x8([-32768, 16384, 32767, 5, 1, -200, 32767, -32768]),
+        ),
+        i16x8([32767, 8192, -32767, 0, 0, -1, 32766, 0])
+    );
+}
+
+#[test]
+fn narrow_saturates_signed_lanes() {
+    let left = i16x8([-200, 200, -1, 127, 128, -128, -129, 0]);
+    let right = i16x8([300, -300, 255, 256, 1, -1, 32767, -32768]);
+    // i8x16.narrow_i16x8_s
+    assert_eq!(
+        binop(0x65, left, right),
+        i8x16([
+            -128, 127, -1, 127, 127, -128, -128, 0, //
+            127, -128, 127, 127, 1, -1, 127, -128,
+        ])
+    );
+    // i8x16.narrow_i16x8_u
+    assert_eq!(
+        binop(0x66, left, right),
+        [0, 200, 0, 127, 128, 0, 0, 0, 255, 0, 255, 255, 1, 0, 255, 0]
+    );
+    // i16x8.narrow_i32x4_u
+    assert_eq!(
+        binop(
+            0x86,
+            i32x4([-1, 65536, 65535, 40000]),
+            i32x4([i32::MIN, i32::MAX, 0, 1])
+        ),
+        i16x8([0, -1, -1, 40000u16 as i16, 0, -1, 0, 1])
+    );
+}
+
+// Unlike `min` and `max`, `pmin` and `pmax` return the first operand unless the second compares
+// less or greater, so they don't propagate a NaN in the second operand and don't order zeros.
+#[test]
+fn pmin_and_pmax_return_first_operand_unless_ordered() {
+    let left = f32x4([1.0, f32::NAN, -0.0, 2.0]);
+    let right = f32x4([f32::NAN, 1.0, 0.0, 3.0]);
+    // f32x4.pmin
+    assert_eq!(binop(0xea, left, right), f32x4([1.0, f32::NAN, -0.0, 2.0]));
+    // f32x4.pmax
+    assert_eq!(binop(0xeb, left, right), f32x4([1.0, f32::NAN, -0.0, 3.0]));
+    // f64x2.pmin
+    assert_eq!(
+        binop(0xf6, f64x2([0.0, 5.0]), f64x2([-0.0, 4.0])),
+        f64x2([0.0, 4.0])
+    );
+}
+
+#[test]
+fn swizzle_zeroes_lanes_with_out_of_range_index() {
+    let mut operand = [0; 16];
+    operand
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, v)| *v = 0xa0 + i as u8);
+    let indices = [
+        0, 15, 16, 255, 0x80, 0x70, 1, 17, 0x8f, 14, 3, 32, 7, 0x7f, 0x10, 2,
+    ];
+    let expected = [
+        0xa0, 0xaf, 0, 0, 0, 0, 0xa1, 0, 0, 0xae, 0xa3, 0, 0xa7, 0, 0, 0xa2,
+    ];
+    // i8x16.swizzle
+    assert_eq!(binop(0x0e, operand, indices), expected);
+}
+
+// The shift count is taken modulo the width of the lanes.
+#[test]
+fn shift_count_wraps_around_lane_width() {
+    let bytes = i8x16([1, -128, 3, 64, 0, -1, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14]);
+    // i8x16.shl by 9
+    assert_eq!(
+        shift(0x6b, bytes, 9),
+        i8x16([2, 0, 6, -128, 0, -2, 10, 12, 14, 16, 18, 20, 22, 24, 26, 28])
+    );
+    // i8x16.shr_u by -1, which is 7
+    assert_eq!(
+        shift(0x6d, bytes, -1),
+        i8x16([0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+    );
+    // i8x16.shr_s by 8, which is 0
+    assert_eq!(shift(0x6c, bytes, 8), bytes);
+    // i32x4.shl by 33
+    assert_eq!(
+        shift(0xab, i32x4([1, -1, i32::MIN, 3]), 33),
+        i32x4([2, -2, 0, 6])
+    );
+    // i64x2.shr_s by 65
+    assert_eq!(shift(0xcc, i64x2([-4, 8]), 65), i64x2([-2, 4]));
+}
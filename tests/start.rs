@@ -0,0 +1,46 @@
+mod common;
+
+use common::*;
+use nrt::runtime::HostModule;
+use nrt::wasm::LoadError;
+
+// The start function runs after the data segments are copied, and before the instance is
+// returned.
+#[test]
+fn start_function_runs_at_instantiation() {
+    let bytes = ModuleBuilder::new()
+        .func_type(&[], &[])
+        .memory(1, None)
+        // (i32.store (i32.const 0) (i32.add (i32.load (i32.const 0)) (i32.const 1)))
+        .function(
+            0,
+            &[],
+            &[
+                0x41, 0x00, 0x41, 0x00, 0x28, 0x02, 0x00, //
+                0x41, 0x01, 0x6a, 0x36, 0x02, 0x00,
+            ],
+        )
+        .export("memory", EXPORT_MEMORY, 0)
+        .start(0)
+        .data(0, &[41])
+        .build();
+    let compartment = explicit_compartment();
+    let instance = instantiate(&compartment, &bytes, &HostModule::new("env")).unwrap();
+    let memory = instance.get_memory("memory").unwrap();
+    assert_eq!(memory.read_bytes(0, 1), Some(vec![42]));
+}
+
+#[test]
+fn trapping_start_function_fails_instantiation() {
+    let bytes = ModuleBuilder::new()
+        .func_type(&[], &[])
+        // (unreachable)
+        .function(0, &[], &[0x00])
+        .start(0)
+        .build();
+    let compartment = explicit_compartment();
+    match instantiate(&compartment, &bytes, &HostModule::new("env")) {
+        Err(LoadError::Instantiate(_)) => {}
+        _ => panic!("the trap of the start function wasn't reported"),
+    }
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use common::*;
+use nrt::runtime::{Compartment, HostModule};
+use nrt::wasm::{LoadError, Value};
+
+// Exports a table of 2 elements without a maximum, whose first element returns 42.
+fn exporter_module() -> Vec<u8> {
+    ModuleBuilder::new()
+        .func_type(&[], &[I32])
+        .function(0, &[], &[0x41, 0x2a])
+        .table(2, None)
+        .export("table", EXPORT_TABLE, 0)
+        .element(0, &[0])
+        .build()
+}
+
+// Imports `env.table`, stores its own function returning 7 at element 1, and exports a function
+// which calls both elements.
+fn importer_module(min: u32, max: Option<u32>) -> Vec<u8> {
+    ModuleBuilder::new()
+        .func_type(&[], &[I32])
+        .import_table("env", "table", min, max)
+        .function(0, &[], &[0x41, 0x07])
+        .function(
+            0,
+            &[],
+            &[
+                0x41, 0x00, 0x11, 0x00, 0x00, //
+                0x41, 0x01, 0x11, 0x00, 0x00, //
+                0x6a,
+            ],
+        )
+        .export("main", EXPORT_FUNCTION, 1)
+        .element(1, &[0])
+        .build()
+}
+
+#[test]
+fn imported_table_is_shared() {
+    let compartment = explicit_compartment();
+    let exporter = instantiate(&compartment, &exporter_module(), &HostModule::new("env")).unwrap();
+    let table = exporter.get_table("table").unwrap();
+    let host = HostModule::new("env").table("table", table.clone());
+    let importer = instantiate(&compartment, &importer_module(1, None), &host).unwrap();
+    assert!(std::sync::Arc::ptr_eq(&importer.tables()[0], &table));
+    assert_ne!(table.get(1), Some(0));
+
+    let main = importer.get_func("main").unwrap();
+    assert_eq!(main.call(&[]).unwrap(), vec![Value::I32(49)]);
+
+    // The table outlives the importer, but not its functions.
+    drop(importer);
+    assert_eq!(table.get(1), Some(0));
+    assert_ne!(table.get(0), Some(0));
+}
+
+#[test]
+fn imported_table_type_must_match() {
+    let compartment = explicit_compartment();
+    let exporter = instantiate(&compartment, &exporter_module(), &HostModule::new("env")).unwrap();
+    let host = HostModule::new("env").table("table", exporter.get_table("table").unwrap());
+    // The table is smaller than the minimum of the import.
+    match instantiate(&compartment, &importer_module(3, None), &host) {
+        Err(LoadError::Instantiate(_)) => {}
+        _ => panic!("a table smaller than the import was linked"),
+    }
+    // The table may grow beyond the maximum of the import.
+    match instantiate(&compartment, &importer_module(1, Some(4)), &host) {
+        Err(LoadError::Instantiate(_)) => {}
+        _ => panic!("a table without a maximum was linked to an import with one"),
+    }
+    match instantiate(
+        &compartment,
+        &importer_module(1, None),
+        &HostModule::new("env"),
+    ) {
+        Err(LoadError::Instantiate(_)) => {}
+        _ => panic!("an unresolved table was linked"),
+    }
+}
+
+#[test]
+fn table_of_another_compartment_is_rejected() {
+    let compartment = explicit_compartment();
+    let exporter = instantiate(&compartment, &exporter_module(), &HostModule::new("env")).unwrap();
+    let host = HostModule::new("env").table("table", exporter.get_table("table").unwrap());
+    let other = Compartment::new().unwrap();
+    match instantiate(&other, &importer_module(1, None), &host) {
+        Err(LoadError::Instantiate(_)) => {}
+        _ => panic!("a table of another compartment was linked"),
+    }
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use common::*;
+use nrt::runtime::{HostModule, TrapKind};
+use nrt::wasm::{Module, Value};
+use std::thread;
+use std::time::Duration;
+
+// Exports a shared memory of one page, and a function which wakes one waiter at address 0.
+fn notifier_module() -> Vec<u8> {
+    ModuleBuilder::new()
+        .func_type(&[], &[I32])
+        .shared_memory(1, Some(1))
+        // (memory.atomic.notify (i32.const 0) (i32.const 1))
+        .function(
+            0,
+            &[],
+            &[
+                0x41, 0x00, 0x41, 0x01, //
+                0xfe, 0x00, 0x02, 0x00,
+            ],
+        )
+        .export("notify", EXPORT_FUNCTION, 0)
+        .export("memory", EXPORT_MEMORY, 0)
+        .build()
+}
+
+// Imports the shared memory, and waits at address 0 without a timeout.
+fn waiter_module() -> Vec<u8> {
+    ModuleBuilder::new()
+        .func_type(&[], &[I32])
+        .import_memory("env", "memory", 1, Some(1), true)
+        // (memory.atomic.wait32 (i32.const 0) (i32.const 0) (i64.const -1))
+        .function(
+            0,
+            &[],
+            &[
+                0x41, 0x00, 0x41, 0x00, 0x42, 0x7f, //
+                0xfe, 0x01, 0x02, 0x00,
+            ],
+        )
+        .export("wait", EXPORT_FUNCTION, 0)
+        .build()
+}
+
+#[test]
+fn waiter_on_another_thread_is_woken_by_notify() {
+    let compartment = explicit_compartment();
+    let notifier = instantiate(&compartment, &notifier_module(), &HostModule::new("env")).unwrap();
+    let host = HostModule::new("env").memory("memory", notifier.get_memory("memory").unwrap());
+
+    let waiter_compartment = compartment.clone();
+    let waiter = thread::spawn(move || {
+        let waiter = instantiate(&waiter_compartment, &waiter_module(), &host).unwrap();
+        waiter.get_func("wait").unwrap().call(&[]).unwrap()
+    });
+
+    // The waiter may not be parked yet, in which case nothing is woken.
+    let notify = notifier.get_func("notify").unwrap();
+    while notify.call(&[]).unwrap() != vec![Value::I32(1)] {
+        thread::sleep(Duration::from_millis(1));
+    }
+    // 0 is "ok", which means that the waiter was woken.
+    assert_eq!(waiter.join().unwrap(), vec![Value::I32(0)]);
+}
+
+#[test]
+fn misaligned_atomic_access_traps() {
+    let bytes = ModuleBuilder::new()
+        .func_type(&[], &[I32])
+        .shared_memory(1, Some(1))
+        // (i32.atomic.load (i32.const 2))
+        .function(0, &[], &[0x41, 0x02, 0xfe, 0x10, 0x02, 0x00])
+        // (i32.atomic.load offset=1 (i32.const 0))
+        .function(0, &[], &[0x41, 0x00, 0xfe, 0x10, 0x02, 0x01])
+        .export("misaligned_addr", EXPORT_FUNCTION, 0)
+        .export("misaligned_offset", EXPORT_FUNCTION, 1)
+        .build();
+    let compartment = explicit_compartment();
+    let instance = instantiate(&compartment, &bytes, &HostModule::new("env")).unwrap();
+    for name in ["misaligned_addr", "misaligned_offset"].iter() {
+        let trap = instance.get_func(name).unwrap().call(&[]).unwrap_err();
+        assert_eq!(
+            trap.kind(),
+            TrapKind::MisalignedAtomicMemoryAccess,
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn shared_memory_must_have_a_maximum() {
+    let bytes = ModuleBuilder::new().shared_memory(1, None).build();
+    let module = Module::parse(&bytes).unwrap();
+    assert!(nrt::wasm::validate(&module).is_err());
+
+    let bytes = ModuleBuilder::new().shared_memory(1, Some(2)).build();
+    let module = Module::parse(&bytes).unwrap();
+    assert!(nrt::wasm::validate(&module).is_ok());
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::*;
+use nrt::codegen::BoundsCheckMode;
+use nrt::runtime::{Compartment, HostModule, Trap, TrapKind};
+use nrt::wasm::Value;
+
+const MODES: [BoundsCheckMode; 2] = [BoundsCheckMode::GuardPages, BoundsCheckMode::Explicit];
+
+// Function 0 doesn't trap, so that the index reported by a trap is the one of the function
+// which raised it.
+fn trap_module() -> Vec<u8> {
+    let nan = 0x7fc0_0000u32.to_le_bytes();
+    ModuleBuilder::new()
+        .func_type(&[], &[I32])
+        .memory(1, Some(1))
+        .function(0, &[], &[0x41, 0x00])
+        // (unreachable)
+        .function(0, &[], &[0x00])
+        // (i32.div_u (i32.const 1) (i32.const 0))
+        .function(0, &[], &[0x41, 0x01, 0x41, 0x00, 0x6e])
+        // (i32.div_s (i32.const 0x80000000) (i32.const -1))
+        .function(
+            0,
+            &[],
+            &[&[0x41][..], &leb_i32(i32::MIN), &[0x41, 0x7f, 0x6d]].concat(),
+        )
+        // (i32.trunc_f32_s (f32.const nan))
+        .function(0, &[], &[&[0x43][..], &nan, &[0xa8]].concat())
+        // (i32.load (i32.const 65536))
+        .function(0, &[], &[0x41, 0x80, 0x80, 0x04, 0x28, 0x02, 0x00])
+        // (i32.load (i32.const 65533)), whose last byte is out of bounds
+        .function(0, &[], &[0x41, 0xfd, 0xff, 0x03, 0x28, 0x02, 0x00])
+        // (i32.load offset=65536 (i32.const 0))
+        .function(0, &[], &[0x41, 0x00, 0x28, 0x02, 0x80, 0x80, 0x04])
+        .export("ok", EXPORT_FUNCTION, 0)
+        .export("unreachable", EXPORT_FUNCTION, 1)
+        .export("div_by_zero", EXPORT_FUNCTION, 2)
+        .export("overflow", EXPORT_FUNCTION, 3)
+        .export("invalid_conversion", EXPORT_FUNCTION, 4)
+        .export("oob_addr", EXPORT_FUNCTION, 5)
+        .export("oob_last_byte", EXPORT_FUNCTION, 6)
+        .export("oob_offset", EXPORT_FUNCTION, 7)
+        .build()
+}
+
+fn call(mode: BoundsCheckMode, name: &str) -> Result<Vec<Value>, Trap> {
+    let compartment = Compartment::with_bounds_check(mode).unwrap();
+    let instance = instantiate(&compartment, &trap_module(), &HostModule::new("env")).unwrap();
+    instance.get_func(name).unwrap().call(&[])
+}
+
+fn assert_trap(name: &str, kind: TrapKind, func_index: u32) {
+    for mode in MODES.iter() {
+        let trap = call(*mode, name).unwrap_err();
+        assert_eq!(trap.kind(), kind, "{} in {:?} mode", name, mode);
+        assert_eq!(
+            trap.func_index(),
+            Some(func_index),
+            "{} in {:?} mode",
+            name,
+            mode
+        );
+    }
+}
+
+#[test]
+fn unreachable_traps() {
+    assert_trap("unreachable", TrapKind::Unreachable, 1);
+}
+
+#[test]
+fn integer_divide_by_zero_traps() {
+    assert_trap("div_by_zero", TrapKind::IntegerDivideByZero, 2);
+}
+
+#[test]
+fn integer_overflow_traps() {
+    assert_trap("overflow", TrapKind::IntegerOverflow, 3);
+}
+
+#[test]
+fn invalid_conversion_to_integer_traps() {
+    assert_trap(
+        "invalid_conversion",
+        TrapKind::InvalidConversionToInteger,
+        4,
+    );
+}
+
+#[test]
+fn out_of_bounds_memory_access_traps() {
+    assert_trap("oob_addr", TrapKind::OutOfBoundsMemoryAccess, 5);
+    assert_trap("oob_last_byte", TrapKind::OutOfBoundsMemoryAccess, 6);
+    assert_trap("oob_offset", TrapKind::OutOfBoundsMemoryAccess, 7);
+}
+
+// A trap unwinds only the call which raised it, the instance can still be called.
+#[test]
+fn instance_is_usable_after_trap() {
+    for mode in MODES.iter() {
+        let compartment = Compartment::with_bounds_check(*mode).unwrap();
+        let instance = instantiate(&compartment, &trap_module(), &HostModule::new("env")).unwrap();
+        assert!(instance.get_func("oob_addr").unwrap().call(&[]).is_err());
+        let ok = instance.get_func("ok").unwrap();
+        assert_eq!(ok.call(&[]).unwrap(), vec![Value::I32(0)]);
+    }
+}